@@ -2,9 +2,7 @@ use std::io;
 use std::time::Instant;
 
 use v4l::buffer::Type;
-use v4l::io::traits::CaptureStream;
-use v4l::prelude::*;
-use v4l::video::Capture;
+use v4l::prelude::capture::*;
 
 fn main() -> io::Result<()> {
     let path = "/dev/video0";
@@ -2,6 +2,7 @@ use std::io;
 use std::time::Instant;
 
 use v4l::buffer::Type;
+use v4l::format::FieldOrder;
 use v4l::io::traits::{CaptureStream, OutputStream};
 use v4l::prelude::*;
 use v4l::video::{Capture, Output};
@@ -68,7 +69,7 @@ fn main() -> io::Result<()> {
         let buf_out = &mut buf_out[0..buf_in.len()];
 
         buf_out.copy_from_slice(buf_in);
-        buf_out_meta.field = 0;
+        buf_out_meta.field = FieldOrder::Any;
         buf_out_meta.bytesused = buf_in_meta.bytesused;
         let duration_us = t0.elapsed().as_micros();
 
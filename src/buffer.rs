@@ -1,7 +1,16 @@
 use bitflags::bitflags;
-use std::fmt;
+use std::convert::TryFrom;
+use std::{fmt, io, mem};
 
-use crate::timestamp::Timestamp;
+use crate::capability;
+use crate::device::Handle;
+use crate::format::mplane::MAX_PLANES;
+use crate::format::FieldOrder;
+use crate::memory::Memory;
+use crate::timecode::Timecode;
+use crate::timestamp::{self, Timestamp};
+use crate::v4l2;
+use crate::v4l_sys::*;
 
 /// Buffer type
 ///
@@ -30,6 +39,86 @@ pub enum Type {
     Private             = 0x80,
 }
 
+impl Type {
+    /// Returns whether buffers of this type consist of separate planes, see
+    /// [`crate::format::mplane`]
+    pub fn is_multiplanar(&self) -> bool {
+        matches!(self, Type::VideoCaptureMplane | Type::VideoOutputMplane)
+    }
+
+    /// Returns whether buffers of this type are filled by the application and consumed by the
+    /// device
+    pub fn is_output(&self) -> bool {
+        matches!(
+            self,
+            Type::VideoOutput
+                | Type::VideoOutputMplane
+                | Type::VbiOutput
+                | Type::SlicedVbiOutput
+                | Type::SdrOutput
+                | Type::MetaOutput
+        )
+    }
+
+    /// Returns the device capabilities which support this buffer type, any of them suffices
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::buffer::Type;
+    /// use v4l::capability::Flags;
+    ///
+    /// assert!(Type::VideoCapture.capabilities().contains(Flags::VIDEO_M2M));
+    /// assert!(!Type::VideoCapture.capabilities().contains(Flags::VIDEO_CAPTURE_MPLANE));
+    /// ```
+    pub fn capabilities(&self) -> capability::Flags {
+        use capability::Flags;
+
+        match self {
+            Type::VideoCapture => Flags::VIDEO_CAPTURE | Flags::VIDEO_M2M,
+            Type::VideoOutput => Flags::VIDEO_OUTPUT | Flags::VIDEO_M2M,
+            Type::VideoOverlay => Flags::VIDEO_OVERLAY,
+            Type::VbiCaputre => Flags::VBI_CAPTURE,
+            Type::VbiOutput => Flags::VBI_OUTPUT,
+            Type::SlicedVbiCapture => Flags::SLICED_VBI_CAPTURE,
+            Type::SlicedVbiOutput => Flags::SLICED_VBI_OUTPUT,
+            Type::VideoOutputOverlay => Flags::VIDEO_OUTPUT_OVERLAY,
+            Type::VideoCaptureMplane => Flags::VIDEO_CAPTURE_MPLANE | Flags::VIDEO_M2M_MPLANE,
+            Type::VideoOutputMplane => Flags::VIDEO_OUTPUT_MPLANE | Flags::VIDEO_M2M_MPLANE,
+            Type::SdrCapture => Flags::SDR_CAPTURE,
+            Type::SdrOutput => Flags::SDR_OUTPUT,
+            Type::MetaCapture => Flags::META_CAPTURE,
+            Type::MetaOutput => Flags::META_OUTPUT,
+            Type::Private => Flags::all(),
+        }
+    }
+}
+
+impl TryFrom<u32> for Type {
+    type Error = ();
+
+    fn try_from(repr: u32) -> Result<Self, Self::Error> {
+        match repr {
+            1 => Ok(Type::VideoCapture),
+            2 => Ok(Type::VideoOutput),
+            3 => Ok(Type::VideoOverlay),
+            4 => Ok(Type::VbiCaputre),
+            5 => Ok(Type::VbiOutput),
+            6 => Ok(Type::SlicedVbiCapture),
+            7 => Ok(Type::SlicedVbiOutput),
+            8 => Ok(Type::VideoOutputOverlay),
+            9 => Ok(Type::VideoCaptureMplane),
+            10 => Ok(Type::VideoOutputMplane),
+            11 => Ok(Type::SdrCapture),
+            12 => Ok(Type::SdrOutput),
+            13 => Ok(Type::MetaCapture),
+            14 => Ok(Type::MetaOutput),
+            0x80 => Ok(Type::Private),
+            _ => Err(()),
+        }
+    }
+}
+
 bitflags! {
     #[allow(clippy::unreadable_literal)]
     pub struct Flags: u32 {
@@ -67,7 +156,7 @@ bitflags! {
         const TSTAMP_SRC_MASK       = 0x00070000;
         const TSTAMP_SRC_EOF        = 0x00000000;
         const TSTAMP_SRC_SOE        = 0x00010000;
-        /// mem2mem encoder/decoder
+        /// Last buffer of a draining mem2mem encoder/decoder, the stream ends with it
         const LAST                  = 0x00100000;
         /// request_fd is valid
         const REQUEST_FD            = 0x00800000;
@@ -106,9 +195,269 @@ pub struct Metadata {
     /// Buffer flags
     pub flags: Flags,
     /// Indicates the field order of the image in the buffer.
-    pub field: u32,
+    pub field: FieldOrder,
     /// Time of capture (usually set by the driver)
     pub timestamp: Timestamp,
     /// Sequence number, counting the frames
     pub sequence: u32,
+    /// SMPTE timecode, present if [`Flags::TIMECODE`] is set
+    ///
+    /// Setting it on output buffers sets the flag when queuing them.
+    pub timecode: Option<Timecode>,
+}
+
+impl Metadata {
+    /// Returns the metadata of a raw buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Buffer, e.g. as returned by [`crate::v4l2::vidioc::VIDIOC_DQBUF`]
+    pub fn from_raw(buf: &v4l2_buffer) -> Self {
+        Metadata {
+            bytesused: buf.bytesused,
+            flags: buf.flags.into(),
+            field: FieldOrder::try_from(buf.field).unwrap_or(FieldOrder::Any),
+            timestamp: buf.timestamp.into(),
+            sequence: buf.sequence,
+            timecode: if Flags::from(buf.flags).contains(Flags::TIMECODE) {
+                Timecode::try_from(buf.timecode).ok()
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Returns a raw buffer holding the metadata, all other fields are zeroed
+    ///
+    /// Fields which are not modeled by this crate can be set on the returned struct before
+    /// passing it to the driver.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::buffer::{Flags, Metadata};
+    /// use v4l::timecode::{Timecode, Type};
+    ///
+    /// let meta = Metadata {
+    ///     bytesused: 42,
+    ///     flags: Flags::KEYFRAME,
+    ///     ..Metadata::default()
+    /// };
+    /// let raw = meta.as_raw();
+    /// assert_eq!(raw.bytesused, 42);
+    /// assert_eq!(Metadata::from_raw(&raw).flags, Flags::KEYFRAME);
+    ///
+    /// // timecodes are carried along with the flag that marks them valid
+    /// let timecode = Timecode::new(Type::Fps25, 1, 2, 3, 4).unwrap();
+    /// let meta = Metadata {
+    ///     timecode: Some(timecode),
+    ///     ..meta
+    /// };
+    /// let meta = Metadata::from_raw(&meta.as_raw());
+    /// assert!(meta.flags.contains(Flags::TIMECODE));
+    /// assert_eq!(meta.timecode, Some(timecode));
+    /// ```
+    pub fn as_raw(&self) -> v4l2_buffer {
+        let mut buf = v4l2_buffer {
+            bytesused: self.bytesused,
+            flags: self.flags.into(),
+            field: self.field as u32,
+            timestamp: self.timestamp.into(),
+            sequence: self.sequence,
+            ..unsafe { mem::zeroed() }
+        };
+        self.apply_timecode(&mut buf);
+        buf
+    }
+
+    /// Stores the timecode in a raw buffer and sets or clears [`Flags::TIMECODE`] accordingly
+    pub(crate) fn apply_timecode(&self, buf: &mut v4l2_buffer) {
+        match self.timecode {
+            Some(timecode) => {
+                buf.timecode = timecode.into();
+                buf.flags |= u32::from(Flags::TIMECODE);
+            }
+            None => buf.flags &= !u32::from(Flags::TIMECODE),
+        }
+    }
+
+    /// Returns whether the timestamp was copied from the matching output buffer
+    ///
+    /// Memory-to-memory devices like codecs copy the timestamp of each output buffer to the
+    /// capture buffers produced from it, so frames can be matched with their source frames by
+    /// comparing timestamps.
+    pub fn is_timestamp_copied(&self) -> bool {
+        self.flags & Flags::TIMESTAMP_MASK == Flags::TIMESTAMP_COPY
+    }
+
+    /// Returns whether the buffer holds a keyframe, i.e. decoding can start with it
+    pub fn is_keyframe(&self) -> bool {
+        self.flags.contains(Flags::KEYFRAME)
+    }
+
+    /// Returns whether the driver flagged the buffer data as corrupted
+    pub fn is_corrupted(&self) -> bool {
+        self.flags.contains(Flags::ERROR)
+    }
+
+    /// Returns whether this is the last buffer of the stream
+    ///
+    /// Codecs set [`Flags::LAST`] on the final capture buffer after being drained with the
+    /// `V4L2_DEC_CMD_STOP` or `V4L2_ENC_CMD_STOP` command. The buffer may be empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::buffer::{Flags, Metadata};
+    ///
+    /// let meta = Metadata {
+    ///     flags: Flags::KEYFRAME | Flags::LAST,
+    ///     ..Metadata::default()
+    /// };
+    /// assert!(meta.is_keyframe());
+    /// assert!(meta.is_last());
+    /// assert!(!meta.is_corrupted());
+    /// ```
+    pub fn is_last(&self) -> bool {
+        self.flags.contains(Flags::LAST)
+    }
+
+    /// Returns the clock the timestamp was taken from, `None` if it is unknown to this crate
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::buffer::{Flags, Metadata};
+    /// use v4l::timestamp::{Clock, Source};
+    ///
+    /// let meta = Metadata {
+    ///     flags: Flags::TIMESTAMP_MONOTONIC | Flags::TSTAMP_SRC_SOE,
+    ///     ..Metadata::default()
+    /// };
+    /// assert_eq!(meta.clock(), Some(Clock::Monotonic));
+    /// assert_eq!(meta.timestamp_source(), Some(Source::StartOfExposure));
+    /// assert!(meta.system_time().is_some());
+    /// ```
+    pub fn clock(&self) -> Option<timestamp::Clock> {
+        timestamp::Clock::try_from(u32::from(self.flags)).ok()
+    }
+
+    /// Returns the moment of the frame the timestamp refers to, `None` if it is unknown to this
+    /// crate
+    pub fn timestamp_source(&self) -> Option<timestamp::Source> {
+        timestamp::Source::try_from(u32::from(self.flags)).ok()
+    }
+
+    /// Returns the timestamp as wall clock time, e.g. for correlating frames with other sensors
+    ///
+    /// Only timestamps of [`timestamp::Clock::Monotonic`] can be converted, see
+    /// [`Timestamp::to_system_time()`].
+    pub fn system_time(&self) -> Option<std::time::SystemTime> {
+        match self.clock() {
+            Some(timestamp::Clock::Monotonic) => self.timestamp.to_system_time().ok(),
+            _ => None,
+        }
+    }
+
+    /// Emits a trace event for a dequeued buffer
+    #[cfg(feature = "tracing")]
+    pub(crate) fn trace_dequeue(&self, index: usize) {
+        if self.flags.contains(Flags::ERROR) {
+            tracing::warn!(index, sequence = self.sequence, "dequeued corrupted buffer");
+        } else {
+            tracing::trace!(
+                index,
+                sequence = self.sequence,
+                bytesused = self.bytesused,
+                "dequeued buffer"
+            );
+        }
+    }
+}
+
+/// State of a buffer, as reported by [`crate::v4l2::vidioc::VIDIOC_QUERYBUF`]
+#[derive(Copy, Clone)]
+pub struct State {
+    /// Index of the buffer
+    pub index: u32,
+    /// Size of the buffer in bytes, the sum of all planes for multi-planar buffers
+    pub length: u32,
+    /// Offset to pass to mmap, only valid for [`Memory::Mmap`] buffers
+    ///
+    /// For multi-planar buffers, this is the offset of the first plane.
+    pub offset: u32,
+    /// Metadata of the buffer, i.e. its flags and the details of the last frame it held
+    pub meta: Metadata,
+}
+
+impl State {
+    /// Queries the state of a buffer
+    pub(crate) fn query(
+        handle: &Handle,
+        buf_type: Type,
+        memory: Memory,
+        index: u32,
+    ) -> io::Result<Self> {
+        let mmap = matches!(memory, Memory::Mmap);
+        let mut v4l2_planes: [v4l2_plane; MAX_PLANES] = unsafe { mem::zeroed() };
+        let mut v4l2_buf = v4l2_buffer {
+            index,
+            type_: buf_type as u32,
+            memory: memory as u32,
+            ..unsafe { mem::zeroed() }
+        };
+        if buf_type.is_multiplanar() {
+            v4l2_buf.m.planes = v4l2_planes.as_mut_ptr();
+            v4l2_buf.length = MAX_PLANES as u32;
+        }
+        unsafe {
+            v4l2::ioctl(
+                handle.fd(),
+                v4l2::vidioc::VIDIOC_QUERYBUF,
+                &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        let mut meta = Metadata::from_raw(&v4l2_buf);
+        let (length, offset) = if buf_type.is_multiplanar() {
+            let planes = &v4l2_planes[..(v4l2_buf.length as usize).min(MAX_PLANES)];
+            meta.bytesused = planes.iter().map(|plane| plane.bytesused).sum();
+            (
+                planes.iter().map(|plane| plane.length).sum(),
+                planes
+                    .first()
+                    .map_or(0, |plane| unsafe { plane.m.mem_offset }),
+            )
+        } else {
+            (v4l2_buf.length, unsafe { v4l2_buf.m.offset })
+        };
+
+        Ok(State {
+            index: v4l2_buf.index,
+            length,
+            offset: if mmap { offset } else { 0 },
+            meta,
+        })
+    }
+
+    /// Returns whether the buffer is owned by the driver, i.e. queued but not dequeued yet
+    pub fn is_queued(&self) -> bool {
+        self.meta.flags.intersects(Flags::QUEUED | Flags::DONE)
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "index          : {}", self.index)?;
+        writeln!(f, "length         : {}", self.length)?;
+        writeln!(f, "offset         : {:#x}", self.offset)?;
+        writeln!(f, "bytesused      : {}", self.meta.bytesused)?;
+        writeln!(f, "flags          : {}", self.meta.flags)?;
+        writeln!(f, "sequence       : {}", self.meta.sequence)?;
+        writeln!(f, "timestamp      : {}", self.meta.timestamp)?;
+        if let Some(timecode) = self.meta.timecode {
+            writeln!(f, "timecode       : {}", timecode)?;
+        }
+        Ok(())
+    }
 }
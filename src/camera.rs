@@ -0,0 +1,364 @@
+use std::io;
+use std::path::Path;
+
+use crate::buffer::Type;
+use crate::capability::Flags;
+use crate::context;
+use crate::control::{Control, Description, Value};
+#[cfg(feature = "conversions")]
+use crate::convert::{self, Image, Orientation};
+use crate::device::Device;
+use crate::focus::Focus;
+use crate::format::{Format, FourCC};
+use crate::fraction::Fraction;
+use crate::io::mmap::split::{Buffer, Producer};
+use crate::io::mmap::Stream;
+use crate::video::capture::Parameters;
+use crate::video::Capture;
+use crate::white_balance::WhiteBalance;
+
+/// Number of buffers used for capturing
+const BUFFER_COUNT: u32 = 4;
+
+#[cfg(feature = "conversions")]
+const V4L2_CID_HFLIP: u32 = 0x0098_0914;
+#[cfg(feature = "conversions")]
+const V4L2_CID_VFLIP: u32 = 0x0098_0915;
+#[cfg(feature = "conversions")]
+const V4L2_CID_ROTATE: u32 = 0x0098_0922;
+
+/// Camera with all buffer management taken care of
+///
+/// This is the easiest way to get frames out of a webcam. Configure the camera first, then
+/// iterate over its frames. Changing the configuration while frames are being captured restarts
+/// the capture.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::{Camera, FourCC};
+///
+/// let mut cam = Camera::new(0).expect("Failed to open camera");
+/// cam.set_pixel_format(FourCC::new(b"MJPG")).expect("Failed to set pixel format");
+/// cam.set_resolution(1280, 720).expect("Failed to set resolution");
+/// cam.set_fps(30).expect("Failed to set frame rate");
+///
+/// for frame in cam.frames().take(100) {
+///     let frame = frame.expect("Failed to capture frame");
+///     println!("frame {}: {} bytes", frame.meta().sequence, frame.meta().bytesused);
+/// }
+/// ```
+pub struct Camera {
+    dev: Device,
+    producer: Option<Producer<'static>>,
+    #[cfg(feature = "conversions")]
+    orientation: Orientation,
+}
+
+impl Camera {
+    /// Returns a camera by index
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index (0: first, 1: second, ..)
+    pub fn new(index: usize) -> io::Result<Self> {
+        Self::with_device(Device::new(index)?)
+    }
+
+    /// Returns a camera by path
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path (e.g. "/dev/video0")
+    pub fn with_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::with_device(Device::with_path(path)?)
+    }
+
+    /// Returns a camera by its bus location
+    ///
+    /// Unlike device indices, the bus location of a camera stays the same across reboots and
+    /// replugging, as long as it is connected to the same port.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - Bus location as reported by the driver (e.g. "usb-0000:00:14.0-1")
+    pub fn with_bus(bus: &str) -> io::Result<Self> {
+        let mut nodes = context::enum_devices();
+        nodes.sort_by_key(|node| node.index());
+
+        for node in nodes {
+            let dev = match Device::with_path(node.path()) {
+                Ok(dev) => dev,
+                Err(_) => continue,
+            };
+            match dev.query_caps() {
+                // devices may expose additional nodes (e.g. metadata) on the same bus
+                Ok(caps) if caps.bus == bus && caps.capabilities.contains(Flags::VIDEO_CAPTURE) => {
+                    return Self::with_device(dev)
+                }
+                _ => continue,
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no camera found on bus {}", bus),
+        ))
+    }
+
+    /// Wraps a device as camera
+    ///
+    /// Fails if the device cannot capture video.
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Capture device
+    pub fn with_device(dev: Device) -> io::Result<Self> {
+        let caps = dev.query_caps()?;
+        if !caps
+            .capabilities
+            .contains(Flags::VIDEO_CAPTURE | Flags::STREAMING)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "device does not support video capture streaming",
+            ));
+        }
+
+        Ok(Camera {
+            dev,
+            producer: None,
+            #[cfg(feature = "conversions")]
+            orientation: Orientation::default(),
+        })
+    }
+
+    /// Returns the underlying device
+    pub fn device(&self) -> &Device {
+        &self.dev
+    }
+
+    /// Returns the active format
+    pub fn format(&self) -> io::Result<Format> {
+        Capture::format(&self.dev)
+    }
+
+    /// Sets the resolution and returns the one chosen by the driver
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width in pixels
+    /// * `height` - Height in pixels
+    pub fn set_resolution(&mut self, width: u32, height: u32) -> io::Result<(u32, u32)> {
+        let mut format = self.format()?;
+        format.width = width;
+        format.height = height;
+        let format = self.set_format(&format)?;
+        Ok((format.width, format.height))
+    }
+
+    /// Sets the pixel format and returns the one chosen by the driver
+    ///
+    /// # Arguments
+    ///
+    /// * `fourcc` - Pixel format
+    pub fn set_pixel_format(&mut self, fourcc: FourCC) -> io::Result<FourCC> {
+        let mut format = self.format()?;
+        format.fourcc = fourcc;
+        Ok(self.set_format(&format)?.fourcc)
+    }
+
+    /// Sets the format and returns the one chosen by the driver
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Desired format
+    pub fn set_format(&mut self, format: &Format) -> io::Result<Format> {
+        // buffers are sized for the active format, so they have to go first
+        self.producer = None;
+        Capture::set_format(&self.dev, format)
+    }
+
+    /// Sets the frame rate and returns the interval chosen by the driver
+    ///
+    /// # Arguments
+    ///
+    /// * `fps` - Frames per second
+    pub fn set_fps(&mut self, fps: u32) -> io::Result<Fraction> {
+        Ok(Capture::set_params(&self.dev, &Parameters::with_fps(fps))?.interval)
+    }
+
+    /// Returns the controls of the camera, such as brightness, focus, white balance, etc.
+    pub fn controls(&self) -> io::Result<Vec<Description>> {
+        self.dev.query_controls()
+    }
+
+    /// Returns the current value of a control
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Control identifier
+    pub fn control(&self, id: u32) -> io::Result<Value> {
+        Ok(self.dev.control_from_id(id)?.value)
+    }
+
+    /// Sets the value of a control
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Control identifier
+    /// * `value` - New value
+    pub fn set_control(&self, id: u32, value: Value) -> io::Result<()> {
+        self.dev.set_control(Control { id, value })
+    }
+
+    /// Returns the lens focus of the camera
+    ///
+    /// Fails if the camera has no focus control, e.g. because it has a fixed focus lens.
+    pub fn focus(&self) -> io::Result<Focus<'_>> {
+        Focus::new(&self.dev)
+    }
+
+    /// Returns the white balance of the camera
+    ///
+    /// Fails if the camera has no white balance controls.
+    pub fn white_balance(&self) -> io::Result<WhiteBalance<'_>> {
+        WhiteBalance::new(&self.dev)
+    }
+
+    /// Rotates frames clockwise
+    ///
+    /// The driver rotates frames if it supports the angle, in which case the resolution reported
+    /// by [`Self::format()`] changes accordingly. Otherwise, frames are rotated in software by
+    /// [`Self::to_rgb()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `degrees` - Angle, a multiple of 90
+    #[cfg(feature = "conversions")]
+    pub fn set_rotation(&mut self, degrees: u32) -> io::Result<()> {
+        if degrees % 90 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cannot rotate by {} degrees", degrees),
+            ));
+        }
+
+        let degrees = degrees % 360;
+        // the buffers no longer fit if width and height are swapped
+        if self.set_hw_control(V4L2_CID_ROTATE, Value::Integer(degrees as i64), true) {
+            self.orientation.rotation = 0;
+        } else {
+            self.orientation.rotation = degrees;
+        }
+        Ok(())
+    }
+
+    /// Mirrors frames
+    ///
+    /// Flipping is done by the driver where supported and by [`Self::to_rgb()`] otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `horizontal` - Mirror left and right
+    /// * `vertical` - Mirror top and bottom
+    #[cfg(feature = "conversions")]
+    pub fn set_flip(&mut self, horizontal: bool, vertical: bool) -> io::Result<()> {
+        self.orientation.hflip =
+            !self.set_hw_control(V4L2_CID_HFLIP, Value::Boolean(horizontal), false) && horizontal;
+        self.orientation.vflip =
+            !self.set_hw_control(V4L2_CID_VFLIP, Value::Boolean(vertical), false) && vertical;
+        Ok(())
+    }
+
+    /// Returns the part of the orientation that is corrected in software
+    #[cfg(feature = "conversions")]
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Converts a frame to RGB, correcting its orientation if the driver cannot
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame data in the active format
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::{Camera, FourCC};
+    ///
+    /// let mut cam = Camera::new(0).expect("Failed to open camera");
+    /// cam.set_pixel_format(FourCC::new(b"YUYV")).expect("Failed to set pixel format");
+    /// cam.set_rotation(180).expect("Failed to set rotation");
+    ///
+    /// let frame = cam.frames().next().unwrap().expect("Failed to capture frame");
+    /// let image = cam.to_rgb(frame.data()).expect("Failed to convert frame");
+    /// println!("{}x{}", image.width, image.height);
+    /// ```
+    #[cfg(feature = "conversions")]
+    pub fn to_rgb(&self, frame: &[u8]) -> io::Result<Image> {
+        let image = convert::to_rgb(frame, &self.format()?)?;
+        self.orientation.apply(image)
+    }
+
+    /// Returns an iterator over captured frames
+    ///
+    /// Capturing starts with the first frame. Each frame holds on to one of a few buffers, so drop
+    /// frames once they have been processed. The iterator never ends by itself, but yields an
+    /// error if capturing fails.
+    pub fn frames(&mut self) -> Frames<'_> {
+        Frames { cam: self }
+    }
+
+    /// Sets a control if the driver supports the value, returns whether it did
+    #[cfg(feature = "conversions")]
+    fn set_hw_control(&mut self, id: u32, value: Value, restart: bool) -> bool {
+        let supported = match (self.dev.query_control(id), &value) {
+            (Ok(desc), _)
+                if desc.flags.intersects(
+                    crate::control::Flags::DISABLED | crate::control::Flags::READ_ONLY,
+                ) =>
+            {
+                false
+            }
+            (Ok(desc), Value::Integer(val)) => {
+                (desc.minimum..=desc.maximum).contains(val)
+                    && (val - desc.minimum) % desc.step.max(1) as i64 == 0
+            }
+            (Ok(_), _) => true,
+            (Err(_), _) => false,
+        };
+        if !supported {
+            return false;
+        }
+
+        if restart {
+            self.producer = None;
+        }
+        self.dev.set_control(Control { id, value }).is_ok()
+    }
+
+    fn producer(&mut self) -> io::Result<&mut Producer<'static>> {
+        if self.producer.is_none() {
+            let stream = Stream::with_buffers(&self.dev, Type::VideoCapture, BUFFER_COUNT)?;
+            let (producer, _) = stream.split()?;
+            self.producer = Some(producer);
+        }
+
+        Ok(self.producer.as_mut().unwrap())
+    }
+}
+
+/// Iterator over the frames of a [`Camera`]
+pub struct Frames<'a> {
+    cam: &'a mut Camera,
+}
+
+impl<'a> Iterator for Frames<'a> {
+    type Item = io::Result<Buffer<'static>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.cam.producer().and_then(|producer| producer.dequeue()))
+    }
+}
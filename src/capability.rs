@@ -1,10 +1,13 @@
 use bitflags::bitflags;
-use std::{fmt, str};
+use std::ffi::OsStr;
+use std::fmt;
 
+use crate::util;
 use crate::v4l_sys::*;
 
 bitflags! {
     #[allow(clippy::unreadable_literal)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Flags: u32 {
         const VIDEO_CAPTURE         = 0x00000001;
         const VIDEO_OUTPUT          = 0x00000002;
@@ -63,6 +66,7 @@ impl fmt::Display for Flags {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Device capabilities
 pub struct Capabilities {
     /// Driver name, e.g. uvc for usb video class devices
@@ -76,29 +80,69 @@ pub struct Capabilities {
 
     /// Capability flags
     pub capabilities: Flags,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    driver_bytes: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    card_bytes: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    bus_bytes: Vec<u8>,
+}
+
+impl Capabilities {
+    /// Returns the driver name exactly as reported by the driver
+    ///
+    /// Unlike [`Self::driver`], invalid UTF-8 sequences are preserved.
+    pub fn driver_bytes(&self) -> &[u8] {
+        &self.driver_bytes
+    }
+
+    /// Returns the driver name exactly as reported by the driver
+    pub fn driver_os_str(&self) -> &OsStr {
+        util::os_str(&self.driver_bytes)
+    }
+
+    /// Returns the card name exactly as reported by the driver
+    ///
+    /// Unlike [`Self::card`], invalid UTF-8 sequences are preserved.
+    pub fn card_bytes(&self) -> &[u8] {
+        &self.card_bytes
+    }
+
+    /// Returns the card name exactly as reported by the driver
+    pub fn card_os_str(&self) -> &OsStr {
+        util::os_str(&self.card_bytes)
+    }
+
+    /// Returns the bus name exactly as reported by the driver
+    ///
+    /// Unlike [`Self::bus`], invalid UTF-8 sequences are preserved, so it can be used to identify
+    /// a device reliably.
+    pub fn bus_bytes(&self) -> &[u8] {
+        &self.bus_bytes
+    }
+
+    /// Returns the bus name exactly as reported by the driver
+    pub fn bus_os_str(&self) -> &OsStr {
+        util::os_str(&self.bus_bytes)
+    }
 }
 
 impl From<v4l2_capability> for Capabilities {
     fn from(cap: v4l2_capability) -> Self {
         Self {
-            driver: str::from_utf8(&cap.driver)
-                .unwrap()
-                .trim_matches(char::from(0))
-                .to_string(),
-            card: str::from_utf8(&cap.card)
-                .unwrap()
-                .trim_matches(char::from(0))
-                .to_string(),
-            bus: str::from_utf8(&cap.bus_info)
-                .unwrap()
-                .trim_matches(char::from(0))
-                .to_string(),
+            driver: util::string_lossy(&cap.driver),
+            card: util::string_lossy(&cap.card),
+            bus: util::string_lossy(&cap.bus_info),
             version: (
                 ((cap.version >> 16) & 0xff) as u8,
                 ((cap.version >> 8) & 0xff) as u8,
                 (cap.version & 0xff) as u8,
             ),
             capabilities: Flags::from(cap.device_caps),
+            driver_bytes: util::until_nul(&cap.driver).to_vec(),
+            card_bytes: util::until_nul(&cap.card).to_vec(),
+            bus_bytes: util::until_nul(&cap.bus_info).to_vec(),
         }
     }
 }
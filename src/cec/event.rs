@@ -0,0 +1,124 @@
+use bitflags::bitflags;
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::v4l2::cec::cec_event;
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    pub struct Flags: u32 {
+        /// The event reports the state at the time the device was opened
+        const INITIAL_STATE     = 0x00000001;
+        /// Events of this type were dropped because they were not dequeued in time
+        const DROPPED_EVENTS    = 0x00000002;
+    }
+}
+
+impl Default for Flags {
+    fn default() -> Self {
+        Flags::from(0)
+    }
+}
+
+impl From<u32> for Flags {
+    fn from(flags: u32) -> Self {
+        Self::from_bits_truncate(flags)
+    }
+}
+
+impl From<Flags> for u32 {
+    fn from(flags: Flags) -> Self {
+        flags.bits()
+    }
+}
+
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Event payload
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Payload {
+    /// The physical or logical addresses of the adapter changed
+    StateChange {
+        /// Physical address, 0xffff if there is none, e.g. because HDMI is disconnected
+        physical_address: u16,
+        /// Bitmask of the claimed logical addresses
+        logical_addresses: u16,
+    },
+    /// Received messages were dropped because they were not dequeued in time
+    LostMessages(u32),
+    /// The CEC pin went low, only reported in pin monitoring mode
+    PinCecLow,
+    /// The CEC pin went high, only reported in pin monitoring mode
+    PinCecHigh,
+    /// The hotplug detect pin went low
+    PinHpdLow,
+    /// The hotplug detect pin went high
+    PinHpdHigh,
+    /// The 5V pin went low
+    Pin5vLow,
+    /// The 5V pin went high
+    Pin5vHigh,
+}
+
+/// CEC adapter event
+#[derive(Debug, Copy, Clone)]
+pub struct Event {
+    /// Time of the event, in nanoseconds of the monotonic clock
+    pub timestamp: u64,
+    /// Event flags
+    pub flags: Flags,
+    /// Event payload
+    pub payload: Payload,
+}
+
+impl TryFrom<cec_event> for Event {
+    type Error = ();
+
+    fn try_from(ev: cec_event) -> Result<Self, Self::Error> {
+        let payload = match ev.event {
+            1 => {
+                let state = unsafe { ev.payload.state_change };
+                Payload::StateChange {
+                    physical_address: state.phys_addr,
+                    logical_addresses: state.log_addr_mask,
+                }
+            }
+            2 => Payload::LostMessages(unsafe { ev.payload.lost_msgs }),
+            3 => Payload::PinCecLow,
+            4 => Payload::PinCecHigh,
+            5 => Payload::PinHpdLow,
+            6 => Payload::PinHpdHigh,
+            7 => Payload::Pin5vLow,
+            8 => Payload::Pin5vHigh,
+            _ => return Err(()),
+        };
+
+        Ok(Event {
+            timestamp: ev.ts,
+            flags: Flags::from(ev.flags),
+            payload,
+        })
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.payload {
+            Payload::StateChange {
+                physical_address,
+                logical_addresses,
+            } => write!(
+                f,
+                "state change: physical address {:04x}, logical addresses {:#06x}",
+                physical_address, logical_addresses
+            )?,
+            Payload::LostMessages(count) => write!(f, "lost {} messages", count)?,
+            payload => write!(f, "{:?}", payload)?,
+        }
+        write!(f, " [{}]", self.flags)
+    }
+}
@@ -0,0 +1,424 @@
+use bitflags::bitflags;
+use std::convert::TryFrom;
+use std::time::Duration;
+use std::{fmt, io, mem};
+
+use crate::v4l2::cec::{cec_msg, CEC_MAX_MSG_SIZE};
+
+/// Logical address which addresses all devices on the bus
+pub const BROADCAST: u8 = 0xf;
+
+/// Message opcode
+///
+/// Only the opcodes of the common one touch play, standby, routing and power status features are
+/// modeled, others can be sent with [`Message::with_raw_opcode()`].
+#[allow(clippy::unreadable_literal)]
+#[rustfmt::skip]
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Opcode {
+    FeatureAbort                = 0x00,
+    ImageViewOn                 = 0x04,
+    TextViewOn                  = 0x0d,
+    Standby                     = 0x36,
+    UserControlPressed          = 0x44,
+    UserControlReleased         = 0x45,
+    GiveOsdName                 = 0x46,
+    SetOsdName                  = 0x47,
+    RoutingChange               = 0x80,
+    ActiveSource                = 0x82,
+    GivePhysicalAddr            = 0x83,
+    ReportPhysicalAddr          = 0x84,
+    RequestActiveSource         = 0x85,
+    SetStreamPath               = 0x86,
+    DeviceVendorId              = 0x87,
+    GiveDeviceVendorId          = 0x8c,
+    GiveDevicePowerStatus       = 0x8f,
+    ReportPowerStatus           = 0x90,
+    InactiveSource              = 0x9d,
+    CecVersion                  = 0x9e,
+    GetCecVersion               = 0x9f,
+}
+
+impl TryFrom<u8> for Opcode {
+    type Error = ();
+
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        match code {
+            0x00 => Ok(Opcode::FeatureAbort),
+            0x04 => Ok(Opcode::ImageViewOn),
+            0x0d => Ok(Opcode::TextViewOn),
+            0x36 => Ok(Opcode::Standby),
+            0x44 => Ok(Opcode::UserControlPressed),
+            0x45 => Ok(Opcode::UserControlReleased),
+            0x46 => Ok(Opcode::GiveOsdName),
+            0x47 => Ok(Opcode::SetOsdName),
+            0x80 => Ok(Opcode::RoutingChange),
+            0x82 => Ok(Opcode::ActiveSource),
+            0x83 => Ok(Opcode::GivePhysicalAddr),
+            0x84 => Ok(Opcode::ReportPhysicalAddr),
+            0x85 => Ok(Opcode::RequestActiveSource),
+            0x86 => Ok(Opcode::SetStreamPath),
+            0x87 => Ok(Opcode::DeviceVendorId),
+            0x8c => Ok(Opcode::GiveDeviceVendorId),
+            0x8f => Ok(Opcode::GiveDevicePowerStatus),
+            0x90 => Ok(Opcode::ReportPowerStatus),
+            0x9d => Ok(Opcode::InactiveSource),
+            0x9e => Ok(Opcode::CecVersion),
+            0x9f => Ok(Opcode::GetCecVersion),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Power status as reported by [`Opcode::ReportPowerStatus`]
+#[allow(clippy::unreadable_literal)]
+#[rustfmt::skip]
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PowerStatus {
+    On                  = 0,
+    Standby             = 1,
+    /// Transitioning from standby to on
+    ToOn                = 2,
+    /// Transitioning from on to standby
+    ToStandby           = 3,
+}
+
+impl TryFrom<u8> for PowerStatus {
+    type Error = ();
+
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        match code {
+            0 => Ok(PowerStatus::On),
+            1 => Ok(PowerStatus::Standby),
+            2 => Ok(PowerStatus::ToOn),
+            3 => Ok(PowerStatus::ToStandby),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for PowerStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    pub struct TxStatus: u8 {
+        /// The message was acknowledged
+        const OK            = 0x01;
+        /// Another initiator won the arbitration
+        const ARB_LOST      = 0x02;
+        /// The message was not acknowledged
+        const NACK          = 0x04;
+        /// The bus was pulled low unexpectedly
+        const LOW_DRIVE     = 0x08;
+        /// Some other error occurred
+        const ERROR         = 0x10;
+        /// Transmission failed after retrying
+        const MAX_RETRIES   = 0x20;
+        /// Transmission was aborted, e.g. because the adapter was unconfigured
+        const ABORTED       = 0x40;
+        /// The driver did not report a result in time
+        const TIMEOUT       = 0x80;
+    }
+}
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    pub struct RxStatus: u8 {
+        /// The reply was received
+        const OK            = 0x01;
+        /// No reply was received in time
+        const TIMEOUT       = 0x02;
+        /// The destination replied with [`Opcode::FeatureAbort`]
+        const FEATURE_ABORT = 0x04;
+        /// Waiting for the reply was aborted
+        const ABORTED       = 0x08;
+    }
+}
+
+macro_rules! status_impls {
+    ($name:ident) => {
+        impl Default for $name {
+            fn default() -> Self {
+                $name::from(0)
+            }
+        }
+
+        impl From<u8> for $name {
+            fn from(status: u8) -> Self {
+                Self::from_bits_truncate(status)
+            }
+        }
+
+        impl From<$name> for u8 {
+            fn from(status: $name) -> Self {
+                status.bits()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(self, f)
+            }
+        }
+    };
+}
+
+status_impls!(TxStatus);
+status_impls!(RxStatus);
+
+/// CEC message
+///
+/// The first byte holds the initiator and destination logical address, followed by the opcode
+/// and its operands. The initiator is filled in by the kernel when transmitting.
+///
+/// # Example
+///
+/// ```
+/// use v4l::cec::message::{Message, Opcode, PowerStatus};
+///
+/// let msg = Message::give_device_power_status(0);
+/// assert_eq!(msg.destination(), 0);
+/// assert_eq!(msg.opcode(), Some(Opcode::GiveDevicePowerStatus));
+/// assert_eq!(msg.reply, Some(Opcode::ReportPowerStatus as u8));
+///
+/// let reply = Message::new(4, Opcode::ReportPowerStatus, &[PowerStatus::Standby as u8]).unwrap();
+/// assert_eq!(reply.power_status(), Some(PowerStatus::Standby));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Message {
+    /// Raw message bytes, at most 16
+    pub bytes: Vec<u8>,
+    /// Opcode of the reply to wait for when transmitting
+    pub reply: Option<u8>,
+    /// Maximum duration to wait for the reply
+    pub timeout: Duration,
+    /// Sequence number assigned by the kernel when transmitting
+    pub sequence: u32,
+    /// Transmit status, set for transmitted messages
+    pub tx_status: TxStatus,
+    /// Receive status, set for received messages and replies
+    pub rx_status: RxStatus,
+    /// Time of transmission, in nanoseconds of the monotonic clock
+    pub tx_ts: u64,
+    /// Time of reception, in nanoseconds of the monotonic clock
+    pub rx_ts: u64,
+}
+
+impl Message {
+    /// Returns a message
+    ///
+    /// Fails if the destination is no logical address or the operands do not fit.
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - Logical address of the destination, [`BROADCAST`] for all devices
+    /// * `opcode` - Opcode
+    /// * `operands` - Operands of the opcode
+    pub fn new(destination: u8, opcode: Opcode, operands: &[u8]) -> io::Result<Self> {
+        Self::with_raw_opcode(destination, opcode as u8, operands)
+    }
+
+    /// Returns a message with an opcode which is not modeled by [`Opcode`]
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - Logical address of the destination, [`BROADCAST`] for all devices
+    /// * `opcode` - Raw opcode
+    /// * `operands` - Operands of the opcode
+    pub fn with_raw_opcode(destination: u8, opcode: u8, operands: &[u8]) -> io::Result<Self> {
+        if destination > BROADCAST || operands.len() + 2 > CEC_MAX_MSG_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid destination or too many operands",
+            ));
+        }
+
+        let mut bytes = vec![destination, opcode];
+        bytes.extend_from_slice(operands);
+        Ok(Message {
+            bytes,
+            reply: None,
+            timeout: Duration::ZERO,
+            sequence: 0,
+            tx_status: TxStatus::default(),
+            rx_status: RxStatus::default(),
+            tx_ts: 0,
+            rx_ts: 0,
+        })
+    }
+
+    /// Returns a message asking a device for its power status
+    ///
+    /// Transmitting waits for [`Opcode::ReportPowerStatus`], see [`Message::power_status()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - Logical address of the device, e.g. 0 for the TV
+    pub fn give_device_power_status(destination: u8) -> Self {
+        let mut msg = Self::fixed(destination, Opcode::GiveDevicePowerStatus, &[]);
+        msg.reply = Some(Opcode::ReportPowerStatus as u8);
+        msg.timeout = Duration::from_secs(1);
+        msg
+    }
+
+    /// Returns a message reporting the power status of this device
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - Logical address of the device which asked
+    /// * `status` - Power status
+    pub fn report_power_status(destination: u8, status: PowerStatus) -> Self {
+        Self::fixed(destination, Opcode::ReportPowerStatus, &[status as u8])
+    }
+
+    /// Returns a message announcing this device as the active source to all devices
+    ///
+    /// # Arguments
+    ///
+    /// * `physical_address` - Physical address of this device
+    pub fn active_source(physical_address: u16) -> Self {
+        Self::fixed(
+            BROADCAST,
+            Opcode::ActiveSource,
+            &physical_address.to_be_bytes(),
+        )
+    }
+
+    /// Returns a message asking the active source to announce itself
+    pub fn request_active_source() -> Self {
+        Self::fixed(BROADCAST, Opcode::RequestActiveSource, &[])
+    }
+
+    /// Returns a message turning a display on
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - Logical address of the display, e.g. 0 for the TV
+    pub fn image_view_on(destination: u8) -> Self {
+        Self::fixed(destination, Opcode::ImageViewOn, &[])
+    }
+
+    /// Returns a message switching a device to standby
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - Logical address of the device, [`BROADCAST`] for all devices
+    pub fn standby(destination: u8) -> Self {
+        Self::fixed(destination, Opcode::Standby, &[])
+    }
+
+    /// Returns the logical address of the initiator
+    pub fn initiator(&self) -> u8 {
+        self.bytes.first().map_or(BROADCAST, |header| header >> 4)
+    }
+
+    /// Returns the logical address of the destination
+    pub fn destination(&self) -> u8 {
+        self.bytes.first().map_or(BROADCAST, |header| header & 0xf)
+    }
+
+    /// Returns whether the message is addressed to all devices
+    pub fn is_broadcast(&self) -> bool {
+        self.destination() == BROADCAST
+    }
+
+    /// Returns the raw opcode, poll messages have none
+    pub fn raw_opcode(&self) -> Option<u8> {
+        self.bytes.get(1).copied()
+    }
+
+    /// Returns the opcode, if it is modeled by [`Opcode`]
+    pub fn opcode(&self) -> Option<Opcode> {
+        self.raw_opcode()
+            .and_then(|code| Opcode::try_from(code).ok())
+    }
+
+    /// Returns the operands
+    pub fn operands(&self) -> &[u8] {
+        self.bytes.get(2..).unwrap_or(&[])
+    }
+
+    /// Returns the power status carried by [`Opcode::ReportPowerStatus`]
+    pub fn power_status(&self) -> Option<PowerStatus> {
+        match (self.opcode(), self.operands()) {
+            (Some(Opcode::ReportPowerStatus), [status, ..]) => PowerStatus::try_from(*status).ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the physical address carried by [`Opcode::ActiveSource`],
+    /// [`Opcode::ReportPhysicalAddr`] and [`Opcode::SetStreamPath`]
+    pub fn physical_address(&self) -> Option<u16> {
+        match (self.opcode(), self.operands()) {
+            (
+                Some(Opcode::ActiveSource | Opcode::ReportPhysicalAddr | Opcode::SetStreamPath),
+                [hi, lo, ..],
+            ) => Some(u16::from_be_bytes([*hi, *lo])),
+            _ => None,
+        }
+    }
+
+    /// Returns a message whose operands are known to fit
+    fn fixed(destination: u8, opcode: Opcode, operands: &[u8]) -> Self {
+        Self::new(destination, opcode, operands).unwrap()
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:x} -> {:x}", self.initiator(), self.destination())?;
+        match (self.opcode(), self.raw_opcode()) {
+            (Some(opcode), _) => write!(f, ": {}", opcode)?,
+            (None, Some(code)) => write!(f, ": {:#04x}", code)?,
+            (None, None) => write!(f, ": poll")?,
+        }
+        for operand in self.operands() {
+            write!(f, " {:02x}", operand)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<cec_msg> for Message {
+    fn from(msg: cec_msg) -> Self {
+        let len = (msg.len as usize).min(CEC_MAX_MSG_SIZE);
+        Message {
+            bytes: msg.msg[..len].to_vec(),
+            reply: if msg.reply == 0 {
+                None
+            } else {
+                Some(msg.reply)
+            },
+            timeout: Duration::from_millis(msg.timeout as u64),
+            sequence: msg.sequence,
+            tx_status: TxStatus::from(msg.tx_status),
+            rx_status: RxStatus::from(msg.rx_status),
+            tx_ts: msg.tx_ts,
+            rx_ts: msg.rx_ts,
+        }
+    }
+}
+
+impl From<&Message> for cec_msg {
+    fn from(msg: &Message) -> Self {
+        let mut raw: cec_msg = unsafe { mem::zeroed() };
+        let len = msg.bytes.len().min(CEC_MAX_MSG_SIZE);
+        raw.msg[..len].copy_from_slice(&msg.bytes[..len]);
+        raw.len = len as u32;
+        raw.reply = msg.reply.unwrap_or(0);
+        raw.timeout = msg.timeout.as_millis() as u32;
+        raw
+    }
+}
@@ -0,0 +1,372 @@
+//! HDMI CEC adapters
+//!
+//! HDMI capture and output hardware often comes with a CEC adapter (`/dev/cecX`), which talks to
+//! the other devices of the HDMI bus, e.g. to turn on the TV or to announce the active source.
+//! The adapter has to be configured with logical addresses before messages can be transmitted,
+//! which is usually done by `cec-ctl` or the driver itself.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use v4l::cec::message::Message;
+//! use v4l::cec::{Device, Follower, Initiator};
+//!
+//! let dev = Device::new(0).expect("Failed to open CEC adapter");
+//! dev.set_mode(Initiator::Initiator, Follower::Follower)
+//!     .expect("Failed to set mode");
+//!
+//! let reply = dev
+//!     .transmit(&Message::give_device_power_status(0))
+//!     .expect("Failed to transmit");
+//! println!("TV power status: {:?}", reply.power_status());
+//!
+//! let msg = dev.receive(None).expect("Failed to receive");
+//! println!("{}", msg);
+//! ```
+
+pub mod event;
+pub use event::Event;
+pub mod message;
+pub use message::Message;
+
+use bitflags::bitflags;
+use std::convert::TryFrom;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use std::{fmt, io, mem};
+
+use crate::device::Handle;
+use crate::util;
+use crate::v4l2;
+use crate::v4l2::cec::*;
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    pub struct Flags: u32 {
+        /// The physical address is set by userspace
+        const PHYS_ADDR         = 0x00000001;
+        /// The logical addresses are set by userspace
+        const LOG_ADDRS         = 0x00000002;
+        /// Messages can be transmitted
+        const TRANSMIT          = 0x00000004;
+        /// Messages handled by the kernel are passed through to followers as well
+        const PASSTHROUGH       = 0x00000008;
+        /// Remote control key presses are reported through an input device
+        const RC                = 0x00000010;
+        /// All messages on the bus can be monitored
+        const MONITOR_ALL       = 0x00000020;
+        /// The hotplug detect pin has to be high for the adapter to work
+        const NEEDS_HPD         = 0x00000040;
+        /// The CEC pin can be monitored
+        const MONITOR_PIN       = 0x00000080;
+        /// The connector the adapter belongs to is known
+        const CONNECTOR_INFO    = 0x00000100;
+    }
+}
+
+impl From<u32> for Flags {
+    fn from(flags: u32) -> Self {
+        Self::from_bits_truncate(flags)
+    }
+}
+
+impl From<Flags> for u32 {
+    fn from(flags: Flags) -> Self {
+        flags.bits()
+    }
+}
+
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Adapter capabilities
+#[derive(Debug, Clone)]
+pub struct Capabilities {
+    /// Name of the driver
+    pub driver: String,
+    /// Name of the adapter
+    pub name: String,
+    /// Number of logical addresses the adapter can claim
+    pub available_log_addrs: u32,
+    /// Capability flags
+    pub capabilities: Flags,
+    /// Version of the CEC framework, encoded as (major << 16) | (minor << 8) | patch
+    pub version: u32,
+}
+
+impl From<cec_caps> for Capabilities {
+    fn from(caps: cec_caps) -> Self {
+        Capabilities {
+            driver: util::string_lossy(util::c_chars(&caps.driver)),
+            name: util::string_lossy(util::c_chars(&caps.name)),
+            available_log_addrs: caps.available_log_addrs,
+            capabilities: Flags::from(caps.capabilities),
+            version: caps.version,
+        }
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Driver       : {}", self.driver)?;
+        writeln!(f, "Name         : {}", self.name)?;
+        writeln!(f, "Log. Addrs   : {}", self.available_log_addrs)?;
+        writeln!(
+            f,
+            "Version      : {}.{}.{}",
+            (self.version >> 16) & 0xff,
+            (self.version >> 8) & 0xff,
+            self.version & 0xff
+        )?;
+        writeln!(f, "Capabilities : {}", self.capabilities)?;
+        Ok(())
+    }
+}
+
+/// Whether this file handle may transmit messages
+#[allow(clippy::unreadable_literal)]
+#[rustfmt::skip]
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Initiator {
+    /// Transmitting is not allowed
+    None            = 0,
+    /// Transmitting is allowed
+    Initiator       = 1,
+    /// Only this file handle may transmit
+    Exclusive       = 2,
+}
+
+/// Which received messages this file handle gets to see
+#[allow(clippy::unreadable_literal)]
+#[rustfmt::skip]
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Follower {
+    /// Only replies to transmitted messages
+    None                = 0x00,
+    /// Messages addressed to this adapter
+    Follower            = 0x10,
+    /// Messages addressed to this adapter, no other file handle gets them
+    Exclusive           = 0x20,
+    /// Like [`Follower::Exclusive`], including the messages the kernel would handle itself
+    ExclusivePassthru   = 0x30,
+    /// CEC pin changes, see [`event::Payload::PinCecLow`]
+    MonitorPin          = 0xd0,
+    /// All messages addressed to or sent by this adapter
+    Monitor             = 0xe0,
+    /// All messages on the bus
+    MonitorAll          = 0xf0,
+}
+
+impl TryFrom<u32> for Initiator {
+    type Error = ();
+
+    fn try_from(mode: u32) -> Result<Self, Self::Error> {
+        match mode & 0xf {
+            0 => Ok(Initiator::None),
+            1 => Ok(Initiator::Initiator),
+            2 => Ok(Initiator::Exclusive),
+            _ => Err(()),
+        }
+    }
+}
+
+impl TryFrom<u32> for Follower {
+    type Error = ();
+
+    fn try_from(mode: u32) -> Result<Self, Self::Error> {
+        match mode & 0xf0 {
+            0x00 => Ok(Follower::None),
+            0x10 => Ok(Follower::Follower),
+            0x20 => Ok(Follower::Exclusive),
+            0x30 => Ok(Follower::ExclusivePassthru),
+            0xd0 => Ok(Follower::MonitorPin),
+            0xe0 => Ok(Follower::Monitor),
+            0xf0 => Ok(Follower::MonitorAll),
+            _ => Err(()),
+        }
+    }
+}
+
+/// CEC adapter
+pub struct Device {
+    /// Raw handle
+    handle: Arc<Handle>,
+}
+
+impl Device {
+    /// Returns a CEC adapter by index
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index (0: first, 1: second, ..)
+    pub fn new(index: usize) -> io::Result<Self> {
+        Self::with_path(format!("{}{}", "/dev/cec", index))
+    }
+
+    /// Returns a CEC adapter by path
+    ///
+    /// The adapter is opened in blocking mode, so [`Device::transmit()`] waits for the result of
+    /// the transmission.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path (e.g. "/dev/cec0")
+    pub fn with_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let fd = v4l2::open(&path, libc::O_RDWR)?;
+
+        Ok(Device {
+            handle: Arc::new(Handle::new(fd)),
+        })
+    }
+
+    /// Returns the raw device handle
+    ///
+    /// Poll it for `POLLIN` to wait for messages and for `POLLPRI` to wait for events.
+    pub fn handle(&self) -> Arc<Handle> {
+        self.handle.clone()
+    }
+
+    /// Returns the capabilities of the adapter
+    pub fn query_caps(&self) -> io::Result<Capabilities> {
+        unsafe {
+            let mut caps: cec_caps = mem::zeroed();
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::CEC_ADAP_G_CAPS,
+                &mut caps as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            Ok(Capabilities::from(caps))
+        }
+    }
+
+    /// Returns the physical address, 0xffff if there is none
+    pub fn physical_address(&self) -> io::Result<u16> {
+        let mut addr: u16 = 0;
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::CEC_ADAP_G_PHYS_ADDR,
+                &mut addr as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+        Ok(addr)
+    }
+
+    /// Returns the mode of this file handle
+    pub fn mode(&self) -> io::Result<(Initiator, Follower)> {
+        let mut mode: u32 = 0;
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::CEC_G_MODE,
+                &mut mode as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        match (Initiator::try_from(mode), Follower::try_from(mode)) {
+            (Ok(initiator), Ok(follower)) => Ok((initiator, follower)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown mode {:#x}", mode),
+            )),
+        }
+    }
+
+    /// Sets the mode of this file handle
+    ///
+    /// Receiving messages other than replies requires a follower mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `initiator` - Whether messages may be transmitted
+    /// * `follower` - Which messages are received
+    pub fn set_mode(&self, initiator: Initiator, follower: Follower) -> io::Result<()> {
+        let mut mode = initiator as u32 | follower as u32;
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::CEC_S_MODE,
+                &mut mode as *mut _ as *mut std::os::raw::c_void,
+            )
+        }
+    }
+
+    /// Transmits a message and returns it along with its transmit status
+    ///
+    /// If the message expects a reply, this waits for it and returns the reply instead, check
+    /// [`Message::rx_status`] to see whether it arrived. Fails with
+    /// [`io::ErrorKind::Other`] if the message was not acknowledged.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - Message to transmit
+    pub fn transmit(&self, msg: &Message) -> io::Result<Message> {
+        let mut raw = cec_msg::from(msg);
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::CEC_TRANSMIT,
+                &mut raw as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        let sent = Message::from(raw);
+        if !sent.tx_status.contains(message::TxStatus::OK) {
+            return Err(io::Error::other(format!(
+                "transmit failed: {}",
+                sent.tx_status
+            )));
+        }
+        Ok(sent)
+    }
+
+    /// Waits for a received message
+    ///
+    /// Fails with [`io::ErrorKind::TimedOut`] if no message arrived in time.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Maximum duration to wait for, `None` waits indefinitely
+    pub fn receive(&self, timeout: Option<Duration>) -> io::Result<Message> {
+        let mut raw: cec_msg = unsafe { mem::zeroed() };
+        // a zero timeout waits indefinitely, so round up to at least a millisecond
+        raw.timeout = timeout.map_or(0, |timeout| (timeout.as_millis() as u32).max(1));
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::CEC_RECEIVE,
+                &mut raw as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+        Ok(Message::from(raw))
+    }
+
+    /// Waits for an adapter event
+    ///
+    /// The state of the adapter is reported as first event, with [`event::Flags::INITIAL_STATE`]
+    /// set.
+    pub fn dequeue_event(&self) -> io::Result<Event> {
+        let mut raw: cec_event = unsafe { mem::zeroed() };
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::CEC_DQEVENT,
+                &mut raw as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        Event::try_from(raw).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown event {}", raw.event),
+            )
+        })
+    }
+}
@@ -0,0 +1,375 @@
+//! Lightweight compliance checks
+//!
+//! The checks in this module exercise a device the way applications do and report any behavior
+//! which violates the V4L2 specification, e.g. wrong error codes, inconsistent capabilities or
+//! formats which change when being set again. They are no replacement for `v4l2-compliance`, but
+//! can easily be run from the test suite of a custom driver.
+//!
+//! The checks modify the device state, e.g. by allocating buffers, and therefore must not be run
+//! while another application is streaming from the device.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use v4l::compliance;
+//! use v4l::Device;
+//!
+//! let dev = Device::with_path("/dev/video0").expect("Failed to open device");
+//! let report = compliance::check(&dev).expect("Failed to run checks");
+//! assert!(report.is_compliant(), "{}", report);
+//! ```
+
+use std::{fmt, io, mem};
+
+use crate::buffer::{self, Flags as BufferFlags};
+use crate::capability::Flags;
+use crate::device::Device;
+use crate::format::Format;
+use crate::memory::Memory;
+use crate::v4l2;
+use crate::v4l_sys::*;
+use crate::video::{Capture, Output};
+
+/// Violation of the specification found by a check
+#[derive(Debug, Clone)]
+pub struct Violation {
+    /// Name of the check which failed
+    pub check: &'static str,
+    /// Description of the violation
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.check, self.message)
+    }
+}
+
+/// Results of all checks
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    /// Checks which were run
+    pub checks: Vec<&'static str>,
+    /// Violations found by the checks
+    pub violations: Vec<Violation>,
+}
+
+impl Report {
+    /// Returns whether no violations were found
+    pub fn is_compliant(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    fn run(&mut self, check: &'static str, f: impl FnOnce(&mut Vec<String>) -> io::Result<()>) {
+        let mut messages = Vec::new();
+        if let Err(e) = f(&mut messages) {
+            messages.push(format!("unexpected error: {}", e));
+        }
+
+        self.checks.push(check);
+        self.violations.extend(
+            messages
+                .into_iter()
+                .map(|message| Violation { check, message }),
+        );
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} checks, {} violations",
+            self.checks.len(),
+            self.violations.len()
+        )?;
+        for violation in &self.violations {
+            writeln!(f, " * {}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs all checks against a device
+///
+/// Only failing to query the capabilities is reported as an error, errors during the individual
+/// checks are reported as violations.
+///
+/// # Arguments
+///
+/// * `dev` - Device to check
+pub fn check(dev: &Device) -> io::Result<Report> {
+    let caps = dev.query_caps()?.capabilities;
+    let mut report = Report::default();
+
+    report.run("capabilities", |v| check_capabilities(dev, v));
+    report.run("unknown ioctl", |v| check_unknown_ioctl(dev, v));
+    report.run("invalid control", |v| check_invalid_control(dev, v));
+
+    let queues = [
+        (buffer::Type::VideoCapture, Flags::VIDEO_CAPTURE),
+        (buffer::Type::VideoOutput, Flags::VIDEO_OUTPUT),
+    ];
+    for (buf_type, flag) in queues {
+        if !caps.intersects(flag | Flags::VIDEO_M2M) {
+            continue;
+        }
+
+        report.run("format enumeration", |v| {
+            check_enum_formats(dev, buf_type, v)
+        });
+        report.run("format round-trip", |v| {
+            check_format_round_trip(dev, buf_type, v)
+        });
+        if caps.contains(Flags::STREAMING) {
+            report.run("buffer flags", |v| check_buffers(dev, buf_type, v));
+        }
+    }
+
+    Ok(report)
+}
+
+/// Returns the errno of an ioctl result, if it failed
+fn errno(ret: &io::Result<()>) -> Option<i32> {
+    ret.as_ref().err().and_then(io::Error::raw_os_error)
+}
+
+fn check_capabilities(dev: &Device, v: &mut Vec<String>) -> io::Result<()> {
+    let caps = dev.query_caps()?;
+
+    for (name, value) in [
+        ("driver", &caps.driver),
+        ("card", &caps.card),
+        ("bus", &caps.bus),
+    ] {
+        if value.is_empty() {
+            v.push(format!("{} name is empty", name));
+        }
+    }
+
+    let flags = caps.capabilities;
+    if flags.contains(Flags::DEVICE_CAPS) {
+        v.push("DEVICE_CAPS must only be set in the capabilities of the driver".to_string());
+    }
+    let io_types = Flags::VIDEO_CAPTURE
+        | Flags::VIDEO_OUTPUT
+        | Flags::VIDEO_OVERLAY
+        | Flags::VBI_CAPTURE
+        | Flags::VBI_OUTPUT
+        | Flags::SLICED_VBI_CAPTURE
+        | Flags::SLICED_VBI_OUTPUT
+        | Flags::VIDEO_OUTPUT_OVERLAY
+        | Flags::VIDEO_CAPTURE_MPLANE
+        | Flags::VIDEO_OUTPUT_MPLANE
+        | Flags::VIDEO_M2M
+        | Flags::VIDEO_M2M_MPLANE
+        | Flags::SDR_CAPTURE
+        | Flags::SDR_OUTPUT
+        | Flags::META_CAPTURE
+        | Flags::META_OUTPUT
+        | Flags::TOUCH
+        | Flags::RDS_CAPTURE
+        | Flags::RDS_OUTPUT
+        | Flags::TUNER
+        | Flags::MODULATOR
+        | Flags::RADIO;
+    if !flags.intersects(io_types) {
+        v.push(format!("no device type in capabilities {}", flags));
+    }
+    if flags.intersects(Flags::VIDEO_CAPTURE | Flags::VIDEO_OUTPUT | Flags::VIDEO_M2M)
+        && !flags.intersects(Flags::STREAMING | Flags::READ_WRITE)
+    {
+        v.push("video device supports neither streaming nor read/write I/O".to_string());
+    }
+
+    Ok(())
+}
+
+fn check_unknown_ioctl(dev: &Device, v: &mut Vec<String>) -> io::Result<()> {
+    // the highest number is not assigned to any ioctl
+    const VIDIOC_UNKNOWN: v4l2::vidioc::_IOC_TYPE = v4l2::vidioc::iowr::<u32>(b'V', 255);
+
+    let mut arg = 0u32;
+    let ret = unsafe {
+        v4l2::ioctl(
+            dev.handle().fd(),
+            VIDIOC_UNKNOWN,
+            &mut arg as *mut _ as *mut std::os::raw::c_void,
+        )
+    };
+    if errno(&ret) != Some(libc::ENOTTY) {
+        v.push(format!(
+            "unknown ioctl returned {:?} instead of ENOTTY",
+            ret
+        ));
+    }
+
+    Ok(())
+}
+
+fn check_invalid_control(dev: &Device, v: &mut Vec<String>) -> io::Result<()> {
+    let ret = dev.query_control(0).map(|_| ());
+    if errno(&ret) != Some(libc::EINVAL) {
+        v.push(format!(
+            "querying control 0 returned {:?} instead of EINVAL",
+            ret
+        ));
+    }
+
+    Ok(())
+}
+
+fn check_enum_formats(dev: &Device, buf_type: buffer::Type, v: &mut Vec<String>) -> io::Result<()> {
+    let mut v4l2_fmt = v4l2_fmtdesc {
+        type_: buf_type as u32,
+        ..unsafe { mem::zeroed() }
+    };
+    let ret = loop {
+        let ret = unsafe {
+            v4l2::ioctl(
+                dev.handle().fd(),
+                v4l2::vidioc::VIDIOC_ENUM_FMT,
+                &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
+            )
+        };
+        if ret.is_err() || v4l2_fmt.index >= 1024 {
+            break ret;
+        }
+        v4l2_fmt.index += 1;
+    };
+
+    if v4l2_fmt.index == 0 {
+        v.push(format!("no {:?} formats", buf_type));
+    } else if errno(&ret) != Some(libc::EINVAL) {
+        v.push(format!(
+            "enumerating past the last {:?} format returned {:?} instead of EINVAL",
+            buf_type, ret
+        ));
+    }
+
+    Ok(())
+}
+
+fn check_format_round_trip(
+    dev: &Device,
+    buf_type: buffer::Type,
+    v: &mut Vec<String>,
+) -> io::Result<()> {
+    let (get, set): (fn(&Device) -> io::Result<Format>, _) = match buf_type {
+        buffer::Type::VideoOutput => (
+            <Device as Output>::format,
+            <Device as Output>::set_format as fn(&Device, &Format) -> io::Result<Format>,
+        ),
+        _ => (
+            <Device as Capture>::format,
+            <Device as Capture>::set_format as fn(&Device, &Format) -> io::Result<Format>,
+        ),
+    };
+
+    let current = get(dev)?;
+    let applied = set(dev, &current)?;
+    let stable = |a: &Format, b: &Format| {
+        a.width == b.width
+            && a.height == b.height
+            && a.fourcc == b.fourcc
+            && a.stride == b.stride
+            && a.size == b.size
+            && a.field_order as u32 == b.field_order as u32
+    };
+    if !stable(&current, &applied) {
+        v.push(format!(
+            "setting the current {:?} format changed it from {}x{} {} to {}x{} {}",
+            buf_type,
+            current.width,
+            current.height,
+            current.fourcc,
+            applied.width,
+            applied.height,
+            applied.fourcc
+        ));
+    }
+    if current.size == 0 {
+        v.push(format!("{:?} format has an image size of 0", buf_type));
+    }
+
+    Ok(())
+}
+
+fn check_buffers(dev: &Device, buf_type: buffer::Type, v: &mut Vec<String>) -> io::Result<()> {
+    let reqbufs = |count| {
+        let mut v4l2_reqbufs = v4l2_requestbuffers {
+            count,
+            type_: buf_type as u32,
+            memory: Memory::Mmap as u32,
+            ..unsafe { mem::zeroed() }
+        };
+        unsafe {
+            v4l2::ioctl(
+                dev.handle().fd(),
+                v4l2::vidioc::VIDIOC_REQBUFS,
+                &mut v4l2_reqbufs as *mut _ as *mut std::os::raw::c_void,
+            )
+        }
+        .map(|_| v4l2_reqbufs.count)
+    };
+    let querybuf = |index| {
+        let mut v4l2_buf = v4l2_buffer {
+            index,
+            type_: buf_type as u32,
+            memory: Memory::Mmap as u32,
+            ..unsafe { mem::zeroed() }
+        };
+        unsafe {
+            v4l2::ioctl(
+                dev.handle().fd(),
+                v4l2::vidioc::VIDIOC_QUERYBUF,
+                &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
+            )
+        }
+        .map(|_| v4l2_buf)
+    };
+
+    let count = reqbufs(2)?;
+    if count == 0 {
+        v.push("REQBUFS allocated no buffers".to_string());
+    }
+
+    let result = (|| {
+        for index in 0..count {
+            let buf = querybuf(index)?;
+            let flags = BufferFlags::from(buf.flags);
+            if buf.index != index || buf.type_ != buf_type as u32 {
+                v.push(format!("QUERYBUF {} returned the wrong buffer", index));
+            }
+            if buf.length == 0 {
+                v.push(format!("buffer {} has a length of 0", index));
+            }
+            if flags.intersects(BufferFlags::QUEUED | BufferFlags::DONE) {
+                v.push(format!("new buffer {} has flags {}", index, flags));
+            }
+            let timestamp = flags & BufferFlags::TIMESTAMP_MASK;
+            if timestamp != BufferFlags::TIMESTAMP_MONOTONIC
+                && timestamp != BufferFlags::TIMESTAMP_COPY
+            {
+                v.push(format!(
+                    "buffer {} has an unknown timestamp type {}",
+                    index, timestamp
+                ));
+            }
+        }
+
+        let ret = querybuf(count).map(|_| ());
+        if errno(&ret) != Some(libc::EINVAL) {
+            v.push(format!(
+                "QUERYBUF past the last buffer returned {:?} instead of EINVAL",
+                ret
+            ));
+        }
+
+        Ok(())
+    })();
+
+    // free the buffers in any case
+    reqbufs(0)?;
+    result
+}
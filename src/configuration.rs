@@ -0,0 +1,218 @@
+use std::io;
+
+use crate::buffer::Type;
+use crate::control::Control;
+use crate::device::Device;
+use crate::feature::Features;
+use crate::format::Format;
+use crate::fraction::Fraction;
+use crate::selection::{self, Rect};
+use crate::video::{capture, output, Capture, Output};
+
+/// Configuration applied to a device as a whole
+///
+/// Created by [`Device::configure()`]. Nothing is applied until [`Configuration::apply()`] is
+/// called. The steps are applied in order: format, frame interval, selections and finally
+/// controls. If one of them fails, all previous steps are rolled back, so the device is never
+/// left half-configured.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::control::{Control, Value};
+/// use v4l::selection::{Rect, Target};
+/// use v4l::{Device, Format, FourCC, Fraction};
+///
+/// let dev = Device::new(0).expect("Failed to open device");
+/// dev.configure()
+///     .format(Format::new(1280, 720, FourCC::new(b"YUYV")))
+///     .interval(Fraction::new(1, 30))
+///     .selection(Target::Crop, Rect::new(0, 0, 1280, 720))
+///     .control(Control {
+///         id: 0x00980900, // brightness
+///         value: Value::Integer(128),
+///     })
+///     .apply()
+///     .expect("Failed to configure device");
+/// ```
+pub struct Configuration<'a> {
+    dev: &'a Device,
+    buf_type: Type,
+    format: Option<Format>,
+    interval: Option<Fraction>,
+    selections: Vec<(selection::Target, Rect)>,
+    controls: Vec<Control>,
+}
+
+/// Previous state of an applied step
+enum Undo {
+    Format(Format),
+    Interval(Fraction),
+    Selection(selection::Target, Rect),
+    Controls(Vec<Control>),
+}
+
+impl<'a> Configuration<'a> {
+    pub(crate) fn new(dev: &'a Device) -> Self {
+        Configuration {
+            dev,
+            buf_type: Type::VideoCapture,
+            format: None,
+            interval: None,
+            selections: Vec::new(),
+            controls: Vec::new(),
+        }
+    }
+
+    /// Sets the queue to configure, [`Type::VideoCapture`] by default
+    ///
+    /// Only [`Type::VideoCapture`] and [`Type::VideoOutput`] are supported.
+    pub fn buf_type(mut self, buf_type: Type) -> Self {
+        self.buf_type = buf_type;
+        self
+    }
+
+    /// Sets the format
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Sets the frame interval
+    pub fn interval(mut self, interval: Fraction) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Sets a selection rectangle, e.g. the cropping area
+    pub fn selection(mut self, target: selection::Target, rect: Rect) -> Self {
+        self.selections.push((target, rect));
+        self
+    }
+
+    /// Sets a control value
+    pub fn control(mut self, ctrl: Control) -> Self {
+        self.controls.push(ctrl);
+        self
+    }
+
+    /// Applies the configuration, rolling back all changes on failure
+    ///
+    /// The error of the failed step is returned. Rolling back is done on a best effort basis,
+    /// since the device may refuse to restore its previous state as well.
+    pub fn apply(self) -> io::Result<()> {
+        match self.buf_type {
+            Type::VideoCapture | Type::VideoOutput => {}
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "only single-planar video capture and output queues are supported",
+                ))
+            }
+        }
+
+        if !self.selections.is_empty() && !self.dev.probe_features()?.contains(Features::SELECTION)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "device does not support selections",
+            ));
+        }
+
+        let mut undo = Vec::new();
+        if let Err(e) = self.apply_steps(&mut undo) {
+            for step in undo.into_iter().rev() {
+                let _ = self.revert(step);
+            }
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    fn apply_steps(&self, undo: &mut Vec<Undo>) -> io::Result<()> {
+        if let Some(format) = &self.format {
+            let previous = self.get_format()?;
+            self.set_format(format)?;
+            undo.push(Undo::Format(previous));
+        }
+
+        if let Some(interval) = self.interval {
+            let previous = self.get_interval()?;
+            self.set_interval(interval)?;
+            undo.push(Undo::Interval(previous));
+        }
+
+        for (target, rect) in &self.selections {
+            let previous = self.dev.selection(self.buf_type, *target)?;
+            self.dev
+                .set_selection(self.buf_type, *target, *rect, selection::Flags::empty())?;
+            undo.push(Undo::Selection(*target, previous));
+        }
+
+        // Controls can only be set atomically within one class.
+        let mut classes: Vec<u32> = self.controls.iter().map(|c| c.id & 0xFFFF0000).collect();
+        classes.sort_unstable();
+        classes.dedup();
+        for class in classes {
+            let ctrls: Vec<Control> = self
+                .controls
+                .iter()
+                .filter(|c| c.id & 0xFFFF0000 == class)
+                .cloned()
+                .collect();
+            // Write-only controls (e.g. buttons) cannot be restored, skip them.
+            let previous = ctrls
+                .iter()
+                .filter_map(|c| self.dev.control_from_id(c.id).ok())
+                .collect();
+            self.dev.set_controls(ctrls)?;
+            undo.push(Undo::Controls(previous));
+        }
+
+        Ok(())
+    }
+
+    fn revert(&self, step: Undo) -> io::Result<()> {
+        match step {
+            Undo::Format(format) => self.set_format(&format).map(|_| ()),
+            Undo::Interval(interval) => self.set_interval(interval),
+            Undo::Selection(target, rect) => self
+                .dev
+                .set_selection(self.buf_type, target, rect, selection::Flags::empty())
+                .map(|_| ()),
+            Undo::Controls(ctrls) if ctrls.is_empty() => Ok(()),
+            Undo::Controls(ctrls) => self.dev.set_controls(ctrls),
+        }
+    }
+
+    fn get_format(&self) -> io::Result<Format> {
+        match self.buf_type {
+            Type::VideoOutput => Output::format(self.dev),
+            _ => Capture::format(self.dev),
+        }
+    }
+
+    fn set_format(&self, format: &Format) -> io::Result<Format> {
+        match self.buf_type {
+            Type::VideoOutput => Output::set_format(self.dev, format),
+            _ => Capture::set_format(self.dev, format),
+        }
+    }
+
+    fn get_interval(&self) -> io::Result<Fraction> {
+        match self.buf_type {
+            Type::VideoOutput => Ok(Output::params(self.dev)?.interval),
+            _ => Ok(Capture::params(self.dev)?.interval),
+        }
+    }
+
+    fn set_interval(&self, interval: Fraction) -> io::Result<()> {
+        match self.buf_type {
+            Type::VideoOutput => {
+                Output::set_params(self.dev, &output::Parameters::new(interval)).map(|_| ())
+            }
+            _ => Capture::set_params(self.dev, &capture::Parameters::new(interval)).map(|_| ()),
+        }
+    }
+}
@@ -1,4 +1,6 @@
+use std::ffi::OsString;
 use std::fs;
+use std::os::unix::ffi::OsStringExt;
 use std::path::{Path, PathBuf};
 
 /// Returns a list of devices currently known to the system
@@ -23,9 +25,7 @@ pub fn enum_devices() -> Vec<Node> {
             };
 
             let file_name = dentry.file_name();
-            let file_name = file_name.to_str().unwrap();
-
-            if file_name.starts_with("video") {
+            if file_name.to_string_lossy().starts_with("video") {
                 let node = Node::new(dentry.path());
                 devices.push(node);
             }
@@ -72,7 +72,7 @@ impl Node {
         let file_name = self.path.file_name().unwrap();
 
         let mut index_str = String::new();
-        for c in file_name.to_str().unwrap().chars() {
+        for c in file_name.to_string_lossy().chars() {
             if !c.is_ascii_digit() {
                 continue;
             }
@@ -94,4 +94,17 @@ impl Node {
             Err(_) => None,
         }
     }
+
+    /// Returns name of the device exactly as found in its sysfs entry
+    ///
+    /// Unlike [`Self::name()`], names which are not valid UTF-8 are returned as well.
+    pub fn name_os(&self) -> Option<OsString> {
+        let index = self.index();
+        let path = format!("{}{}{}", "/sys/class/video4linux/video", index, "/name");
+        let mut name = fs::read(path).ok()?;
+        while name.last().is_some_and(u8::is_ascii_whitespace) {
+            name.pop();
+        }
+        Some(OsString::from_vec(name))
+    }
 }
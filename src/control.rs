@@ -1,7 +1,9 @@
 use bitflags::bitflags;
 use std::convert::{TryFrom, TryInto};
-use std::{ffi, fmt, mem, str};
+use std::ffi::OsStr;
+use std::{fmt, mem};
 
+use crate::util;
 use crate::v4l_sys::*;
 
 /// Control data type
@@ -9,6 +11,7 @@ use crate::v4l_sys::*;
 #[rustfmt::skip]
 #[repr(u32)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Type {
     Integer         = 1,
     Boolean         = 2,
@@ -65,6 +68,7 @@ impl fmt::Display for Type {
 
 bitflags! {
     #[allow(clippy::unreadable_literal)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Flags: u32 {
         const DISABLED              = 0x0001;
         const GRABBED               = 0x0002;
@@ -101,7 +105,8 @@ impl fmt::Display for Flags {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Device control menu item
 pub enum MenuItem {
     Name(String),
@@ -128,12 +133,9 @@ impl TryFrom<(Type, v4l2_querymenu)> for MenuItem {
     fn try_from(item: (Type, v4l2_querymenu)) -> Result<Self, Self::Error> {
         unsafe {
             match item.0 {
-                Type::Menu => Ok(MenuItem::Name(
-                    str::from_utf8(&item.1.__bindgen_anon_1.name)
-                        .unwrap()
-                        .trim_matches(char::from(0))
-                        .to_string(),
-                )),
+                Type::Menu => Ok(MenuItem::Name(util::string_lossy(
+                    &item.1.__bindgen_anon_1.name,
+                ))),
                 Type::IntegerMenu => Ok(MenuItem::Value(item.1.__bindgen_anon_1.value)),
                 _ => Err(()),
             }
@@ -141,7 +143,60 @@ impl TryFrom<(Type, v4l2_querymenu)> for MenuItem {
     }
 }
 
-#[derive(Debug)]
+/// First ID of the legacy range of driver-private controls (`V4L2_CID_PRIVATE_BASE`)
+pub const PRIVATE_BASE: u32 = 0x0800_0000;
+
+/// Returns whether a control is specific to a driver rather than defined by the kernel API
+///
+/// This covers the legacy range starting at [`PRIVATE_BASE`] as well as the driver ranges within
+/// each class, which start 0x1000 after the first standard control of the class.
+///
+/// # Arguments
+///
+/// * `id` - Control identifier
+///
+/// # Example
+///
+/// ```
+/// use v4l::control;
+///
+/// // V4L2_CID_BRIGHTNESS
+/// assert!(!control::is_driver_specific(0x00980900));
+/// // first control of the MEYE driver range in the user class
+/// assert!(control::is_driver_specific(0x00981900));
+/// assert!(control::is_driver_specific(control::PRIVATE_BASE));
+/// ```
+pub fn is_driver_specific(id: u32) -> bool {
+    id >= PRIVATE_BASE || id & 0xffff >= 0x0900 + 0x1000
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Controls of a single class, as returned by [`crate::Device::query_private_controls()`]
+pub struct Group {
+    /// Control class, see [`Description::class()`]
+    pub class: u32,
+    /// Name of the class as reported by the driver, e.g. "Camera Controls"
+    pub name: Option<String>,
+    /// Controls of the class
+    pub controls: Vec<Description>,
+}
+
+impl fmt::Display for Group {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => writeln!(f, "{} ({:#010x})", name, self.class)?,
+            None => writeln!(f, "{:#010x}", self.class)?,
+        }
+        for ctrl in &self.controls {
+            writeln!(f, " * {:#010x}: {} ({})", ctrl.id, ctrl.name, ctrl.typ)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Device control description
 pub struct Description {
     /// Control identifier, set by the the application
@@ -163,24 +218,64 @@ pub struct Description {
 
     /// Items for menu controls (only valid if [`Self::typ`] is a menu type)
     pub items: Option<Vec<(u32, MenuItem)>>,
+
+    /// Size of a single element in bytes
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub elem_size: u32,
+    /// Number of elements, larger than one for arrays and matrices
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub elems: u32,
+    /// Size of each dimension of arrays and matrices, empty for single values
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dims: Vec<u32>,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    name_bytes: Vec<u8>,
 }
 
-impl From<v4l2_query_ext_ctrl> for Description {
-    fn from(ctrl: v4l2_query_ext_ctrl) -> Self {
-        Self {
+impl Description {
+    /// Returns the class of the control, e.g. 0x009a0000 for camera controls
+    pub fn class(&self) -> u32 {
+        self.id & 0x0fff_0000
+    }
+
+    /// Returns whether the control is specific to a driver, see [`is_driver_specific()`]
+    pub fn is_driver_specific(&self) -> bool {
+        is_driver_specific(self.id)
+    }
+
+    /// Returns the name exactly as reported by the driver
+    ///
+    /// Unlike [`Self::name`], invalid UTF-8 sequences are preserved.
+    pub fn name_bytes(&self) -> &[u8] {
+        &self.name_bytes
+    }
+
+    /// Returns the name exactly as reported by the driver
+    pub fn name_os_str(&self) -> &OsStr {
+        util::os_str(&self.name_bytes)
+    }
+}
+
+impl TryFrom<v4l2_query_ext_ctrl> for Description {
+    type Error = ();
+
+    fn try_from(ctrl: v4l2_query_ext_ctrl) -> Result<Self, Self::Error> {
+        Ok(Self {
             id: ctrl.id,
-            typ: Type::try_from(ctrl.type_).unwrap(),
-            name: unsafe { ffi::CStr::from_ptr(ctrl.name.as_ptr()) }
-                .to_str()
-                .unwrap()
-                .to_string(),
+            typ: Type::try_from(ctrl.type_)?,
+            name: util::string_lossy(util::c_chars(&ctrl.name)),
             minimum: ctrl.minimum,
             maximum: ctrl.maximum,
             step: ctrl.step,
             default: ctrl.default_value,
             flags: Flags::from(ctrl.flags),
             items: None,
-        }
+            elem_size: ctrl.elem_size,
+            elems: ctrl.elems,
+            dims: ctrl.dims[..(ctrl.nr_of_dims as usize).min(ctrl.dims.len())].to_vec(),
+            name_bytes: util::until_nul(util::c_chars(&ctrl.name)).to_vec(),
+        })
     }
 }
 
@@ -194,6 +289,9 @@ impl fmt::Display for Description {
         writeln!(f, "Step       : {}", self.step)?;
         writeln!(f, "Default    : {}", self.default)?;
         writeln!(f, "Flags      : {}", self.flags)?;
+        if !self.dims.is_empty() {
+            writeln!(f, "Dimensions : {:?}", self.dims)?;
+        }
         if let Some(items) = &self.items {
             writeln!(f, "Menu ==>")?;
             for item in items {
@@ -204,13 +302,15 @@ impl fmt::Display for Description {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Control {
     pub id: u32,
     pub value: Value,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Device control value
 pub enum Value {
     /* buttons */
@@ -250,3 +350,35 @@ impl TryInto<v4l2_control> for Control {
         }
     }
 }
+
+/// Control value which the driver adjusted while setting it
+///
+/// Drivers silently clamp values to the valid range, round them to the step size or ignore them
+/// altogether, e.g. while an automatic mode owns the control.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Adjustment {
+    /// Control identifier
+    pub id: u32,
+    /// Value which was written
+    pub requested: Value,
+    /// Value which was read back
+    pub applied: Value,
+    /// Control flags at the time of reading back, e.g. [`Flags::INACTIVE`] if an automatic
+    /// mode is active
+    pub flags: Flags,
+}
+
+impl fmt::Display for Adjustment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "control {:#x}: requested {:?}, applied {:?}",
+            self.id, self.requested, self.applied
+        )?;
+        if self.flags.contains(Flags::INACTIVE) {
+            write!(f, " (inactive)")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use std::convert::TryFrom;
+use std::io;
+use std::os::raw::c_int;
+
+use crate::convert::Image;
+
+// libyuv calls packed RGB with red in the first byte "RAW", its "RGB24" is BGR
+#[link(name = "yuv")]
+extern "C" {
+    fn YUY2ToARGB(
+        src_yuy2: *const u8,
+        src_stride_yuy2: c_int,
+        dst_argb: *mut u8,
+        dst_stride_argb: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+    fn UYVYToARGB(
+        src_uyvy: *const u8,
+        src_stride_uyvy: c_int,
+        dst_argb: *mut u8,
+        dst_stride_argb: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+    fn NV12ToRAW(
+        src_y: *const u8,
+        src_stride_y: c_int,
+        src_uv: *const u8,
+        src_stride_uv: c_int,
+        dst_raw: *mut u8,
+        dst_stride_raw: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+    fn I420ToRAW(
+        src_y: *const u8,
+        src_stride_y: c_int,
+        src_u: *const u8,
+        src_stride_u: c_int,
+        src_v: *const u8,
+        src_stride_v: c_int,
+        dst_raw: *mut u8,
+        dst_stride_raw: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+    fn ARGBToRAW(
+        src_argb: *const u8,
+        src_stride_argb: c_int,
+        dst_raw: *mut u8,
+        dst_stride_raw: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+    fn RAWToARGB(
+        src_raw: *const u8,
+        src_stride_raw: c_int,
+        dst_argb: *mut u8,
+        dst_stride_argb: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+    fn ARGBScale(
+        src_argb: *const u8,
+        src_stride_argb: c_int,
+        src_width: c_int,
+        src_height: c_int,
+        dst_argb: *mut u8,
+        dst_stride_argb: c_int,
+        dst_width: c_int,
+        dst_height: c_int,
+        filtering: c_int,
+    ) -> c_int;
+}
+
+/// `kFilterBilinear`
+const FILTER_BILINEAR: c_int = 2;
+
+/// Both strides must have been validated against the image size and `src` against the size of
+/// all planes, libyuv reads the memory without any bounds checks.
+pub fn to_rgb(repr: &[u8; 4], src: &[u8], stride: usize, chroma_stride: usize, dst: &mut Image) {
+    let (width, height) = (dst.width as c_int, dst.height as c_int);
+    let dst_stride = dst.stride() as c_int;
+    let luma = stride * dst.height as usize;
+    unsafe {
+        match repr {
+            b"YUYV" | b"UYVY" => {
+                let mut argb = vec![0u8; dst.width as usize * dst.height as usize * 4];
+                let convert = if repr == b"YUYV" {
+                    YUY2ToARGB
+                } else {
+                    UYVYToARGB
+                };
+                convert(
+                    src.as_ptr(),
+                    stride as c_int,
+                    argb.as_mut_ptr(),
+                    width * 4,
+                    width,
+                    height,
+                );
+                ARGBToRAW(
+                    argb.as_ptr(),
+                    width * 4,
+                    dst.data.as_mut_ptr(),
+                    dst_stride,
+                    width,
+                    height,
+                );
+            }
+            b"NV12" => {
+                NV12ToRAW(
+                    src.as_ptr(),
+                    stride as c_int,
+                    src[luma..].as_ptr(),
+                    chroma_stride as c_int,
+                    dst.data.as_mut_ptr(),
+                    dst_stride,
+                    width,
+                    height,
+                );
+            }
+            _ => {
+                let chroma = chroma_stride * (dst.height as usize).div_ceil(2);
+                I420ToRAW(
+                    src.as_ptr(),
+                    stride as c_int,
+                    src[luma..].as_ptr(),
+                    chroma_stride as c_int,
+                    src[luma + chroma..].as_ptr(),
+                    chroma_stride as c_int,
+                    dst.data.as_mut_ptr(),
+                    dst_stride,
+                    width,
+                    height,
+                );
+            }
+        }
+    }
+}
+
+/// Returns the size of an image as libyuv takes it, after checking its pixel data
+///
+/// libyuv reads and writes the memory without any bounds checks, so the pixel data must match the
+/// size and the ARGB stride must fit into a `c_int`.
+fn dimensions(image: &Image) -> io::Result<(c_int, c_int)> {
+    image.check_size()?;
+    match (c_int::try_from(image.width), c_int::try_from(image.height)) {
+        (Ok(width), Ok(height)) if width.checked_mul(4).is_some() => Ok((width, height)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "image of {}x{} pixels is too large",
+                image.width, image.height
+            ),
+        )),
+    }
+}
+
+/// Converts an image to ARGB, which most libyuv operations work on
+pub fn to_argb(src: &Image) -> io::Result<Vec<u8>> {
+    let (width, height) = dimensions(src)?;
+    let mut argb = vec![0u8; src.width as usize * src.height as usize * 4];
+    unsafe {
+        RAWToARGB(
+            src.data.as_ptr(),
+            width * 3,
+            argb.as_mut_ptr(),
+            width * 4,
+            width,
+            height,
+        );
+    }
+    Ok(argb)
+}
+
+/// Converts ARGB data back to an image of the same size
+pub fn from_argb(argb: &[u8], dst: &mut Image) -> io::Result<()> {
+    let (width, height) = dimensions(dst)?;
+    if argb.len() != dst.width as usize * dst.height as usize * 4 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "ARGB data of {}x{} pixels holds {} bytes",
+                dst.width,
+                dst.height,
+                argb.len()
+            ),
+        ));
+    }
+
+    unsafe {
+        ARGBToRAW(
+            argb.as_ptr(),
+            width * 4,
+            dst.data.as_mut_ptr(),
+            width * 3,
+            width,
+            height,
+        );
+    }
+    Ok(())
+}
+
+pub fn scale(src: &Image, dst: &mut Image) -> io::Result<()> {
+    let argb = to_argb(src)?;
+    let (width, height) = dimensions(dst)?;
+    let mut scaled = vec![0u8; dst.width as usize * dst.height as usize * 4];
+    unsafe {
+        ARGBScale(
+            argb.as_ptr(),
+            src.width as c_int * 4,
+            src.width as c_int,
+            src.height as c_int,
+            scaled.as_mut_ptr(),
+            width * 4,
+            width,
+            height,
+            FILTER_BILINEAR,
+        );
+    }
+    from_argb(&scaled, dst)
+}
@@ -0,0 +1,428 @@
+//! Pixel format conversion
+//!
+//! Most applications want RGB images, while cameras deliver YUV. The functions in this module
+//! convert frames of the common YUV formats to packed RGB (`RGB3`, i.e. red, green and blue
+//! bytes for each pixel) and scale them.
+//!
+//! The conversions are implemented in plain Rust by default. Enable the `libyuv` feature to route
+//! them through [libyuv](https://chromium.googlesource.com/libyuv/libyuv) instead, which is
+//! considerably faster thanks to its SIMD code paths for x86 and ARM. The system library is
+//! linked in that case.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use v4l::buffer::Type;
+//! use v4l::convert;
+//! use v4l::io::mmap::Stream;
+//! use v4l::io::traits::CaptureStream;
+//! use v4l::video::Capture;
+//! use v4l::Device;
+//!
+//! let dev = Device::new(0).expect("Failed to open device");
+//! let format = dev.format().expect("Failed to get format");
+//! let mut stream = Stream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+//!
+//! let (buf, _) = stream.next().expect("Failed to capture frame");
+//! let image = convert::to_rgb(buf, &format).expect("Failed to convert frame");
+//! let thumbnail = convert::scale(&image, 160, 120).expect("Failed to scale image");
+//! ```
+
+#[cfg(feature = "libyuv")]
+mod libyuv;
+
+use std::io;
+
+use crate::format::{Format, FourCC};
+
+/// Packed RGB image, three bytes per pixel without any padding
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Image {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Pixel data, `width * height * 3` bytes
+    pub data: Vec<u8>,
+}
+
+impl Image {
+    /// Returns a black image
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width in pixels
+    /// * `height` - Height in pixels
+    pub fn new(width: u32, height: u32) -> Self {
+        Image {
+            width,
+            height,
+            data: vec![0; width as usize * height as usize * 3],
+        }
+    }
+
+    /// Returns the number of bytes of a row
+    pub fn stride(&self) -> usize {
+        self.width as usize * 3
+    }
+
+    /// Checks that the pixel data matches the size, the fields are public after all
+    fn check_size(&self) -> io::Result<()> {
+        let size = (self.width as usize)
+            .checked_mul(self.height as usize)
+            .and_then(|pixels| pixels.checked_mul(3));
+        if size != Some(self.data.len()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "image of {}x{} pixels holds {} bytes",
+                    self.width,
+                    self.height,
+                    self.data.len()
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns whether a pixel format can be converted by [`to_rgb()`]
+///
+/// # Arguments
+///
+/// * `fourcc` - Pixel format
+pub fn is_supported(fourcc: FourCC) -> bool {
+    [b"RGB3", b"YUYV", b"UYVY", b"NV12", b"YU12"]
+        .iter()
+        .any(|repr| fourcc == FourCC::new(repr))
+}
+
+/// Converts a frame to packed RGB
+///
+/// YUV formats are assumed to use the BT.601 matrix with limited range, which is what most
+/// cameras deliver.
+///
+/// # Arguments
+///
+/// * `src` - Frame data
+/// * `format` - Format of the frame, one of `RGB3`, `YUYV`, `UYVY`, `NV12` or `YU12`
+///
+/// # Example
+///
+/// ```
+/// use v4l::convert;
+/// use v4l::format::{Format, FourCC};
+///
+/// // chroma planes of odd sized frames cover the last column and row as well
+/// let format = Format::new(3, 3, FourCC::new(b"YU12"));
+/// assert!(convert::to_rgb(&[128; 9 + 2 * 4], &format).is_ok());
+/// assert!(convert::to_rgb(&[128; 9 + 2 * 2], &format).is_err());
+///
+/// // rows must fit into the stride
+/// let format = Format {
+///     stride: 4,
+///     ..Format::new(2, 2, FourCC::new(b"RGB3"))
+/// };
+/// assert!(convert::to_rgb(&[0; 12], &format).is_err());
+/// ```
+pub fn to_rgb(src: &[u8], format: &Format) -> io::Result<Image> {
+    let (width, height) = (format.width as usize, format.height as usize);
+    // chroma is subsampled by rounding up, so odd sizes cover the last column and row as well
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+    let repr = format.fourcc.repr;
+    // bytes read per row of the first plane and of each chroma plane
+    let (row, chroma_row, chroma_planes) = match &repr {
+        b"RGB3" => (width * 3, 0, 0),
+        b"YUYV" | b"UYVY" => (chroma_width * 4, 0, 0),
+        b"NV12" => (width, chroma_width * 2, 1),
+        b"YU12" => (width, chroma_width, 2),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("cannot convert {}", format.fourcc),
+            ))
+        }
+    };
+    // the chroma planes follow the luma plane, with half the stride for YU12
+    let (stride, chroma_stride) = if format.stride > 0 {
+        let stride = format.stride as usize;
+        (
+            stride,
+            if chroma_planes == 2 {
+                stride / 2
+            } else {
+                stride
+            },
+        )
+    } else {
+        (row, chroma_row)
+    };
+    if stride < row || (chroma_planes > 0 && chroma_stride < chroma_row) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "stride of {} bytes is too small for {} pixels",
+                stride, format.width
+            ),
+        ));
+    }
+    let size = stride * height + chroma_stride * chroma_height * chroma_planes;
+    if src.len() < size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("frame holds {} bytes, expected {}", src.len(), size),
+        ));
+    }
+
+    let mut image = Image::new(format.width, format.height);
+    if &repr == b"RGB3" {
+        for (dst, src) in image
+            .data
+            .chunks_exact_mut(width * 3)
+            .zip(src.chunks(stride))
+        {
+            dst.copy_from_slice(&src[..width * 3]);
+        }
+        return Ok(image);
+    }
+
+    #[cfg(feature = "libyuv")]
+    libyuv::to_rgb(&repr, src, stride, chroma_stride, &mut image);
+    #[cfg(not(feature = "libyuv"))]
+    soft::to_rgb(&repr, src, stride, chroma_stride, &mut image);
+    Ok(image)
+}
+
+/// Scales an image
+///
+/// Fails with [`io::ErrorKind::InvalidInput`] if the pixel data of the source image does not
+/// match its size.
+///
+/// # Arguments
+///
+/// * `src` - Source image
+/// * `width` - Width of the scaled image in pixels
+/// * `height` - Height of the scaled image in pixels
+///
+/// # Example
+///
+/// ```
+/// use v4l::convert::{self, Image};
+///
+/// let image = Image {
+///     data: vec![0; 4],
+///     ..Image::new(2, 2)
+/// };
+/// assert!(convert::scale(&image, 4, 4).is_err());
+/// ```
+pub fn scale(src: &Image, width: u32, height: u32) -> io::Result<Image> {
+    src.check_size()?;
+    let mut dst = Image::new(width, height);
+    if src.width == 0 || src.height == 0 {
+        return Ok(dst);
+    }
+
+    #[cfg(feature = "libyuv")]
+    libyuv::scale(src, &mut dst)?;
+    #[cfg(not(feature = "libyuv"))]
+    soft::scale(src, &mut dst);
+    Ok(dst)
+}
+
+/// Flips an image
+///
+/// Fails with [`io::ErrorKind::InvalidInput`] if the pixel data of the image does not match its
+/// size.
+///
+/// # Arguments
+///
+/// * `src` - Source image
+/// * `horizontal` - Mirror left and right
+/// * `vertical` - Mirror top and bottom
+pub fn flip(src: &Image, horizontal: bool, vertical: bool) -> io::Result<Image> {
+    src.check_size()?;
+    let (width, height) = (src.width as usize, src.height as usize);
+    let stride = src.stride();
+    let mut dst = Image::new(src.width, src.height);
+    for row in 0..height {
+        let src_row = if vertical { height - 1 - row } else { row };
+        let src_row = &src.data[src_row * stride..][..stride];
+        let dst_row = &mut dst.data[row * stride..][..stride];
+        if horizontal {
+            for col in 0..width {
+                dst_row[col * 3..][..3].copy_from_slice(&src_row[(width - 1 - col) * 3..][..3]);
+            }
+        } else {
+            dst_row.copy_from_slice(src_row);
+        }
+    }
+    Ok(dst)
+}
+
+/// Rotates an image clockwise
+///
+/// Rotating by 90 or 270 degrees swaps width and height. Fails with
+/// [`io::ErrorKind::InvalidInput`] if the pixel data of the image does not match its size.
+///
+/// # Arguments
+///
+/// * `src` - Source image
+/// * `degrees` - Angle, a multiple of 90
+pub fn rotate(src: &Image, degrees: u32) -> io::Result<Image> {
+    if degrees % 90 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("cannot rotate by {} degrees", degrees),
+        ));
+    }
+    src.check_size()?;
+
+    let (width, height) = (src.width as usize, src.height as usize);
+    let stride = src.stride();
+    let quarters = degrees / 90 % 4;
+    let mut dst = match quarters {
+        0 => return Ok(src.clone()),
+        2 => return flip(src, true, true),
+        _ => Image::new(src.height, src.width),
+    };
+    let dst_stride = dst.stride();
+    for row in 0..height {
+        for col in 0..width {
+            let (x, y) = if quarters == 1 {
+                (height - 1 - row, col)
+            } else {
+                (row, width - 1 - col)
+            };
+            dst.data[y * dst_stride + x * 3..][..3]
+                .copy_from_slice(&src.data[row * stride + col * 3..][..3]);
+        }
+    }
+    Ok(dst)
+}
+
+/// Orientation correction for images
+///
+/// # Example
+///
+/// ```
+/// use v4l::convert::{Image, Orientation};
+///
+/// let image = Image::new(640, 480);
+/// let orientation = Orientation { rotation: 90, ..Default::default() };
+/// let rotated = orientation.apply(image).unwrap();
+/// assert_eq!((rotated.width, rotated.height), (480, 640));
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Orientation {
+    /// Clockwise rotation in degrees, a multiple of 90
+    pub rotation: u32,
+    /// Mirror left and right
+    pub hflip: bool,
+    /// Mirror top and bottom
+    pub vflip: bool,
+}
+
+impl Orientation {
+    /// Returns whether images are left untouched
+    pub fn is_identity(&self) -> bool {
+        self.rotation % 360 == 0 && !self.hflip && !self.vflip
+    }
+
+    /// Flips and then rotates an image
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - Image to be corrected
+    pub fn apply(&self, image: Image) -> io::Result<Image> {
+        if self.is_identity() {
+            return Ok(image);
+        }
+
+        let image = if self.hflip || self.vflip {
+            flip(&image, self.hflip, self.vflip)?
+        } else {
+            image
+        };
+        if self.rotation % 360 == 0 {
+            Ok(image)
+        } else {
+            rotate(&image, self.rotation)
+        }
+    }
+}
+
+#[cfg_attr(feature = "libyuv", allow(dead_code))]
+mod soft {
+    use super::Image;
+
+    /// Converts a BT.601 limited range sample to RGB
+    fn rgb(y: u8, u: u8, v: u8) -> [u8; 3] {
+        let c = 298 * (y as i32 - 16);
+        let d = u as i32 - 128;
+        let e = v as i32 - 128;
+        let clamp = |x: i32| ((x + 128) >> 8).clamp(0, 255) as u8;
+        [
+            clamp(c + 409 * e),
+            clamp(c - 100 * d - 208 * e),
+            clamp(c + 516 * d),
+        ]
+    }
+
+    pub fn to_rgb(
+        repr: &[u8; 4],
+        src: &[u8],
+        stride: usize,
+        chroma_stride: usize,
+        dst: &mut Image,
+    ) {
+        let (width, height) = (dst.width as usize, dst.height as usize);
+        let dst_stride = dst.stride();
+        for row in 0..height {
+            let out = &mut dst.data[row * dst_stride..(row + 1) * dst_stride];
+            for col in 0..width {
+                let (y, u, v) = match repr {
+                    b"YUYV" | b"UYVY" => {
+                        let pair = &src[row * stride + col / 2 * 4..][..4];
+                        let (y0, u, y1, v) = if repr == b"YUYV" {
+                            (pair[0], pair[1], pair[2], pair[3])
+                        } else {
+                            (pair[1], pair[0], pair[3], pair[2])
+                        };
+                        (if col % 2 == 0 { y0 } else { y1 }, u, v)
+                    }
+                    b"NV12" => {
+                        let chroma =
+                            &src[stride * height + row / 2 * chroma_stride + col / 2 * 2..];
+                        (src[row * stride + col], chroma[0], chroma[1])
+                    }
+                    _ => {
+                        let u_plane = stride * height;
+                        let v_plane = u_plane + chroma_stride * height.div_ceil(2);
+                        let offset = row / 2 * chroma_stride + col / 2;
+                        (
+                            src[row * stride + col],
+                            src[u_plane + offset],
+                            src[v_plane + offset],
+                        )
+                    }
+                };
+                out[col * 3..col * 3 + 3].copy_from_slice(&rgb(y, u, v));
+            }
+        }
+    }
+
+    /// Scales an image with nearest neighbour sampling
+    pub fn scale(src: &Image, dst: &mut Image) {
+        let (width, height) = (dst.width as usize, dst.height as usize);
+        let (src_stride, dst_stride) = (src.stride(), dst.stride());
+        for row in 0..height {
+            let src_row = row * src.height as usize / height;
+            for col in 0..width {
+                let src_col = col * src.width as usize / width;
+                let pixel = &src.data[src_row * src_stride + src_col * 3..][..3];
+                dst.data[row * dst_stride + col * 3..][..3].copy_from_slice(pixel);
+            }
+        }
+    }
+}
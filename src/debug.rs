@@ -0,0 +1,134 @@
+//! Register access for driver bring-up and debugging
+//!
+//! Reading and writing registers bypasses the driver entirely, so a wrong write can leave the
+//! hardware in a state the driver does not expect or even damage it. Drivers only implement these
+//! ioctls if the kernel was built with `CONFIG_VIDEO_ADV_DEBUG`, and the caller needs
+//! `CAP_SYS_ADMIN`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use v4l::debug::Match;
+//! use v4l::Device;
+//!
+//! let dev = Device::new(0).expect("Failed to open device");
+//! let chip = dev.chip_info(Match::Bridge(0)).expect("Failed to query chip");
+//! println!("{} ({})", chip.name, chip.flags);
+//!
+//! let reg = dev.register(Match::Bridge(0), 0x3000).expect("Failed to read register");
+//! println!("0x3000: {:#x} ({} bytes)", reg.value, reg.size);
+//! ```
+
+use bitflags::bitflags;
+use std::{fmt, mem};
+
+use crate::util;
+use crate::v4l_sys::*;
+
+/// Chip whose registers are accessed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Match {
+    /// Bridge chip by index, 0 being the main chip of the device
+    Bridge(u32),
+    /// Sub-device by index
+    Subdev(u32),
+}
+
+impl fmt::Display for Match {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Match::Bridge(index) => write!(f, "bridge {}", index),
+            Match::Subdev(index) => write!(f, "subdev {}", index),
+        }
+    }
+}
+
+impl From<Match> for v4l2_dbg_match {
+    fn from(chip: Match) -> Self {
+        let (type_, addr) = match chip {
+            Match::Bridge(index) => (V4L2_CHIP_MATCH_BRIDGE, index),
+            Match::Subdev(index) => (V4L2_CHIP_MATCH_SUBDEV, index),
+        };
+        let mut raw: v4l2_dbg_match = unsafe { mem::zeroed() };
+        raw.type_ = type_;
+        raw.__bindgen_anon_1.addr = addr;
+        raw
+    }
+}
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    pub struct Flags: u32 {
+        /// Registers can be read
+        const READABLE = 0x00000001;
+        /// Registers can be written
+        const WRITABLE = 0x00000002;
+    }
+}
+
+impl From<u32> for Flags {
+    fn from(flags: u32) -> Self {
+        Self::from_bits_truncate(flags)
+    }
+}
+
+impl From<Flags> for u32 {
+    fn from(flags: Flags) -> Self {
+        flags.bits()
+    }
+}
+
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Chip information
+#[derive(Debug, Clone)]
+pub struct ChipInfo {
+    /// Name of the chip
+    pub name: String,
+    /// Supported register access
+    pub flags: Flags,
+}
+
+impl From<v4l2_dbg_chip_info> for ChipInfo {
+    fn from(info: v4l2_dbg_chip_info) -> Self {
+        let name = info.name;
+        let flags = info.flags;
+        ChipInfo {
+            name: util::string_lossy(util::c_chars(&name)),
+            flags: Flags::from(flags),
+        }
+    }
+}
+
+impl fmt::Display for ChipInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Name  : {}", self.name)?;
+        writeln!(f, "Flags : {}", self.flags)?;
+        Ok(())
+    }
+}
+
+/// Register value
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Register {
+    /// Register address
+    pub reg: u64,
+    /// Register value
+    pub value: u64,
+    /// Register width in bytes as reported by the driver
+    pub size: u32,
+}
+
+impl From<v4l2_dbg_register> for Register {
+    fn from(reg: v4l2_dbg_register) -> Self {
+        Register {
+            reg: reg.reg,
+            value: reg.val,
+            size: reg.size,
+        }
+    }
+}
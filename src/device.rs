@@ -1,171 +1,1697 @@
+use std::collections::VecDeque;
 use std::convert::TryFrom;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
 use std::{io, mem};
 
 use libc;
 
-use crate::capability::Capabilities;
+use crate::buffer;
+use crate::capability::{Capabilities, Flags};
+use crate::configuration::Configuration;
 use crate::control::{self, Control, Description};
+#[cfg(feature = "dangerous-debug-registers")]
+use crate::debug;
+use crate::event::{self, CtrlChanges, Event, SubscriptionFlags};
+use crate::feature::Features;
+use crate::format;
+use crate::io::mmap::split::Buffer;
+use crate::io::worker::{CaptureConfig, CaptureThread};
+#[cfg(feature = "media-controller")]
+use crate::media::Request;
+use crate::memory::Memory;
+use crate::port;
+use crate::selection::{self, Rect};
+use crate::snapshot::Snapshot;
 use crate::v4l2;
-use crate::v4l2::videodev::v4l2_ext_controls;
+use crate::v4l2::videodev::{v4l2_ext_controls, v4l2_remove_buffers};
 use crate::v4l_sys::*;
 
+/// Advisory lock held on a device, see [`Device::try_lock_exclusive()`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Lock {
+    /// Any number of processes may hold a shared lock, e.g. for monitoring controls
+    Shared,
+    /// Only a single process may hold an exclusive lock, e.g. for streaming
+    Exclusive,
+}
+
 /// Linux capture device abstraction
 pub struct Device {
     /// Raw handle
     handle: Arc<Handle>,
+    /// Cached control descriptions, if enabled
+    control_cache: Option<Mutex<ControlCache>>,
+    /// Events dequeued while refreshing the control cache, kept for the application
+    pending_events: Mutex<VecDeque<Event>>,
+    /// Device capabilities to check requests against, if enabled
+    checked_caps: Option<Flags>,
+    /// Called after switching inputs, if set
+    input_hook: Option<InputHook>,
+}
+
+/// Control descriptions cached by [`Device::enable_control_cache()`]
+struct ControlCache {
+    controls: Vec<Description>,
+    /// Controls whose change events the cache subscribed to
+    subscribed: Vec<u32>,
 }
 
-impl Device {
-    /// Returns a capture device by index
+/// Callback invoked after switching inputs, see [`Device::on_input_change()`]
+type InputHook = Arc<dyn Fn(&port::InputState) + Send + Sync>;
+
+impl Device {
+    /// Returns a capture device by index
+    ///
+    /// Devices are usually enumerated by the system.
+    /// An index of zero thus represents the first device the system got to know about.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index (0: first, 1: second, ..)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::device::Device;
+    /// let dev = Device::new(0);
+    /// ```
+    pub fn new(index: usize) -> io::Result<Self> {
+        let path = format!("{}{}", "/dev/video", index);
+        let fd = v4l2::open(path, libc::O_RDWR | libc::O_NONBLOCK)?;
+
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Device {
+            handle: Arc::new(Handle::new(fd)),
+            control_cache: None,
+            pending_events: Mutex::default(),
+            checked_caps: None,
+            input_hook: None,
+        })
+    }
+
+    /// Returns a capture device by path
+    ///
+    /// Linux device nodes are usually found in /dev/videoX or /sys/class/video4linux/videoX.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path (e.g. "/dev/video0")
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::device::Device;
+    /// let dev = Device::with_path("/dev/video0");
+    /// ```
+    pub fn with_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let fd = v4l2::open(&path, libc::O_RDWR | libc::O_NONBLOCK)?;
+
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Device {
+            handle: Arc::new(Handle::new(fd)),
+            control_cache: None,
+            pending_events: Mutex::default(),
+            checked_caps: None,
+            input_hook: None,
+        })
+    }
+
+    /// Returns a capture device by path, holding an advisory lock
+    ///
+    /// Fails with [`io::ErrorKind::WouldBlock`] if a conflicting lock is held by another
+    /// application. Locks are purely advisory: they only coordinate applications which lock the
+    /// device as well, the driver itself is not affected.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path (e.g. "/dev/video0")
+    /// * `lock` - Lock to acquire
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::device::{Device, Lock};
+    /// let dev = Device::with_path_locked("/dev/video0", Lock::Exclusive);
+    /// ```
+    pub fn with_path_locked<P: AsRef<Path>>(path: P, lock: Lock) -> io::Result<Self> {
+        let dev = Self::with_path(path)?;
+        let op = match lock {
+            Lock::Shared => libc::LOCK_SH,
+            Lock::Exclusive => libc::LOCK_EX,
+        };
+        dev.handle.flock(op | libc::LOCK_NB)?;
+        Ok(dev)
+    }
+
+    /// Tries to acquire an exclusive advisory lock without blocking
+    ///
+    /// Returns `false` if another application holds a lock on the device. An existing shared lock
+    /// of this device is upgraded. Locks belong to the open file, so they are shared with devices
+    /// created by [`Device::try_clone()`] and released once the last of them is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::device::Device;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// if !dev.try_lock_exclusive().expect("Failed to lock device") {
+    ///     println!("Device is in use by another application");
+    /// }
+    /// ```
+    pub fn try_lock_exclusive(&self) -> io::Result<bool> {
+        self.try_lock(libc::LOCK_EX)
+    }
+
+    /// Tries to acquire a shared advisory lock without blocking
+    ///
+    /// Returns `false` if another application holds an exclusive lock on the device. An existing
+    /// exclusive lock of this device is downgraded.
+    pub fn try_lock_shared(&self) -> io::Result<bool> {
+        self.try_lock(libc::LOCK_SH)
+    }
+
+    /// Releases the advisory lock, if any
+    pub fn unlock(&self) -> io::Result<()> {
+        self.handle.flock(libc::LOCK_UN)
+    }
+
+    fn try_lock(&self, op: i32) -> io::Result<bool> {
+        match self.handle.flock(op | libc::LOCK_NB) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the raw device handle
+    pub fn handle(&self) -> Arc<Handle> {
+        self.handle.clone()
+    }
+
+    pub(crate) fn from_handle(handle: Handle) -> Self {
+        Device {
+            handle: Arc::new(handle),
+            control_cache: None,
+            pending_events: Mutex::default(),
+            checked_caps: None,
+            input_hook: None,
+        }
+    }
+
+    /// Returns a new device owning a duplicate of the file descriptor
+    ///
+    /// Unlike cloning the [`Handle`] reference, the returned device can outlive this one, e.g. one
+    /// component may keep a control-only device open while another one owns the device used for
+    /// streaming. Both refer to the same open file, so the format, controls and buffer queue
+    /// ownership are shared. The control cache and the input hook are not copied.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::device::Device;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let controls = dev.try_clone().expect("Failed to duplicate device");
+    /// drop(dev);
+    /// let ctrls = controls.query_controls().expect("Failed to query controls");
+    /// ```
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(Device {
+            handle: Arc::new(self.handle.try_clone()?),
+            control_cache: None,
+            pending_events: Mutex::default(),
+            checked_caps: None,
+            input_hook: None,
+        })
+    }
+
+    /// Returns video4linux framework defined information such as card, driver, etc.
+    pub fn query_caps(&self) -> io::Result<Capabilities> {
+        unsafe {
+            let mut v4l2_caps: v4l2_capability = mem::zeroed();
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_QUERYCAP,
+                &mut v4l2_caps as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            Ok(Capabilities::from(v4l2_caps))
+        }
+    }
+
+    /// Probes which optional parts of the API the device supports
+    ///
+    /// Each feature is detected by issuing a harmless request, e.g. one with an invalid argument,
+    /// which only fails with ENOTTY if the ioctl is not implemented at all. Buffer related features
+    /// are probed on the primary video queue of the device, without allocating any buffers.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::device::Device;
+    /// use v4l::feature::Features;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let features = dev.probe_features().expect("Failed to probe features");
+    /// if !features.contains(Features::SELECTION) {
+    ///     println!("cropping is not supported");
+    /// }
+    /// ```
+    pub fn probe_features(&self) -> io::Result<Features> {
+        // EINVAL and friends mean that the ioctl exists, but rejected the probe
+        fn implemented(res: io::Result<()>) -> bool {
+            match res {
+                Ok(()) => true,
+                Err(e) => e.raw_os_error() != Some(libc::ENOTTY),
+            }
+        }
+
+        let caps = self.query_caps()?;
+        let fd = self.handle().fd();
+        let mut features = Features::empty();
+
+        unsafe {
+            let mut v4l2_ctrl = v4l2_query_ext_ctrl {
+                id: V4L2_CTRL_FLAG_NEXT_CTRL,
+                ..mem::zeroed()
+            };
+            features.set(
+                Features::QUERY_EXT_CTRL,
+                implemented(v4l2::ioctl(
+                    fd,
+                    v4l2::vidioc::VIDIOC_QUERY_EXT_CTRL,
+                    &mut v4l2_ctrl as *mut _ as *mut std::os::raw::c_void,
+                )),
+            );
+
+            // Kernels which predate the which field treat it as unknown control class.
+            let mut v4l2_ctrls = v4l2_ext_controls {
+                which: V4L2_CTRL_WHICH_DEF_VAL,
+                count: 0,
+                ..mem::zeroed()
+            };
+            features.set(
+                Features::EXT_CTRLS_WHICH,
+                v4l2::ioctl(
+                    fd,
+                    v4l2::vidioc::VIDIOC_G_EXT_CTRLS,
+                    &mut v4l2_ctrls as *mut _ as *mut std::os::raw::c_void,
+                )
+                .is_ok(),
+            );
+
+            // buffer type zero is invalid
+            let mut v4l2_sel: v4l2_selection = mem::zeroed();
+            features.set(
+                Features::SELECTION,
+                implemented(v4l2::ioctl(
+                    fd,
+                    v4l2::vidioc::VIDIOC_G_SELECTION,
+                    &mut v4l2_sel as *mut _ as *mut std::os::raw::c_void,
+                )),
+            );
+        }
+
+        let buf_type = if caps
+            .capabilities
+            .intersects(Flags::VIDEO_CAPTURE | Flags::VIDEO_M2M)
+        {
+            buffer::Type::VideoCapture
+        } else if caps.capabilities.contains(Flags::VIDEO_OUTPUT) {
+            buffer::Type::VideoOutput
+        } else if caps
+            .capabilities
+            .intersects(Flags::VIDEO_CAPTURE_MPLANE | Flags::VIDEO_M2M_MPLANE)
+        {
+            buffer::Type::VideoCaptureMplane
+        } else if caps.capabilities.contains(Flags::VIDEO_OUTPUT_MPLANE) {
+            buffer::Type::VideoOutputMplane
+        } else {
+            return Ok(features);
+        };
+
+        unsafe {
+            let mut v4l2_fmt = v4l2_format {
+                type_: buf_type as u32,
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                fd,
+                v4l2::vidioc::VIDIOC_G_FMT,
+                &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            // A count of zero only reports the capabilities of the queue.
+            let mut v4l2_create = v4l2_create_buffers {
+                count: 0,
+                memory: Memory::Mmap as u32,
+                format: v4l2_fmt,
+                ..mem::zeroed()
+            };
+            let res = v4l2::ioctl(
+                fd,
+                v4l2::vidioc::VIDIOC_CREATE_BUFS,
+                &mut v4l2_create as *mut _ as *mut std::os::raw::c_void,
+            );
+            features.set(Features::CREATE_BUFS, implemented(res));
+            features.set(
+                Features::REQUESTS,
+                v4l2_create.capabilities & V4L2_BUF_CAP_SUPPORTS_REQUESTS != 0,
+            );
+            features.set(
+                Features::DMABUF,
+                v4l2_create.capabilities & V4L2_BUF_CAP_SUPPORTS_DMABUF != 0,
+            );
+            features.set(
+                Features::ORPHANED_BUFS,
+                v4l2_create.capabilities & V4L2_BUF_CAP_SUPPORTS_ORPHANED_BUFS != 0,
+            );
+
+            // Removing zero buffers is a no-op.
+            let mut v4l2_remove = v4l2_remove_buffers {
+                index: 0,
+                count: 0,
+                type_: buf_type as u32,
+                reserved: [0; 13],
+            };
+            features.set(
+                Features::REMOVE_BUFS,
+                implemented(v4l2::ioctl(
+                    fd,
+                    v4l2::vidioc::VIDIOC_REMOVE_BUFS,
+                    &mut v4l2_remove as *mut _ as *mut std::os::raw::c_void,
+                )),
+            );
+        }
+
+        Ok(features)
+    }
+
+    /// Returns the supported controls for a device such as gain, focus, white balance, etc.
+    ///
+    /// If the control cache is enabled (see [`Device::enable_control_cache()`]), the cached
+    /// descriptions are returned instead, after refreshing the ones the driver reported changes
+    /// for.
+    pub fn query_controls(&self) -> io::Result<Vec<Description>> {
+        if let Some(cache) = &self.control_cache {
+            let mut cache = cache.lock().unwrap();
+            self.refresh_control_cache(&mut cache)?;
+            return Ok(cache.controls.clone());
+        }
+
+        self.enumerate_controls()
+    }
+
+    /// Queries the descriptions of all controls, bypassing the cache
+    fn enumerate_controls(&self) -> io::Result<Vec<Description>> {
+        let mut controls = Vec::new();
+        unsafe {
+            let mut v4l2_ctrl: v4l2_query_ext_ctrl = mem::zeroed();
+
+            loop {
+                v4l2_ctrl.id |= V4L2_CTRL_FLAG_NEXT_CTRL;
+                v4l2_ctrl.id |= V4L2_CTRL_FLAG_NEXT_COMPOUND;
+                match v4l2::ioctl(
+                    self.handle().fd(),
+                    v4l2::vidioc::VIDIOC_QUERY_EXT_CTRL,
+                    &mut v4l2_ctrl as *mut _ as *mut std::os::raw::c_void,
+                ) {
+                    Ok(_) => {
+                        // skip controls of types unknown to this crate instead of failing
+                        if let Ok(control) = self.describe(v4l2_ctrl) {
+                            controls.push(control);
+                        }
+                    }
+                    Err(e) => {
+                        if controls.is_empty() || e.kind() != io::ErrorKind::InvalidInput {
+                            return Err(e);
+                        } else {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(controls)
+    }
+
+    /// Returns all driver-specific controls grouped by class
+    ///
+    /// Besides the controls enumerated by [`Device::query_controls()`], the legacy range
+    /// starting at [`control::PRIVATE_BASE`] is probed, which older drivers do not enumerate.
+    /// This makes vendor-specific controls, e.g. UVC extension unit controls mapped by
+    /// uvcdynctrl, discoverable without hard-coding their IDs.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::Device;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// for group in dev
+    ///     .query_private_controls()
+    ///     .expect("Failed to query controls")
+    /// {
+    ///     print!("{}", group);
+    /// }
+    /// ```
+    pub fn query_private_controls(&self) -> io::Result<Vec<control::Group>> {
+        let controls = self.query_controls()?;
+        let mut private: Vec<Description> = controls
+            .iter()
+            .filter(|ctrl| ctrl.typ != control::Type::CtrlClass && ctrl.is_driver_specific())
+            .cloned()
+            .collect();
+
+        for id in control::PRIVATE_BASE.. {
+            if private.iter().any(|ctrl| ctrl.id == id) {
+                continue;
+            }
+            match self.query_control(id) {
+                Ok(ctrl) => private.push(ctrl),
+                // the legacy range ends at the first unknown control
+                Err(e) if e.kind() == io::ErrorKind::InvalidInput => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut groups: Vec<control::Group> = Vec::new();
+        for ctrl in private {
+            let class = ctrl.class();
+            match groups.iter_mut().find(|group| group.class == class) {
+                Some(group) => group.controls.push(ctrl),
+                None => groups.push(control::Group {
+                    class,
+                    name: controls
+                        .iter()
+                        .find(|c| c.typ == control::Type::CtrlClass && c.class() == class)
+                        .map(|c| c.name.clone()),
+                    controls: vec![ctrl],
+                }),
+            }
+        }
+
+        Ok(groups)
+    }
+
+    /// Returns the description of a single control
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Control identifier
+    pub fn query_control(&self, id: u32) -> io::Result<Description> {
+        unsafe {
+            let mut v4l2_ctrl = v4l2_query_ext_ctrl {
+                id,
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_QUERY_EXT_CTRL,
+                &mut v4l2_ctrl as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            self.describe(v4l2_ctrl)
+        }
+    }
+
+    /// Builds a control description, enumerating the items of menu controls
+    fn describe(&self, v4l2_ctrl: v4l2_query_ext_ctrl) -> io::Result<Description> {
+        // get the basic control information
+        let mut control = Description::try_from(v4l2_ctrl).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "unknown type {} of control {}",
+                    v4l2_ctrl.type_, v4l2_ctrl.id
+                ),
+            )
+        })?;
+
+        // if this is a menu control, enumerate its items
+        if control.typ == control::Type::Menu || control.typ == control::Type::IntegerMenu {
+            let mut items = Vec::new();
+
+            for i in (v4l2_ctrl.minimum..=v4l2_ctrl.maximum).step_by(v4l2_ctrl.step as usize) {
+                let mut v4l2_menu = v4l2_querymenu {
+                    id: v4l2_ctrl.id,
+                    index: i as u32,
+                    ..unsafe { mem::zeroed() }
+                };
+                let res = unsafe {
+                    v4l2::ioctl(
+                        self.handle().fd(),
+                        v4l2::vidioc::VIDIOC_QUERYMENU,
+                        &mut v4l2_menu as *mut _ as *mut std::os::raw::c_void,
+                    )
+                };
+
+                // BEWARE OF DRAGONS!
+                // The API docs [1] state VIDIOC_QUERYMENU should may return EINVAL
+                // for some indices between minimum and maximum when an item is not
+                // supported by a driver.
+                //
+                // I have no idea why it is advertised in the first place then, but
+                // have seen this happen with a Logitech C920 HD Pro webcam.
+                // In case of errors, let's just skip the offending index.
+                //
+                // [1] https://github.com/torvalds/linux/blob/master/Documentation/userspace-api/media/v4l/vidioc-queryctrl.rst#description
+                if res.is_err() {
+                    continue;
+                }
+
+                if let Ok(item) = control::MenuItem::try_from((control.typ, v4l2_menu)) {
+                    items.push((v4l2_menu.index, item));
+                }
+            }
+
+            control.items = Some(items);
+        }
+
+        Ok(control)
+    }
+
+    /// Enables caching of control descriptions
+    ///
+    /// Querying all controls takes dozens of ioctls, which adds up when done repeatedly, e.g. in
+    /// UI loops. With the cache enabled, [`Device::query_controls()`] only queries the controls
+    /// whose flags or range changed according to the control events of the driver.
+    ///
+    /// Fails if the driver does not support control events, since the cache could never be
+    /// invalidated then.
+    ///
+    /// The cache subscribes to the change events of all controls. Events dequeued while
+    /// refreshing the cache are kept and handed out by [`Device::dequeue_event()`] and
+    /// [`Device::wait_event()`], so the cache can be combined with other event subscriptions.
+    /// Disabling the cache ends the control event subscriptions it made, including those of
+    /// controls the application subscribed to itself.
+    pub fn enable_control_cache(&mut self) -> io::Result<()> {
+        if self.control_cache.is_some() {
+            return Ok(());
+        }
+
+        let controls = self.enumerate_controls()?;
+        let subscribed = self.subscribe_controls(&controls)?;
+        self.control_cache = Some(Mutex::new(ControlCache {
+            controls,
+            subscribed,
+        }));
+        Ok(())
+    }
+
+    /// Subscribes to the change events of all controls backing the cache and returns their ids
+    fn subscribe_controls(&self, controls: &[Description]) -> io::Result<Vec<u32>> {
+        let mut subscribed = Vec::new();
+        for ctrl in controls
+            .iter()
+            .filter(|ctrl| ctrl.typ != control::Type::CtrlClass)
+        {
+            if let Err(e) =
+                self.subscribe_event(event::Type::Ctrl, ctrl.id, SubscriptionFlags::empty())
+            {
+                let _ = self.unsubscribe_controls(&subscribed);
+                return Err(e);
+            }
+            subscribed.push(ctrl.id);
+        }
+        Ok(subscribed)
+    }
+
+    /// Ends the change event subscriptions of the given controls
+    fn unsubscribe_controls(&self, ids: &[u32]) -> io::Result<()> {
+        for &id in ids {
+            self.unsubscribe_event(event::Type::Ctrl, id)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the cached control descriptions, e.g. because the set of controls changed
+    fn reload_control_cache(&self, cache: &mut ControlCache) -> io::Result<()> {
+        self.unsubscribe_controls(&cache.subscribed)?;
+        cache.subscribed.clear();
+        cache.controls = self.enumerate_controls()?;
+        cache.subscribed = self.subscribe_controls(&cache.controls)?;
+        Ok(())
+    }
+
+    /// Disables caching of control descriptions
+    pub fn disable_control_cache(&mut self) -> io::Result<()> {
+        if let Some(cache) = self.control_cache.take() {
+            let cache = cache.into_inner().unwrap();
+            self.unsubscribe_controls(&cache.subscribed)?;
+        }
+        Ok(())
+    }
+
+    /// Enables checking requests against the device capabilities
+    ///
+    /// Drivers answer requests for buffer types they do not support with a bare EINVAL. With the
+    /// checks enabled, streams, formats and selections of such buffer types fail with
+    /// [`io::ErrorKind::Unsupported`] and an error naming the missing capability instead,
+    /// without issuing the ioctl. The capabilities are queried once, when enabling the checks.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::buffer::Type;
+    /// use v4l::io::mmap::Stream;
+    /// use v4l::Device;
+    ///
+    /// let mut dev = Device::new(0).expect("Failed to open device");
+    /// dev.enable_capability_checks().expect("Failed to query capabilities");
+    /// if let Err(e) = Stream::new(&dev, Type::VideoCaptureMplane) {
+    ///     // e.g. "device lacks VIDEO_CAPTURE_MPLANE | VIDEO_M2M_MPLANE; it exposes ..."
+    ///     println!("{}", e);
+    /// }
+    /// ```
+    pub fn enable_capability_checks(&mut self) -> io::Result<()> {
+        self.checked_caps = Some(self.query_caps()?.capabilities);
+        Ok(())
+    }
+
+    /// Disables checking requests against the device capabilities
+    pub fn disable_capability_checks(&mut self) {
+        self.checked_caps = None;
+    }
+
+    /// Fails if capability checks are enabled and the device lacks all of the given capabilities
+    pub(crate) fn check_caps(&self, required: Flags) -> io::Result<()> {
+        match self.checked_caps {
+            Some(caps) if !caps.intersects(required) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("device lacks {}; it exposes {}", required, caps),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails if capability checks are enabled and the device cannot stream buffers of a type
+    pub(crate) fn check_streaming(&self, buf_type: buffer::Type) -> io::Result<()> {
+        self.check_caps(buf_type.capabilities())?;
+        self.check_caps(Flags::STREAMING)
+    }
+
+    /// Dequeues pending events and refreshes the cache entries of changed controls
+    ///
+    /// The events are kept for the application. Like the driver does, control events of the same
+    /// control are merged, so they cannot pile up if the application does not dequeue events.
+    fn refresh_control_cache(&self, cache: &mut ControlCache) -> io::Result<()> {
+        loop {
+            let ev = match self.dequeue_driver_event() {
+                Ok(ev) => ev,
+                // no more pending events
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+                Err(e) => return Err(e),
+            };
+            self.keep_event(ev);
+
+            let ctrl = match ev.payload {
+                event::Payload::Ctrl(ctrl) => ctrl,
+                _ => continue,
+            };
+            // the descriptions do not hold the control value
+            if !ctrl
+                .changes
+                .intersects(CtrlChanges::FLAGS | CtrlChanges::RANGE | CtrlChanges::DIMENSIONS)
+            {
+                continue;
+            }
+
+            if let Some(entry) = cache.controls.iter_mut().find(|desc| desc.id == ev.id) {
+                *entry = self.query_control(ev.id)?;
+            }
+        }
+    }
+
+    /// Keeps an event dequeued on behalf of the application
+    fn keep_event(&self, mut ev: Event) {
+        let mut pending = self.pending_events.lock().unwrap();
+        let id = ev.id;
+        if let event::Payload::Ctrl(ctrl) = &mut ev.payload {
+            let merged = pending
+                .iter()
+                .position(|old| old.id == id && matches!(old.payload, event::Payload::Ctrl(_)));
+            if let Some(event::Payload::Ctrl(old)) = merged
+                .and_then(|index| pending.remove(index))
+                .map(|old| old.payload)
+            {
+                ctrl.changes |= old.changes;
+            }
+        }
+        pending.push_back(ev);
+    }
+
+    /// Starts capturing frames on a dedicated thread
+    ///
+    /// The thread owns the stream and delivers each frame to the callback. The buffer is handed
+    /// back to the driver once the callback drops it, so it may also be sent to another thread
+    /// for processing. Timeouts and interrupted system calls are handled internally, other
+    /// errors stop the thread and are returned by [`CaptureThread::stop()`] or
+    /// [`CaptureThread::join()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Capture configuration
+    /// * `callback` - Function called for every frame
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::{thread, time::Duration};
+    /// use v4l::device::Device;
+    /// use v4l::io::worker::CaptureConfig;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let capture = dev
+    ///     .start_capture_thread(CaptureConfig::default(), |buf| {
+    ///         println!("frame {}: {} bytes", buf.meta().sequence, buf.meta().bytesused);
+    ///     })
+    ///     .expect("Failed to start capture thread");
+    ///
+    /// thread::sleep(Duration::from_secs(5));
+    /// capture.stop().expect("Capture failed");
+    /// ```
+    pub fn start_capture_thread<F>(
+        &self,
+        config: CaptureConfig,
+        callback: F,
+    ) -> io::Result<CaptureThread>
+    where
+        F: FnMut(Buffer<'static>) + Send + 'static,
+    {
+        CaptureThread::spawn(self, config, callback)
+    }
+
+    /// Starts capturing frames on a dedicated thread, delivering them through a channel
+    ///
+    /// Works like [`Device::start_capture_thread()`]. Buffers are handed back to the driver
+    /// when the receiver drops them, so receive them in a timely manner: the driver cannot
+    /// capture any frames while all buffers are waiting in the channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Capture configuration
+    pub fn start_capture_channel(
+        &self,
+        config: CaptureConfig,
+    ) -> io::Result<(CaptureThread, mpsc::Receiver<Buffer<'static>>)> {
+        let (tx, rx) = mpsc::channel();
+        let thread = CaptureThread::spawn(self, config, move |buf| {
+            // The receiver may be gone already, the buffer is requeued in that case.
+            let _ = tx.send(buf);
+        })?;
+        Ok((thread, rx))
+    }
+
+    /// Returns a snapshot of the full device state, similar to `v4l2-ctl --all`
+    ///
+    /// Only querying the capabilities may fail, state the device does not support is left empty.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::Device;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// println!("{}", dev.snapshot().expect("Failed to take snapshot"));
+    /// ```
+    pub fn snapshot(&self) -> io::Result<Snapshot> {
+        Snapshot::take(self)
+    }
+
+    /// Returns a builder to apply several settings at once
+    ///
+    /// See [`Configuration`] for details.
+    pub fn configure(&self) -> Configuration<'_> {
+        Configuration::new(self)
+    }
+
+    /// Returns the raw format of a buffer type
+    ///
+    /// Unlike [`crate::video::Capture::format()`], this exposes all fields reported by the
+    /// driver, including multi-planar and metadata formats.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf_type` - Buffer type
+    pub fn format_raw(&self, buf_type: buffer::Type) -> io::Result<v4l2_format> {
+        self.check_caps(buf_type.capabilities())?;
+        unsafe {
+            let mut v4l2_fmt = v4l2_format {
+                type_: buf_type as u32,
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_G_FMT,
+                &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            Ok(v4l2_fmt)
+        }
+    }
+
+    /// Modifies the raw format, which is updated with the format chosen by the driver
+    ///
+    /// This allows setting fields not covered by [`crate::Format`] yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `fmt` - Desired format, `type_` selects the buffer type
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::buffer::Type;
+    /// use v4l::Device;
+    ///
+    /// const V4L2_YCBCR_ENC_709: u32 = 2;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let mut fmt = dev
+    ///     .format_raw(Type::VideoCapture)
+    ///     .expect("Failed to get format");
+    /// // the encoding is not modeled by the safe API
+    /// fmt.fmt.pix.__bindgen_anon_1.ycbcr_enc = V4L2_YCBCR_ENC_709;
+    /// dev.set_format_raw(&mut fmt).expect("Failed to set format");
+    /// ```
+    pub fn set_format_raw(&self, fmt: &mut v4l2_format) -> io::Result<()> {
+        let buf_type = buffer::Type::try_from(fmt.type_).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown buffer type {}", fmt.type_),
+            )
+        })?;
+        self.check_caps(buf_type.capabilities())?;
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_S_FMT,
+                fmt as *mut _ as *mut std::os::raw::c_void,
+            )
+        }
+    }
+
+    /// Returns all formats supported for a buffer type
+    ///
+    /// Unlike [`crate::video::Capture::enum_formats()`], this works for every queue of
+    /// multi-function devices, e.g. both queues of a memory-to-memory device or the metadata
+    /// queue of a camera.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf_type` - Buffer type
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::buffer::Type;
+    /// use v4l::Device;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// for buf_type in [Type::VideoOutputMplane, Type::VideoCaptureMplane] {
+    ///     for fmt in dev.enum_formats_for(buf_type).expect("Failed to enumerate formats") {
+    ///         println!("{:?}: {}", buf_type, fmt.fourcc);
+    ///     }
+    /// }
+    /// ```
+    pub fn enum_formats_for(&self, buf_type: buffer::Type) -> io::Result<Vec<format::Description>> {
+        let mut formats = Vec::new();
+        let mut v4l2_fmt = v4l2_fmtdesc {
+            index: 0,
+            type_: buf_type as u32,
+            ..unsafe { mem::zeroed() }
+        };
+
+        loop {
+            let ret = unsafe {
+                v4l2::ioctl(
+                    self.handle.fd(),
+                    v4l2::vidioc::VIDIOC_ENUM_FMT,
+                    &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
+                )
+            };
+            match ret {
+                Ok(()) => formats.push(format::Description::from(v4l2_fmt)),
+                // the end of the list is signalled with EINVAL
+                Err(e) if v4l2_fmt.index > 0 && e.raw_os_error() == Some(libc::EINVAL) => {
+                    return Ok(formats)
+                }
+                Err(e) => return Err(e),
+            }
+
+            v4l2_fmt = v4l2_fmtdesc {
+                index: v4l2_fmt.index + 1,
+                type_: buf_type as u32,
+                ..unsafe { mem::zeroed() }
+            };
+        }
+    }
+
+    /// Returns the format currently in use for a buffer type
+    ///
+    /// # Arguments
+    ///
+    /// * `buf_type` - Buffer type
+    pub fn format_for(&self, buf_type: buffer::Type) -> io::Result<format::AnyFormat> {
+        format::AnyFormat::from_raw(&self.format_raw(buf_type)?)
+    }
+
+    /// Modifies the format of a buffer type and returns the actual format
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the format does not apply to the buffer type.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf_type` - Buffer type
+    /// * `fmt` - Desired format
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::buffer::Type;
+    /// use v4l::format::{mplane, AnyFormat};
+    /// use v4l::{Device, FourCC};
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let fmt = mplane::Format::new(1920, 1080, FourCC::new(b"NM12"));
+    /// let actual = dev
+    ///     .set_format_for(Type::VideoCaptureMplane, &AnyFormat::from(fmt))
+    ///     .expect("Failed to set format");
+    /// ```
+    pub fn set_format_for(
+        &self,
+        buf_type: buffer::Type,
+        fmt: &format::AnyFormat,
+    ) -> io::Result<format::AnyFormat> {
+        self.check_caps(buf_type.capabilities())?;
+        let mut v4l2_fmt = fmt.to_raw(buf_type)?;
+        self.set_format_raw(&mut v4l2_fmt)?;
+        format::AnyFormat::from_raw(&v4l2_fmt)
+    }
+
+    /// Returns the format the driver would choose for a buffer type, without applying it
+    ///
+    /// # Arguments
+    ///
+    /// * `buf_type` - Buffer type
+    /// * `fmt` - Desired format
+    pub fn try_format_for(
+        &self,
+        buf_type: buffer::Type,
+        fmt: &format::AnyFormat,
+    ) -> io::Result<format::AnyFormat> {
+        self.check_caps(buf_type.capabilities())?;
+        let mut v4l2_fmt = fmt.to_raw(buf_type)?;
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_TRY_FMT,
+                &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+        format::AnyFormat::from_raw(&v4l2_fmt)
+    }
+
+    /// Sets the first of several acceptable capture formats which the driver supports
+    ///
+    /// Each candidate is tried with `VIDIOC_TRY_FMT` first, so the active format only changes
+    /// once a supported one has been found. Returns the index of that candidate along with the
+    /// format applied by the driver.
+    ///
+    /// # Arguments
+    ///
+    /// * `candidates` - Acceptable formats, in order of preference
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::format::FormatRequest;
+    /// use v4l::{Device, FourCC};
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let (index, fmt) = dev
+    ///     .set_format_any(&[
+    ///         FormatRequest::new(FourCC::new(b"NV12"), 1920, 1080),
+    ///         FormatRequest::new(FourCC::new(b"YUYV"), 1920, 1080),
+    ///         FormatRequest::new(FourCC::new(b"MJPG"), 1920, 1080),
+    ///     ])
+    ///     .expect("No acceptable format");
+    /// println!("candidate {} won: {}", index, fmt);
+    /// ```
+    pub fn set_format_any(
+        &self,
+        candidates: &[format::FormatRequest],
+    ) -> io::Result<(usize, format::Format)> {
+        let buf_type = buffer::Type::VideoCapture;
+        for (index, candidate) in candidates.iter().enumerate() {
+            let requested = format::AnyFormat::from(format::Format::from(candidate));
+            // some old drivers do not implement TRY_FMT, let S_FMT do the checking for them
+            let tried = match self.try_format_for(buf_type, &requested) {
+                Err(e) if e.raw_os_error() == Some(libc::ENOTTY) => None,
+                res => Some(res?),
+            };
+            if let Some(format::AnyFormat::Video(fmt)) = tried {
+                if !candidate.matches(&fmt) {
+                    continue;
+                }
+            }
+
+            if let format::AnyFormat::Video(fmt) = self.set_format_for(buf_type, &requested)? {
+                if candidate.matches(&fmt) {
+                    return Ok((index, fmt));
+                }
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "none of the candidate formats is supported",
+        ))
+    }
+
+    /// Returns the name of a chip and whether its registers can be accessed
+    ///
+    /// # Arguments
+    ///
+    /// * `chip` - Chip to query
+    #[cfg(feature = "dangerous-debug-registers")]
+    pub fn chip_info(&self, chip: debug::Match) -> io::Result<debug::ChipInfo> {
+        unsafe {
+            let mut v4l2_info = v4l2_dbg_chip_info {
+                match_: chip.into(),
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_DBG_G_CHIP_INFO,
+                &mut v4l2_info as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            Ok(debug::ChipInfo::from(v4l2_info))
+        }
+    }
+
+    /// Reads a register of a chip, bypassing the driver
+    ///
+    /// Requires `CAP_SYS_ADMIN` and a kernel built with `CONFIG_VIDEO_ADV_DEBUG`.
+    ///
+    /// # Arguments
+    ///
+    /// * `chip` - Chip which holds the register
+    /// * `reg` - Register address
+    #[cfg(feature = "dangerous-debug-registers")]
+    pub fn register(&self, chip: debug::Match, reg: u64) -> io::Result<debug::Register> {
+        unsafe {
+            let mut v4l2_reg = v4l2_dbg_register {
+                match_: chip.into(),
+                reg,
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_DBG_G_REGISTER,
+                &mut v4l2_reg as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            Ok(debug::Register::from(v4l2_reg))
+        }
+    }
+
+    /// Writes a register of a chip, bypassing the driver
+    ///
+    /// The driver is not aware of the new value, so its state may no longer match the hardware.
+    /// Requires `CAP_SYS_ADMIN` and a kernel built with `CONFIG_VIDEO_ADV_DEBUG`.
+    ///
+    /// # Arguments
+    ///
+    /// * `chip` - Chip which holds the register
+    /// * `reg` - Register address
+    /// * `value` - New value, truncated to the register width by the driver
+    #[cfg(feature = "dangerous-debug-registers")]
+    pub fn set_register(&self, chip: debug::Match, reg: u64, value: u64) -> io::Result<()> {
+        unsafe {
+            let mut v4l2_reg = v4l2_dbg_register {
+                match_: chip.into(),
+                reg,
+                val: value,
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_DBG_S_REGISTER,
+                &mut v4l2_reg as *mut _ as *mut std::os::raw::c_void,
+            )
+        }
+    }
+
+    /// Returns a selection rectangle, e.g. the cropping area
+    ///
+    /// # Arguments
+    ///
+    /// * `buf_type` - Type of the queue, e.g. [`buffer::Type::VideoCapture`]
+    /// * `target` - Selection target
+    pub fn selection(&self, buf_type: buffer::Type, target: selection::Target) -> io::Result<Rect> {
+        self.check_caps(selection_capabilities(buf_type))?;
+        unsafe {
+            let mut v4l2_sel = v4l2_selection {
+                type_: buf_type as u32,
+                target: target as u32,
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_G_SELECTION,
+                &mut v4l2_sel as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            Ok(Rect::from(v4l2_sel.r))
+        }
+    }
+
+    /// Modifies a selection rectangle and returns the actual rectangle
+    ///
+    /// The driver adjusts the rectangle to the closest one it supports, the flags constrain in
+    /// which direction it may do so.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf_type` - Type of the queue, e.g. [`buffer::Type::VideoCapture`]
+    /// * `target` - Selection target, only [`selection::Target::Crop`] and
+    ///              [`selection::Target::Compose`] can be modified
+    /// * `rect` - Desired rectangle
+    /// * `flags` - Constraints for adjusting the rectangle
+    pub fn set_selection(
+        &self,
+        buf_type: buffer::Type,
+        target: selection::Target,
+        rect: Rect,
+        flags: selection::Flags,
+    ) -> io::Result<Rect> {
+        self.check_caps(selection_capabilities(buf_type))?;
+        unsafe {
+            let mut v4l2_sel = v4l2_selection {
+                type_: buf_type as u32,
+                target: target as u32,
+                flags: flags.into(),
+                r: rect.into(),
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_S_SELECTION,
+                &mut v4l2_sel as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            Ok(Rect::from(v4l2_sel.r))
+        }
+    }
+
+    /// Crops the image to an aspect ratio and zoom factor and returns the actual cropping area
+    ///
+    /// The largest area of the requested aspect ratio within the cropping bounds is computed and
+    /// magnified by the zoom factor around its center. The driver may only shrink the area to
+    /// satisfy its alignment constraints, so the result never exceeds the bounds, but its aspect
+    /// ratio may deviate slightly from the requested one.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf_type` - Type of the queue, e.g. [`buffer::Type::VideoCapture`]
+    /// * `width` - Horizontal part of the aspect ratio, e.g. 16
+    /// * `height` - Vertical part of the aspect ratio, e.g. 9
+    /// * `zoom` - Digital zoom factor, 1.0 to use the full area
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::buffer::Type;
+    /// use v4l::device::Device;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let crop = dev
+    ///     .crop_to_aspect(Type::VideoCapture, 16, 9, 1.5)
+    ///     .expect("Failed to crop");
+    /// println!("cropping {}", crop);
+    /// ```
+    pub fn crop_to_aspect(
+        &self,
+        buf_type: buffer::Type,
+        width: u32,
+        height: u32,
+        zoom: f64,
+    ) -> io::Result<Rect> {
+        let bounds = self.selection(buf_type, selection::Target::CropBounds)?;
+        let rect = bounds.fit_aspect(width, height).zoom(zoom);
+        self.set_selection(
+            buf_type,
+            selection::Target::Crop,
+            rect,
+            selection::Flags::LE,
+        )
+    }
+
+    /// Composes the image into the largest area of the given aspect ratio and returns the actual
+    /// composing area
+    ///
+    /// The area is centered within the composing bounds, i.e. the image is letterboxed or
+    /// pillarboxed. Pass the size of the cropping area to preserve the aspect ratio of the
+    /// source.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf_type` - Type of the queue, e.g. [`buffer::Type::VideoOutput`]
+    /// * `width` - Horizontal part of the aspect ratio
+    /// * `height` - Vertical part of the aspect ratio
+    pub fn compose_to_aspect(
+        &self,
+        buf_type: buffer::Type,
+        width: u32,
+        height: u32,
+    ) -> io::Result<Rect> {
+        let bounds = self.selection(buf_type, selection::Target::ComposeBounds)?;
+        let rect = bounds.fit_aspect(width, height);
+        self.set_selection(
+            buf_type,
+            selection::Target::Compose,
+            rect,
+            selection::Flags::LE,
+        )
+    }
+
+    /// Returns all video inputs of the device
+    ///
+    /// Inputs of types unknown to this crate are skipped. Devices without any inputs, including
+    /// those which do not implement the IO control, return an empty list.
+    pub fn enum_inputs(&self) -> io::Result<Vec<port::Input>> {
+        let mut inputs = Vec::new();
+        for index in 0.. {
+            match self.query_input(index) {
+                Ok(input) => inputs.push(input),
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => continue,
+                // EINVAL ends the enumeration, ENOTTY for the first index means there is none
+                Err(e)
+                    if e.kind() == io::ErrorKind::InvalidInput
+                        || (index == 0 && e.raw_os_error() == Some(libc::ENOTTY)) =>
+                {
+                    break
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(inputs)
+    }
+
+    /// Returns a single video input, including its current signal status
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the input
+    pub fn query_input(&self, index: u32) -> io::Result<port::Input> {
+        unsafe {
+            let mut v4l2_input = v4l2_input {
+                index,
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_ENUMINPUT,
+                &mut v4l2_input as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            port::Input::try_from(v4l2_input).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown type {} of input {}", v4l2_input.type_, index),
+                )
+            })
+        }
+    }
+
+    /// Returns the index of the current video input
+    pub fn input(&self) -> io::Result<u32> {
+        let mut index: std::os::raw::c_int = 0;
+        unsafe {
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_G_INPUT,
+                &mut index as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        Ok(index as u32)
+    }
+
+    /// Selects the current video input
+    ///
+    /// Multi-input devices often offer different formats and controls per input. If the control
+    /// cache is enabled, it is reloaded for the new input. Afterwards, the hook registered with
+    /// [`Device::on_input_change()`] is called.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the input
+    pub fn set_input(&self, index: u32) -> io::Result<()> {
+        let mut raw_index = index as std::os::raw::c_int;
+        unsafe {
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_S_INPUT,
+                &mut raw_index as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        if let Some(cache) = &self.control_cache {
+            self.reload_control_cache(&mut cache.lock().unwrap())?;
+        }
+        if let Some(hook) = &self.input_hook {
+            hook(&self.input_state(index)?);
+        }
+        Ok(())
+    }
+
+    /// Returns a video input along with the formats and controls it offers
+    ///
+    /// Formats are enumerated for the capture queue of the device, preferring the multi-planar
+    /// one. Only the current input reports meaningful formats and controls.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the input
+    pub fn input_state(&self, index: u32) -> io::Result<port::InputState> {
+        let caps = self.query_caps()?.capabilities;
+        let formats = if caps.intersects(Flags::VIDEO_CAPTURE_MPLANE | Flags::VIDEO_M2M_MPLANE) {
+            self.enum_formats_for(buffer::Type::VideoCaptureMplane)?
+        } else if caps.intersects(Flags::VIDEO_CAPTURE | Flags::VIDEO_M2M) {
+            self.enum_formats_for(buffer::Type::VideoCapture)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(port::InputState {
+            input: self.query_input(index)?,
+            formats,
+            controls: self.query_controls()?,
+        })
+    }
+
+    /// Registers a hook which is called whenever [`Device::set_input()`] switched inputs
+    ///
+    /// The hook receives the re-enumerated formats and controls of the new input, so cached
+    /// lists can be replaced in one place. It replaces any previously registered hook.
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - Callback receiving the state of the new input
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::Device;
+    ///
+    /// let mut dev = Device::new(0).expect("Failed to open device");
+    /// dev.on_input_change(|state| {
+    ///     println!(
+    ///         "{}: {} formats, {} controls, standards {}",
+    ///         state.input.name,
+    ///         state.formats.len(),
+    ///         state.controls.len(),
+    ///         state.input.std
+    ///     );
+    /// });
+    /// dev.set_input(1).expect("Failed to select input");
+    /// ```
+    pub fn on_input_change<F>(&mut self, hook: F)
+    where
+        F: Fn(&port::InputState) + Send + Sync + 'static,
+    {
+        self.input_hook = Some(Arc::new(hook));
+    }
+
+    /// Removes the hook registered with [`Device::on_input_change()`]
+    pub fn clear_input_hook(&mut self) {
+        self.input_hook = None;
+    }
+
+    /// Selects the first video input which receives a signal and returns it
+    ///
+    /// Each input is selected in turn and checked for a signal: the status reported by the driver
+    /// must not indicate a loss of power, signal or sync, and inputs supporting analog standards or
+    /// DV timings must have detected one. Fails with [`io::ErrorKind::NotFound`] if none of the
+    /// inputs receives a signal, in which case the previous input is selected again.
+    ///
+    /// # Arguments
+    ///
+    /// * `settle` - Time to wait after switching inputs, giving the hardware a chance to lock
+    ///   onto the signal
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use v4l::Device;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// match dev.select_live_input(Duration::from_millis(200)) {
+    ///     Ok(input) => println!("capturing from {}", input.name),
+    ///     Err(e) => println!("no signal: {}", e),
+    /// }
+    /// ```
+    pub fn select_live_input(&self, settle: Duration) -> io::Result<port::Input> {
+        let previous = self.input()?;
+
+        for input in self.enum_inputs()? {
+            self.set_input(input.index)?;
+            if !settle.is_zero() {
+                std::thread::sleep(settle);
+            }
+
+            // the status is only valid for the current input
+            let input = self.query_input(input.index)?;
+            if self.has_signal(&input) {
+                return Ok(input);
+            }
+        }
+
+        self.set_input(previous)?;
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no input receives a signal",
+        ))
+    }
+
+    /// Returns whether the current input receives a signal
+    fn has_signal(&self, input: &port::Input) -> bool {
+        if !input.status.has_signal() {
+            return false;
+        }
+
+        if input.capabilities.contains(port::Capabilities::STD) {
+            let mut std: v4l2_std_id = 0;
+            let ret = unsafe {
+                v4l2::ioctl(
+                    self.handle().fd(),
+                    v4l2::vidioc::VIDIOC_QUERYSTD,
+                    &mut std as *mut _ as *mut std::os::raw::c_void,
+                )
+            };
+            match ret {
+                // no standard detected
+                Ok(()) if std == 0 => return false,
+                Ok(()) => return true,
+                Err(e) if e.raw_os_error() == Some(libc::ENODATA) => return false,
+                // detection is not supported, rely on the status
+                Err(_) => {}
+            }
+        }
+
+        if input.capabilities.contains(port::Capabilities::DV_TIMINGS) {
+            let mut timings: v4l2_dv_timings = unsafe { mem::zeroed() };
+            let ret = unsafe {
+                v4l2::ioctl(
+                    self.handle().fd(),
+                    v4l2::vidioc::VIDIOC_QUERY_DV_TIMINGS,
+                    &mut timings as *mut _ as *mut std::os::raw::c_void,
+                )
+            };
+            match ret.map_err(|e| e.raw_os_error()) {
+                Ok(()) => return true,
+                Err(Some(libc::ENOLINK)) | Err(Some(libc::ENOLCK)) | Err(Some(libc::ERANGE)) => {
+                    return false
+                }
+                Err(_) => {}
+            }
+        }
+
+        true
+    }
+
+    /// Returns all video outputs of the device
+    ///
+    /// Outputs of types unknown to this crate are skipped. Devices without any outputs, including
+    /// those which do not implement the IO control, return an empty list.
+    pub fn enum_outputs(&self) -> io::Result<Vec<port::Output>> {
+        let mut outputs = Vec::new();
+        for index in 0.. {
+            let mut v4l2_output = v4l2_output {
+                index,
+                ..unsafe { mem::zeroed() }
+            };
+            let ret = unsafe {
+                v4l2::ioctl(
+                    self.handle().fd(),
+                    v4l2::vidioc::VIDIOC_ENUMOUTPUT,
+                    &mut v4l2_output as *mut _ as *mut std::os::raw::c_void,
+                )
+            };
+            match ret {
+                Ok(()) => outputs.extend(port::Output::try_from(v4l2_output)),
+                // EINVAL ends the enumeration, ENOTTY for the first index means there is none
+                Err(e)
+                    if e.kind() == io::ErrorKind::InvalidInput
+                        || (index == 0 && e.raw_os_error() == Some(libc::ENOTTY)) =>
+                {
+                    break
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    /// Returns the index of the current video output
+    pub fn output(&self) -> io::Result<u32> {
+        let mut index: std::os::raw::c_int = 0;
+        unsafe {
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_G_OUTPUT,
+                &mut index as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        Ok(index as u32)
+    }
+
+    /// Selects the current video output
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the output
+    pub fn set_output(&self, index: u32) -> io::Result<()> {
+        let mut index = index as std::os::raw::c_int;
+        unsafe {
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_S_OUTPUT,
+                &mut index as *mut _ as *mut std::os::raw::c_void,
+            )
+        }
+    }
+
+    /// Subscribes to an event
     ///
-    /// Devices are usually enumerated by the system.
-    /// An index of zero thus represents the first device the system got to know about.
+    /// Pending events are signalled by `POLLPRI` and can be retrieved with
+    /// [`Device::dequeue_event()`].
     ///
     /// # Arguments
     ///
-    /// * `index` - Index (0: first, 1: second, ..)
+    /// * `typ` - Event type
+    /// * `id` - Event source, e.g. the control id for control events
+    /// * `flags` - Subscription flags
+    pub fn subscribe_event(
+        &self,
+        typ: event::Type,
+        id: u32,
+        flags: SubscriptionFlags,
+    ) -> io::Result<()> {
+        unsafe {
+            let mut v4l2_sub = v4l2_event_subscription {
+                type_: typ as u32,
+                id,
+                flags: flags.into(),
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_SUBSCRIBE_EVENT,
+                &mut v4l2_sub as *mut _ as *mut std::os::raw::c_void,
+            )
+        }
+    }
+
+    /// Unsubscribes from an event
     ///
-    /// # Example
+    /// # Arguments
     ///
-    /// ```
-    /// use v4l::device::Device;
-    /// let dev = Device::new(0);
-    /// ```
-    pub fn new(index: usize) -> io::Result<Self> {
-        let path = format!("{}{}", "/dev/video", index);
-        let fd = v4l2::open(path, libc::O_RDWR | libc::O_NONBLOCK)?;
-
-        if fd == -1 {
-            return Err(io::Error::last_os_error());
+    /// * `typ` - Event type, [`event::Type::All`] unsubscribes from all events
+    /// * `id` - Event source, e.g. the control id for control events
+    pub fn unsubscribe_event(&self, typ: event::Type, id: u32) -> io::Result<()> {
+        unsafe {
+            let mut v4l2_sub = v4l2_event_subscription {
+                type_: typ as u32,
+                id,
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_UNSUBSCRIBE_EVENT,
+                &mut v4l2_sub as *mut _ as *mut std::os::raw::c_void,
+            )
         }
-
-        Ok(Device {
-            handle: Arc::new(Handle::new(fd)),
-        })
     }
 
-    /// Returns a capture device by path
+    /// Waits for an event and dequeues it
     ///
-    /// Linux device nodes are usually found in /dev/videoX or /sys/class/video4linux/videoX.
+    /// Fails with [`io::ErrorKind::TimedOut`] if no event arrived in time.
     ///
     /// # Arguments
     ///
-    /// * `path` - Path (e.g. "/dev/video0")
-    ///
-    /// # Example
-    ///
-    /// ```
-    /// use v4l::device::Device;
-    /// let dev = Device::with_path("/dev/video0");
-    /// ```
-    pub fn with_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let fd = v4l2::open(&path, libc::O_RDWR | libc::O_NONBLOCK)?;
-
-        if fd == -1 {
-            return Err(io::Error::last_os_error());
+    /// * `timeout` - Timeout, `None` means infinite timeout (blocking)
+    pub fn wait_event(&self, timeout: Option<Duration>) -> io::Result<Event> {
+        if let Some(ev) = self.pending_events.lock().unwrap().pop_front() {
+            return Ok(ev);
+        }
+        if self.handle.poll(libc::POLLPRI, timeout)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_DQEVENT"));
         }
 
-        Ok(Device {
-            handle: Arc::new(Handle::new(fd)),
-        })
+        self.dequeue_event()
     }
 
-    /// Returns the raw device handle
-    pub fn handle(&self) -> Arc<Handle> {
-        self.handle.clone()
+    /// Dequeues a pending event
+    ///
+    /// Fails with [`io::ErrorKind::NotFound`] if no event is pending.
+    pub fn dequeue_event(&self) -> io::Result<Event> {
+        if let Some(ev) = self.pending_events.lock().unwrap().pop_front() {
+            return Ok(ev);
+        }
+        self.dequeue_driver_event()
     }
 
-    /// Returns video4linux framework defined information such as card, driver, etc.
-    pub fn query_caps(&self) -> io::Result<Capabilities> {
+    /// Dequeues a pending event from the driver, bypassing the events kept for the application
+    fn dequeue_driver_event(&self) -> io::Result<Event> {
         unsafe {
-            let mut v4l2_caps: v4l2_capability = mem::zeroed();
+            let mut v4l2_ev: v4l2_event = mem::zeroed();
             v4l2::ioctl(
                 self.handle().fd(),
-                v4l2::vidioc::VIDIOC_QUERYCAP,
-                &mut v4l2_caps as *mut _ as *mut std::os::raw::c_void,
+                v4l2::vidioc::VIDIOC_DQEVENT,
+                &mut v4l2_ev as *mut _ as *mut std::os::raw::c_void,
             )?;
 
-            Ok(Capabilities::from(v4l2_caps))
-        }
-    }
-
-    /// Returns the supported controls for a device such as gain, focus, white balance, etc.
-    pub fn query_controls(&self) -> io::Result<Vec<Description>> {
-        let mut controls = Vec::new();
-        unsafe {
-            let mut v4l2_ctrl: v4l2_query_ext_ctrl = mem::zeroed();
-
-            loop {
-                v4l2_ctrl.id |= V4L2_CTRL_FLAG_NEXT_CTRL;
-                v4l2_ctrl.id |= V4L2_CTRL_FLAG_NEXT_COMPOUND;
-                match v4l2::ioctl(
-                    self.handle().fd(),
-                    v4l2::vidioc::VIDIOC_QUERY_EXT_CTRL,
-                    &mut v4l2_ctrl as *mut _ as *mut std::os::raw::c_void,
-                ) {
-                    Ok(_) => {
-                        // get the basic control information
-                        let mut control = Description::from(v4l2_ctrl);
-
-                        // if this is a menu control, enumerate its items
-                        if control.typ == control::Type::Menu
-                            || control.typ == control::Type::IntegerMenu
-                        {
-                            dbg!(&control);
-                            let mut items = Vec::new();
-
-                            for i in (v4l2_ctrl.minimum..=v4l2_ctrl.maximum)
-                                .step_by(v4l2_ctrl.step as usize)
-                            {
-                                let mut v4l2_menu = v4l2_querymenu {
-                                    id: v4l2_ctrl.id,
-                                    index: i as u32,
-                                    ..mem::zeroed()
-                                };
-                                let res = v4l2::ioctl(
-                                    self.handle().fd(),
-                                    v4l2::vidioc::VIDIOC_QUERYMENU,
-                                    &mut v4l2_menu as *mut _ as *mut std::os::raw::c_void,
-                                );
-
-                                // BEWARE OF DRAGONS!
-                                // The API docs [1] state VIDIOC_QUERYMENU should may return EINVAL
-                                // for some indices between minimum and maximum when an item is not
-                                // supported by a driver.
-                                //
-                                // I have no idea why it is advertised in the first place then, but
-                                // have seen this happen with a Logitech C920 HD Pro webcam.
-                                // In case of errors, let's just skip the offending index.
-                                //
-                                // [1] https://github.com/torvalds/linux/blob/master/Documentation/userspace-api/media/v4l/vidioc-queryctrl.rst#description
-                                if res.is_err() {
-                                    continue;
-                                }
-
-                                let item =
-                                    control::MenuItem::try_from((control.typ, v4l2_menu)).unwrap();
-                                items.push((v4l2_menu.index, item));
-                            }
-
-                            control.items = Some(items);
-                        }
-
-                        controls.push(control);
-                    }
-                    Err(e) => {
-                        if controls.is_empty() || e.kind() != io::ErrorKind::InvalidInput {
-                            return Err(e);
-                        } else {
-                            break;
-                        }
-                    }
-                }
-            }
+            Ok(Event::from(v4l2_ev))
         }
-
-        Ok(controls)
     }
 
     /// Returns the current control value from its [`Description`]
@@ -174,14 +1700,47 @@ impl Device {
     ///
     /// * `desc` - Control description
     pub fn control(&self, desc: &Description) -> io::Result<Control> {
+        self.get_ext_control(desc, V4L2_CTRL_WHICH_CUR_VAL, 0)
+    }
+
+    /// Returns the control value applied by a completed request
+    ///
+    /// # Arguments
+    ///
+    /// * `desc` - Control description
+    /// * `request` - Request which completed
+    #[cfg(feature = "media-controller")]
+    pub fn request_control(&self, desc: &Description, request: &Request) -> io::Result<Control> {
+        self.get_ext_control(desc, V4L2_CTRL_WHICH_REQUEST_VAL, request.fd())
+    }
+
+    fn get_ext_control(
+        &self,
+        desc: &Description,
+        which: u32,
+        request_fd: std::os::raw::c_int,
+    ) -> io::Result<Control> {
         unsafe {
             // query the actual control value
             let mut v4l2_ctrl = v4l2_ext_control {
                 id: desc.id,
                 ..mem::zeroed()
             };
+            // compound payloads are read into a buffer aligned for any of the element types
+            let size = desc.elems as usize * desc.elem_size as usize;
+            let mut payload = Vec::<u32>::new();
+            if matches!(
+                desc.typ,
+                control::Type::U8 | control::Type::U16 | control::Type::U32
+            ) {
+                payload.resize(size.div_ceil(mem::size_of::<u32>()), 0);
+                v4l2_ctrl.__bindgen_anon_1.ptr = payload.as_mut_ptr() as *mut std::os::raw::c_void;
+                v4l2_ctrl.size = size as u32;
+            }
             let mut v4l2_ctrls = v4l2_ext_controls {
+                which,
                 count: 1,
+                request_fd,
                 controls: &mut v4l2_ctrl,
                 ..mem::zeroed()
             };
@@ -191,6 +1750,7 @@ impl Device {
                 &mut v4l2_ctrls as *mut _ as *mut std::os::raw::c_void,
             )?;
 
+            let elems = desc.elems as usize;
             let value = match desc.typ {
                 control::Type::Integer | control::Type::Integer64 | control::Type::Menu => {
                     control::Value::Integer(v4l2_ctrl.__bindgen_anon_1.value64 as i64)
@@ -198,6 +1758,16 @@ impl Device {
                 control::Type::Boolean => {
                     control::Value::Boolean(v4l2_ctrl.__bindgen_anon_1.value == 1)
                 }
+                control::Type::U8 => control::Value::CompoundU8(
+                    std::slice::from_raw_parts(payload.as_ptr() as *const u8, elems).to_vec(),
+                ),
+                control::Type::U16 => control::Value::CompoundU16(
+                    std::slice::from_raw_parts(payload.as_ptr() as *const u16, elems).to_vec(),
+                ),
+                control::Type::U32 => {
+                    payload.truncate(elems);
+                    control::Value::CompoundU32(payload)
+                }
                 _ => {
                     return Err(io::Error::new(
                         io::ErrorKind::Other,
@@ -231,7 +1801,13 @@ impl Device {
             queryctrl
         };
 
-        self.control(&Description::from(queryctrl))
+        let desc = Description::try_from(queryctrl).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown type {} of control {}", queryctrl.type_, id),
+            )
+        })?;
+        self.control(&desc)
     }
 
     /// Modifies the control value
@@ -240,86 +1816,152 @@ impl Device {
     ///
     /// * `ctrl` - Control to be set
     pub fn set_control(&self, ctrl: Control) -> io::Result<()> {
-        self.set_controls(vec![ctrl])
+        self.set_controls([ctrl])
     }
 
     /// Modifies the control values atomically
     ///
+    /// Up to eight controls are set without allocating any memory, which makes this suitable for
+    /// changing controls on a per-frame basis.
+    ///
     /// # Arguments
     ///
-    /// * `ctrls` - Vec of the controls to be set
-    pub fn set_controls(&self, ctrls: Vec<Control>) -> io::Result<()> {
-        unsafe {
-            let mut control_list: Vec<v4l2_ext_control> = vec![];
-            let mut class: Option<u32> = None;
+    /// * `ctrls` - Controls to be set, e.g. a Vec, array or slice
+    pub fn set_controls<C: AsRef<[Control]>>(&self, ctrls: C) -> io::Result<()> {
+        self.set_ext_controls(ctrls.as_ref(), None)
+    }
 
-            if ctrls.is_empty() {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "ctrls cannot be empty",
-                ));
+    /// Modifies the control value and reads it back
+    ///
+    /// Returns the adjustment if the driver applied a different value than the requested one.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctrl` - Control to be set
+    pub fn set_control_verified(&self, ctrl: Control) -> io::Result<Option<control::Adjustment>> {
+        Ok(self.set_controls_verified([ctrl])?.pop())
+    }
+
+    /// Modifies the control values atomically and reads them back
+    ///
+    /// Returns all controls for which the driver applied a different value than the requested
+    /// one, e.g. because it was clamped or rounded. Only integer, menu and boolean controls are
+    /// verified, write-only controls and buttons are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctrls` - Controls to be set, e.g. a Vec, array or slice
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::control::{Control, Value};
+    /// use v4l::Device;
+    ///
+    /// const V4L2_CID_BRIGHTNESS: u32 = 0x00980900;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let brightness = Control {
+    ///     id: V4L2_CID_BRIGHTNESS,
+    ///     value: Value::Integer(1000),
+    /// };
+    /// for adjustment in dev
+    ///     .set_controls_verified([brightness])
+    ///     .expect("Failed to set controls")
+    /// {
+    ///     println!("{}", adjustment);
+    /// }
+    /// ```
+    pub fn set_controls_verified<C: AsRef<[Control]>>(
+        &self,
+        ctrls: C,
+    ) -> io::Result<Vec<control::Adjustment>> {
+        let ctrls = ctrls.as_ref();
+        self.set_controls(ctrls)?;
+
+        let mut adjustments = Vec::new();
+        for ctrl in ctrls {
+            if let control::Value::None = ctrl.value {
+                continue;
+            }
+            let desc = self.query_control(ctrl.id)?;
+            if desc.flags.contains(control::Flags::WRITE_ONLY)
+                || !matches!(
+                    desc.typ,
+                    control::Type::Integer
+                        | control::Type::Integer64
+                        | control::Type::Menu
+                        | control::Type::Boolean
+                )
+            {
+                continue;
             }
 
-            for ref ctrl in ctrls {
-                let mut control = v4l2_ext_control {
+            let applied = self.control(&desc)?.value;
+            if applied != ctrl.value {
+                adjustments.push(control::Adjustment {
                     id: ctrl.id,
-                    ..mem::zeroed()
-                };
-                class = match class {
-                    Some(c) => {
-                        if c != (control.id & 0xFFFF0000) {
-                            return Err(io::Error::new(
-                                io::ErrorKind::InvalidInput,
-                                "All controls must be in the same class",
-                            ));
-                        } else {
-                            Some(c)
-                        }
-                    }
-                    None => Some(control.id & 0xFFFF0000),
-                };
+                    requested: ctrl.value.clone(),
+                    applied,
+                    flags: desc.flags,
+                });
+            }
+        }
 
-                match ctrl.value {
-                    control::Value::None => {}
-                    control::Value::Integer(val) => {
-                        control.__bindgen_anon_1.value64 = val;
-                        control.size = std::mem::size_of::<i64>() as u32;
-                    }
-                    control::Value::Boolean(val) => {
-                        control.__bindgen_anon_1.value64 = val as i64;
-                        control.size = std::mem::size_of::<i64>() as u32;
-                    }
-                    control::Value::String(ref val) => {
-                        control.__bindgen_anon_1.string = val.as_ptr() as *mut std::os::raw::c_char;
-                        control.size = val.len() as u32;
-                    }
-                    control::Value::CompoundU8(ref val) => {
-                        control.__bindgen_anon_1.p_u8 = val.as_ptr() as *mut u8;
-                        control.size = (val.len() * std::mem::size_of::<u8>()) as u32;
-                    }
-                    control::Value::CompoundU16(ref val) => {
-                        control.__bindgen_anon_1.p_u16 = val.as_ptr() as *mut u16;
-                        control.size = (val.len() * std::mem::size_of::<u16>()) as u32;
-                    }
-                    control::Value::CompoundU32(ref val) => {
-                        control.__bindgen_anon_1.p_u32 = val.as_ptr() as *mut u32;
-                        control.size = (val.len() * std::mem::size_of::<u32>()) as u32;
-                    }
-                    control::Value::CompoundPtr(ref val) => {
-                        control.__bindgen_anon_1.ptr = val.as_ptr() as *mut std::os::raw::c_void;
-                        control.size = (val.len() * std::mem::size_of::<u8>()) as u32;
-                    }
-                };
+        Ok(adjustments)
+    }
 
-                control_list.push(control);
-            }
+    /// Stores control values in a request, they are applied once the request is processed
+    ///
+    /// Unlike [`Device::set_controls()`], the controls may belong to different classes.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctrls` - Controls to be set, e.g. a Vec, array or slice
+    /// * `request` - Request which has not been queued yet
+    #[cfg(feature = "media-controller")]
+    pub fn set_request_controls<C: AsRef<[Control]>>(
+        &self,
+        ctrls: C,
+        request: &Request,
+    ) -> io::Result<()> {
+        self.set_ext_controls(ctrls.as_ref(), Some(request.fd()))
+    }
 
-            let class = class.ok_or_else(|| {
-                io::Error::new(
+    fn set_ext_controls(
+        &self,
+        ctrls: &[Control],
+        request_fd: Option<std::os::raw::c_int>,
+    ) -> io::Result<()> {
+        unsafe {
+            let class = match ctrls.first() {
+                Some(ctrl) => ctrl.id & 0xFFFF0000,
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "ctrls cannot be empty",
+                    ))
+                }
+            };
+            if request_fd.is_none() && ctrls.iter().any(|ctrl| ctrl.id & 0xFFFF0000 != class) {
+                return Err(io::Error::new(
                     io::ErrorKind::InvalidInput,
-                    "failed to determine control class",
-                )
-            })?;
+                    "All controls must be in the same class",
+                ));
+            }
+
+            let mut inline: [v4l2_ext_control; INLINE_CONTROLS] = mem::zeroed();
+            let mut allocated;
+            let control_list = if ctrls.len() <= INLINE_CONTROLS {
+                &mut inline[..ctrls.len()]
+            } else {
+                allocated = vec![mem::zeroed(); ctrls.len()];
+                &mut allocated[..]
+            };
+
+            for (control, ctrl) in control_list.iter_mut().zip(ctrls) {
+                *control = ext_control(ctrl);
+            }
 
             let mut controls = v4l2_ext_controls {
                 count: control_list.len() as u32,
@@ -328,6 +1970,10 @@ impl Device {
                 which: class,
                 ..mem::zeroed()
             };
+            if let Some(request_fd) = request_fd {
+                controls.which = V4L2_CTRL_WHICH_REQUEST_VAL;
+                controls.request_fd = request_fd;
+            }
 
             v4l2::ioctl(
                 self.handle().fd(),
@@ -338,36 +1984,77 @@ impl Device {
     }
 }
 
+/// Returns the capabilities which support selections on a buffer type
+fn selection_capabilities(buf_type: buffer::Type) -> Flags {
+    // the single-planar types select on multi-planar queues as well
+    match buf_type {
+        buffer::Type::VideoCapture => {
+            buf_type.capabilities() | buffer::Type::VideoCaptureMplane.capabilities()
+        }
+        buffer::Type::VideoOutput => {
+            buf_type.capabilities() | buffer::Type::VideoOutputMplane.capabilities()
+        }
+        _ => buf_type.capabilities(),
+    }
+}
+
+/// Maximum number of controls which are set without allocating memory
+const INLINE_CONTROLS: usize = 8;
+
+/// Returns the raw representation of a control
+///
+/// The returned struct points into the value of `ctrl` for non-scalar controls, so `ctrl` must
+/// outlive it.
+unsafe fn ext_control(ctrl: &Control) -> v4l2_ext_control {
+    let mut control = v4l2_ext_control {
+        id: ctrl.id,
+        ..mem::zeroed()
+    };
+
+    match ctrl.value {
+        control::Value::None => {}
+        control::Value::Integer(val) => {
+            control.__bindgen_anon_1.value64 = val;
+            control.size = std::mem::size_of::<i64>() as u32;
+        }
+        control::Value::Boolean(val) => {
+            control.__bindgen_anon_1.value64 = val as i64;
+            control.size = std::mem::size_of::<i64>() as u32;
+        }
+        control::Value::String(ref val) => {
+            control.__bindgen_anon_1.string = val.as_ptr() as *mut std::os::raw::c_char;
+            control.size = val.len() as u32;
+        }
+        control::Value::CompoundU8(ref val) => {
+            control.__bindgen_anon_1.p_u8 = val.as_ptr() as *mut u8;
+            control.size = (val.len() * std::mem::size_of::<u8>()) as u32;
+        }
+        control::Value::CompoundU16(ref val) => {
+            control.__bindgen_anon_1.p_u16 = val.as_ptr() as *mut u16;
+            control.size = (val.len() * std::mem::size_of::<u16>()) as u32;
+        }
+        control::Value::CompoundU32(ref val) => {
+            control.__bindgen_anon_1.p_u32 = val.as_ptr() as *mut u32;
+            control.size = (val.len() * std::mem::size_of::<u32>()) as u32;
+        }
+        control::Value::CompoundPtr(ref val) => {
+            control.__bindgen_anon_1.ptr = val.as_ptr() as *mut std::os::raw::c_void;
+            control.size = (val.len() * std::mem::size_of::<u8>()) as u32;
+        }
+    };
+
+    control
+}
+
 impl io::Read for Device {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        unsafe {
-            let ret = libc::read(
-                self.handle().fd(),
-                buf.as_mut_ptr() as *mut std::os::raw::c_void,
-                buf.len(),
-            );
-            match ret {
-                -1 => Err(io::Error::last_os_error()),
-                ret => Ok(ret as usize),
-            }
-        }
+        v4l2::read(self.handle().fd(), buf)
     }
 }
 
 impl io::Write for Device {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        unsafe {
-            let ret = libc::write(
-                self.handle().fd(),
-                buf.as_ptr() as *const std::os::raw::c_void,
-                buf.len(),
-            );
-
-            match ret {
-                -1 => Err(io::Error::last_os_error()),
-                ret => Ok(ret as usize),
-            }
-        }
+        v4l2::write(self.handle().fd(), buf)
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -385,7 +2072,7 @@ pub struct Handle {
 }
 
 impl Handle {
-    fn new(fd: std::os::raw::c_int) -> Self {
+    pub(crate) fn new(fd: std::os::raw::c_int) -> Self {
         Self { fd }
     }
 
@@ -394,41 +2081,151 @@ impl Handle {
         self.fd
     }
 
+    fn flock(&self, op: i32) -> io::Result<()> {
+        v4l2::flock(self.fd, op)
+    }
+
+    /// Closes the file descriptor and reports errors
+    ///
+    /// Dropping a handle closes it as well, but ignores errors. Handles shared through an [`Arc`]
+    /// can be closed once the last reference was obtained through [`Arc::try_unwrap()`].
+    pub fn close(mut self) -> io::Result<()> {
+        let fd = mem::replace(&mut self.fd, -1);
+        v4l2::close(fd)
+    }
+
+    /// Returns a new handle owning a duplicate of the file descriptor
+    ///
+    /// The duplicate refers to the same open file and is closed independently, see
+    /// [`v4l2::dup()`].
+    pub fn try_clone(&self) -> io::Result<Self> {
+        v4l2::dup(self.fd).map(Self::new)
+    }
+
+    /// Issues an arbitrary ioctl on the file descriptor
+    ///
+    /// This is an escape hatch for vendor specific or new requests which are not wrapped by this
+    /// crate yet. Requests can be encoded with [`v4l2::vidioc::iowr()`] and friends. The size
+    /// encoded in the request must match the size of `T`, otherwise the call is rejected before
+    /// reaching the driver.
+    ///
+    /// # Safety
+    ///
+    /// `T` must have the memory layout the driver expects for the request. In particular, the
+    /// driver may dereference pointers stored in `T`, so these must be valid.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - IO control code
+    /// * `arg` - Argument passed to and possibly modified by the driver
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::device::Device;
+    /// use v4l::v4l2::vidioc;
+    /// use v4l::v4l_sys::v4l2_capability;
+    ///
+    /// const VIDIOC_QUERYCAP: vidioc::_IOC_TYPE = vidioc::ior::<v4l2_capability>(b'V', 0);
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let mut caps: v4l2_capability = unsafe { std::mem::zeroed() };
+    /// unsafe { dev.handle().ioctl(VIDIOC_QUERYCAP, &mut caps) }.expect("Failed to query caps");
+    /// ```
+    pub unsafe fn ioctl<T>(&self, request: v4l2::vidioc::_IOC_TYPE, arg: &mut T) -> io::Result<()> {
+        let size = v4l2::vidioc::size(request);
+        if size != 0 && size != mem::size_of::<T>() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "argument size {} does not match request size {}",
+                    mem::size_of::<T>(),
+                    size
+                ),
+            ));
+        }
+
+        v4l2::ioctl(self.fd, request, arg as *mut T as *mut std::os::raw::c_void)
+    }
+
     /// Polls the file descriptor for I/O events
     ///
+    /// Returns the number of ready file descriptors, so zero means that the call timed out.
+    ///
+    /// # Arguments
+    ///
+    /// * `events`  - The events you are interested in (e.g. POLLIN)
+    /// * `timeout` - Timeout with nanosecond resolution, `None` means infinite timeout (blocking).
+    ///               A zero timeout returns immediately, even if the fd is not ready.
+    pub fn poll(&self, events: i16, timeout: Option<Duration>) -> io::Result<i32> {
+        self.ppoll(events, timeout, None)
+    }
+
+    /// Polls the file descriptor for I/O events while atomically replacing the signal mask
+    ///
+    /// Unlike [`Handle::poll()`], signals which are blocked by the calling thread can be unblocked
+    /// for the duration of the call only. This allows for reliably interrupting a capture thread
+    /// with a signal: checking a flag set by the signal handler before calling this function
+    /// cannot miss a signal, since it stays blocked until the call starts waiting. An interrupted
+    /// call fails with [`io::ErrorKind::Interrupted`].
+    ///
     /// # Arguments
     ///
     /// * `events`  - The events you are interested in (e.g. POLLIN)
+    /// * `timeout` - Timeout with nanosecond resolution, `None` means infinite timeout (blocking)
+    /// * `sigmask` - Signal mask to install during the call, `None` keeps the current one
     ///
-    /// * `timeout` - Timeout in milliseconds
-    ///               A value of zero returns immedately, even if the fd is not ready.
-    ///               A negative value means infinite timeout (blocking).
-    pub fn poll(&self, events: i16, timeout: i32) -> io::Result<i32> {
-        match unsafe {
-            libc::poll(
-                [libc::pollfd {
-                    fd: self.fd,
-                    events,
-                    revents: 0,
-                }]
-                .as_mut_ptr(),
-                1,
-                timeout,
-            )
-        } {
-            -1 => Err(io::Error::last_os_error()),
-            ret => {
-                // A return value of zero means that we timed out. A positive value signifies the
-                // number of fds with non-zero revents fields (aka I/O activity).
-                assert!(ret == 0 || ret == 1);
-                Ok(ret)
-            }
-        }
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::{mem, time::Duration};
+    /// use v4l::device::Device;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    ///
+    /// // wait for a frame, allowing SIGINT to interrupt the call
+    /// let mask = unsafe {
+    ///     let mut mask = mem::zeroed();
+    ///     libc::sigemptyset(&mut mask);
+    ///     mask
+    /// };
+    /// let ready = dev
+    ///     .handle()
+    ///     .ppoll(libc::POLLIN, Some(Duration::from_millis(500)), Some(&mask));
+    /// ```
+    pub fn ppoll(
+        &self,
+        events: i16,
+        timeout: Option<Duration>,
+        sigmask: Option<&libc::sigset_t>,
+    ) -> io::Result<i32> {
+        let ret = v4l2::ppoll(
+            &mut [libc::pollfd {
+                fd: self.fd,
+                events,
+                revents: 0,
+            }],
+            timeout,
+            sigmask,
+        )?;
+        // A return value of zero means that we timed out. A positive value signifies the number
+        // of fds with non-zero revents fields (aka I/O activity).
+        assert!(ret == 0 || ret == 1);
+        Ok(ret as i32)
     }
 }
 
 impl Drop for Handle {
     fn drop(&mut self) {
-        v4l2::close(self.fd).unwrap();
+        if self.fd < 0 {
+            // already closed by Handle::close()
+            return;
+        }
+        // There is nobody to report an error to, use Handle::close() to handle it.
+        #[allow(unused_variables)]
+        if let Err(err) = v4l2::close(self.fd) {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(fd = self.fd, %err, "failed to close device handle");
+        }
     }
 }
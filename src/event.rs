@@ -0,0 +1,235 @@
+use bitflags::bitflags;
+use std::convert::TryFrom;
+use std::{fmt, time};
+
+use crate::control;
+use crate::v4l_sys::*;
+
+/// Event type
+#[allow(clippy::unreadable_literal)]
+#[rustfmt::skip]
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Type {
+    /// All events, only valid for unsubscribing
+    All             = 0,
+    /// Vertical sync
+    Vsync           = 1,
+    /// End of stream
+    Eos             = 2,
+    /// Control change
+    Ctrl            = 3,
+    /// Start of frame
+    FrameSync       = 4,
+    /// Source parameters (e.g. resolution) changed
+    SourceChange    = 5,
+    /// Motion detection state changed
+    MotionDet       = 6,
+}
+
+impl TryFrom<u32> for Type {
+    type Error = ();
+
+    fn try_from(repr: u32) -> Result<Self, Self::Error> {
+        match repr {
+            0 => Ok(Type::All),
+            1 => Ok(Type::Vsync),
+            2 => Ok(Type::Eos),
+            3 => Ok(Type::Ctrl),
+            4 => Ok(Type::FrameSync),
+            5 => Ok(Type::SourceChange),
+            6 => Ok(Type::MotionDet),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<Type> for u32 {
+    fn from(t: Type) -> Self {
+        t as Self
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    pub struct SubscriptionFlags: u32 {
+        /// Send an initial event carrying the current state (control events only)
+        const SEND_INITIAL      = 0x0001;
+        /// Also report changes caused by this file handle (control events only)
+        const ALLOW_FEEDBACK    = 0x0002;
+    }
+}
+
+impl Default for SubscriptionFlags {
+    fn default() -> Self {
+        SubscriptionFlags::from(0)
+    }
+}
+
+impl From<u32> for SubscriptionFlags {
+    fn from(flags: u32) -> Self {
+        Self::from_bits_truncate(flags)
+    }
+}
+
+impl From<SubscriptionFlags> for u32 {
+    fn from(flags: SubscriptionFlags) -> Self {
+        flags.bits()
+    }
+}
+
+impl fmt::Display for SubscriptionFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    pub struct CtrlChanges: u32 {
+        /// The value changed
+        const VALUE             = 0x0001;
+        /// The flags changed
+        const FLAGS             = 0x0002;
+        /// The minimum, maximum, step or default value changed
+        const RANGE             = 0x0004;
+        /// The dimensions of an array control changed
+        const DIMENSIONS        = 0x0008;
+    }
+}
+
+impl From<u32> for CtrlChanges {
+    fn from(flags: u32) -> Self {
+        Self::from_bits_truncate(flags)
+    }
+}
+
+impl From<CtrlChanges> for u32 {
+    fn from(flags: CtrlChanges) -> Self {
+        flags.bits()
+    }
+}
+
+impl fmt::Display for CtrlChanges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+/// Control change event
+pub struct CtrlEvent {
+    /// What changed about the control
+    pub changes: CtrlChanges,
+    /// Raw control type
+    pub typ: u32,
+    /// Current value (not valid for string and compound controls)
+    pub value: i64,
+    /// Control flags
+    pub flags: control::Flags,
+    /// Minimum value, inclusive
+    pub minimum: i32,
+    /// Maximum value, inclusive
+    pub maximum: i32,
+    /// Step size
+    pub step: i32,
+    /// Default value
+    pub default: i32,
+}
+
+impl From<v4l2_event_ctrl> for CtrlEvent {
+    fn from(ev: v4l2_event_ctrl) -> Self {
+        let value = unsafe {
+            if ev.type_ == control::Type::Integer64 as u32 {
+                ev.__bindgen_anon_1.value64
+            } else {
+                ev.__bindgen_anon_1.value as i64
+            }
+        };
+
+        CtrlEvent {
+            changes: CtrlChanges::from(ev.changes),
+            typ: ev.type_,
+            value,
+            flags: control::Flags::from(ev.flags),
+            minimum: ev.minimum,
+            maximum: ev.maximum,
+            step: ev.step,
+            default: ev.default_value,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+/// Event specific data
+pub enum Payload {
+    Vsync {
+        field: u8,
+    },
+    Eos,
+    Ctrl(CtrlEvent),
+    FrameSync {
+        frame_sequence: u32,
+    },
+    SourceChange {
+        changes: u32,
+    },
+    /// Event types without a dedicated representation, e.g. driver private ones
+    Other {
+        typ: u32,
+        data: [u8; 64],
+    },
+}
+
+#[derive(Debug, Copy, Clone)]
+/// Event dequeued from a device
+pub struct Event {
+    /// Event specific data
+    pub payload: Payload,
+    /// Number of events still pending
+    pub pending: u32,
+    /// Event sequence number, incremented for each event of the device
+    pub sequence: u32,
+    /// Time of the event (CLOCK_MONOTONIC)
+    pub timestamp: time::Duration,
+    /// Identifier of the event source, e.g. the control id
+    pub id: u32,
+}
+
+impl From<v4l2_event> for Event {
+    fn from(ev: v4l2_event) -> Self {
+        let payload = unsafe {
+            match Type::try_from(ev.type_) {
+                Ok(Type::Vsync) => Payload::Vsync {
+                    field: ev.u.vsync.field,
+                },
+                Ok(Type::Eos) => Payload::Eos,
+                Ok(Type::Ctrl) => Payload::Ctrl(CtrlEvent::from(ev.u.ctrl)),
+                Ok(Type::FrameSync) => Payload::FrameSync {
+                    frame_sequence: ev.u.frame_sync.frame_sequence,
+                },
+                Ok(Type::SourceChange) => Payload::SourceChange {
+                    changes: ev.u.src_change.changes,
+                },
+                _ => Payload::Other {
+                    typ: ev.type_,
+                    data: ev.u.data,
+                },
+            }
+        };
+
+        Event {
+            payload,
+            pending: ev.pending,
+            sequence: ev.sequence,
+            timestamp: time::Duration::new(ev.timestamp.tv_sec as u64, ev.timestamp.tv_nsec as u32),
+            id: ev.id,
+        }
+    }
+}
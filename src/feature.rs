@@ -0,0 +1,40 @@
+use bitflags::bitflags;
+use std::fmt;
+
+bitflags! {
+    /// Optional parts of the API supported by a device
+    ///
+    /// Drivers and kernels differ in which of the newer ioctls they implement. Unsupported
+    /// ioctls fail with ENOTTY, so probing them upfront (see
+    /// [`crate::Device::probe_features()`]) allows for picking a fallback path instead.
+    pub struct Features: u32 {
+        /// Extended control descriptions (`VIDIOC_QUERY_EXT_CTRL`)
+        const QUERY_EXT_CTRL    = 0x00000001;
+        /// Reading default values of controls (`V4L2_CTRL_WHICH_DEF_VAL`)
+        const EXT_CTRLS_WHICH   = 0x00000002;
+        /// Selection rectangles (`VIDIOC_G_SELECTION`, `VIDIOC_S_SELECTION`)
+        const SELECTION         = 0x00000004;
+        /// Allocating additional buffers (`VIDIOC_CREATE_BUFS`)
+        const CREATE_BUFS       = 0x00000008;
+        /// Freeing individual buffers (`VIDIOC_REMOVE_BUFS`)
+        const REMOVE_BUFS       = 0x00000010;
+        /// Queueing buffers as part of a media request
+        const REQUESTS          = 0x00000020;
+        /// Sharing buffers with other devices (`V4L2_MEMORY_DMABUF`)
+        const DMABUF            = 0x00000040;
+        /// Freeing buffers which are still mapped by the application
+        const ORPHANED_BUFS     = 0x00000080;
+    }
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Features::empty()
+    }
+}
+
+impl fmt::Display for Features {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
@@ -0,0 +1,236 @@
+//! Lens focus helpers
+//!
+//! The focus controls of V4L2 use device specific units: one camera reports positions from 0 to
+//! 255 in steps of 5, the next one from 1 to 1023. [`Focus`] maps positions to the range
+//! 0.0 (farthest) to 1.0 (nearest) instead, or to dioptres given a calibration of the lens, so
+//! autofocus algorithms work the same on any device.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use v4l::focus::Focus;
+//! use v4l::Device;
+//!
+//! let dev = Device::new(0).expect("Failed to open device");
+//! let focus = Focus::new(&dev).expect("Device has no focus control");
+//! focus.set_auto(false).expect("Failed to disable autofocus");
+//!
+//! // sweep from infinity to the closest focus distance
+//! for i in 0..=10 {
+//!     let applied = focus.set_position(i as f64 / 10.0).expect("Failed to move lens");
+//!     println!("lens at {:.2}", applied);
+//! }
+//! ```
+
+use std::io;
+
+use crate::control::{Control, Description, Flags, Value};
+use crate::device::Device;
+
+/// Absolute focus position, larger values focus closer (`V4L2_CID_FOCUS_ABSOLUTE`)
+pub const V4L2_CID_FOCUS_ABSOLUTE: u32 = 0x009a_090a;
+/// Focus movement relative to the current position (`V4L2_CID_FOCUS_RELATIVE`)
+pub const V4L2_CID_FOCUS_RELATIVE: u32 = 0x009a_090b;
+/// Continuous automatic focus (`V4L2_CID_FOCUS_AUTO`)
+pub const V4L2_CID_FOCUS_AUTO: u32 = 0x009a_090c;
+
+/// Optical power of the lens at both ends of the focus range
+///
+/// V4L2 does not specify the unit of focus positions, so converting them to dioptres
+/// (1 / focus distance in meters) requires measuring the lens. Positions in between are mapped
+/// linearly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    /// Dioptres at the farthest position, 0.0 for infinity
+    pub far: f64,
+    /// Dioptres at the nearest position, e.g. 10.0 for 10 cm
+    pub near: f64,
+}
+
+impl Default for Calibration {
+    /// Typical webcam lens focusing from infinity down to 10 cm
+    fn default() -> Self {
+        Calibration {
+            far: 0.0,
+            near: 10.0,
+        }
+    }
+}
+
+/// Focus of a device lens
+pub struct Focus<'a> {
+    dev: &'a Device,
+    absolute: Option<Description>,
+    relative: Option<Description>,
+    calibration: Calibration,
+}
+
+impl<'a> Focus<'a> {
+    /// Returns the focus of a device
+    ///
+    /// Fails if the device has neither an absolute nor a relative focus control.
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Device
+    pub fn new(dev: &'a Device) -> io::Result<Self> {
+        let query = |id| {
+            dev.query_control(id)
+                .ok()
+                .filter(|desc| !desc.flags.contains(Flags::DISABLED))
+        };
+        let absolute = query(V4L2_CID_FOCUS_ABSOLUTE);
+        let relative = query(V4L2_CID_FOCUS_RELATIVE);
+        if absolute.is_none() && relative.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "device has no focus control",
+            ));
+        }
+
+        Ok(Focus {
+            dev,
+            absolute,
+            relative,
+            calibration: Calibration::default(),
+        })
+    }
+
+    /// Sets the calibration used for dioptre conversions
+    ///
+    /// # Arguments
+    ///
+    /// * `calibration` - Lens calibration
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = calibration;
+        self
+    }
+
+    /// Returns whether the lens can be moved to absolute positions
+    pub fn is_absolute(&self) -> bool {
+        self.absolute.is_some()
+    }
+
+    /// Returns whether the lens can be moved relative to its current position
+    pub fn is_relative(&self) -> bool {
+        self.relative.is_some()
+    }
+
+    /// Returns the number of distinct absolute positions
+    pub fn steps(&self) -> Option<u64> {
+        self.absolute
+            .as_ref()
+            .map(|desc| (desc.maximum - desc.minimum) as u64 / desc.step.max(1) + 1)
+    }
+
+    /// Enables or disables continuous autofocus
+    ///
+    /// Manual focus controls are usually inactive while autofocus is enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether the device focuses by itself
+    pub fn set_auto(&self, enable: bool) -> io::Result<()> {
+        self.dev.set_control(Control {
+            id: V4L2_CID_FOCUS_AUTO,
+            value: Value::Boolean(enable),
+        })
+    }
+
+    /// Returns the lens position, from 0.0 (farthest) to 1.0 (nearest)
+    pub fn position(&self) -> io::Result<f64> {
+        let desc = self.absolute()?;
+        match self.dev.control(desc)?.value {
+            Value::Integer(val) => Ok(normalize(desc, val)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "focus control has no integer value",
+            )),
+        }
+    }
+
+    /// Moves the lens and returns the position it was moved to
+    ///
+    /// The position is rounded to the nearest step of the control.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Position from 0.0 (farthest) to 1.0 (nearest), clamped
+    pub fn set_position(&self, position: f64) -> io::Result<f64> {
+        let desc = self.absolute()?;
+        let val = denormalize(desc, position);
+        self.dev.set_control(Control {
+            id: desc.id,
+            value: Value::Integer(val),
+        })?;
+        Ok(normalize(desc, val))
+    }
+
+    /// Returns the optical power the lens is focused at, see [`Calibration`]
+    pub fn dioptres(&self) -> io::Result<f64> {
+        let Calibration { far, near } = self.calibration;
+        Ok(far + self.position()? * (near - far))
+    }
+
+    /// Focuses the lens at an optical power and returns the one it was moved to
+    ///
+    /// # Arguments
+    ///
+    /// * `dioptres` - 1 / focus distance in meters
+    pub fn set_dioptres(&self, dioptres: f64) -> io::Result<f64> {
+        let Calibration { far, near } = self.calibration;
+        let position = self.set_position((dioptres - far) / (near - far))?;
+        Ok(far + position * (near - far))
+    }
+
+    /// Moves the lens by a number of steps
+    ///
+    /// Positive values move the focus closer. The relative focus control is used if the device
+    /// has one, otherwise the lens is moved from its current absolute position.
+    ///
+    /// # Arguments
+    ///
+    /// * `steps` - Steps of the control to move by
+    pub fn move_by(&self, steps: i64) -> io::Result<()> {
+        if let Some(desc) = &self.relative {
+            let val = (steps * desc.step.max(1) as i64).clamp(desc.minimum, desc.maximum);
+            return self.dev.set_control(Control {
+                id: desc.id,
+                value: Value::Integer(val),
+            });
+        }
+
+        let desc = self.absolute()?;
+        let current = denormalize(desc, self.position()?);
+        let val = (current + steps * desc.step.max(1) as i64).clamp(desc.minimum, desc.maximum);
+        self.dev.set_control(Control {
+            id: desc.id,
+            value: Value::Integer(val),
+        })
+    }
+
+    fn absolute(&self) -> io::Result<&Description> {
+        self.absolute.as_ref().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "device has no absolute focus control",
+            )
+        })
+    }
+}
+
+/// Maps a control value to 0.0..=1.0
+fn normalize(desc: &Description, val: i64) -> f64 {
+    if desc.maximum <= desc.minimum {
+        return 0.0;
+    }
+    (val - desc.minimum) as f64 / (desc.maximum - desc.minimum) as f64
+}
+
+/// Maps 0.0..=1.0 to the nearest valid control value
+fn denormalize(desc: &Description, position: f64) -> i64 {
+    let step = desc.step.max(1) as i64;
+    let offset = position.clamp(0.0, 1.0) * (desc.maximum - desc.minimum) as f64;
+    let steps = (offset / step as f64).round() as i64;
+    (desc.minimum + steps * step).min(desc.maximum)
+}
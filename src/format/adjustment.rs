@@ -0,0 +1,149 @@
+use std::fmt;
+
+use crate::format::{Colorspace, FieldOrder, Format, FourCC, Quantization, TransferFunction};
+
+/// Format property which the driver adjusted, holding the requested and the applied value
+#[derive(Debug, Copy, Clone)]
+pub enum Change {
+    /// Width and height in pixels
+    Resolution((u32, u32), (u32, u32)),
+    /// Pixel format
+    FourCC(FourCC, FourCC),
+    /// Field order
+    FieldOrder(FieldOrder, FieldOrder),
+    /// Bytes per line
+    Stride(u32, u32),
+    /// Colorspace
+    Colorspace(Colorspace, Colorspace),
+    /// Quantization
+    Quantization(Quantization, Quantization),
+    /// Transfer function
+    Transfer(TransferFunction, TransferFunction),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Resolution((w1, h1), (w2, h2)) => {
+                write!(f, "resolution {}x{} -> {}x{}", w1, h1, w2, h2)
+            }
+            Change::FourCC(requested, applied) => {
+                write!(f, "fourcc {} -> {}", requested, applied)
+            }
+            Change::FieldOrder(requested, applied) => {
+                write!(f, "field order {} -> {}", requested, applied)
+            }
+            Change::Stride(requested, applied) => {
+                write!(f, "stride {} -> {}", requested, applied)
+            }
+            Change::Colorspace(requested, applied) => {
+                write!(f, "colorspace {} -> {}", requested, applied)
+            }
+            Change::Quantization(requested, applied) => {
+                write!(f, "quantization {} -> {}", requested, applied)
+            }
+            Change::Transfer(requested, applied) => {
+                write!(f, "transfer {} -> {}", requested, applied)
+            }
+        }
+    }
+}
+
+/// Differences between a requested format and the one the driver applied
+///
+/// Properties left for the driver to choose, i.e. a stride of zero or the default colorspace,
+/// quantization, transfer function and field order, are never reported as changed.
+///
+/// # Example
+///
+/// ```
+/// use v4l::format::Adjustments;
+/// use v4l::{Format, FourCC};
+///
+/// let requested = Format::new(1920, 1080, FourCC::new(b"YUYV"));
+/// let applied = Format::new(1280, 720, FourCC::new(b"MJPG"));
+///
+/// let adjustments = Adjustments::new(requested, applied);
+/// assert_eq!(adjustments.changes.len(), 2);
+/// assert_eq!(
+///     adjustments.to_string(),
+///     "resolution 1920x1080 -> 1280x720, fourcc YUYV -> MJPG"
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Adjustments {
+    /// Format passed to the driver
+    pub requested: Format,
+    /// Format applied by the driver
+    pub applied: Format,
+    /// Properties which differ
+    pub changes: Vec<Change>,
+}
+
+impl Adjustments {
+    /// Compares a requested and an applied format
+    ///
+    /// # Arguments
+    ///
+    /// * `requested` - Format passed to the driver
+    /// * `applied` - Format applied by the driver
+    pub fn new(requested: Format, applied: Format) -> Self {
+        let (req, app) = (&requested, &applied);
+        let mut changes = Vec::new();
+        if (req.width, req.height) != (app.width, app.height) {
+            changes.push(Change::Resolution(
+                (req.width, req.height),
+                (app.width, app.height),
+            ));
+        }
+        if req.fourcc != app.fourcc {
+            changes.push(Change::FourCC(req.fourcc, app.fourcc));
+        }
+        if !matches!(req.field_order, FieldOrder::Any)
+            && req.field_order as u32 != app.field_order as u32
+        {
+            changes.push(Change::FieldOrder(req.field_order, app.field_order));
+        }
+        if req.stride != 0 && req.stride != app.stride {
+            changes.push(Change::Stride(req.stride, app.stride));
+        }
+        if !matches!(req.colorspace, Colorspace::Default)
+            && req.colorspace as u32 != app.colorspace as u32
+        {
+            changes.push(Change::Colorspace(req.colorspace, app.colorspace));
+        }
+        if !matches!(req.quantization, Quantization::Default)
+            && req.quantization as u32 != app.quantization as u32
+        {
+            changes.push(Change::Quantization(req.quantization, app.quantization));
+        }
+        if !matches!(req.transfer, TransferFunction::Default)
+            && req.transfer as u32 != app.transfer as u32
+        {
+            changes.push(Change::Transfer(req.transfer, app.transfer));
+        }
+
+        Adjustments {
+            requested,
+            applied,
+            changes,
+        }
+    }
+
+    /// Returns whether the driver applied the format as requested
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for Adjustments {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, change) in self.changes.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use std::io;
+use std::mem;
+
+use crate::buffer::Type;
+use crate::format::{mplane, Format, FourCC};
+use crate::v4l_sys::*;
+#[cfg(feature = "vbi")]
+use crate::vbi;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Format of buffers holding opaque data, i.e. SDR samples or metadata
+pub struct DataFormat {
+    /// data format code
+    pub fourcc: FourCC,
+    /// maximum number of bytes required to store a buffer
+    pub buffersize: u32,
+}
+
+#[derive(Debug, Clone)]
+/// Format of any buffer type, as returned by [`crate::Device::format_for()`]
+///
+/// Overlay windows are not covered, see [`crate::video::Overlay`] instead.
+///
+/// The VBI and SDR variants only exist with the `vbi` and `sdr` features, so matches must include
+/// a wildcard arm to compile regardless of the features enabled elsewhere in the dependency graph.
+#[non_exhaustive]
+pub enum AnyFormat {
+    /// Single-planar video capture or output
+    Video(Format),
+    /// Multi-planar video capture or output
+    VideoMplane(mplane::Format),
+    /// Raw VBI capture or output
+    #[cfg(feature = "vbi")]
+    Vbi(vbi::raw::Format),
+    /// Sliced VBI capture or output
+    #[cfg(feature = "vbi")]
+    SlicedVbi(vbi::sliced::Format),
+    /// Software defined radio capture or output
+    #[cfg(feature = "sdr")]
+    Sdr(DataFormat),
+    /// Metadata capture or output
+    Meta(DataFormat),
+}
+
+impl AnyFormat {
+    /// Returns whether the format applies to a buffer type
+    ///
+    /// # Arguments
+    ///
+    /// * `buf_type` - Buffer type
+    pub fn matches(&self, buf_type: Type) -> bool {
+        match self {
+            AnyFormat::Video(_) => matches!(buf_type, Type::VideoCapture | Type::VideoOutput),
+            AnyFormat::VideoMplane(_) => {
+                matches!(buf_type, Type::VideoCaptureMplane | Type::VideoOutputMplane)
+            }
+            #[cfg(feature = "vbi")]
+            AnyFormat::Vbi(_) => matches!(buf_type, Type::VbiCaputre | Type::VbiOutput),
+            #[cfg(feature = "vbi")]
+            AnyFormat::SlicedVbi(_) => {
+                matches!(buf_type, Type::SlicedVbiCapture | Type::SlicedVbiOutput)
+            }
+            #[cfg(feature = "sdr")]
+            AnyFormat::Sdr(_) => matches!(buf_type, Type::SdrCapture | Type::SdrOutput),
+            AnyFormat::Meta(_) => matches!(buf_type, Type::MetaCapture | Type::MetaOutput),
+        }
+    }
+
+    /// Decodes the format of a raw struct according to its buffer type
+    pub(crate) fn from_raw(fmt: &v4l2_format) -> io::Result<Self> {
+        unsafe {
+            Ok(match fmt.type_ {
+                t if t == Type::VideoCapture as u32 || t == Type::VideoOutput as u32 => {
+                    AnyFormat::Video(Format::from(fmt.fmt.pix))
+                }
+                t if t == Type::VideoCaptureMplane as u32
+                    || t == Type::VideoOutputMplane as u32 =>
+                {
+                    AnyFormat::VideoMplane(mplane::Format::from(fmt.fmt.pix_mp))
+                }
+                #[cfg(feature = "vbi")]
+                t if t == Type::VbiCaputre as u32 || t == Type::VbiOutput as u32 => {
+                    AnyFormat::Vbi(vbi::raw::Format::from(fmt.fmt.vbi))
+                }
+                #[cfg(feature = "vbi")]
+                t if t == Type::SlicedVbiCapture as u32 || t == Type::SlicedVbiOutput as u32 => {
+                    AnyFormat::SlicedVbi(vbi::sliced::Format::from(fmt.fmt.sliced))
+                }
+                #[cfg(feature = "sdr")]
+                t if t == Type::SdrCapture as u32 || t == Type::SdrOutput as u32 => {
+                    AnyFormat::Sdr(DataFormat {
+                        fourcc: FourCC::from(fmt.fmt.sdr.pixelformat),
+                        buffersize: fmt.fmt.sdr.buffersize,
+                    })
+                }
+                t if t == Type::MetaCapture as u32 || t == Type::MetaOutput as u32 => {
+                    AnyFormat::Meta(DataFormat {
+                        fourcc: FourCC::from(fmt.fmt.meta.dataformat),
+                        buffersize: fmt.fmt.meta.buffersize,
+                    })
+                }
+                t => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        format!("unsupported buffer type {}", t),
+                    ))
+                }
+            })
+        }
+    }
+
+    /// Encodes the format into a raw struct for a buffer type
+    pub(crate) fn to_raw(&self, buf_type: Type) -> io::Result<v4l2_format> {
+        if !self.matches(buf_type) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("format does not apply to buffer type {:?}", buf_type),
+            ));
+        }
+
+        let mut v4l2_fmt = v4l2_format {
+            type_: buf_type as u32,
+            ..unsafe { mem::zeroed() }
+        };
+        match self {
+            AnyFormat::Video(fmt) => v4l2_fmt.fmt.pix = (*fmt).into(),
+            AnyFormat::VideoMplane(fmt) => v4l2_fmt.fmt.pix_mp = fmt.into(),
+            #[cfg(feature = "vbi")]
+            AnyFormat::Vbi(fmt) => v4l2_fmt.fmt.vbi = (*fmt).into(),
+            #[cfg(feature = "vbi")]
+            AnyFormat::SlicedVbi(fmt) => v4l2_fmt.fmt.sliced = (*fmt).into(),
+            #[cfg(feature = "sdr")]
+            AnyFormat::Sdr(fmt) => {
+                v4l2_fmt.fmt.sdr.pixelformat = fmt.fourcc.into();
+                v4l2_fmt.fmt.sdr.buffersize = fmt.buffersize;
+            }
+            AnyFormat::Meta(fmt) => {
+                v4l2_fmt.fmt.meta.dataformat = fmt.fourcc.into();
+                v4l2_fmt.fmt.meta.buffersize = fmt.buffersize;
+            }
+        }
+        Ok(v4l2_fmt)
+    }
+}
+
+impl From<Format> for AnyFormat {
+    fn from(fmt: Format) -> Self {
+        AnyFormat::Video(fmt)
+    }
+}
+
+impl From<mplane::Format> for AnyFormat {
+    fn from(fmt: mplane::Format) -> Self {
+        AnyFormat::VideoMplane(fmt)
+    }
+}
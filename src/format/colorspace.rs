@@ -2,6 +2,7 @@ use std::convert::TryFrom;
 use std::fmt;
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u32)]
 /// Colorspace for pixels.
 ///
@@ -1,11 +1,14 @@
 use bitflags::bitflags;
-use std::{fmt, str};
+use std::ffi::OsStr;
+use std::fmt;
 
 use crate::format::FourCC;
+use crate::util;
 use crate::v4l_sys::*;
 
 bitflags! {
     #[allow(clippy::unreadable_literal)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Flags : u32 {
         const COMPRESSED            = 0x0001;
         const EMULATED              = 0x0002;
@@ -33,6 +36,7 @@ impl fmt::Display for Flags {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Format description as returned by [`crate::v4l2::vidioc::VIDIOC_ENUM_FMT`]
 pub struct Description {
     pub index: u32,
@@ -40,6 +44,23 @@ pub struct Description {
     pub flags: Flags,
     pub description: String,
     pub fourcc: FourCC,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    description_bytes: Vec<u8>,
+}
+
+impl Description {
+    /// Returns the description exactly as reported by the driver
+    ///
+    /// Unlike [`Self::description`], invalid UTF-8 sequences are preserved.
+    pub fn description_bytes(&self) -> &[u8] {
+        &self.description_bytes
+    }
+
+    /// Returns the description exactly as reported by the driver
+    pub fn description_os_str(&self) -> &OsStr {
+        util::os_str(&self.description_bytes)
+    }
 }
 
 impl fmt::Display for Description {
@@ -59,11 +80,9 @@ impl From<v4l2_fmtdesc> for Description {
             index: desc.index,
             typ: desc.type_,
             flags: Flags::from(desc.flags),
-            description: str::from_utf8(&desc.description)
-                .unwrap()
-                .trim_matches(char::from(0))
-                .to_string(),
+            description: util::string_lossy(&desc.description),
             fourcc: FourCC::from(desc.pixelformat),
+            description_bytes: util::until_nul(&desc.description).to_vec(),
         }
     }
 }
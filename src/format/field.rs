@@ -1,11 +1,13 @@
 use std::convert::TryFrom;
 use std::fmt;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u32)]
 /// Represents how fields are interlaced (if they are)
 pub enum FieldOrder {
     /// Progressive, Top, Bottom, or Interlaced is acceptable; driver will pick one
+    #[default]
     Any = 0,
     /// progressive, not interlaced
     Progressive = 1,
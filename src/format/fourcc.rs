@@ -65,3 +65,19 @@ impl From<FourCC> for u32 {
         Self::from_le_bytes(fourcc.repr)
     }
 }
+
+/// Serialized as the numeric pixelformat code, which also covers codes that are no valid UTF-8,
+/// e.g. those with the big-endian flag set
+#[cfg(feature = "serde")]
+impl serde::Serialize for FourCC {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(u32::from(*self))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FourCC {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u32::deserialize(deserializer).map(FourCC::from)
+    }
+}
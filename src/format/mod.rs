@@ -3,6 +3,12 @@ use std::{convert::TryFrom, fmt, mem};
 
 use crate::v4l_sys::*;
 
+pub mod adjustment;
+pub use adjustment::Adjustments;
+
+pub mod any;
+pub use any::{AnyFormat, DataFormat};
+
 pub mod colorspace;
 pub use colorspace::Colorspace;
 
@@ -15,14 +21,20 @@ pub use field::FieldOrder;
 pub mod fourcc;
 pub use fourcc::FourCC;
 
+pub mod mplane;
+
 pub mod quantization;
 pub use quantization::Quantization;
 
+pub mod request;
+pub use request::FormatRequest;
+
 pub mod transfer;
 pub use transfer::TransferFunction;
 
 bitflags! {
     #[allow(clippy::unreadable_literal)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Flags : u32 {
         const PREMUL_ALPHA  = 0x00000001;
     }
@@ -47,6 +59,7 @@ impl fmt::Display for Flags {
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Streaming format (single-planar)
 pub struct Format {
     /// width in pixels
@@ -103,6 +116,36 @@ impl Format {
             transfer: TransferFunction::Default,
         }
     }
+
+    /// Returns the format of a raw struct
+    ///
+    /// Fields which are not modeled by this crate are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw` - Raw format
+    pub fn from_raw(raw: v4l2_pix_format) -> Self {
+        Self::from(raw)
+    }
+
+    /// Returns the raw struct of the format, all fields not modeled by this crate are zeroed
+    ///
+    /// Together with [`crate::Device::set_format_raw()`], this allows setting fields the safe
+    /// API does not cover yet.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::{Format, FourCC};
+    ///
+    /// let fmt = Format::new(640, 480, FourCC::new(b"YUYV"));
+    /// let raw = fmt.as_raw();
+    /// assert_eq!(raw.width, 640);
+    /// assert_eq!(Format::from_raw(raw).fourcc, fmt.fourcc);
+    /// ```
+    pub fn as_raw(&self) -> v4l2_pix_format {
+        (*self).into()
+    }
 }
 
 impl fmt::Display for Format {
@@ -126,13 +169,15 @@ impl From<v4l2_pix_format> for Format {
             width: fmt.width,
             height: fmt.height,
             fourcc: FourCC::from(fmt.pixelformat),
-            field_order: FieldOrder::try_from(fmt.field).expect("Invalid field order"),
+            // drivers may report values added to the API after this crate was written
+            field_order: FieldOrder::try_from(fmt.field).unwrap_or(FieldOrder::Any),
             stride: fmt.bytesperline,
             size: fmt.sizeimage,
             flags: Flags::from(fmt.flags),
-            colorspace: Colorspace::try_from(fmt.colorspace).expect("Invalid colorspace"),
-            quantization: Quantization::try_from(fmt.quantization).expect("Invalid quantization"),
-            transfer: TransferFunction::try_from(fmt.xfer_func).expect("Invalid transfer function"),
+            colorspace: Colorspace::try_from(fmt.colorspace).unwrap_or(Colorspace::Default),
+            quantization: Quantization::try_from(fmt.quantization).unwrap_or(Quantization::Default),
+            transfer: TransferFunction::try_from(fmt.xfer_func)
+                .unwrap_or(TransferFunction::Default),
         }
     }
 }
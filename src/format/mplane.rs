@@ -0,0 +1,150 @@
+use std::convert::TryFrom;
+use std::{fmt, mem};
+
+use crate::format::{Colorspace, FieldOrder, FourCC, Quantization, TransferFunction};
+use crate::v4l_sys::*;
+
+/// Maximum number of planes of a multi-planar format
+pub const MAX_PLANES: usize = 8;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Layout of a single plane
+pub struct Plane {
+    /// bytes per line
+    pub stride: u32,
+    /// maximum number of bytes required to store the plane
+    pub size: u32,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Streaming format (multi-planar)
+///
+/// Unlike single-planar formats, each plane (e.g. luma and chroma of NV12M) lives in a buffer of
+/// its own.
+pub struct Format {
+    /// width in pixels
+    pub width: u32,
+    /// height in pixels
+    pub height: u32,
+    /// pixelformat code
+    pub fourcc: FourCC,
+    /// field order for interlacing
+    pub field_order: FieldOrder,
+
+    /// layout of each plane, at most [`MAX_PLANES`]
+    pub planes: Vec<Plane>,
+
+    /// flags set by the application or driver
+    pub flags: u8,
+
+    /// supplements the pixelformat (fourcc) information
+    pub colorspace: Colorspace,
+    /// the way colors are mapped
+    pub quantization: Quantization,
+    /// the transfer function for the colorspace
+    pub transfer: TransferFunction,
+}
+
+impl Format {
+    /// Returns a multi-planar format, the driver fills in the plane layout
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width in pixels
+    /// * `height` - Height in pixels
+    /// * `fourcc` - Four character code (pixelformat)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::format::mplane::Format;
+    /// use v4l::FourCC;
+    /// let fmt = Format::new(640, 480, FourCC::new(b"NM12"));
+    /// ```
+    pub fn new(width: u32, height: u32, fourcc: FourCC) -> Self {
+        Format {
+            width,
+            height,
+            fourcc,
+            field_order: FieldOrder::Any,
+            planes: Vec::new(),
+            flags: 0,
+            colorspace: Colorspace::Default,
+            quantization: Quantization::Default,
+            transfer: TransferFunction::Default,
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "width          : {}", self.width)?;
+        writeln!(f, "height         : {}", self.height)?;
+        writeln!(f, "fourcc         : {}", self.fourcc)?;
+        writeln!(f, "field          : {}", self.field_order)?;
+        for (i, plane) in self.planes.iter().enumerate() {
+            writeln!(
+                f,
+                "plane {}        : stride {}, size {}",
+                i, plane.stride, plane.size
+            )?;
+        }
+        writeln!(f, "colorspace     : {}", self.colorspace)?;
+        writeln!(f, "quantization   : {}", self.quantization)?;
+        writeln!(f, "transfer       : {}", self.transfer)?;
+        Ok(())
+    }
+}
+
+impl From<v4l2_pix_format_mplane> for Format {
+    fn from(fmt: v4l2_pix_format_mplane) -> Self {
+        // the struct is packed, so copy the plane array out before borrowing it
+        let plane_fmt = fmt.plane_fmt;
+        let num_planes = (fmt.num_planes as usize).min(MAX_PLANES);
+        Self {
+            width: fmt.width,
+            height: fmt.height,
+            fourcc: FourCC::from(fmt.pixelformat),
+            field_order: FieldOrder::try_from(fmt.field).unwrap_or(FieldOrder::Any),
+            planes: plane_fmt[..num_planes]
+                .iter()
+                .map(|plane| Plane {
+                    stride: plane.bytesperline,
+                    size: plane.sizeimage,
+                })
+                .collect(),
+            flags: fmt.flags,
+            colorspace: Colorspace::try_from(fmt.colorspace).unwrap_or(Colorspace::Default),
+            quantization: Quantization::try_from(fmt.quantization as u32)
+                .unwrap_or(Quantization::Default),
+            transfer: TransferFunction::try_from(fmt.xfer_func as u32)
+                .unwrap_or(TransferFunction::Default),
+        }
+    }
+}
+
+impl From<&Format> for v4l2_pix_format_mplane {
+    fn from(format: &Format) -> Self {
+        let mut plane_fmt: [v4l2_plane_pix_format; MAX_PLANES] = unsafe { mem::zeroed() };
+        for (raw, plane) in plane_fmt.iter_mut().zip(&format.planes) {
+            raw.bytesperline = plane.stride;
+            raw.sizeimage = plane.size;
+        }
+
+        Self {
+            width: format.width,
+            height: format.height,
+            pixelformat: format.fourcc.into(),
+            field: format.field_order as u32,
+            colorspace: format.colorspace as u32,
+            plane_fmt,
+            num_planes: format.planes.len().min(MAX_PLANES) as u8,
+            flags: format.flags,
+            quantization: format.quantization as u8,
+            xfer_func: format.transfer as u8,
+            ..unsafe { mem::zeroed() }
+        }
+    }
+}
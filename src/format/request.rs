@@ -0,0 +1,58 @@
+use crate::format::{Format, FourCC};
+
+/// Acceptable format, see [`crate::Device::set_format_any()`]
+///
+/// Drivers replace unsupported pixel formats with one they support, so a candidate only matches
+/// if the driver keeps its pixel format. The resolution is adjusted freely unless the request
+/// is [`exact`](Self::exact).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FormatRequest {
+    /// Pixel format
+    pub fourcc: FourCC,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Whether only this exact resolution is acceptable
+    pub exact: bool,
+}
+
+impl FormatRequest {
+    /// Returns a request for a pixel format, at the resolution closest to the given one
+    ///
+    /// # Arguments
+    ///
+    /// * `fourcc` - Pixel format
+    /// * `width` - Width in pixels
+    /// * `height` - Height in pixels
+    pub fn new(fourcc: FourCC, width: u32, height: u32) -> Self {
+        FormatRequest {
+            fourcc,
+            width,
+            height,
+            exact: false,
+        }
+    }
+
+    /// Only accepts the exact resolution
+    pub fn exact(mut self) -> Self {
+        self.exact = true;
+        self
+    }
+
+    /// Returns whether a format adjusted by the driver satisfies the request
+    ///
+    /// # Arguments
+    ///
+    /// * `fmt` - Format returned by the driver
+    pub fn matches(&self, fmt: &Format) -> bool {
+        fmt.fourcc == self.fourcc
+            && (!self.exact || (fmt.width, fmt.height) == (self.width, self.height))
+    }
+}
+
+impl From<&FormatRequest> for Format {
+    fn from(req: &FormatRequest) -> Self {
+        Format::new(req.width, req.height, req.fourcc)
+    }
+}
@@ -2,6 +2,7 @@ use std::convert::TryFrom;
 use std::fmt;
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u32)]
 /// Transfer function for the colorspace. The driver decides this for capture streams and the user
 /// sets it for output streams.
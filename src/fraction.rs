@@ -1,8 +1,14 @@
 use crate::v4l_sys::*;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::fmt;
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Fraction used for timing settings
+///
+/// Fractions are equal if their numerators and denominators are, use [`Fraction::cmp_value()`]
+/// to compare their values.
 pub struct Fraction {
     pub numerator: u32,
     pub denominator: u32,
@@ -28,6 +34,156 @@ impl Fraction {
             denominator: denom,
         }
     }
+
+    /// Returns the frame interval for a frame rate
+    ///
+    /// Rates which are not integers are approximated as closely as possible, e.g. the NTSC rate
+    /// of 29.97 fps.
+    ///
+    /// # Arguments
+    ///
+    /// * `fps` - Frames per second
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::fraction::Fraction;
+    /// assert_eq!(Fraction::from_fps(30.0), Fraction::new(1, 30));
+    /// assert_eq!(Fraction::from_fps(29.97), Fraction::new(100, 2997));
+    /// ```
+    pub fn from_fps(fps: f64) -> Self {
+        if !fps.is_finite() || fps <= 0.0 {
+            return Fraction::new(0, 1);
+        }
+
+        // continued fraction expansion of the rate, the convergents are its best approximations
+        let (mut p0, mut q0, mut p1, mut q1) = (0u64, 1u64, 1u64, 0u64);
+        let mut x = fps;
+        loop {
+            let a = x.floor();
+            if a > u32::MAX as f64 {
+                break;
+            }
+            let a = a as u64;
+            let (p2, q2) = (a * p1 + p0, a * q1 + q0);
+            if p2 > u32::MAX as u64 || q2 > u32::MAX as u64 {
+                break;
+            }
+            (p0, q0, p1, q1) = (p1, q1, p2, q2);
+
+            let rest = x - a as f64;
+            if (p1 as f64 / q1 as f64 - fps).abs() <= fps * 1e-9 || rest < 1e-9 {
+                break;
+            }
+            x = 1.0 / rest;
+        }
+
+        if p1 == 0 {
+            // the rate is too low to be represented
+            return Fraction::new(u32::MAX, 1);
+        }
+        // the interval is the inverse of the rate
+        Fraction::new(q1 as u32, p1 as u32)
+    }
+
+    /// Returns the frame rate of a frame interval
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::fraction::Fraction;
+    /// assert_eq!(Fraction::new(1, 30).to_fps(), 30.0);
+    /// ```
+    pub fn to_fps(&self) -> f64 {
+        self.denominator as f64 / self.numerator as f64
+    }
+
+    /// Returns the fraction as floating point value
+    pub fn to_f64(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// Returns the fraction in lowest terms
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::fraction::Fraction;
+    /// let frac = Fraction::new(1001, 30000 * 1001).reduced();
+    /// assert_eq!((frac.numerator, frac.denominator), (1, 30000));
+    /// ```
+    pub fn reduced(&self) -> Self {
+        let gcd = gcd(self.numerator as u64, self.denominator as u64);
+        if gcd == 0 {
+            return *self;
+        }
+        Fraction::new(
+            (self.numerator as u64 / gcd) as u32,
+            (self.denominator as u64 / gcd) as u32,
+        )
+    }
+
+    /// Compares the values of two fractions, e.g. `1/30` equals `2/60`
+    ///
+    /// Returns `None` if either denominator is zero, such fractions have no value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::cmp::Ordering;
+    /// use v4l::fraction::Fraction;
+    ///
+    /// let frac = Fraction::new(1, 30);
+    /// assert_eq!(frac.cmp_value(&Fraction::new(2, 60)), Some(Ordering::Equal));
+    /// assert_eq!(frac.cmp_value(&Fraction::new(1, 25)), Some(Ordering::Less));
+    /// assert_eq!(frac.cmp_value(&Fraction::default()), None);
+    /// ```
+    pub fn cmp_value(&self, other: &Self) -> Option<Ordering> {
+        if self.denominator == 0 || other.denominator == 0 {
+            return None;
+        }
+        // cross multiplying cannot overflow with 64 bits
+        Some(
+            (self.numerator as u64 * other.denominator as u64)
+                .cmp(&(other.numerator as u64 * self.denominator as u64)),
+        )
+    }
+
+    /// Multiplies two fractions, the result is in lowest terms
+    ///
+    /// Returns `None` if the reduced result does not fit into 32 bits.
+    ///
+    /// # Arguments
+    ///
+    /// * `rhs` - Fraction to multiply with
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::fraction::Fraction;
+    ///
+    /// let frac = Fraction::new(1, 30);
+    /// assert_eq!(frac.checked_mul(Fraction::new(2, 1)), Some(Fraction::new(1, 15)));
+    ///
+    /// let frac = Fraction::new(u32::MAX, 1);
+    /// assert_eq!(frac.checked_mul(Fraction::new(2, 1)), None);
+    /// ```
+    pub fn checked_mul(&self, rhs: Fraction) -> Option<Fraction> {
+        let num = self.numerator as u64 * rhs.numerator as u64;
+        let denom = self.denominator as u64 * rhs.denominator as u64;
+        let gcd = gcd(num, denom).max(1);
+        Some(Fraction::new(
+            u32::try_from(num / gcd).ok()?,
+            u32::try_from(denom / gcd).ok()?,
+        ))
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
 }
 
 impl fmt::Display for Fraction {
@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::convert::TryFrom;
 use std::fmt;
 
@@ -5,6 +6,7 @@ use crate::{format::FourCC, fraction::Fraction};
 use crate::{v4l_sys, v4l_sys::*};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Format description as returned by [`crate::v4l2::vidioc::VIDIOC_ENUM_FRAMEINTERVALS`]
 pub struct FrameInterval {
     pub index: u32,
@@ -22,6 +24,7 @@ impl fmt::Display for FrameInterval {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FrameIntervalEnum {
     Discrete(Fraction),
     Stepwise(Stepwise),
@@ -63,6 +66,7 @@ impl TryFrom<v4l2_frmivalenum> for FrameIntervalEnum {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Stepwise {
     /// Minimum frame interval (in seconds).
     pub min: Fraction,
@@ -72,6 +76,34 @@ pub struct Stepwise {
     pub step: Fraction,
 }
 
+impl Stepwise {
+    /// Returns whether an interval lies within the range and on a step
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - Frame interval
+    pub fn contains(&self, interval: Fraction) -> bool {
+        let in_range = matches!(
+            (interval.cmp_value(&self.min), interval.cmp_value(&self.max)),
+            (
+                Some(Ordering::Equal | Ordering::Greater),
+                Some(Ordering::Equal | Ordering::Less)
+            )
+        );
+        if !in_range {
+            return false;
+        }
+
+        // (interval - min) / step must be an integer
+        let num = interval.numerator as u128 * self.min.denominator as u128
+            - self.min.numerator as u128 * interval.denominator as u128;
+        let denom = interval.denominator as u128 * self.min.denominator as u128;
+        let step_num = self.step.numerator as u128;
+        let step_denom = self.step.denominator as u128;
+        step_num == 0 || (num * step_denom).checked_rem(denom * step_num) == Some(0)
+    }
+}
+
 impl fmt::Display for Stepwise {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} - {} with step {}", self.min, self.max, self.step)?;
@@ -6,6 +6,7 @@ use crate::v4l_sys;
 use crate::v4l_sys::*;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Format description as returned by [`crate::v4l2::vidioc::VIDIOC_ENUM_FRAMESIZES`]
 pub struct FrameSize {
     pub index: u32,
@@ -21,6 +22,7 @@ impl fmt::Display for FrameSize {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FrameSizeEnum {
     Discrete(Discrete),
     Stepwise(Stepwise),
@@ -91,6 +93,7 @@ impl TryFrom<v4l2_frmsizeenum> for FrameSizeEnum {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Discrete {
     /// Width of the frame (in pixels).
     pub width: u32,
@@ -106,6 +109,7 @@ impl fmt::Display for Discrete {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Stepwise {
     /// Minimum frame width (in pixels).
     pub min_width: u32,
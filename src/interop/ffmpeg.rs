@@ -0,0 +1,77 @@
+use crate::format::FourCC;
+
+/// Mapping of V4L2 pixelformats to FFmpeg pixel format names (`AVPixelFormat`)
+#[rustfmt::skip]
+const PIXEL_FORMATS: &[(&[u8; 4], &str)] = &[
+    (b"YUYV", "yuyv422"),
+    (b"YVYU", "yvyu422"),
+    (b"UYVY", "uyvy422"),
+    (b"NV12", "nv12"),
+    (b"NV21", "nv21"),
+    (b"NV16", "nv16"),
+    (b"NV24", "nv24"),
+    (b"NV42", "nv42"),
+    (b"YU12", "yuv420p"),
+    (b"422P", "yuv422p"),
+    (b"411P", "yuv411p"),
+    (b"P010", "p010le"),
+    (b"GREY", "gray"),
+    (b"Y10 ", "gray10le"),
+    (b"Y12 ", "gray12le"),
+    (b"Y16 ", "gray16le"),
+    (b"RGB3", "rgb24"),
+    (b"BGR3", "bgr24"),
+    (b"RGBP", "rgb565le"),
+    (b"RGBO", "rgb555le"),
+    (b"XR24", "bgr0"),
+    (b"AR24", "bgra"),
+    (b"RX24", "0bgr"),
+    (b"RA24", "abgr"),
+    (b"XB24", "rgb0"),
+    (b"AB24", "rgba"),
+    (b"BX24", "0rgb"),
+    (b"BA24", "argb"),
+    (b"BA81", "bayer_bggr8"),
+    (b"GBRG", "bayer_gbrg8"),
+    (b"GRBG", "bayer_grbg8"),
+    (b"RGGB", "bayer_rggb8"),
+    // deprecated formats with ambiguous alpha handling, only used for V4L2 -> FFmpeg
+    (b"BGR4", "bgr0"),
+    (b"RGB4", "0rgb"),
+];
+
+/// Returns the FFmpeg pixel format name of a pixelformat
+///
+/// The name can be passed to `av_get_pix_fmt()` or used as `-pixel_format` argument.
+///
+/// # Arguments
+///
+/// * `fourcc` - Four character code (pixelformat)
+///
+/// # Example
+///
+/// ```
+/// use v4l::interop::ffmpeg;
+/// use v4l::FourCC;
+///
+/// assert_eq!(ffmpeg::pix_fmt(FourCC::new(b"YU12")), Some("yuv420p"));
+/// assert_eq!(ffmpeg::fourcc("yuyv422"), Some(FourCC::new(b"YUYV")));
+/// ```
+pub fn pix_fmt(fourcc: FourCC) -> Option<&'static str> {
+    PIXEL_FORMATS
+        .iter()
+        .find(|(repr, _)| FourCC::new(repr) == fourcc)
+        .map(|(_, name)| *name)
+}
+
+/// Returns the pixelformat for an FFmpeg pixel format name
+///
+/// # Arguments
+///
+/// * `name` - FFmpeg pixel format name, as returned by `av_get_pix_fmt_name()`
+pub fn fourcc(name: &str) -> Option<FourCC> {
+    PIXEL_FORMATS
+        .iter()
+        .find(|(_, other)| *other == name)
+        .map(|(repr, _)| FourCC::new(repr))
+}
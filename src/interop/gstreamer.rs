@@ -0,0 +1,265 @@
+use std::fmt::Write;
+use std::str;
+
+use crate::format::{Format, FourCC};
+use crate::fraction::Fraction;
+
+/// Raw video formats, mapping V4L2 pixelformats to GStreamer `video/x-raw` format names
+#[rustfmt::skip]
+const RAW_FORMATS: &[(&[u8; 4], &str)] = &[
+    (b"YUYV", "YUY2"),
+    (b"YVYU", "YVYU"),
+    (b"UYVY", "UYVY"),
+    (b"VYUY", "VYUY"),
+    (b"NV12", "NV12"),
+    (b"NV21", "NV21"),
+    (b"NV16", "NV16"),
+    (b"NV61", "NV61"),
+    (b"NV24", "NV24"),
+    (b"YU12", "I420"),
+    (b"YV12", "YV12"),
+    (b"422P", "Y42B"),
+    (b"P010", "P010_10LE"),
+    (b"GREY", "GRAY8"),
+    (b"Y16 ", "GRAY16_LE"),
+    (b"RGB3", "RGB"),
+    (b"BGR3", "BGR"),
+    (b"RGBP", "RGB16"),
+    (b"RGBO", "RGB15"),
+    (b"XR24", "BGRx"),
+    (b"AR24", "BGRA"),
+    (b"RX24", "xBGR"),
+    (b"RA24", "ABGR"),
+    (b"XB24", "RGBx"),
+    (b"AB24", "RGBA"),
+    (b"BX24", "xRGB"),
+    (b"BA24", "ARGB"),
+    // deprecated formats with ambiguous alpha handling, only used for V4L2 -> GStreamer
+    (b"BGR4", "BGRx"),
+    (b"RGB4", "xRGB"),
+];
+
+/// Compressed formats, mapping V4L2 pixelformats to complete GStreamer media types
+#[rustfmt::skip]
+const ENCODED_FORMATS: &[(&[u8; 4], &str)] = &[
+    (b"MJPG", "image/jpeg"),
+    (b"JPEG", "image/jpeg"),
+    (b"H264", "video/x-h264,stream-format=byte-stream,alignment=au"),
+    (b"HEVC", "video/x-h265,stream-format=byte-stream,alignment=au"),
+    (b"VP80", "video/x-vp8"),
+    (b"VP90", "video/x-vp9"),
+    (b"MPG2", "video/mpeg,mpegversion=2"),
+    (b"MPG4", "video/mpeg,mpegversion=4"),
+];
+
+/// Returns the GStreamer `video/x-raw` format name of a raw pixelformat
+///
+/// # Arguments
+///
+/// * `fourcc` - Four character code (pixelformat)
+///
+/// # Example
+///
+/// ```
+/// use v4l::interop::gstreamer;
+/// use v4l::FourCC;
+///
+/// assert_eq!(gstreamer::video_format(FourCC::new(b"YUYV")), Some("YUY2"));
+/// ```
+pub fn video_format(fourcc: FourCC) -> Option<&'static str> {
+    RAW_FORMATS
+        .iter()
+        .find(|(repr, _)| FourCC::new(repr) == fourcc)
+        .map(|(_, name)| *name)
+}
+
+/// Returns the pixelformat for a GStreamer `video/x-raw` format name
+///
+/// # Arguments
+///
+/// * `name` - GStreamer format name, e.g. "NV12"
+pub fn fourcc(name: &str) -> Option<FourCC> {
+    RAW_FORMATS
+        .iter()
+        .find(|(_, other)| *other == name)
+        .map(|(repr, _)| FourCC::new(repr))
+}
+
+/// GStreamer caps describing a single, fixed video format
+///
+/// Use [`Caps::to_caps_string()`] to generate a caps string (e.g. for an `appsrc` element) and
+/// [`str::FromStr`] to parse one.
+///
+/// # Example
+///
+/// ```
+/// use v4l::interop::gstreamer::Caps;
+/// use v4l::{Format, FourCC, Fraction};
+///
+/// let caps = Caps::new(Format::new(640, 480, FourCC::new(b"NV12")))
+///     .with_interval(Fraction::new(1, 30));
+/// assert_eq!(
+///     caps.to_caps_string().as_deref(),
+///     Some("video/x-raw,format=NV12,width=640,height=480,framerate=30/1")
+/// );
+///
+/// // formats without a GStreamer counterpart have no caps string
+/// let caps = Caps::new(Format::new(640, 480, FourCC::new(b"XXXX")));
+/// assert_eq!(caps.to_caps_string(), None);
+///
+/// let caps: Caps = "video/x-raw,format=(string)YUY2,width=(int)1280,height=(int)720"
+///     .parse()
+///     .unwrap();
+/// assert_eq!(caps.format.fourcc, FourCC::new(b"YUYV"));
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct Caps {
+    /// Video format, only width, height and fourcc are represented in the caps
+    pub format: Format,
+    /// Frame interval, e.g. 1/30 for 30 frames per second
+    pub interval: Option<Fraction>,
+}
+
+impl Caps {
+    /// Returns caps for a format without a framerate
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Video format
+    pub fn new(format: Format) -> Self {
+        Caps {
+            format,
+            interval: None,
+        }
+    }
+
+    /// Adds the frame interval (the inverse of the framerate) to the caps
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - Time per frame, as reported by the streaming parameters
+    pub fn with_interval(mut self, interval: Fraction) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Returns the caps string, or `None` if GStreamer has no counterpart for the pixelformat
+    pub fn to_caps_string(&self) -> Option<String> {
+        let mut caps = format!(
+            "{},width={},height={}",
+            self.media_type()?,
+            self.format.width,
+            self.format.height
+        );
+        if let Some(interval) = self.interval {
+            // GStreamer uses frames per second whereas V4L2 uses time per frame. Writing to a
+            // String cannot fail.
+            let _ = write!(
+                caps,
+                ",framerate={}/{}",
+                interval.denominator, interval.numerator
+            );
+        }
+        Some(caps)
+    }
+
+    /// Returns the media type and fixed fields for the pixelformat of the caps
+    fn media_type(&self) -> Option<String> {
+        if let Some(name) = video_format(self.format.fourcc) {
+            return Some(format!("video/x-raw,format={}", name));
+        }
+
+        ENCODED_FORMATS
+            .iter()
+            .find(|(repr, _)| FourCC::new(repr) == self.format.fourcc)
+            .map(|(_, media_type)| media_type.to_string())
+    }
+}
+
+/// Strips an optional type annotation, e.g. "(int)640" -> "640"
+fn strip_type(value: &str) -> &str {
+    let value = value.trim();
+    if value.starts_with('(') {
+        if let Some(end) = value.find(')') {
+            return value[end + 1..].trim();
+        }
+    }
+    value
+}
+
+impl str::FromStr for Caps {
+    type Err = String;
+
+    fn from_str(caps: &str) -> Result<Self, Self::Err> {
+        let mut fields = caps.split(',');
+        let media_type = fields.next().unwrap_or_default().trim();
+
+        let mut fourcc = None;
+        let mut width = None;
+        let mut height = None;
+        let mut interval = None;
+        let mut extra = Vec::new();
+
+        for field in fields {
+            let (key, value) = match field.find('=') {
+                Some(pos) => (field[..pos].trim(), strip_type(&field[pos + 1..])),
+                None => return Err(format!("Invalid caps field: {}", field)),
+            };
+            if value.starts_with('{') || value.starts_with('[') {
+                return Err(format!("Caps are not fixed: {}", field));
+            }
+
+            match key {
+                "format" => {
+                    fourcc = Some(
+                        self::fourcc(value)
+                            .ok_or_else(|| format!("Unknown video format: {}", value))?,
+                    )
+                }
+                "width" => width = Some(value.parse::<u32>().map_err(|e| e.to_string())?),
+                "height" => height = Some(value.parse::<u32>().map_err(|e| e.to_string())?),
+                "framerate" => {
+                    let mut parts = value.split('/');
+                    let num = parts.next().unwrap_or_default();
+                    let denom = parts.next().unwrap_or("1");
+                    let num = num.trim().parse::<u32>().map_err(|e| e.to_string())?;
+                    let denom = denom.trim().parse::<u32>().map_err(|e| e.to_string())?;
+                    // a framerate of 0/1 denotes a variable framerate
+                    if num != 0 {
+                        interval = Some(Fraction::new(denom, num));
+                    }
+                }
+                _ => extra.push(format!("{}={}", key, value)),
+            }
+        }
+
+        let fourcc = match (media_type, fourcc) {
+            ("video/x-raw", Some(fourcc)) => fourcc,
+            ("video/x-raw", None) => return Err("Missing format field".to_string()),
+            (media_type, _) => ENCODED_FORMATS
+                .iter()
+                .find(|(_, other)| {
+                    // The media type may carry additional fields such as the mpegversion. Only
+                    // consider those which are actually present in the caps for matching.
+                    let mut fields = other.split(',');
+                    fields.next() == Some(media_type)
+                        && fields.all(|field| {
+                            let key = field.split('=').next().unwrap_or_default();
+                            extra
+                                .iter()
+                                .all(|e| e.split('=').next() != Some(key) || e == field)
+                        })
+                })
+                .map(|(repr, _)| FourCC::new(repr))
+                .ok_or_else(|| format!("Unknown media type: {}", media_type))?,
+        };
+
+        let width = width.ok_or_else(|| "Missing width field".to_string())?;
+        let height = height.ok_or_else(|| "Missing height field".to_string())?;
+
+        Ok(Caps {
+            format: Format::new(width, height, fourcc),
+            interval,
+        })
+    }
+}
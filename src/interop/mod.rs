@@ -0,0 +1,19 @@
+//! Interoperability helpers for other multimedia frameworks
+//!
+//! The modules in here do not depend on the respective frameworks unless explicitly noted. They
+//! merely translate between the types of this crate and the representation used by the other side.
+//!
+//! The `ndarray` and `vulkan` modules are the exceptions: they build types of the `ndarray` and
+//! `ash` crates and are only available with the `ndarray` and `ash` features respectively.
+
+#[cfg(feature = "ffmpeg")]
+pub mod ffmpeg;
+
+#[cfg(feature = "gstreamer")]
+pub mod gstreamer;
+
+#[cfg(feature = "ndarray")]
+pub mod ndarray;
+
+#[cfg(feature = "ash")]
+pub mod vulkan;
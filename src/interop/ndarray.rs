@@ -0,0 +1,119 @@
+use std::io;
+
+use ndarray::{ArrayView3, ShapeBuilder};
+
+use crate::format::{Format, FourCC};
+
+/// Layout of a plane: horizontal and vertical subsampling, channels and stride divisor
+type Plane = (u32, u32, usize, u32);
+
+/// Formats with 8 bit samples, along with the layout of their planes
+///
+/// Packed YUV formats are represented as two channels per pixel, holding the luma and the
+/// alternating chroma samples respectively.
+#[rustfmt::skip]
+const FORMATS: &[(&[u8; 4], &[Plane])] = &[
+    (b"GREY", &[(1, 1, 1, 1)]),
+    (b"RGB3", &[(1, 1, 3, 1)]),
+    (b"BGR3", &[(1, 1, 3, 1)]),
+    (b"AR24", &[(1, 1, 4, 1)]),
+    (b"XR24", &[(1, 1, 4, 1)]),
+    (b"RA24", &[(1, 1, 4, 1)]),
+    (b"RX24", &[(1, 1, 4, 1)]),
+    (b"AB24", &[(1, 1, 4, 1)]),
+    (b"XB24", &[(1, 1, 4, 1)]),
+    (b"BA24", &[(1, 1, 4, 1)]),
+    (b"BX24", &[(1, 1, 4, 1)]),
+    (b"BGR4", &[(1, 1, 4, 1)]),
+    (b"RGB4", &[(1, 1, 4, 1)]),
+    (b"YUYV", &[(1, 1, 2, 1)]),
+    (b"YVYU", &[(1, 1, 2, 1)]),
+    (b"UYVY", &[(1, 1, 2, 1)]),
+    (b"VYUY", &[(1, 1, 2, 1)]),
+    (b"NV12", &[(1, 1, 1, 1), (2, 2, 2, 1)]),
+    (b"NV21", &[(1, 1, 1, 1), (2, 2, 2, 1)]),
+    (b"NV16", &[(1, 1, 1, 1), (2, 1, 2, 1)]),
+    (b"NV61", &[(1, 1, 1, 1), (2, 1, 2, 1)]),
+    (b"YU12", &[(1, 1, 1, 1), (2, 2, 1, 2), (2, 2, 1, 2)]),
+    (b"YV12", &[(1, 1, 1, 1), (2, 2, 1, 2), (2, 2, 1, 2)]),
+    (b"422P", &[(1, 1, 1, 1), (2, 1, 1, 2), (2, 1, 1, 2)]),
+];
+
+/// Returns views of all planes of a frame
+///
+/// Each view has the shape height × width × channels and honors the stride (bytes per line) of
+/// the format, so padding at the end of each line is skipped. Planes of multi-planar formats are
+/// expected to be stored contiguously, which is the case for single-planar buffers.
+///
+/// # Arguments
+///
+/// * `buf` - Frame data
+/// * `format` - Format of the frame, as returned by the device
+pub fn planes<'a>(buf: &'a [u8], format: &Format) -> io::Result<Vec<ArrayView3<'a, u8>>> {
+    let (_, layout) = FORMATS
+        .iter()
+        .find(|(repr, _)| FourCC::new(repr) == format.fourcc)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported pixelformat: {}", format.fourcc),
+            )
+        })?;
+
+    let mut offset = 0;
+    let mut views = Vec::with_capacity(layout.len());
+    for (hsub, vsub, channels, stride_div) in layout.iter() {
+        let width = (format.width / hsub) as usize;
+        let height = (format.height / vsub) as usize;
+        // drivers may report a stride of zero for formats without padding
+        let stride = match format.stride {
+            0 => width * channels,
+            stride => (stride / stride_div) as usize,
+        };
+
+        let data = buf.get(offset..).unwrap_or_default();
+        let view = ArrayView3::from_shape(
+            (height, width, *channels).strides((stride, *channels, 1)),
+            data,
+        )
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        views.push(view);
+        offset += stride * height;
+    }
+
+    Ok(views)
+}
+
+/// Returns a view of a frame with a single plane
+///
+/// See [`planes`] for details about the shape of the view.
+///
+/// # Arguments
+///
+/// * `buf` - Frame data
+/// * `format` - Format of the frame, as returned by the device
+///
+/// # Example
+///
+/// ```
+/// use v4l::interop::ndarray;
+/// use v4l::{Format, FourCC};
+///
+/// let mut format = Format::new(4, 2, FourCC::new(b"RGB3"));
+/// format.stride = 16;
+///
+/// let buf = [0u8; 32];
+/// let view = ndarray::view(&buf, &format).unwrap();
+/// assert_eq!(view.dim(), (2, 4, 3));
+/// ```
+pub fn view<'a>(buf: &'a [u8], format: &Format) -> io::Result<ArrayView3<'a, u8>> {
+    let mut planes = planes(buf, format)?;
+    if planes.len() != 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("pixelformat {} has multiple planes", format.fourcc),
+        ));
+    }
+
+    Ok(planes.remove(0))
+}
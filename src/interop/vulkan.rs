@@ -0,0 +1,207 @@
+use std::io;
+use std::os::unix::io::{AsRawFd, IntoRawFd, OwnedFd};
+
+use ash::vk;
+
+use crate::format::{Format, FourCC};
+
+/// Linear (non-tiled) buffer layout, the only layout V4L2 drivers export
+pub const DRM_FORMAT_MOD_LINEAR: u64 = 0;
+
+/// (stride, height) divisors of a plane relative to the first plane
+type Plane = (u32, u32);
+
+/// Formats which can be sampled by Vulkan, along with the bytes per pixel of their first plane
+/// and the planes of their memory layout
+#[rustfmt::skip]
+const FORMATS: &[(&[u8; 4], vk::Format, u32, &[Plane])] = &[
+    (b"GREY", vk::Format::R8_UNORM, 1, &[(1, 1)]),
+    (b"Y16 ", vk::Format::R16_UNORM, 2, &[(1, 1)]),
+    (b"RGB3", vk::Format::R8G8B8_UNORM, 3, &[(1, 1)]),
+    (b"BGR3", vk::Format::B8G8R8_UNORM, 3, &[(1, 1)]),
+    (b"AR24", vk::Format::B8G8R8A8_UNORM, 4, &[(1, 1)]),
+    (b"XR24", vk::Format::B8G8R8A8_UNORM, 4, &[(1, 1)]),
+    (b"AB24", vk::Format::R8G8B8A8_UNORM, 4, &[(1, 1)]),
+    (b"XB24", vk::Format::R8G8B8A8_UNORM, 4, &[(1, 1)]),
+    (b"YUYV", vk::Format::G8B8G8R8_422_UNORM, 2, &[(1, 1)]),
+    (b"UYVY", vk::Format::B8G8R8G8_422_UNORM, 2, &[(1, 1)]),
+    (b"NV12", vk::Format::G8_B8R8_2PLANE_420_UNORM, 1, &[(1, 1), (1, 2)]),
+    (b"NV16", vk::Format::G8_B8R8_2PLANE_422_UNORM, 1, &[(1, 1), (1, 1)]),
+    (b"P010", vk::Format::G10X6_B10X6R10X6_2PLANE_420_UNORM_3PACK16, 2, &[(1, 1), (1, 2)]),
+    (b"YU12", vk::Format::G8_B8_R8_3PLANE_420_UNORM, 1, &[(1, 1), (2, 2), (2, 2)]),
+    (b"422P", vk::Format::G8_B8_R8_3PLANE_422_UNORM, 1, &[(1, 1), (2, 1), (2, 1)]),
+];
+
+/// Returns the Vulkan format matching the memory layout of a pixelformat
+///
+/// # Arguments
+///
+/// * `fourcc` - Four character code (pixelformat)
+///
+/// # Example
+///
+/// ```
+/// use ash::vk;
+/// use v4l::interop::vulkan;
+/// use v4l::FourCC;
+///
+/// assert_eq!(
+///     vulkan::format(FourCC::new(b"NV12")),
+///     Some(vk::Format::G8_B8R8_2PLANE_420_UNORM)
+/// );
+/// ```
+pub fn format(fourcc: FourCC) -> Option<vk::Format> {
+    FORMATS
+        .iter()
+        .find(|(repr, _, _, _)| FourCC::new(repr) == fourcc)
+        .map(|(_, format, _, _)| *format)
+}
+
+/// Exported DMA buffer along with the information required to import it into Vulkan
+///
+/// Use [`crate::io::mmap::Stream::export`] to obtain the file descriptor of a capture buffer.
+/// The structures returned by the methods of this type are meant to be chained into the
+/// `p_next` fields of `vk::ImageCreateInfo` (with `vk::ImageTiling::DRM_FORMAT_MODIFIER_EXT`)
+/// and `vk::MemoryAllocateInfo` respectively, as described by `VK_EXT_external_memory_dma_buf`
+/// and `VK_EXT_image_drm_format_modifier`.
+#[derive(Debug)]
+pub struct DmaBuf {
+    /// DMABUF file descriptor
+    pub fd: OwnedFd,
+    /// Format of the image stored in the buffer
+    pub format: Format,
+    /// DRM format modifier describing the memory layout
+    pub modifier: u64,
+}
+
+impl DmaBuf {
+    /// Returns a linear DMA buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `fd` - DMABUF file descriptor
+    /// * `format` - Format of the image, as returned by the device
+    pub fn new(fd: OwnedFd, format: Format) -> Self {
+        DmaBuf {
+            fd,
+            format,
+            modifier: DRM_FORMAT_MOD_LINEAR,
+        }
+    }
+
+    /// Overrides the DRM format modifier, for drivers producing tiled or compressed layouts
+    ///
+    /// # Arguments
+    ///
+    /// * `modifier` - DRM format modifier
+    pub fn with_modifier(mut self, modifier: u64) -> Self {
+        self.modifier = modifier;
+        self
+    }
+
+    /// Returns the Vulkan format of the image, if it can be represented
+    pub fn vk_format(&self) -> Option<vk::Format> {
+        format(self.format.fourcc)
+    }
+
+    /// Returns the extent of the image
+    pub fn extent(&self) -> vk::Extent3D {
+        vk::Extent3D {
+            width: self.format.width,
+            height: self.format.height,
+            depth: 1,
+        }
+    }
+
+    /// Returns the layout of each plane of the image within the buffer
+    ///
+    /// The planes of multi-planar formats are expected to be stored contiguously, which is the
+    /// case for all single-planar V4L2 buffers. Subsampled planes of odd-sized images are rounded
+    /// up, and drivers which leave the stride to the application are assumed to pack the rows.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use v4l::interop::vulkan::DmaBuf;
+    /// use v4l::{Format, FourCC};
+    ///
+    /// let fd = File::open("/dev/null").expect("Failed to open file").into();
+    /// let buf = DmaBuf::new(fd, Format::new(640, 481, FourCC::new(b"NV12")));
+    /// let layouts = buf.plane_layouts().expect("Failed to get plane layouts");
+    /// assert_eq!(layouts[1].offset, 640 * 481);
+    /// assert_eq!(layouts[1].row_pitch, 640);
+    /// ```
+    pub fn plane_layouts(&self) -> Option<Vec<vk::SubresourceLayout>> {
+        let (_, _, bpp, planes) = FORMATS
+            .iter()
+            .find(|(repr, _, _, _)| FourCC::new(repr) == self.format.fourcc)?;
+
+        let stride = match self.format.stride {
+            0 => u64::from(self.format.width) * u64::from(*bpp),
+            stride => u64::from(stride),
+        };
+        let mut offset = 0;
+        let layouts = planes
+            .iter()
+            .map(|(stride_div, height_div)| {
+                let row_pitch = stride.div_ceil(u64::from(*stride_div));
+                let layout = vk::SubresourceLayout {
+                    offset,
+                    row_pitch,
+                    ..Default::default()
+                };
+                offset += row_pitch * u64::from(self.format.height.div_ceil(*height_div));
+                layout
+            })
+            .collect();
+        Some(layouts)
+    }
+
+    /// Returns the info required to create an image which can be bound to external memory
+    pub fn external_memory_image_info() -> vk::ExternalMemoryImageCreateInfo {
+        vk::ExternalMemoryImageCreateInfo {
+            handle_types: vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the explicit DRM format modifier info for image creation
+    ///
+    /// # Arguments
+    ///
+    /// * `layouts` - Plane layouts as returned by [`DmaBuf::plane_layouts`]
+    ///
+    /// The returned structure points into `layouts`, so they must outlive it.
+    pub fn modifier_info(
+        &self,
+        layouts: &[vk::SubresourceLayout],
+    ) -> vk::ImageDrmFormatModifierExplicitCreateInfoEXT {
+        vk::ImageDrmFormatModifierExplicitCreateInfoEXT {
+            drm_format_modifier: self.modifier,
+            drm_format_modifier_plane_count: layouts.len() as u32,
+            p_plane_layouts: layouts.as_ptr(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the info required to import the buffer as device memory
+    ///
+    /// A successful import transfers ownership of the file descriptor to the Vulkan
+    /// implementation, so a duplicate is handed out here. If the import fails, the caller is
+    /// responsible for closing the duplicate file descriptor.
+    pub fn import_info(&self) -> io::Result<vk::ImportMemoryFdInfoKHR> {
+        let fd = self.fd.try_clone()?;
+        Ok(vk::ImportMemoryFdInfoKHR {
+            handle_type: vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT,
+            fd: fd.into_raw_fd(),
+            ..Default::default()
+        })
+    }
+}
+
+impl AsRawFd for DmaBuf {
+    fn as_raw_fd(&self) -> std::os::raw::c_int {
+        self.fd.as_raw_fd()
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::time::{Duration, Instant};
+use std::{io, slice};
+
+use crate::buffer::{Metadata, Type};
+use crate::device::{Device, Handle};
+use crate::io::mmap::Stream;
+use crate::io::traits::{CaptureStream, Stream as StreamTrait};
+
+/// What to do when a subscriber does not keep up with the capture rate
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Policy {
+    /// Drop the oldest queued frame to make room for the new one
+    DropOldest,
+    /// Drop the new frame, keeping the queued ones
+    DropNewest,
+}
+
+/// Frame shared between all subscribers of a [`FrameBroadcaster`]
+///
+/// The underlying buffer is handed back to the driver once the last reference is dropped, so
+/// frames should not be kept around for longer than necessary.
+pub struct Frame {
+    stream: Arc<Mutex<Stream<'static>>>,
+    index: usize,
+    data: *const u8,
+    len: usize,
+    meta: Metadata,
+}
+
+// The buffer memory stays mapped for as long as the stream is alive, which the frame keeps a
+// reference to. It is never written by userspace while the buffer is dequeued.
+unsafe impl Send for Frame {}
+unsafe impl Sync for Frame {}
+
+impl Frame {
+    /// Returns the buffer data
+    ///
+    /// Only the first `meta().bytesused` bytes hold actual frame data.
+    pub fn data(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.data, self.len) }
+    }
+
+    /// Returns the buffer metadata
+    pub fn meta(&self) -> &Metadata {
+        &self.meta
+    }
+
+    /// Returns the index of the underlying buffer
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl Drop for Frame {
+    fn drop(&mut self) {
+        if let Ok(mut stream) = self.stream.lock() {
+            // There is nobody to report an error to, the buffer is lost in this case.
+            let _ = CaptureStream::queue(&mut *stream, self.index);
+        }
+    }
+}
+
+struct Queue {
+    frames: VecDeque<Arc<Frame>>,
+    capacity: usize,
+    policy: Policy,
+    dropped: u64,
+    closed: bool,
+}
+
+struct Channel {
+    queue: Mutex<Queue>,
+    ready: Condvar,
+}
+
+/// Receiving end of a [`FrameBroadcaster`]
+pub struct Subscriber {
+    channel: Arc<Channel>,
+}
+
+impl Subscriber {
+    /// Returns the next frame, waiting for it if necessary
+    ///
+    /// Fails with [`io::ErrorKind::BrokenPipe`] once the broadcaster is gone and all queued
+    /// frames have been received.
+    pub fn recv(&self) -> io::Result<Arc<Frame>> {
+        let mut queue = self.channel.queue.lock().unwrap();
+        loop {
+            if let Some(frame) = queue.frames.pop_front() {
+                return Ok(frame);
+            }
+            if queue.closed {
+                return Err(io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    "broadcaster stopped",
+                ));
+            }
+            queue = self.channel.ready.wait(queue).unwrap();
+        }
+    }
+
+    /// Returns the next frame, waiting at most for the given duration
+    ///
+    /// Fails with [`io::ErrorKind::TimedOut`] if no frame arrived in time.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Maximum duration to wait for
+    pub fn recv_timeout(&self, timeout: Duration) -> io::Result<Arc<Frame>> {
+        let deadline = Instant::now() + timeout;
+        let mut queue = self.channel.queue.lock().unwrap();
+        loop {
+            if let Some(frame) = queue.frames.pop_front() {
+                return Ok(frame);
+            }
+            if queue.closed {
+                return Err(io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    "broadcaster stopped",
+                ));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "no frame received"));
+            }
+            queue = self
+                .channel
+                .ready
+                .wait_timeout(queue, deadline - now)
+                .unwrap()
+                .0;
+        }
+    }
+
+    /// Returns the next frame if one is queued already
+    pub fn try_recv(&self) -> Option<Arc<Frame>> {
+        self.channel.queue.lock().unwrap().frames.pop_front()
+    }
+
+    /// Returns the number of frames this subscriber missed because it did not keep up
+    pub fn dropped(&self) -> u64 {
+        self.channel.queue.lock().unwrap().dropped
+    }
+}
+
+/// Distributes captured frames to multiple consumers
+///
+/// The broadcaster owns a capture stream of mapped buffers. Each captured frame is handed out to
+/// all subscribers by reference, so no data is copied. A buffer is only handed back to the
+/// driver once every subscriber dropped its reference to the frame.
+///
+/// Each subscriber has a queue of limited capacity. If it does not keep up, frames are dropped
+/// for this subscriber only, according to its [`Policy`]. Keep the sum of all queue capacities
+/// below the number of buffers, or the driver may run out of buffers to capture into.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::thread;
+/// use v4l::buffer::Type;
+/// use v4l::device::Device;
+/// use v4l::io::broadcast::{FrameBroadcaster, Policy};
+///
+/// let dev = Device::new(0).expect("Failed to open device");
+/// let mut broadcaster = FrameBroadcaster::with_buffers(&dev, Type::VideoCapture, 8)
+///     .expect("Failed to create broadcaster");
+///
+/// let preview = broadcaster.subscribe(1, Policy::DropOldest);
+/// thread::spawn(move || {
+///     while let Ok(frame) = preview.recv() {
+///         println!("preview: {}", frame.meta().sequence);
+///     }
+/// });
+///
+/// let recording = broadcaster.subscribe(4, Policy::DropNewest);
+/// thread::spawn(move || {
+///     while let Ok(frame) = recording.recv() {
+///         println!("recording: {} bytes", frame.meta().bytesused);
+///     }
+/// });
+///
+/// loop {
+///     broadcaster.broadcast().expect("Failed to capture frame");
+/// }
+/// ```
+pub struct FrameBroadcaster {
+    handle: Arc<Handle>,
+    stream: Arc<Mutex<Stream<'static>>>,
+    subscribers: Vec<Weak<Channel>>,
+    timeout: Option<Duration>,
+}
+
+impl FrameBroadcaster {
+    /// Returns a broadcaster capturing into four buffers
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Capture device ref to get its file descriptor
+    /// * `buf_type` - Type of the buffers
+    pub fn new(dev: &Device, buf_type: Type) -> io::Result<Self> {
+        FrameBroadcaster::with_buffers(dev, buf_type, 4)
+    }
+
+    /// Returns a broadcaster capturing into the given number of buffers
+    ///
+    /// Streaming is started right away.
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Capture device ref to get its file descriptor
+    /// * `buf_type` - Type of the buffers
+    /// * `buf_count` - Number of buffers to request, the driver may allocate more
+    pub fn with_buffers(dev: &Device, buf_type: Type, buf_count: u32) -> io::Result<Self> {
+        let mut stream = Stream::with_buffers(dev, buf_type, buf_count)?;
+        for index in 0..stream.len() {
+            CaptureStream::queue(&mut stream, index)?;
+        }
+        stream.start()?;
+
+        Ok(FrameBroadcaster {
+            handle: dev.handle(),
+            stream: Arc::new(Mutex::new(stream)),
+            subscribers: Vec::new(),
+            timeout: None,
+        })
+    }
+
+    /// Sets a timeout for waiting on the next frame
+    pub fn set_timeout(&mut self, duration: Duration) {
+        self.timeout = Some(duration);
+    }
+
+    /// Clears the timeout for waiting on the next frame
+    pub fn clear_timeout(&mut self) {
+        self.timeout = None;
+    }
+
+    /// Adds a subscriber which receives all frames captured from now on
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - Maximum number of frames queued for the subscriber
+    /// * `policy` - What to do when the queue is full
+    pub fn subscribe(&mut self, capacity: usize, policy: Policy) -> Subscriber {
+        let channel = Arc::new(Channel {
+            queue: Mutex::new(Queue {
+                frames: VecDeque::with_capacity(capacity),
+                capacity: capacity.max(1),
+                policy,
+                dropped: 0,
+                closed: false,
+            }),
+            ready: Condvar::new(),
+        });
+        self.subscribers.push(Arc::downgrade(&channel));
+        Subscriber { channel }
+    }
+
+    /// Returns the number of active subscribers
+    pub fn subscribers(&self) -> usize {
+        self.subscribers
+            .iter()
+            .filter(|channel| channel.strong_count() > 0)
+            .count()
+    }
+
+    /// Captures a frame and hands it to all subscribers
+    ///
+    /// The frame is returned as well, so the capturing thread can act as a consumer, too.
+    pub fn broadcast(&mut self) -> io::Result<Arc<Frame>> {
+        // Do not hold the lock while waiting, frames are requeued concurrently.
+        if self.handle.poll(libc::POLLIN, self.timeout)? == 0 {
+            // This condition can only happen if there was a timeout.
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_DQBUF"));
+        }
+
+        let frame = {
+            let mut stream = self.stream.lock().unwrap();
+            let index = stream.dequeue_ready()?;
+            let (buf, meta) = stream.get_mut(index).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "invalid buffer index")
+            })?;
+            Frame {
+                stream: self.stream.clone(),
+                index,
+                data: buf.as_ptr(),
+                len: buf.len(),
+                meta: *meta,
+            }
+        };
+        let frame = Arc::new(frame);
+
+        self.subscribers.retain(|channel| {
+            let channel = match channel.upgrade() {
+                Some(channel) => channel,
+                None => return false,
+            };
+
+            // Frames pushed out of the queue are dropped after releasing the lock, since that
+            // requeues their buffer.
+            let mut evicted = None;
+            {
+                let mut queue = channel.queue.lock().unwrap();
+                if queue.frames.len() >= queue.capacity {
+                    queue.dropped += 1;
+                    match queue.policy {
+                        Policy::DropOldest => evicted = queue.frames.pop_front(),
+                        Policy::DropNewest => return true,
+                    }
+                }
+                queue.frames.push_back(frame.clone());
+            }
+            channel.ready.notify_one();
+            drop(evicted);
+
+            true
+        });
+
+        Ok(frame)
+    }
+}
+
+impl Drop for FrameBroadcaster {
+    fn drop(&mut self) {
+        for channel in self.subscribers.iter().filter_map(Weak::upgrade) {
+            channel.queue.lock().unwrap().closed = true;
+            channel.ready.notify_all();
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Association of user values with queued buffers
+//!
+//! Memory-to-memory devices such as encoders and decoders copy the timestamp of each buffer
+//! queued on the output side to the resulting buffer on the capture side (see
+//! [`crate::buffer::Flags::TIMESTAMP_COPY`]). Pipelines use this to correlate submitted input with
+//! the resulting output: [`Cookies`] hands out unique timestamps for arbitrary values, which are
+//! set on the queued buffer and used to look up the value again once a buffer carrying the same
+//! timestamp is dequeued.
+
+use std::collections::HashMap;
+
+use crate::timestamp::Timestamp;
+use crate::v4l_sys::time_t;
+
+/// Map of user values keyed by the timestamps of the buffers they belong to
+///
+/// # Example
+///
+/// ```
+/// use v4l::io::cookie::Cookies;
+///
+/// let mut cookies = Cookies::new();
+/// let first = cookies.insert("frame 0");
+/// let second = cookies.insert("frame 1");
+///
+/// // `first` and `second` are passed to the driver when queueing the input buffers, the driver
+/// // hands them back along with the output buffers
+/// assert_eq!(cookies.take(second), Some("frame 1"));
+/// assert_eq!(cookies.take(first), Some("frame 0"));
+/// assert!(cookies.is_empty());
+/// ```
+#[derive(Debug)]
+pub struct Cookies<T> {
+    next: u64,
+    values: HashMap<u64, T>,
+}
+
+impl<T> Default for Cookies<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Cookies<T> {
+    /// Returns an empty map
+    pub fn new() -> Self {
+        Cookies {
+            next: 1,
+            values: HashMap::new(),
+        }
+    }
+
+    /// Stores a value and returns the timestamp to queue the buffer with
+    ///
+    /// Timestamps are unique, non-zero and increasing, so drivers which reorder frames (e.g.
+    /// encoders producing B-frames) do not confuse them.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value associated with the buffer
+    pub fn insert(&mut self, value: T) -> Timestamp {
+        let token = self.next;
+        self.next += 1;
+        self.values.insert(token, value);
+
+        Timestamp::new((token / 1_000_000) as time_t, (token % 1_000_000) as time_t)
+    }
+
+    /// Returns the value associated with a timestamp without removing it
+    ///
+    /// This is useful if a single input buffer results in several output buffers, e.g. slices.
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - Timestamp of the dequeued buffer
+    pub fn get(&self, timestamp: Timestamp) -> Option<&T> {
+        self.values.get(&Self::token(timestamp))
+    }
+
+    /// Removes the value associated with a timestamp and returns it
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - Timestamp of the dequeued buffer
+    pub fn take(&mut self, timestamp: Timestamp) -> Option<T> {
+        self.values.remove(&Self::token(timestamp))
+    }
+
+    /// Returns the number of values still waiting for their buffers
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns whether no values are waiting for their buffers
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Removes all values, e.g. after the queues were flushed by stopping the stream
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    fn token(timestamp: Timestamp) -> u64 {
+        timestamp.sec as u64 * 1_000_000 + timestamp.usec as u64
+    }
+}
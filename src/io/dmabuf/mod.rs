@@ -0,0 +1,2 @@
+pub mod queue;
+pub use queue::Queue;
@@ -0,0 +1,402 @@
+use std::os::unix::io::AsRawFd;
+use std::time::Duration;
+use std::{io, mem, sync::Arc};
+
+use crate::buffer::{Metadata, State, Type};
+use crate::device::{Device, Handle};
+use crate::format::mplane::MAX_PLANES;
+use crate::format::FieldOrder;
+use crate::io::queue::{self, Queue as Inner};
+use crate::io::traits::{CaptureStream, OutputStream, Stream as StreamTrait};
+use crate::memory::Memory;
+use crate::timestamp::Timestamp;
+use crate::v4l_sys::*;
+
+/// Frame handed back by a [`Queue`]
+pub struct Frame<F> {
+    /// DMABUF file descriptors of the planes, in the order they were queued
+    pub planes: Vec<F>,
+    /// Number of bytes of each plane holding data
+    pub bytesused: Vec<u32>,
+    /// Buffer metadata
+    pub meta: Metadata,
+}
+
+/// Queue of imported DMABUF buffers
+///
+/// The buffers are allocated elsewhere, e.g. by a GPU, an ISP or another video device, and
+/// shared by their DMABUF file descriptors, so frames travel between devices without being
+/// copied. The queue owns the file descriptors while the driver uses the buffers and hands them
+/// back once it is done, which keeps the buffers alive in the meantime. Any type which exposes a
+/// raw file descriptor can be used, e.g. [`std::os::unix::io::OwnedFd`] or [`std::fs::File`].
+///
+/// Capture and output queues of both single- and multi-planar buffer types are supported. Each
+/// buffer must be at least as large as the image size of the active format.
+///
+/// Once imported, buffers can also be cycled by their index through [`CaptureStream`] and
+/// [`OutputStream`], whose items are the file descriptors of the planes. Frames dequeued that way
+/// are held by the queue until they are queued again, rather than handed back to the caller.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::buffer::Type;
+/// use v4l::device::Device;
+/// use v4l::io::dmabuf::Queue;
+/// use v4l::io::mmap::Stream;
+/// use v4l::timestamp::Timestamp;
+///
+/// // buffers exported by a decoder are displayed by an output device
+/// let decoder = Device::new(0).expect("Failed to open decoder");
+/// let display = Device::new(1).expect("Failed to open display");
+///
+/// let stream = Stream::new(&decoder, Type::VideoCapture).expect("Failed to create stream");
+/// let mut queue = Queue::new(&display, Type::VideoOutput, 4).expect("Failed to create queue");
+/// let fd = stream.export(0).expect("Failed to export buffer");
+/// queue
+///     .queue(fd, 0, Timestamp::default())
+///     .expect("Failed to queue buffer");
+/// let frame = queue.dequeue().expect("Failed to dequeue buffer");
+/// ```
+pub struct Queue<F: AsRawFd> {
+    inner: Inner,
+    slots: Vec<Option<Vec<F>>>,
+    held: Vec<Option<Frame<F>>>,
+    held_index: Option<usize>,
+}
+
+impl<F: AsRawFd> Queue<F> {
+    /// Returns a new queue importing DMABUF buffers
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Device ref to get its file descriptor
+    /// * `buf_type` - Type of the buffers
+    /// * `buf_count` - Maximum number of buffers handed to the driver at once
+    pub fn new(dev: &Device, buf_type: Type, buf_count: u32) -> io::Result<Self> {
+        let (inner, count) = Inner::new(dev, buf_type, Memory::DmaBuf, buf_count)?;
+        let mut slots = Vec::new();
+        slots.resize_with(count, || None);
+        let mut held = Vec::new();
+        held.resize_with(count, || None);
+
+        Ok(Queue {
+            inner,
+            slots,
+            held,
+            held_index: None,
+        })
+    }
+
+    /// Returns the raw device handle
+    pub fn handle(&self) -> Arc<Handle> {
+        self.inner.handle().clone()
+    }
+
+    /// Returns the current state of a buffer as reported by the driver
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the buffer
+    pub fn query_buffer(&self, index: usize) -> io::Result<State> {
+        self.inner.query_buffer(index)
+    }
+
+    /// Sets a timeout of the v4l file handle.
+    pub fn set_timeout(&mut self, duration: Duration) {
+        self.inner.set_timeout(Some(duration));
+    }
+
+    /// Clears the timeout of the v4l file handle.
+    pub fn clear_timeout(&mut self) {
+        self.inner.set_timeout(None);
+    }
+
+    /// Returns the number of buffers currently owned by the driver
+    pub fn queued(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    /// Hands a single-plane buffer to the driver
+    ///
+    /// See [`Queue::queue_planes()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `fd` - DMABUF file descriptor of the buffer
+    /// * `bytesused` - Number of bytes holding data, ignored for capture queues
+    /// * `timestamp` - Timestamp of the frame, ignored for capture queues
+    pub fn queue(&mut self, fd: F, bytesused: u32, timestamp: Timestamp) -> io::Result<()> {
+        self.queue_planes(vec![fd], &[bytesused], timestamp)
+    }
+
+    /// Hands a buffer made up of one file descriptor per plane to the driver
+    ///
+    /// Streaming is started with the first buffer. Fails with [`io::ErrorKind::InvalidInput`] if
+    /// the driver owns or the queue holds all buffers already, in which case the file descriptors
+    /// are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `planes` - DMABUF file descriptors, one per plane
+    /// * `bytesused` - Number of bytes holding data per plane, ignored for capture queues
+    /// * `timestamp` - Timestamp of the frame, ignored for capture queues
+    pub fn queue_planes(
+        &mut self,
+        planes: Vec<F>,
+        bytesused: &[u32],
+        timestamp: Timestamp,
+    ) -> io::Result<()> {
+        if planes.is_empty() || planes.len() != bytesused.len() || planes.len() > MAX_PLANES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid number of planes: {}", planes.len()),
+            ));
+        }
+        if !self.is_mplane() && planes.len() > 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?} buffers have a single plane", self.inner.buf_type()),
+            ));
+        }
+
+        let index = self
+            .slots
+            .iter()
+            .zip(&self.held)
+            .position(|(slot, held)| slot.is_none() && held.is_none())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "all buffers are queued"))?;
+
+        self.slots[index] = Some(planes);
+        if let Err(e) = self.submit(index, bytesused, timestamp) {
+            self.slots[index] = None;
+            return Err(e);
+        }
+
+        if !self.inner.is_active() {
+            self.start()?;
+        }
+
+        Ok(())
+    }
+
+    /// Waits for the driver to hand back a buffer and returns it
+    pub fn dequeue(&mut self) -> io::Result<Frame<F>> {
+        self.dequeue_index().map(|(_, frame)| frame)
+    }
+
+    fn dequeue_index(&mut self) -> io::Result<(usize, Frame<F>)> {
+        if self.queued() == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no buffers are queued",
+            ));
+        }
+
+        let mut v4l2_planes: [v4l2_plane; MAX_PLANES] = unsafe { mem::zeroed() };
+        let mut v4l2_buf = self.inner.buffer_desc();
+        if self.is_mplane() {
+            v4l2_buf.m.planes = v4l2_planes.as_mut_ptr();
+            v4l2_buf.length = MAX_PLANES as u32;
+        }
+        self.inner.dqbuf(&mut v4l2_buf)?;
+
+        let planes = self
+            .slots
+            .get_mut(v4l2_buf.index as usize)
+            .and_then(Option::take)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid buffer index"))?;
+        let bytesused = if self.is_mplane() {
+            v4l2_planes[..planes.len()]
+                .iter()
+                .map(|plane| plane.bytesused)
+                .collect()
+        } else {
+            vec![v4l2_buf.bytesused]
+        };
+
+        let frame = Frame {
+            planes,
+            bytesused,
+            meta: Metadata::from_raw(&v4l2_buf),
+        };
+        Ok((v4l2_buf.index as usize, frame))
+    }
+
+    /// Hands the planes of a slot to the driver
+    fn submit(&mut self, index: usize, bytesused: &[u32], timestamp: Timestamp) -> io::Result<()> {
+        let planes = match &self.slots[index] {
+            Some(planes) => planes,
+            None => return Ok(()),
+        };
+
+        let mut v4l2_planes: [v4l2_plane; MAX_PLANES] = unsafe { mem::zeroed() };
+        let mut v4l2_buf = v4l2_buffer {
+            index: index as u32,
+            field: FieldOrder::Progressive as u32,
+            timestamp: timestamp.into(),
+            ..self.inner.buffer_desc()
+        };
+        if self.is_mplane() {
+            for (plane, (fd, &bytesused)) in
+                v4l2_planes.iter_mut().zip(planes.iter().zip(bytesused))
+            {
+                plane.m.fd = fd.as_raw_fd();
+                plane.bytesused = bytesused;
+            }
+            v4l2_buf.m.planes = v4l2_planes.as_mut_ptr();
+            v4l2_buf.length = planes.len() as u32;
+        } else {
+            v4l2_buf.m.fd = planes[0].as_raw_fd();
+            v4l2_buf.bytesused = bytesused[0];
+        }
+        self.inner.qbuf(&mut v4l2_buf)
+    }
+
+    /// Hands a held buffer back to the driver
+    fn requeue(&mut self, index: usize) -> io::Result<()> {
+        let frame = self
+            .held
+            .get_mut(index)
+            .and_then(Option::take)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "buffer is not held"))?;
+        let Frame {
+            planes,
+            mut bytesused,
+            meta,
+        } = frame;
+        if !self.is_mplane() {
+            // like the other streams, the metadata tells how much of a single plane holds data
+            bytesused = vec![meta.bytesused];
+        }
+
+        self.slots[index] = Some(planes);
+        if let Err(e) = self.submit(index, &bytesused, meta.timestamp) {
+            if let Some(planes) = self.slots[index].take() {
+                self.held[index] = Some(Frame {
+                    planes,
+                    bytesused,
+                    meta,
+                });
+            }
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Removes a buffer from the drivers' outgoing queue and holds it
+    fn dequeue_held(&mut self) -> io::Result<usize> {
+        let (index, frame) = self.dequeue_index()?;
+        self.held[index] = Some(frame);
+        Ok(index)
+    }
+
+    fn is_mplane(&self) -> bool {
+        self.inner.buf_type().is_multiplanar()
+    }
+}
+
+impl<F: AsRawFd> StreamTrait for Queue<F> {
+    type Item = [F];
+
+    fn start(&mut self) -> io::Result<()> {
+        self.inner.start()
+    }
+
+    /// Stops streaming, the queue holds all buffers the driver owned until they are queued again
+    fn stop(&mut self) -> io::Result<()> {
+        self.inner.stop()?;
+
+        for (slot, held) in self.slots.iter_mut().zip(self.held.iter_mut()) {
+            if let Some(planes) = slot.take() {
+                *held = Some(Frame {
+                    bytesused: vec![0; planes.len()],
+                    planes,
+                    meta: Metadata::default(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, F: AsRawFd> CaptureStream<'a> for Queue<F> {
+    fn queue(&mut self, index: usize) -> io::Result<()> {
+        self.requeue(index)
+    }
+
+    fn dequeue(&mut self) -> io::Result<usize> {
+        self.dequeue_held()
+    }
+
+    fn next(&'a mut self) -> io::Result<(&Self::Item, &Metadata)> {
+        if !self.inner.is_active() {
+            // Enqueue all held buffers once on stream (re)start
+            for index in 0..self.held.len() {
+                if self.held[index].is_some() {
+                    CaptureStream::queue(self, index)?;
+                }
+            }
+        } else if let Some(index) = self.held_index {
+            CaptureStream::queue(self, index)?;
+        }
+        self.held_index = None;
+
+        if self.queued() == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no buffers are imported, see Queue::queue_planes()",
+            ));
+        }
+        if !self.inner.is_active() {
+            self.start()?;
+        }
+
+        let index = CaptureStream::dequeue(self)?;
+        self.held_index = Some(index);
+        match &self.held[index] {
+            Some(frame) => Ok((&frame.planes, &frame.meta)),
+            None => unreachable!(),
+        }
+    }
+}
+
+impl<'a, F: AsRawFd> OutputStream<'a> for Queue<F> {
+    fn queue(&mut self, index: usize) -> io::Result<()> {
+        self.requeue(index)
+    }
+
+    fn dequeue(&mut self) -> io::Result<usize> {
+        self.dequeue_held()
+    }
+
+    fn next(&'a mut self) -> io::Result<(&mut Self::Item, &mut Metadata)> {
+        // The buffer handed out last has been filled at the call site by now.
+        if let Some(index) = self.held_index.take() {
+            OutputStream::queue(self, index)?;
+            if !self.inner.is_active() {
+                self.start()?;
+            }
+        }
+
+        // Buffers held since the stream was stopped are handed out before waiting for the driver.
+        let index = match self.held.iter().position(Option::is_some) {
+            Some(index) => index,
+            None => OutputStream::dequeue(self)?,
+        };
+        self.held_index = Some(index);
+        match &mut self.held[index] {
+            Some(frame) => Ok((&mut frame.planes, &mut frame.meta)),
+            None => unreachable!(),
+        }
+    }
+}
+
+impl<F: AsRawFd> Drop for Queue<F> {
+    fn drop(&mut self) {
+        // The driver must be done with all buffers before their file descriptors are closed.
+        queue::teardown(self.inner.release());
+    }
+}
@@ -1,9 +1,13 @@
+use std::ops::Range;
 use std::{io, mem, ptr, slice, sync::Arc};
 
 use crate::buffer;
 use crate::device::Handle;
-use crate::memory::Memory;
+use crate::format::mplane::MAX_PLANES;
+use crate::io::queue;
+use crate::memory::{self, Memory};
 use crate::v4l2;
+use crate::v4l2::videodev::v4l2_remove_buffers;
 use crate::v4l_sys::*;
 
 /// Manage mapped buffers
@@ -12,7 +16,8 @@ use crate::v4l_sys::*;
 /// In case of errors during unmapping, we panic because there is memory corruption going on.
 pub struct Arena<'a> {
     handle: Arc<Handle>,
-    pub bufs: Vec<&'a mut [u8]>,
+    /// Mapped planes of each buffer, single-planar buffers consist of exactly one
+    pub bufs: Vec<Vec<&'a mut [u8]>>,
     pub buf_type: buffer::Type,
 }
 
@@ -63,40 +68,152 @@ impl<'a> Arena<'a> {
             )?;
         }
 
-        for index in 0..v4l2_reqbufs.count {
+        self.map(0..v4l2_reqbufs.count)?;
+        Ok(v4l2_reqbufs.count)
+    }
+
+    /// Appends buffers of a format to the ones allocated already
+    ///
+    /// Returns the range of indices of the new buffers.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of buffers to create
+    /// * `format` - Format the buffers must be able to hold
+    pub fn create(&mut self, count: u32, format: v4l2_format) -> io::Result<Range<usize>> {
+        let mut v4l2_create = v4l2_create_buffers {
+            count,
+            memory: Memory::Mmap as u32,
+            format,
+            ..unsafe { mem::zeroed() }
+        };
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_CREATE_BUFS,
+                &mut v4l2_create as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        let range = v4l2_create.index..v4l2_create.index + v4l2_create.count;
+        self.map(range.clone())?;
+        Ok(range.start as usize..range.end as usize)
+    }
+
+    /// Unmaps a range of buffers and frees them, if the driver supports it
+    ///
+    /// Returns whether the driver freed the buffers. Otherwise, they stay allocated until all
+    /// buffers are released. The buffers are left without planes either way.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - Indices of the buffers to remove
+    pub fn remove(&mut self, range: Range<usize>) -> io::Result<bool> {
+        let mut v4l2_remove = v4l2_remove_buffers {
+            index: range.start as u32,
+            count: range.len() as u32,
+            type_: self.buf_type as u32,
+            reserved: [0; 13],
+        };
+        let freed = match unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_REMOVE_BUFS,
+                &mut v4l2_remove as *mut _ as *mut std::os::raw::c_void,
+            )
+        } {
+            Ok(()) => true,
+            // kernels before 6.10 lack the ioctl
+            Err(e) if e.raw_os_error() == Some(libc::ENOTTY) => false,
+            Err(e) => return Err(e),
+        };
+
+        for planes in &mut self.bufs[range] {
+            for plane in planes.drain(..) {
+                unsafe {
+                    v4l2::munmap(plane.as_ptr() as *mut core::ffi::c_void, plane.len())?;
+                }
+            }
+        }
+
+        Ok(freed)
+    }
+
+    /// Maps all planes of a range of buffers
+    fn map(&mut self, range: Range<u32>) -> io::Result<()> {
+        if self.bufs.len() < range.end as usize {
+            self.bufs.resize_with(range.end as usize, Vec::new);
+        }
+
+        for index in range {
+            let mut v4l2_planes: [v4l2_plane; MAX_PLANES] = unsafe { mem::zeroed() };
             let mut v4l2_buf = v4l2_buffer {
                 index,
                 ..self.buffer_desc()
             };
+            if self.buf_type.is_multiplanar() {
+                v4l2_buf.m.planes = v4l2_planes.as_mut_ptr();
+                v4l2_buf.length = MAX_PLANES as u32;
+            }
             unsafe {
                 v4l2::ioctl(
                     self.handle.fd(),
                     v4l2::vidioc::VIDIOC_QUERYBUF,
                     &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
                 )?;
+            }
 
-                let ptr = v4l2::mmap(
-                    ptr::null_mut(),
-                    v4l2_buf.length as usize,
-                    libc::PROT_READ | libc::PROT_WRITE,
-                    libc::MAP_SHARED,
-                    self.handle.fd(),
-                    v4l2_buf.m.offset as libc::off_t,
-                )?;
+            // (offset, length) of each plane
+            let layout: Vec<(u32, u32)> = if self.buf_type.is_multiplanar() {
+                v4l2_planes[..(v4l2_buf.length as usize).min(MAX_PLANES)]
+                    .iter()
+                    .map(|plane| (unsafe { plane.m.mem_offset }, plane.length))
+                    .collect()
+            } else {
+                vec![(unsafe { v4l2_buf.m.offset }, v4l2_buf.length)]
+            };
 
-                let slice =
-                    slice::from_raw_parts_mut::<u8>(ptr as *mut u8, v4l2_buf.length as usize);
-                self.bufs.push(slice);
+            // store each plane right away, so it is unmapped if mapping another one fails
+            for (offset, length) in layout {
+                let slice = unsafe {
+                    let ptr = v4l2::mmap(
+                        ptr::null_mut(),
+                        length as usize,
+                        libc::PROT_READ | libc::PROT_WRITE,
+                        libc::MAP_SHARED,
+                        self.handle.fd(),
+                        offset as libc::off_t,
+                    )?;
+                    slice::from_raw_parts_mut::<u8>(ptr as *mut u8, length as usize)
+                };
+                self.bufs[index as usize].push(slice);
             }
         }
 
-        Ok(v4l2_reqbufs.count)
+        Ok(())
+    }
+
+    /// Locks all buffers into RAM
+    pub fn lock(&mut self) -> io::Result<()> {
+        for plane in self.bufs.iter().flatten() {
+            memory::lock(plane)?;
+        }
+
+        Ok(())
+    }
+
+    /// Touches all pages of all buffers to avoid page faults later on
+    pub fn prefault(&mut self) {
+        for plane in self.bufs.iter_mut().flatten() {
+            memory::prefault(plane);
+        }
     }
 
     pub fn release(&mut self) -> io::Result<()> {
-        for buf in &self.bufs {
+        for plane in self.bufs.iter().flatten() {
+            // unmapping implicitly unlocks the memory as well
             unsafe {
-                v4l2::munmap(buf.as_ptr() as *mut core::ffi::c_void, buf.len())?;
+                v4l2::munmap(plane.as_ptr() as *mut core::ffi::c_void, plane.len())?;
             }
         }
 
@@ -125,18 +242,6 @@ impl<'a> Drop for Arena<'a> {
             return;
         }
 
-        if let Err(e) = self.release() {
-            if let Some(code) = e.raw_os_error() {
-                // ENODEV means the file descriptor wrapped in the handle became invalid, most
-                // likely because the device was unplugged or the connection (USB, PCI, ..)
-                // broke down. Handle this case gracefully by ignoring it.
-                if code == 19 {
-                    /* ignore */
-                    return;
-                }
-            }
-
-            panic!("{:?}", e)
-        }
+        queue::teardown(self.release());
     }
 }
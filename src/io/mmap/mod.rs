@@ -1,4 +1,5 @@
 pub(crate) mod arena;
 
+pub mod split;
 pub mod stream;
-pub use stream::Stream;
+pub use stream::{Frame, Stream, StreamBuilder};
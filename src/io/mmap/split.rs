@@ -0,0 +1,220 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::{io, slice};
+
+use crate::buffer::Metadata;
+use crate::device::Handle;
+use crate::io::mmap::Stream;
+use crate::io::traits::CaptureStream;
+
+/// Captured buffer, handed out by a [`Producer`]
+///
+/// The buffer belongs to the application until it is given back to the driver through
+/// [`Consumer::queue()`]. Dropping it requeues it as well, but errors go unnoticed then.
+pub struct Buffer<'a> {
+    stream: Arc<Mutex<Stream<'a>>>,
+    index: usize,
+    data: *mut u8,
+    len: usize,
+    meta: Metadata,
+    queued: bool,
+}
+
+// The buffer memory stays mapped for as long as the stream is alive, which the buffer keeps a
+// reference to. Neither the driver nor any other buffer accesses it while it is dequeued.
+unsafe impl<'a> Send for Buffer<'a> {}
+unsafe impl<'a> Sync for Buffer<'a> {}
+
+impl<'a> Buffer<'a> {
+    /// Returns the buffer data
+    ///
+    /// Only the first `meta().bytesused` bytes hold actual frame data.
+    pub fn data(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.data, self.len) }
+    }
+
+    /// Returns the buffer data for in-place processing
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.data, self.len) }
+    }
+
+    /// Returns the buffer metadata
+    pub fn meta(&self) -> &Metadata {
+        &self.meta
+    }
+
+    /// Returns the index of the buffer
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    fn queue(&mut self) -> io::Result<()> {
+        self.queued = true;
+        let mut stream = self.stream.lock().unwrap();
+        CaptureStream::queue(&mut *stream, self.index)
+    }
+}
+
+impl<'a> Drop for Buffer<'a> {
+    fn drop(&mut self) {
+        if !self.queued {
+            let _ = self.queue();
+        }
+    }
+}
+
+/// Dequeueing half of a split stream
+///
+/// Blocks until the driver hands out filled buffers.
+pub struct Producer<'a> {
+    handle: Arc<Handle>,
+    stream: Arc<Mutex<Stream<'a>>>,
+    timeout: Option<Duration>,
+}
+
+impl<'a> Producer<'a> {
+    /// Sets a timeout for waiting on the next buffer
+    pub fn set_timeout(&mut self, duration: Duration) {
+        self.timeout = Some(duration);
+    }
+
+    /// Clears the timeout for waiting on the next buffer
+    pub fn clear_timeout(&mut self) {
+        self.timeout = None;
+    }
+
+    /// Removes a buffer from the drivers' outgoing queue, waiting for one if necessary
+    pub fn dequeue(&mut self) -> io::Result<Buffer<'a>> {
+        // Do not hold the lock while waiting, the consumer requeues buffers concurrently.
+        if self.handle.poll(libc::POLLIN, self.timeout)? == 0 {
+            // This condition can only happen if there was a timeout.
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_DQBUF"));
+        }
+
+        let mut stream = self.stream.lock().unwrap();
+        let index = stream.dequeue_ready()?;
+        let (buf, meta) = stream
+            .get_mut(index)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid buffer index"))?;
+
+        Ok(Buffer {
+            stream: self.stream.clone(),
+            index,
+            data: buf.as_mut_ptr(),
+            len: buf.len(),
+            meta: *meta,
+            queued: false,
+        })
+    }
+
+    /// Removes all buffers which are ready from the drivers' outgoing queue without waiting
+    ///
+    /// Helps consumers which fell behind to catch up, e.g. by only processing the newest buffer
+    /// and dropping the others. The number of returned buffers is the backlog of the consumer.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::buffer::Type;
+    /// use v4l::device::Device;
+    /// use v4l::io::mmap::Stream;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let stream = Stream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+    /// let mut producer = stream.frames().expect("Failed to start stream");
+    ///
+    /// loop {
+    ///     let mut bufs = producer.drain().expect("Failed to dequeue buffers");
+    ///     if bufs.len() > 1 {
+    ///         println!("skipping {} stale frames", bufs.len() - 1);
+    ///     }
+    ///     // older buffers are handed back to the driver as they are dropped
+    ///     if let Some(buf) = bufs.pop() {
+    ///         println!("frame {}", buf.meta().sequence);
+    ///     }
+    /// }
+    /// ```
+    pub fn drain(&mut self) -> io::Result<Vec<Buffer<'a>>> {
+        let mut bufs = Vec::new();
+        let mut stream = self.stream.lock().unwrap();
+        loop {
+            let index = match stream.dequeue_ready() {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(bufs),
+                res => res?,
+            };
+            let (buf, meta) = stream.get_mut(index).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "invalid buffer index")
+            })?;
+
+            bufs.push(Buffer {
+                stream: self.stream.clone(),
+                index,
+                data: buf.as_mut_ptr(),
+                len: buf.len(),
+                meta: *meta,
+                queued: false,
+            });
+        }
+    }
+}
+
+impl<'a> Iterator for Producer<'a> {
+    type Item = io::Result<Buffer<'a>>;
+
+    /// Dequeues the next buffer, see [`Producer::dequeue()`]
+    ///
+    /// The iterator never ends, errors such as timeouts are yielded to the caller, who decides
+    /// whether to continue.
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.dequeue())
+    }
+}
+
+/// Requeueing half of a split stream
+///
+/// Hands processed buffers back to the driver.
+pub struct Consumer<'a> {
+    stream: Arc<Mutex<Stream<'a>>>,
+}
+
+impl<'a> Consumer<'a> {
+    /// Inserts a buffer into the drivers' incoming queue
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Buffer dequeued by the producer half of the same stream
+    pub fn queue(&self, mut buf: Buffer<'a>) -> io::Result<()> {
+        if !Arc::ptr_eq(&self.stream, &buf.stream) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "buffer belongs to a different stream",
+            ));
+        }
+
+        buf.queue()
+    }
+}
+
+impl<'a> Clone for Consumer<'a> {
+    fn clone(&self) -> Self {
+        Consumer {
+            stream: self.stream.clone(),
+        }
+    }
+}
+
+pub(crate) fn split<'a>(
+    handle: Arc<Handle>,
+    stream: Stream<'a>,
+    timeout: Option<Duration>,
+) -> (Producer<'a>, Consumer<'a>) {
+    let stream = Arc::new(Mutex::new(stream));
+    (
+        Producer {
+            handle,
+            stream: stream.clone(),
+            timeout,
+        },
+        Consumer { stream },
+    )
+}
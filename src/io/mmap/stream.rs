@@ -1,27 +1,103 @@
-use std::convert::TryInto;
+use std::ops::{ControlFlow, Range};
+use std::os::unix::io::{FromRawFd, OwnedFd};
 use std::time::Duration;
 use std::{io, mem, sync::Arc};
 
-use crate::buffer::{Metadata, Type};
+use crate::buffer::{Metadata, State, Type};
 use crate::device::{Device, Handle};
+use crate::format::mplane::MAX_PLANES;
+use crate::format::AnyFormat;
 use crate::io::mmap::arena::Arena;
+use crate::io::mmap::split::{self, Consumer, Producer};
+use crate::io::queue;
 use crate::io::traits::{CaptureStream, OutputStream, Stream as StreamTrait};
+#[cfg(feature = "media-controller")]
+use crate::media::Request;
 use crate::memory::Memory;
 use crate::v4l2;
 use crate::v4l_sys::*;
 
+/// Captured frame handed to the callback of [`Stream::on_frame()`]
+pub struct Frame<'a> {
+    /// Buffer data, only the first `meta.bytesused` bytes hold actual frame data
+    pub data: &'a [u8],
+    /// Buffer metadata
+    pub meta: &'a Metadata,
+}
+
+/// Builder of a [`Stream`], returned by [`Stream::builder()`]
+pub struct StreamBuilder<'d> {
+    dev: &'d Device,
+    buf_type: Type,
+    buf_count: u32,
+    prefill: bool,
+    start: bool,
+}
+
+impl<'d> StreamBuilder<'d> {
+    /// Sets the number of buffers to request, 4 by default
+    ///
+    /// The driver may grant more or fewer buffers, see [`Stream::buffer_count()`].
+    pub fn buf_count(mut self, buf_count: u32) -> Self {
+        self.buf_count = buf_count;
+        self
+    }
+
+    /// Sets whether all capture buffers are queued when streaming starts, the default
+    ///
+    /// Without prefilling, the application hands buffers to the driver itself through
+    /// [`CaptureStream::queue()`] before requesting frames. Output buffers are never queued
+    /// before they are filled.
+    pub fn prefill(mut self, prefill: bool) -> Self {
+        self.prefill = prefill;
+        self
+    }
+
+    /// Sets whether streaming starts when the stream is built instead of lazily with the first
+    /// frame, the default
+    ///
+    /// Starting right away moves the latency of the driver starting up out of the first frame.
+    pub fn start(mut self, start: bool) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Allocates the buffers and returns the stream
+    pub fn build<'a>(self) -> io::Result<Stream<'a>> {
+        let mut stream = Stream::with_buffers(self.dev, self.buf_type, self.buf_count)?;
+        stream.prefill = self.prefill;
+        if self.start {
+            if stream.prefill && !stream.buf_type.is_output() {
+                stream.queue_all()?;
+            }
+            stream.start()?;
+        }
+        Ok(stream)
+    }
+}
+
 /// Stream of mapped buffers
 ///
 /// An arena instance is used internally for buffer handling.
+///
+/// Multi-planar buffer types are supported as well. The buffers handed out by the stream traits
+/// are the first plane of each buffer then, and `bytesused` of their metadata refers to it. All
+/// planes are accessible through [`Stream::planes()`] and [`Stream::planes_mut()`].
 pub struct Stream<'a> {
     handle: Arc<Handle>,
     arena: Arena<'a>,
     arena_index: usize,
     buf_type: Type,
     buf_meta: Vec<Metadata>,
-    timeout: Option<i32>,
+    /// Bytes used by each plane of multi-planar buffers, the first plane uses the metadata
+    plane_bytesused: Vec<[u32; MAX_PLANES]>,
+    timeout: Option<Duration>,
+    /// Whether capture buffers are queued when streaming starts
+    prefill: bool,
 
     active: bool,
+    /// Whether the application holds the buffer at `arena_index`
+    holding: bool,
 }
 
 impl<'a> Stream<'a> {
@@ -48,7 +124,40 @@ impl<'a> Stream<'a> {
         Stream::with_buffers(dev, buf_type, 4)
     }
 
+    /// Returns a builder to configure the buffers and startup of a stream
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Device ref to get its file descriptor
+    /// * `buf_type` - Type of the buffers
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::buffer::Type;
+    /// use v4l::device::Device;
+    /// use v4l::io::mmap::Stream;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let stream = Stream::builder(&dev, Type::VideoCapture)
+    ///     .buf_count(8)
+    ///     .start(true)
+    ///     .build()
+    ///     .expect("Failed to create stream");
+    /// println!("driver granted {} buffers", stream.buffer_count());
+    /// ```
+    pub fn builder(dev: &Device, buf_type: Type) -> StreamBuilder<'_> {
+        StreamBuilder {
+            dev,
+            buf_type,
+            buf_count: 4,
+            prefill: true,
+            start: false,
+        }
+    }
+
     pub fn with_buffers(dev: &Device, buf_type: Type, buf_count: u32) -> io::Result<Self> {
+        dev.check_streaming(buf_type)?;
         let mut arena = Arena::new(dev.handle(), buf_type);
         let count = arena.allocate(buf_count)?;
         let mut buf_meta = Vec::new();
@@ -60,7 +169,10 @@ impl<'a> Stream<'a> {
             arena_index: 0,
             buf_type,
             buf_meta,
+            plane_bytesused: vec![[0; MAX_PLANES]; count as usize],
             active: false,
+            holding: false,
+            prefill: true,
             timeout: None,
         })
     }
@@ -70,9 +182,225 @@ impl<'a> Stream<'a> {
         self.handle.clone()
     }
 
+    /// Returns the current state of a buffer as reported by the driver
+    ///
+    /// This is mostly useful for debugging, e.g. to find out which buffers are stuck in the
+    /// driver.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the buffer
+    pub fn query_buffer(&self, index: usize) -> io::Result<State> {
+        State::query(&self.handle, self.buf_type, Memory::Mmap, index as u32)
+    }
+
+    /// Returns whether the driver copies the timestamps of output buffers to capture buffers
+    ///
+    /// This is the case for memory-to-memory devices like codecs. The timestamp of each output
+    /// buffer is then taken from the [`Metadata`] handed out by [`OutputStream::next()`] and
+    /// shows up unchanged in the metadata of the resulting capture buffers.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::buffer::Type;
+    /// use v4l::device::Device;
+    /// use v4l::io::mmap::Stream;
+    /// use v4l::io::traits::{CaptureStream, OutputStream};
+    /// use v4l::timestamp::Timestamp;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let mut output = Stream::new(&dev, Type::VideoOutput).expect("Failed to create stream");
+    /// let mut capture = Stream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+    /// assert!(output.is_timestamp_copy().expect("Failed to query buffer"));
+    ///
+    /// let (_, meta) = OutputStream::next(&mut output).expect("Failed to get buffer");
+    /// meta.timestamp = Timestamp::new(0, 42);
+    /// meta.bytesused = 1;
+    /// OutputStream::next(&mut output).expect("Failed to queue buffer");
+    ///
+    /// let (_, meta) = CaptureStream::next(&mut capture).expect("Failed to capture frame");
+    /// assert_eq!(meta.timestamp, Timestamp::new(0, 42));
+    /// ```
+    pub fn is_timestamp_copy(&self) -> io::Result<bool> {
+        Ok(self.query_buffer(0)?.meta.is_timestamp_copied())
+    }
+
+    /// Allocates additional buffers, e.g. for a larger format
+    ///
+    /// Dynamic-resolution decoders need larger buffers once the resolution of the stream
+    /// increases, which can be added this way without tearing down the stream. The new buffers
+    /// are not queued, unless the stream has not been started yet. Fails with ENOTTY if the
+    /// driver does not support `VIDIOC_CREATE_BUFS`, see [`crate::feature::Features`].
+    ///
+    /// Returns the range of indices of the new buffers.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of buffers to create, the driver may allocate fewer
+    /// * `format` - Format the buffers must be able to hold, the current one if `None`
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::buffer::Type;
+    /// use v4l::device::Device;
+    /// use v4l::format::AnyFormat;
+    /// use v4l::io::mmap::Stream;
+    /// use v4l::io::traits::CaptureStream;
+    /// use v4l::video::Capture;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let mut stream = Stream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+    /// CaptureStream::next(&mut stream).expect("Failed to capture frame");
+    ///
+    /// // the source switched to a larger resolution
+    /// let mut fmt = Capture::format(&dev).expect("Failed to get format");
+    /// fmt.width = 3840;
+    /// fmt.height = 2160;
+    /// fmt.size = fmt.width * fmt.height * 2;
+    /// let created = stream
+    ///     .create_buffers(4, Some(&AnyFormat::Video(fmt)))
+    ///     .expect("Failed to create buffers");
+    /// for index in created {
+    ///     CaptureStream::queue(&mut stream, index).expect("Failed to queue buffer");
+    /// }
+    /// ```
+    pub fn create_buffers(
+        &mut self,
+        count: u32,
+        format: Option<&AnyFormat>,
+    ) -> io::Result<Range<usize>> {
+        let v4l2_fmt = match format {
+            Some(format) => format.to_raw(self.buf_type)?,
+            None => unsafe {
+                let mut v4l2_fmt = v4l2_format {
+                    type_: self.buf_type as u32,
+                    ..mem::zeroed()
+                };
+                v4l2::ioctl(
+                    self.handle.fd(),
+                    v4l2::vidioc::VIDIOC_G_FMT,
+                    &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
+                )?;
+                v4l2_fmt
+            },
+        };
+
+        let range = self.arena.create(count, v4l2_fmt)?;
+        let len = self.arena.bufs.len();
+        self.buf_meta.resize(len, Metadata::default());
+        self.plane_bytesused.resize(len, [0; MAX_PLANES]);
+        Ok(range)
+    }
+
+    /// Removes a range of buffers from the stream
+    ///
+    /// This undoes [`Stream::create_buffers()`], e.g. to free the small buffers once a decoder
+    /// switched to a larger resolution. The buffers must not be queued. Removed buffers are
+    /// skipped when queueing all buffers on stream start and their indices may be reused by
+    /// later calls to [`Stream::create_buffers()`].
+    ///
+    /// Returns whether the driver freed the memory of the buffers. Drivers of kernels before
+    /// 6.10 do not support `VIDIOC_REMOVE_BUFS`, in which case the buffers are retired instead:
+    /// they are no longer used by the stream, but stay allocated until it is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - Indices of the buffers to remove
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::buffer::Type;
+    /// use v4l::device::Device;
+    /// use v4l::io::mmap::Stream;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let mut stream = Stream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+    /// let created = stream
+    ///     .create_buffers(4, None)
+    ///     .expect("Failed to create buffers");
+    /// if !stream.remove_buffers(created).expect("Failed to remove buffers") {
+    ///     println!("buffers stay allocated until the stream is dropped");
+    /// }
+    /// ```
+    pub fn remove_buffers(&mut self, range: Range<usize>) -> io::Result<bool> {
+        if range.is_empty() || range.end > self.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid range of buffers: {:?}", range),
+            ));
+        }
+        for index in range.clone() {
+            if self.is_mapped(index) && self.query_buffer(index)?.is_queued() {
+                return Err(io::Error::from_raw_os_error(libc::EBUSY));
+            }
+        }
+
+        self.arena.remove(range)
+    }
+
+    /// Returns the number of buffers granted by the driver which have not been removed
+    pub fn buffer_count(&self) -> usize {
+        (0..self.len())
+            .filter(|&index| self.is_mapped(index))
+            .count()
+    }
+
+    /// Returns the number of planes of each buffer, 1 for single-planar buffer types
+    pub fn num_planes(&self) -> usize {
+        self.arena.bufs.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Returns the planes of the buffer handed out last, along with the number of bytes each
+    /// of them holds
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::buffer::Type;
+    /// use v4l::device::Device;
+    /// use v4l::io::mmap::Stream;
+    /// use v4l::io::traits::CaptureStream;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let mut stream =
+    ///     Stream::new(&dev, Type::VideoCaptureMplane).expect("Failed to create stream");
+    /// CaptureStream::next(&mut stream).expect("Failed to capture frame");
+    /// for (index, (data, bytesused)) in stream.planes().into_iter().enumerate() {
+    ///     println!("plane {}: {} of {} bytes", index, bytesused, data.len());
+    /// }
+    /// ```
+    pub fn planes(&self) -> Vec<(&[u8], u32)> {
+        let index = self.arena_index;
+        self.arena.bufs[index]
+            .iter()
+            .enumerate()
+            .map(|(plane, data)| (&**data, self.bytesused(index, plane)))
+            .collect()
+    }
+
+    /// Returns the planes of the buffer handed out last for filling them, along with the number
+    /// of bytes each of them holds
+    ///
+    /// This is meant for multi-planar output buffers: the number of bytes of each plane is
+    /// passed to the driver when the buffer is queued. The one of the first plane is
+    /// `bytesused` of the buffer metadata.
+    pub fn planes_mut(&mut self) -> Vec<(&mut [u8], &mut u32)> {
+        let index = self.arena_index;
+        let bytesused = std::iter::once(&mut self.buf_meta[index].bytesused)
+            .chain(self.plane_bytesused[index].iter_mut().skip(1));
+        self.arena.bufs[index]
+            .iter_mut()
+            .map(|data| &mut **data)
+            .zip(bytesused)
+            .collect()
+    }
+
     /// Sets a timeout of the v4l file handle.
     pub fn set_timeout(&mut self, duration: Duration) {
-        self.timeout = Some(duration.as_millis().try_into().unwrap());
+        self.timeout = Some(duration);
     }
 
     /// Clears the timeout of the v4l file handle.
@@ -80,30 +408,394 @@ impl<'a> Stream<'a> {
         self.timeout = None;
     }
 
-    fn buffer_desc(&self) -> v4l2_buffer {
-        v4l2_buffer {
+    /// Removes a buffer from the drivers' outgoing queue, waiting at most for the given duration
+    ///
+    /// Unlike [`Stream::set_timeout()`], the timeout only applies to this call. Fails with
+    /// [`io::ErrorKind::TimedOut`] if no buffer became ready in time, e.g. because the sensor
+    /// stopped producing frames.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Maximum duration to wait for
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::io;
+    /// use std::time::Duration;
+    /// use v4l::buffer::Type;
+    /// use v4l::device::Device;
+    /// use v4l::io::mmap::Stream;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let mut stream = Stream::builder(&dev, Type::VideoCapture)
+    ///     .start(true)
+    ///     .build()
+    ///     .expect("Failed to create stream");
+    ///
+    /// match stream.dequeue_timeout(Duration::from_secs(1)) {
+    ///     Ok(index) => println!("buffer {} is ready", index),
+    ///     Err(e) if e.kind() == io::ErrorKind::TimedOut => println!("sensor stalled"),
+    ///     Err(e) => panic!("Failed to dequeue buffer: {}", e),
+    /// }
+    /// ```
+    pub fn dequeue_timeout(&mut self, timeout: Duration) -> io::Result<usize> {
+        let events = if self.buf_type.is_output() {
+            libc::POLLOUT
+        } else {
+            libc::POLLIN
+        };
+        if self.handle.poll(events, Some(timeout))? == 0 {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_DQBUF"));
+        }
+
+        self.dequeue_ready()
+    }
+
+    /// Locks all buffers into RAM, so they are never paged out
+    ///
+    /// This usually requires the `CAP_IPC_LOCK` capability or a sufficiently large
+    /// `RLIMIT_MEMLOCK` limit.
+    pub fn lock(&mut self) -> io::Result<()> {
+        self.arena.lock()
+    }
+
+    /// Touches every page of all buffers, so capturing does not cause any page faults
+    ///
+    /// Call this right after creating the stream to avoid latency spikes during the first frames
+    /// of real-time applications.
+    pub fn prefault(&mut self) {
+        self.arena.prefault()
+    }
+
+    /// Exports a buffer as DMABUF file descriptor
+    ///
+    /// The returned file descriptor refers to the same memory as the mapped buffer and stays
+    /// valid after the stream is dropped. It can be imported by other devices or graphics APIs
+    /// for zero-copy processing.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the buffer to export
+    pub fn export(&self, index: usize) -> io::Result<OwnedFd> {
+        self.export_plane(index, 0)
+    }
+
+    /// Exports a plane of a multi-planar buffer as DMABUF file descriptor
+    ///
+    /// See [`Stream::export()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the buffer to export
+    /// * `plane` - Index of the plane
+    pub fn export_plane(&self, index: usize, plane: usize) -> io::Result<OwnedFd> {
+        let mut v4l2_exp = v4l2_exportbuffer {
+            type_: self.buf_type as u32,
+            index: index as u32,
+            plane: plane as u32,
+            flags: (libc::O_RDWR | libc::O_CLOEXEC) as u32,
+            ..unsafe { mem::zeroed() }
+        };
+
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_EXPBUF,
+                &mut v4l2_exp as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            Ok(OwnedFd::from_raw_fd(v4l2_exp.fd))
+        }
+    }
+
+    /// Inserts a buffer into a request instead of the drivers' incoming queue
+    ///
+    /// The buffer is handed to the driver once the request is queued, see [`Request`].
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the buffer
+    /// * `request` - Request which has not been queued yet
+    #[cfg(feature = "media-controller")]
+    pub fn queue_request(&mut self, index: usize, request: &Request) -> io::Result<()> {
+        let mut v4l2_planes: [v4l2_plane; MAX_PLANES] = unsafe { mem::zeroed() };
+        let mut v4l2_buf = v4l2_buffer {
+            index: index as u32,
+            flags: crate::buffer::Flags::REQUEST_FD.into(),
+            ..self.buffer_desc(&mut v4l2_planes)
+        };
+        v4l2_buf.__bindgen_anon_1.request_fd = request.fd();
+
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_QBUF,
+                &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
+            )
+        }
+    }
+
+    /// Runs a callback for each captured frame until it breaks
+    ///
+    /// Frames are captured on the calling thread, the buffer of each frame is requeued once the
+    /// callback continues. If it breaks, the buffer of the last frame stays dequeued until the
+    /// next frame is requested, e.g. by calling this function again. Unlike
+    /// [`crate::Device::start_capture_thread()`], no thread is spawned and the callback may borrow
+    /// from the caller.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - Function called with each frame
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::ops::ControlFlow;
+    /// use v4l::buffer::Type;
+    /// use v4l::device::Device;
+    /// use v4l::io::mmap::Stream;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let mut stream = Stream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+    ///
+    /// let mut bytes = 0;
+    /// let frames = stream
+    ///     .on_frame(|frame| {
+    ///         bytes += frame.meta.bytesused as usize;
+    ///         if frame.meta.sequence < 100 {
+    ///             ControlFlow::Continue(())
+    ///         } else {
+    ///             ControlFlow::Break(frame.meta.sequence + 1)
+    ///         }
+    ///     })
+    ///     .expect("Failed to capture frames");
+    /// println!("captured {} bytes in {} frames", bytes, frames);
+    /// ```
+    pub fn on_frame<B, F>(&mut self, mut callback: F) -> io::Result<B>
+    where
+        F: FnMut(Frame<'_>) -> ControlFlow<B>,
+    {
+        loop {
+            let (data, meta) = CaptureStream::next(self)?;
+            if let ControlFlow::Break(value) = callback(Frame { data, meta }) {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Splits the stream into a dequeueing and a requeueing half
+    ///
+    /// Both halves can be moved to different threads, e.g. one thread waits for captured frames
+    /// while another one hands the buffers back to the driver once they have been processed.
+    /// Streaming is started if it is not active yet.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::sync::mpsc;
+    /// use std::thread;
+    /// use v4l::buffer::Type;
+    /// use v4l::device::Device;
+    /// use v4l::io::mmap::Stream;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let stream = Stream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+    /// let (mut producer, consumer) = stream.split().expect("Failed to split stream");
+    ///
+    /// let (tx, rx) = mpsc::channel();
+    /// thread::spawn(move || {
+    ///     for buf in rx {
+    ///         // process the buffer, then hand it back to the driver
+    ///         consumer.queue(buf).expect("Failed to queue buffer");
+    ///     }
+    /// });
+    ///
+    /// loop {
+    ///     let buf = producer.dequeue().expect("Failed to dequeue buffer");
+    ///     tx.send(buf).unwrap();
+    /// }
+    /// ```
+    pub fn split(mut self) -> io::Result<(Producer<'a>, Consumer<'a>)> {
+        if !self.active {
+            self.queue_all()?;
+            self.start()?;
+        }
+
+        let handle = self.handle.clone();
+        let timeout = self.timeout;
+        Ok(split::split(handle, self, timeout))
+    }
+
+    /// Turns the stream into an iterator over captured buffers
+    ///
+    /// This is the dequeueing half of [`Stream::split()`] on its own: each buffer is handed back
+    /// to the driver once it is dropped, so no manual queue management is needed. Streaming is
+    /// started if it is not active yet.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::buffer::Type;
+    /// use v4l::device::Device;
+    /// use v4l::io::mmap::Stream;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let stream = Stream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+    ///
+    /// for buf in stream.frames().expect("Failed to start stream").take(100) {
+    ///     let buf = buf.expect("Failed to dequeue buffer");
+    ///     println!("frame {}: {} bytes", buf.meta().sequence, buf.meta().bytesused);
+    /// }
+    /// ```
+    pub fn frames(self) -> io::Result<Producer<'a>> {
+        let (producer, _) = self.split()?;
+        Ok(producer)
+    }
+
+    /// Returns the number of buffers
+    pub(crate) fn len(&self) -> usize {
+        self.arena.bufs.len()
+    }
+
+    /// Returns whether a buffer is mapped, i.e. it has not been removed
+    pub(crate) fn is_mapped(&self, index: usize) -> bool {
+        self.arena
+            .bufs
+            .get(index)
+            .is_some_and(|planes| !planes.is_empty())
+    }
+
+    /// Queues all buffers which have not been removed, before starting the stream
+    pub(crate) fn queue_all(&mut self) -> io::Result<()> {
+        for index in 0..self.len() {
+            if self.is_mapped(index) {
+                CaptureStream::queue(self, index)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the size of the first plane of a buffer in bytes
+    pub(crate) fn buffer_len(&self, index: usize) -> usize {
+        self.arena.bufs[index][0].len()
+    }
+
+    /// Returns the buffer which was dequeued last along with its metadata
+    pub(crate) fn current(&self) -> (&[u8], &Metadata) {
+        (
+            self.arena.bufs[self.arena_index][0],
+            &self.buf_meta[self.arena_index],
+        )
+    }
+
+    /// Returns a buffer along with its metadata
+    pub(crate) fn get_mut(&mut self, index: usize) -> Option<(&mut [u8], &mut Metadata)> {
+        let buf = self.arena.bufs.get_mut(index)?.first_mut()?;
+        let meta = self.buf_meta.get_mut(index)?;
+        Some((buf, meta))
+    }
+
+    /// Returns the number of bytes a plane of a buffer holds
+    fn bytesused(&self, index: usize, plane: usize) -> u32 {
+        if plane == 0 {
+            self.buf_meta[index].bytesused
+        } else {
+            self.plane_bytesused[index][plane]
+        }
+    }
+
+    /// Removes a buffer from the drivers' outgoing queue without waiting for one to become
+    /// ready
+    pub(crate) fn dequeue_ready(&mut self) -> io::Result<usize> {
+        let mut v4l2_planes: [v4l2_plane; MAX_PLANES] = unsafe { mem::zeroed() };
+        let mut v4l2_buf = self.buffer_desc(&mut v4l2_planes);
+
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_DQBUF,
+                &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+        self.arena_index = v4l2_buf.index as usize;
+
+        self.buf_meta[self.arena_index] = Metadata::from_raw(&v4l2_buf);
+        if self.buf_type.is_multiplanar() {
+            for (bytesused, plane) in self.plane_bytesused[self.arena_index]
+                .iter_mut()
+                .zip(&v4l2_planes)
+            {
+                *bytesused = plane.bytesused;
+            }
+            self.buf_meta[self.arena_index].bytesused = v4l2_planes[0].bytesused;
+        }
+        #[cfg(feature = "tracing")]
+        self.buf_meta[self.arena_index].trace_dequeue(self.arena_index);
+
+        Ok(self.arena_index)
+    }
+
+    /// Returns whether the stream is active
+    pub(crate) fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Inserts an output buffer into the drivers' incoming queue without waiting for the device
+    /// to become writable
+    pub(crate) fn queue_output(&mut self, index: usize) -> io::Result<()> {
+        let mut v4l2_planes: [v4l2_plane; MAX_PLANES] = unsafe { mem::zeroed() };
+        let mut v4l2_buf = v4l2_buffer {
+            index: index as u32,
+            ..self.buffer_desc(&mut v4l2_planes)
+        };
+        // output settings
+        //
+        // MetaData.bytesused is initialized to 0. For an output device, when bytesused is
+        // set to 0 v4l2 will set it to the size of the plane:
+        // https://www.kernel.org/doc/html/v4.15/media/uapi/v4l/buffer.html#struct-v4l2-plane
+        if self.buf_type.is_multiplanar() {
+            for (plane, v4l2_plane) in v4l2_planes
+                .iter_mut()
+                .enumerate()
+                .take(self.arena.bufs[index].len())
+            {
+                v4l2_plane.bytesused = self.bytesused(index, plane);
+            }
+        } else {
+            v4l2_buf.bytesused = self.buf_meta[index].bytesused;
+        }
+        v4l2_buf.field = self.buf_meta[index].field as u32;
+        v4l2_buf.timestamp = self.buf_meta[index].timestamp.into();
+        self.buf_meta[index].apply_timecode(&mut v4l2_buf);
+
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_QBUF,
+                &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
+            )
+        }
+    }
+
+    /// Returns a buffer description, pointing to the plane array for multi-planar buffers
+    fn buffer_desc(&self, planes: &mut [v4l2_plane; MAX_PLANES]) -> v4l2_buffer {
+        let mut v4l2_buf = v4l2_buffer {
             type_: self.buf_type as u32,
             memory: Memory::Mmap as u32,
             ..unsafe { mem::zeroed() }
+        };
+        if self.buf_type.is_multiplanar() {
+            v4l2_buf.m.planes = planes.as_mut_ptr();
+            v4l2_buf.length = MAX_PLANES as u32;
         }
+        v4l2_buf
     }
 }
 
 impl<'a> Drop for Stream<'a> {
     fn drop(&mut self) {
-        if let Err(e) = self.stop() {
-            if let Some(code) = e.raw_os_error() {
-                // ENODEV means the file descriptor wrapped in the handle became invalid, most
-                // likely because the device was unplugged or the connection (USB, PCI, ..)
-                // broke down. Handle this case gracefully by ignoring it.
-                if code == 19 {
-                    /* ignore */
-                    return;
-                }
-            }
-
-            panic!("{:?}", e)
-        }
+        queue::teardown(self.stop());
     }
 }
 
@@ -135,15 +827,17 @@ impl<'a> StreamTrait for Stream<'a> {
         }
 
         self.active = false;
+        self.holding = false;
         Ok(())
     }
 }
 
 impl<'a, 'b> CaptureStream<'b> for Stream<'a> {
     fn queue(&mut self, index: usize) -> io::Result<()> {
+        let mut v4l2_planes: [v4l2_plane; MAX_PLANES] = unsafe { mem::zeroed() };
         let mut v4l2_buf = v4l2_buffer {
             index: index as u32,
-            ..self.buffer_desc()
+            ..self.buffer_desc(&mut v4l2_planes)
         };
 
         unsafe {
@@ -158,116 +852,78 @@ impl<'a, 'b> CaptureStream<'b> for Stream<'a> {
     }
 
     fn dequeue(&mut self) -> io::Result<usize> {
-        let mut v4l2_buf = self.buffer_desc();
-
-        if self.handle.poll(libc::POLLIN, self.timeout.unwrap_or(-1))? == 0 {
+        if self.handle.poll(libc::POLLIN, self.timeout)? == 0 {
             // This condition can only happen if there was a timeout.
             // A timeout is only possible if the `timeout` value is non-zero, meaning we should
             // propagate it to the caller.
             return Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_DQBUF"));
         }
 
-        unsafe {
-            v4l2::ioctl(
-                self.handle.fd(),
-                v4l2::vidioc::VIDIOC_DQBUF,
-                &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
-            )?;
-        }
-        self.arena_index = v4l2_buf.index as usize;
-
-        self.buf_meta[self.arena_index] = Metadata {
-            bytesused: v4l2_buf.bytesused,
-            flags: v4l2_buf.flags.into(),
-            field: v4l2_buf.field,
-            timestamp: v4l2_buf.timestamp.into(),
-            sequence: v4l2_buf.sequence,
-        };
-
-        Ok(self.arena_index)
+        self.dequeue_ready()
     }
 
     fn next(&'b mut self) -> io::Result<(&Self::Item, &Metadata)> {
         if !self.active {
             // Enqueue all buffers once on stream start
-            for index in 0..self.arena.bufs.len() {
-                CaptureStream::queue(self, index)?;
+            if self.prefill {
+                self.queue_all()?;
             }
 
             self.start()?;
-        } else {
+        } else if self.holding {
             CaptureStream::queue(self, self.arena_index)?;
+            self.holding = false;
         }
 
         self.arena_index = CaptureStream::dequeue(self)?;
+        self.holding = true;
 
         // The index used to access the buffer elements is given to us by v4l2, so we assume it
         // will always be valid.
-        let bytes = &self.arena.bufs[self.arena_index];
+        let bytes = &self.arena.bufs[self.arena_index][0];
         let meta = &self.buf_meta[self.arena_index];
         Ok((bytes, meta))
     }
-}
 
-impl<'a, 'b> OutputStream<'b> for Stream<'a> {
-    fn queue(&mut self, index: usize) -> io::Result<()> {
-        let mut v4l2_buf = v4l2_buffer {
-            index: index as u32,
-            ..self.buffer_desc()
-        };
-        unsafe {
-            // output settings
-            //
-            // MetaData.bytesused is initialized to 0. For an output device, when bytesused is
-            // set to 0 v4l2 will set it to the size of the plane:
-            // https://www.kernel.org/doc/html/v4.15/media/uapi/v4l/buffer.html#struct-v4l2-plane
-            v4l2_buf.bytesused = self.buf_meta[index].bytesused;
-            v4l2_buf.field = self.buf_meta[index].field;
+    fn swap(&'b mut self) -> io::Result<(&Self::Item, &Metadata)> {
+        if !self.active || !self.holding {
+            return CaptureStream::next(self);
+        }
 
-            if self
-                .handle
-                .poll(libc::POLLOUT, self.timeout.unwrap_or(-1))?
-                == 0
-            {
-                // This condition can only happen if there was a timeout.
-                // A timeout is only possible if the `timeout` value is non-zero, meaning we should
-                // propagate it to the caller.
-                return Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_QBUF"));
-            }
+        CaptureStream::queue(self, self.arena_index)?;
+        self.holding = false;
+        // Only wait for the device if no buffer is ready yet
+        self.arena_index = match self.dequeue_ready() {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => CaptureStream::dequeue(self)?,
+            res => res?,
+        };
+        self.holding = true;
 
-            v4l2::ioctl(
-                self.handle.fd(),
-                v4l2::vidioc::VIDIOC_QBUF,
-                &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
-            )
-        }
+        // The index used to access the buffer elements is given to us by v4l2, so we assume it
+        // will always be valid.
+        let bytes = &self.arena.bufs[self.arena_index][0];
+        let meta = &self.buf_meta[self.arena_index];
+        Ok((bytes, meta))
     }
+}
 
-    fn dequeue(&mut self) -> io::Result<usize> {
-        let mut v4l2_buf = self.buffer_desc();
-
-        unsafe {
-            v4l2::ioctl(
-                self.handle.fd(),
-                v4l2::vidioc::VIDIOC_DQBUF,
-                &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
-            )?;
+impl<'a, 'b> OutputStream<'b> for Stream<'a> {
+    fn queue(&mut self, index: usize) -> io::Result<()> {
+        if self.handle.poll(libc::POLLOUT, self.timeout)? == 0 {
+            // This condition can only happen if there was a timeout.
+            // A timeout is only possible if the `timeout` value is non-zero, meaning we should
+            // propagate it to the caller.
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_QBUF"));
         }
-        self.arena_index = v4l2_buf.index as usize;
 
-        self.buf_meta[self.arena_index] = Metadata {
-            bytesused: v4l2_buf.bytesused,
-            flags: v4l2_buf.flags.into(),
-            field: v4l2_buf.field,
-            timestamp: v4l2_buf.timestamp.into(),
-            sequence: v4l2_buf.sequence,
-        };
+        self.queue_output(index)
+    }
 
-        Ok(self.arena_index)
+    fn dequeue(&mut self) -> io::Result<usize> {
+        self.dequeue_ready()
     }
 
     fn next(&'b mut self) -> io::Result<(&mut Self::Item, &mut Metadata)> {
-        let init = !self.active;
         if !self.active {
             self.start()?;
         }
@@ -275,14 +931,16 @@ impl<'a, 'b> OutputStream<'b> for Stream<'a> {
         // Only queue and dequeue once the buffer has been filled at the call site. The initial
         // call to this function from the call site will happen just after the buffers have been
         // allocated, meaning we need to return the empty buffer initially so it can be filled.
-        if !init {
+        if self.holding {
             OutputStream::queue(self, self.arena_index)?;
+            self.holding = false;
             self.arena_index = OutputStream::dequeue(self)?;
         }
+        self.holding = true;
 
         // The index used to access the buffer elements is given to us by v4l2, so we assume it
         // will always be valid.
-        let bytes = &mut self.arena.bufs[self.arena_index];
+        let bytes = &mut self.arena.bufs[self.arena_index][0];
         let meta = &mut self.buf_meta[self.arena_index];
         Ok((bytes, meta))
     }
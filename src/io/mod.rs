@@ -1,4 +1,12 @@
 pub mod traits;
 
+pub mod broadcast;
+pub mod cookie;
+pub mod dmabuf;
 pub mod mmap;
+pub mod multi;
+pub(crate) mod queue;
+pub mod ring;
 pub mod userptr;
+pub mod watchdog;
+pub mod worker;
@@ -0,0 +1,330 @@
+//! Servicing several queues from a single thread
+//!
+//! Some drivers expose related queues which must be serviced concurrently, e.g. the capture and
+//! output queue of a memory-to-memory device or a video node along with its metadata node.
+//! Instead of dedicating a thread to each queue, a [`MultiStream`] waits for all of them with a
+//! single `poll()` call and dispatches whatever is ready.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::ops::ControlFlow;
+//! use v4l::buffer::Type;
+//! use v4l::io::mmap::Stream;
+//! use v4l::io::multi::{Dispatch, MultiStream};
+//! use v4l::Device;
+//!
+//! let dev = Device::new(0).expect("Failed to open device");
+//! let mut multi = MultiStream::new();
+//! let output = Stream::new(&dev, Type::VideoOutput).expect("Failed to create stream");
+//! let capture = Stream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+//! let input = multi.add_output(output);
+//! multi.add_capture(capture);
+//!
+//! let mut frames = 0;
+//! multi
+//!     .run(None, |dispatch| {
+//!         match dispatch {
+//!             Dispatch::Writable { data, meta, .. } => {
+//!                 // fill the next input frame
+//!                 data.fill(0x80);
+//!                 meta.bytesused = data.len() as u32;
+//!             }
+//!             Dispatch::Captured { queue, meta, .. } => {
+//!                 assert_ne!(queue, input);
+//!                 println!("processed frame {}", meta.sequence);
+//!                 frames += 1;
+//!             }
+//!             Dispatch::Event { event, .. } => println!("event {:?}", event),
+//!         }
+//!         if frames < 100 {
+//!             ControlFlow::Continue(())
+//!         } else {
+//!             ControlFlow::Break(())
+//!         }
+//!     })
+//!     .expect("Failed to stream");
+//! ```
+
+use std::ops::ControlFlow;
+use std::time::Duration;
+use std::{io, mem};
+
+use crate::buffer::Metadata;
+use crate::event::Event;
+use crate::io::mmap::Stream;
+use crate::io::traits::{CaptureStream, Stream as _};
+use crate::v4l2;
+use crate::v4l_sys::*;
+
+/// Direction of a queue
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Direction {
+    Capture,
+    Output,
+}
+
+/// Something which is ready on one of the queues, identified by the index returned when adding
+/// the queue
+pub enum Dispatch<'a> {
+    /// A capture buffer was filled, it is requeued once the callback returns
+    Captured {
+        /// Index of the queue
+        queue: usize,
+        /// Buffer data, only the first `meta.bytesused` bytes hold actual frame data
+        data: &'a [u8],
+        /// Buffer metadata
+        meta: &'a Metadata,
+    },
+    /// An output buffer is available, it is queued once the callback returns
+    Writable {
+        /// Index of the queue
+        queue: usize,
+        /// Buffer data to be filled
+        data: &'a mut [u8],
+        /// Buffer metadata, `bytesused` must be set to the number of bytes filled
+        meta: &'a mut Metadata,
+    },
+    /// An event was dequeued from the device of the queue
+    Event {
+        /// Index of the queue
+        queue: usize,
+        /// Event, see [`crate::Device::subscribe_event()`]
+        event: Event,
+    },
+}
+
+/// Queue serviced by a [`MultiStream`]
+struct Queue<'a> {
+    direction: Direction,
+    stream: Stream<'a>,
+    /// Buffers held by the application, because the callback broke before they were queued
+    held: Vec<usize>,
+}
+
+/// Set of queues serviced by a single thread
+pub struct MultiStream<'a> {
+    queues: Vec<Queue<'a>>,
+}
+
+impl<'a> Default for MultiStream<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> MultiStream<'a> {
+    /// Returns an empty set of queues
+    pub fn new() -> Self {
+        MultiStream { queues: Vec::new() }
+    }
+
+    /// Adds a capture queue and returns its index
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - Stream of the queue, which must not have been started yet
+    pub fn add_capture(&mut self, stream: Stream<'a>) -> usize {
+        self.add(Direction::Capture, stream)
+    }
+
+    /// Adds an output queue and returns its index
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - Stream of the queue, which must not have been started yet
+    pub fn add_output(&mut self, stream: Stream<'a>) -> usize {
+        self.add(Direction::Output, stream)
+    }
+
+    fn add(&mut self, direction: Direction, stream: Stream<'a>) -> usize {
+        // all output buffers are handed to the callback to be filled first
+        let held = match direction {
+            Direction::Capture => Vec::new(),
+            Direction::Output => (0..stream.len()).rev().collect(),
+        };
+        self.queues.push(Queue {
+            direction,
+            stream,
+            held,
+        });
+        self.queues.len() - 1
+    }
+
+    /// Returns the stream of a queue
+    ///
+    /// # Arguments
+    ///
+    /// * `queue` - Index of the queue
+    pub fn stream(&mut self, queue: usize) -> Option<&mut Stream<'a>> {
+        self.queues.get_mut(queue).map(|queue| &mut queue.stream)
+    }
+
+    /// Services all queues until the callback breaks
+    ///
+    /// Capture queues are started with all buffers queued. All buffers of output queues are
+    /// handed to the callback to be filled first, streaming starts with the first one. When the
+    /// callback breaks, the buffer it was handed is kept until the next call, which requeues it
+    /// for capture queues and hands it to the callback again for output queues.
+    ///
+    /// Queues sharing a device, e.g. both queues of a memory-to-memory device, report its
+    /// events only once, with the index of the queue added first. Fails if the device reports an
+    /// error on a queue or hangs up, e.g. because it was unplugged.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Fail with [`io::ErrorKind::TimedOut`] if no queue is ready for this long,
+    ///   `None` means infinite timeout (blocking)
+    /// * `callback` - Function called with everything that is ready
+    pub fn run<F>(&mut self, timeout: Option<Duration>, mut callback: F) -> io::Result<()>
+    where
+        F: FnMut(Dispatch<'_>) -> ControlFlow<()>,
+    {
+        for queue in 0..self.queues.len() {
+            let Queue {
+                direction,
+                stream,
+                held,
+            } = &mut self.queues[queue];
+            match direction {
+                Direction::Capture => {
+                    if !stream.is_active() {
+                        held.clear();
+                        for index in 0..stream.len() {
+                            CaptureStream::queue(stream, index)?;
+                        }
+                        stream.start()?;
+                    }
+                    while let Some(&index) = held.last() {
+                        CaptureStream::queue(stream, index)?;
+                        held.pop();
+                    }
+                }
+                Direction::Output => {
+                    while let Some(&index) = self.queues[queue].held.last() {
+                        if self.write(queue, index, &mut callback)?.is_break() {
+                            return Ok(());
+                        }
+                        self.queues[queue].held.pop();
+                    }
+                }
+            }
+        }
+
+        loop {
+            let mut fds: Vec<libc::pollfd> = self
+                .queues
+                .iter()
+                .enumerate()
+                .map(|(queue, q)| {
+                    let fd = q.stream.handle().fd();
+                    // only wait for events once per device, they are dequeued only once
+                    let shared = self.queues[..queue]
+                        .iter()
+                        .any(|other| other.stream.handle().fd() == fd);
+                    let events = match q.direction {
+                        Direction::Capture => libc::POLLIN,
+                        Direction::Output => libc::POLLOUT,
+                    };
+                    libc::pollfd {
+                        fd,
+                        events: if shared {
+                            events
+                        } else {
+                            events | libc::POLLPRI
+                        },
+                        revents: 0,
+                    }
+                })
+                .collect();
+
+            match v4l2::ppoll(&mut fds, timeout, None) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::TimedOut, "poll")),
+                Ok(_) => {}
+            }
+
+            for (queue, fd) in fds.iter().enumerate() {
+                if fd.revents & libc::POLLPRI != 0 {
+                    let event = self.dequeue_event(queue)?;
+                    if callback(Dispatch::Event { queue, event }).is_break() {
+                        return Ok(());
+                    }
+                }
+                if fd.revents & libc::POLLHUP != 0 {
+                    return Err(io::Error::from_raw_os_error(libc::ENODEV));
+                }
+                if fd.revents & (libc::POLLERR | libc::POLLNVAL) != 0 {
+                    return Err(io::Error::other(format!(
+                        "queue {} reported an error",
+                        queue
+                    )));
+                }
+                if fd.revents & (libc::POLLIN | libc::POLLOUT) == 0 {
+                    continue;
+                }
+
+                let Queue {
+                    direction, stream, ..
+                } = &mut self.queues[queue];
+                let index = match stream.dequeue_ready() {
+                    Ok(index) => index,
+                    // another event woke us up
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                    Err(e) => return Err(e),
+                };
+                let flow = match direction {
+                    Direction::Capture => {
+                        let (data, meta) = stream.get_mut(index).unwrap();
+                        let flow = callback(Dispatch::Captured { queue, data, meta });
+                        if flow.is_continue() {
+                            CaptureStream::queue(stream, index)?;
+                        }
+                        flow
+                    }
+                    Direction::Output => self.write(queue, index, &mut callback)?,
+                };
+                if flow.is_break() {
+                    self.queues[queue].held.push(index);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Hands a free output buffer to the callback and queues it
+    fn write<F>(
+        &mut self,
+        queue: usize,
+        index: usize,
+        callback: &mut F,
+    ) -> io::Result<ControlFlow<()>>
+    where
+        F: FnMut(Dispatch<'_>) -> ControlFlow<()>,
+    {
+        let stream = &mut self.queues[queue].stream;
+        let (data, meta) = stream.get_mut(index).unwrap();
+        let flow = callback(Dispatch::Writable { queue, data, meta });
+        if flow.is_continue() {
+            stream.queue_output(index)?;
+            if !stream.is_active() {
+                stream.start()?;
+            }
+        }
+        Ok(flow)
+    }
+
+    fn dequeue_event(&self, queue: usize) -> io::Result<Event> {
+        unsafe {
+            let mut v4l2_ev: v4l2_event = mem::zeroed();
+            v4l2::ioctl(
+                self.queues[queue].stream.handle().fd(),
+                v4l2::vidioc::VIDIOC_DQEVENT,
+                &mut v4l2_ev as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            Ok(Event::from(v4l2_ev))
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::time::Duration;
+use std::{io, mem, sync::Arc};
+
+use crate::buffer::{State, Type};
+use crate::device::{Device, Handle};
+use crate::memory::Memory;
+use crate::v4l2;
+use crate::v4l_sys::*;
+
+/// Buffer queue of a device, parameterized by the memory type of its buffers
+///
+/// Covers what the queues of all memory types have in common: requesting and freeing the
+/// buffers, starting and stopping the stream and waiting for the driver. Describing the memory
+/// of a buffer to the driver and keeping it alive is left to the queue embedding this one.
+pub(crate) struct Queue {
+    handle: Arc<Handle>,
+    buf_type: Type,
+    memory: Memory,
+    timeout: Option<Duration>,
+
+    active: bool,
+}
+
+impl Queue {
+    /// Requests buffers and returns the queue along with the number of buffers granted
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Device ref to get its file descriptor
+    /// * `buf_type` - Type of the buffers
+    /// * `memory` - Memory type of the buffers
+    /// * `buf_count` - Number of buffers to request
+    pub fn new(
+        dev: &Device,
+        buf_type: Type,
+        memory: Memory,
+        buf_count: u32,
+    ) -> io::Result<(Self, usize)> {
+        dev.check_streaming(buf_type)?;
+
+        let queue = Queue {
+            handle: dev.handle(),
+            buf_type,
+            memory,
+            timeout: None,
+            active: false,
+        };
+        let count = queue.request(buf_count)?;
+        Ok((queue, count as usize))
+    }
+
+    /// Returns the raw device handle
+    pub fn handle(&self) -> &Arc<Handle> {
+        &self.handle
+    }
+
+    /// Returns the type of the buffers
+    pub fn buf_type(&self) -> Type {
+        self.buf_type
+    }
+
+    /// Returns whether the stream is on
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Returns the current state of a buffer as reported by the driver
+    pub fn query_buffer(&self, index: usize) -> io::Result<State> {
+        State::query(&self.handle, self.buf_type, self.memory, index as u32)
+    }
+
+    /// Sets the timeout of [`Queue::wait()`], `None` waits forever
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Returns a buffer description without any memory attached
+    pub fn buffer_desc(&self) -> v4l2_buffer {
+        v4l2_buffer {
+            type_: self.buf_type as u32,
+            memory: self.memory as u32,
+            ..unsafe { mem::zeroed() }
+        }
+    }
+
+    /// Hands a buffer to the driver
+    pub fn qbuf(&self, v4l2_buf: &mut v4l2_buffer) -> io::Result<()> {
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_QBUF,
+                v4l2_buf as *mut _ as *mut std::os::raw::c_void,
+            )
+        }
+    }
+
+    /// Waits for the driver to hand back a buffer and dequeues it
+    pub fn dqbuf(&self, v4l2_buf: &mut v4l2_buffer) -> io::Result<()> {
+        let events = if self.buf_type.is_output() {
+            libc::POLLOUT
+        } else {
+            libc::POLLIN
+        };
+        if self.handle.poll(events, self.timeout)? == 0 {
+            // This condition can only happen if there was a timeout.
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_DQBUF"));
+        }
+
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_DQBUF,
+                v4l2_buf as *mut _ as *mut std::os::raw::c_void,
+            )
+        }
+    }
+
+    pub fn start(&mut self) -> io::Result<()> {
+        unsafe {
+            let mut typ = self.buf_type as u32;
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_STREAMON,
+                &mut typ as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        self.active = true;
+        Ok(())
+    }
+
+    /// Stops streaming, the driver gives up all buffers
+    pub fn stop(&mut self) -> io::Result<()> {
+        unsafe {
+            let mut typ = self.buf_type as u32;
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_STREAMOFF,
+                &mut typ as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        self.active = false;
+        Ok(())
+    }
+
+    /// Stops streaming and frees all buffers
+    pub fn release(&mut self) -> io::Result<()> {
+        self.stop()?;
+        self.request(0)?;
+        Ok(())
+    }
+
+    fn request(&self, count: u32) -> io::Result<u32> {
+        let mut v4l2_reqbufs = v4l2_requestbuffers {
+            count,
+            type_: self.buf_type as u32,
+            memory: self.memory as u32,
+            ..unsafe { mem::zeroed() }
+        };
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_REQBUFS,
+                &mut v4l2_reqbufs as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        Ok(v4l2_reqbufs.count)
+    }
+}
+
+/// Handles the result of tearing down a stream or queue when it is dropped
+///
+/// Drop cannot report errors, so any error other than a lost device panics.
+pub(crate) fn teardown(res: io::Result<()>) {
+    if let Err(e) = res {
+        if let Some(code) = e.raw_os_error() {
+            // ENODEV means the file descriptor wrapped in the handle became invalid, most
+            // likely because the device was unplugged or the connection (USB, PCI, ..)
+            // broke down. Handle this case gracefully by ignoring it.
+            if code == 19 {
+                /* ignore */
+                return;
+            }
+        }
+
+        panic!("{:?}", e)
+    }
+}
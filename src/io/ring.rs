@@ -0,0 +1,216 @@
+use std::collections::VecDeque;
+use std::io;
+use std::time::Duration;
+
+use crate::buffer::{Flags, Metadata};
+use crate::io::broadcast::Frame;
+
+/// Frame retained by a [`RingRecorder`]
+pub struct Recorded {
+    data: Vec<u8>,
+    meta: Metadata,
+}
+
+impl Recorded {
+    /// Returns the frame data
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the frame metadata
+    pub fn meta(&self) -> &Metadata {
+        &self.meta
+    }
+}
+
+/// Keeps the most recent frames of a stream in memory
+///
+/// This is the pre-trigger buffer of dashcams and NVRs: frames are pushed continuously and the
+/// ones older than the window are discarded, so once an event fires, the moments leading up to
+/// it can be written out with [`RingRecorder::flush()`] before recording the live frames.
+///
+/// Frames are copied into the ring, so the driver buffers are handed back right away. The window
+/// is measured using the buffer timestamps. For encoded streams, see
+/// [`RingRecorder::keyframes()`].
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use v4l::buffer::{Flags, Metadata};
+/// use v4l::io::ring::RingRecorder;
+/// use v4l::timestamp::Timestamp;
+///
+/// let mut ring = RingRecorder::new(Duration::from_secs(2)).keyframes(true);
+/// for sec in 0..5 {
+///     let meta = Metadata {
+///         bytesused: 1,
+///         flags: if sec % 2 == 0 { Flags::KEYFRAME } else { Flags::PFRAME },
+///         timestamp: Timestamp::new(sec, 0),
+///         sequence: sec as u32,
+///         ..Metadata::default()
+///     };
+///     ring.push_data(&[sec as u8], &meta);
+/// }
+///
+/// // the last two seconds start with the keyframe of second 2
+/// let mut flushed = Vec::new();
+/// ring.flush(|data, _| {
+///     flushed.push(data[0]);
+///     Ok(())
+/// })
+/// .unwrap();
+/// assert_eq!(flushed, [2, 3, 4]);
+/// assert!(ring.is_empty());
+/// ```
+pub struct RingRecorder {
+    window: Duration,
+    keyframes: bool,
+    frames: VecDeque<Recorded>,
+    bytes: usize,
+}
+
+impl RingRecorder {
+    /// Returns an empty ring
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - Duration of the stream to retain
+    pub fn new(window: Duration) -> Self {
+        RingRecorder {
+            window,
+            keyframes: false,
+            frames: VecDeque::new(),
+            bytes: 0,
+        }
+    }
+
+    /// Makes the ring start at a keyframe, for streams of encoded access units
+    ///
+    /// Frames are only discarded up to the last keyframe before the window, so the retained
+    /// stream can be decoded from its start. The ring then holds a bit more than the window.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to start at a keyframe
+    pub fn keyframes(mut self, enable: bool) -> Self {
+        self.keyframes = enable;
+        self
+    }
+
+    /// Copies a frame into the ring and discards frames which fell out of the window
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame handed out by a [`crate::io::broadcast::FrameBroadcaster`]
+    pub fn push(&mut self, frame: &Frame) {
+        self.push_data(frame.data(), frame.meta())
+    }
+
+    /// Copies frame data into the ring and discards frames which fell out of the window
+    ///
+    /// Only the first `meta.bytesused` bytes are retained.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Frame data
+    /// * `meta` - Frame metadata
+    pub fn push_data(&mut self, data: &[u8], meta: &Metadata) {
+        let len = (meta.bytesused as usize).min(data.len());
+        self.bytes += len;
+        self.frames.push_back(Recorded {
+            data: data[..len].to_vec(),
+            meta: *meta,
+        });
+        self.trim();
+    }
+
+    /// Returns the number of retained frames
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns whether no frames are retained
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns the number of bytes of all retained frames
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Returns the time span between the oldest and the newest retained frame
+    pub fn duration(&self) -> Duration {
+        match (self.frames.front(), self.frames.back()) {
+            (Some(first), Some(last)) => timestamp(last).saturating_sub(timestamp(first)),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Returns the retained frames, oldest first
+    pub fn frames(&self) -> impl Iterator<Item = &Recorded> {
+        self.frames.iter()
+    }
+
+    /// Hands all retained frames to a sink, oldest first, and empties the ring
+    ///
+    /// Frames are removed as they are written, so the ring only holds the remaining frames if the
+    /// sink fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - Called for each frame, e.g. [`crate::output::raw::Recorder::write_frame()`]
+    pub fn flush<F>(&mut self, mut sink: F) -> io::Result<()>
+    where
+        F: FnMut(&[u8], &Metadata) -> io::Result<()>,
+    {
+        while let Some(frame) = self.frames.front() {
+            sink(&frame.data, &frame.meta)?;
+            self.pop();
+        }
+        Ok(())
+    }
+
+    /// Discards all retained frames
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.bytes = 0;
+    }
+
+    fn pop(&mut self) {
+        if let Some(frame) = self.frames.pop_front() {
+            self.bytes -= frame.data.len();
+        }
+    }
+
+    fn trim(&mut self) {
+        let cutoff = match self.frames.back() {
+            Some(last) => timestamp(last).saturating_sub(self.window),
+            None => return,
+        };
+
+        let start = if self.keyframes {
+            // the last keyframe at or before the cutoff, or the first keyframe if there is none,
+            // frames before it cannot be decoded anyway
+            let is_key = |frame: &Recorded| frame.meta.flags.contains(Flags::KEYFRAME);
+            self.frames
+                .iter()
+                .rposition(|frame| is_key(frame) && timestamp(frame) <= cutoff)
+                .or_else(|| self.frames.iter().position(is_key))
+                .unwrap_or(self.frames.len())
+        } else {
+            self.frames
+                .iter()
+                .position(|frame| timestamp(frame) >= cutoff)
+                .unwrap_or(self.frames.len())
+        };
+        for _ in 0..start {
+            self.pop();
+        }
+    }
+}
+
+fn timestamp(frame: &Recorded) -> Duration {
+    frame.meta.timestamp.into()
+}
@@ -13,6 +13,36 @@ pub trait Stream {
     fn stop(&mut self) -> io::Result<()>;
 }
 
+/// Capture side of a stream, implemented by all memory types
+///
+/// Code which only consumes frames can be generic over the memory type of the stream. The items
+/// of memory-mapped and user pointer streams are the bytes of a frame, while the items of a
+/// [`crate::io::dmabuf::Queue`] are the DMABUF file descriptors of its planes.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::buffer::Type;
+/// use v4l::device::Device;
+/// use v4l::io::traits::CaptureStream;
+/// use v4l::io::{mmap, userptr};
+///
+/// fn capture<'a, S>(stream: &'a mut S) -> std::io::Result<usize>
+/// where
+///     S: CaptureStream<'a, Item = [u8]>,
+/// {
+///     let (_buf, meta) = stream.next()?;
+///     Ok(meta.bytesused as usize)
+/// }
+///
+/// let dev = Device::new(0).expect("Failed to open device");
+/// let mut stream = mmap::Stream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+/// capture(&mut stream).expect("Failed to capture frame");
+/// drop(stream);
+/// let mut stream =
+///     userptr::Stream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+/// capture(&mut stream).expect("Failed to capture frame");
+/// ```
 pub trait CaptureStream<'a>: Stream {
     /// Insert a buffer into the drivers' incoming queue
     fn queue(&mut self, index: usize) -> io::Result<()>;
@@ -23,8 +53,18 @@ pub trait CaptureStream<'a>: Stream {
     /// Fetch a new frame by first queueing and then dequeueing.
     /// First time initialization is performed if necessary.
     fn next(&'a mut self) -> io::Result<(&Self::Item, &Metadata)>;
+
+    /// Fetch a new frame by requeueing the current buffer and dequeueing the next ready one.
+    /// Unlike [`CaptureStream::next`], implementations may skip polling the device if a buffer
+    /// is ready already, saving a syscall per frame.
+    fn swap(&'a mut self) -> io::Result<(&Self::Item, &Metadata)> {
+        self.next()
+    }
 }
 
+/// Output side of a stream, implemented by all memory types
+///
+/// See [`CaptureStream`] for writing code which is generic over the memory type.
 pub trait OutputStream<'a>: Stream {
     /// Insert a buffer into the drivers' incoming queue
     fn queue(&mut self, index: usize) -> io::Result<()>;
@@ -2,7 +2,8 @@ use std::{io, mem, sync::Arc};
 
 use crate::buffer;
 use crate::device::Handle;
-use crate::memory::Memory;
+use crate::io::queue;
+use crate::memory::{self, Memory};
 use crate::v4l2;
 use crate::v4l_sys::*;
 
@@ -13,6 +14,7 @@ pub struct Arena {
     handle: Arc<Handle>,
     pub bufs: Vec<Vec<u8>>,
     pub buf_type: buffer::Type,
+    locked: bool,
 }
 
 impl Arena {
@@ -30,6 +32,7 @@ impl Arena {
             handle,
             bufs: Vec::new(),
             buf_type,
+            locked: false,
         }
     }
 
@@ -86,7 +89,32 @@ impl Arena {
         Ok(v4l2_reqbufs.count)
     }
 
+    /// Locks all buffers into RAM
+    pub fn lock(&mut self) -> io::Result<()> {
+        for buf in &self.bufs {
+            memory::lock(buf)?;
+        }
+
+        self.locked = true;
+        Ok(())
+    }
+
+    /// Touches all pages of all buffers to avoid page faults later on
+    pub fn prefault(&mut self) {
+        for buf in &mut self.bufs {
+            memory::prefault(buf);
+        }
+    }
+
     pub fn release(&mut self) -> io::Result<()> {
+        // the heap memory may be reused after the buffers are freed, so do not leave it locked
+        if self.locked {
+            for buf in &self.bufs {
+                memory::unlock(buf)?;
+            }
+            self.locked = false;
+        }
+
         // free all buffers by requesting 0
         let mut v4l2_reqbufs = v4l2_requestbuffers {
             count: 0,
@@ -109,18 +137,6 @@ impl Drop for Arena {
             return;
         }
 
-        if let Err(e) = self.release() {
-            if let Some(code) = e.raw_os_error() {
-                // ENODEV means the file descriptor wrapped in the handle became invalid, most
-                // likely because the device was unplugged or the connection (USB, PCI, ..)
-                // broke down. Handle this case gracefully by ignoring it.
-                if code == 19 {
-                    /* ignore */
-                    return;
-                }
-            }
-
-            panic!("{:?}", e)
-        }
+        queue::teardown(self.release());
     }
 }
@@ -0,0 +1,26 @@
+use std::sync::Arc;
+
+/// Memory which the driver may read from while it is owned by a queue
+///
+/// # Safety
+///
+/// The slice returned by [`AsRef::as_ref()`] must stay valid and at the same address for as long
+/// as the value is alive, even when the value itself is moved. Borrowed slices or types storing
+/// their bytes inline, such as arrays, do not qualify: the driver accesses the memory after the
+/// value has been moved into the queue.
+pub unsafe trait UserBuffer: AsRef<[u8]> {}
+
+/// Memory which the driver may write to while it is owned by a queue
+///
+/// # Safety
+///
+/// The slice returned by [`AsMut::as_mut()`] must stay valid and at the same address for as long
+/// as the value is alive, even when the value itself is moved, and no other value may access it.
+pub unsafe trait UserBufferMut: AsMut<[u8]> {}
+
+unsafe impl UserBuffer for Vec<u8> {}
+unsafe impl UserBuffer for Box<[u8]> {}
+unsafe impl UserBuffer for Arc<[u8]> {}
+
+unsafe impl UserBufferMut for Vec<u8> {}
+unsafe impl UserBufferMut for Box<[u8]> {}
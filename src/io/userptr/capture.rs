@@ -0,0 +1,199 @@
+use std::time::Duration;
+use std::{io, sync::Arc};
+
+use crate::buffer::{Metadata, State, Type};
+use crate::device::{Device, Handle};
+use crate::io::queue::{self, Queue};
+use crate::io::userptr::UserBufferMut;
+use crate::memory::Memory;
+use crate::v4l_sys::*;
+
+/// Frame handed back by a [`CaptureQueue`]
+pub struct Frame<B> {
+    /// Buffer holding the frame data, the first `meta.bytesused` bytes are valid
+    pub buf: B,
+    /// Buffer metadata
+    pub meta: Metadata,
+}
+
+/// Capture queue filling caller-owned buffers
+///
+/// Unlike [`super::Stream`], which allocates its buffers itself, the buffers come from the
+/// caller, e.g. from a custom allocator or a memory pool shared with the next processing stage.
+/// The queue takes ownership of each buffer while the driver writes to it and hands it back by
+/// [`CaptureQueue::dequeue()`], so the memory is guaranteed to stay alive while it is queued. The
+/// buffers must be heap allocations which do not move along with their owner, see
+/// [`UserBufferMut`], e.g. `Vec<u8>` or `Box<[u8]>`.
+///
+/// Drivers require each buffer to be at least as large as the image size of the active format,
+/// some of them additionally require page aligned memory.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::buffer::Type;
+/// use v4l::device::Device;
+/// use v4l::io::userptr::CaptureQueue;
+/// use v4l::video::Capture;
+///
+/// let dev = Device::new(0).expect("Failed to open device");
+/// let format = Capture::format(&dev).expect("Failed to get format");
+///
+/// let mut queue = CaptureQueue::new(&dev, Type::VideoCapture, 4)
+///     .expect("Failed to create queue");
+/// for _ in 0..4 {
+///     queue
+///         .queue(vec![0u8; format.size as usize])
+///         .expect("Failed to queue buffer");
+/// }
+///
+/// for _ in 0..10 {
+///     let frame = queue.dequeue().expect("Failed to dequeue buffer");
+///     println!("captured {} bytes", frame.meta.bytesused);
+///     // hand the buffer back to the driver for the next frame
+///     queue.queue(frame.buf).expect("Failed to queue buffer");
+/// }
+/// ```
+pub struct CaptureQueue<B: UserBufferMut> {
+    inner: Queue,
+    slots: Vec<Option<B>>,
+}
+
+impl<B: UserBufferMut> CaptureQueue<B> {
+    /// Returns a new capture queue
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Capture device ref to get its file descriptor
+    /// * `buf_type` - Type of the buffers, a single-planar capture type
+    /// * `buf_count` - Maximum number of buffers handed to the driver at once
+    pub fn new(dev: &Device, buf_type: Type, buf_count: u32) -> io::Result<Self> {
+        if !matches!(
+            buf_type,
+            Type::VideoCapture
+                | Type::VbiCaputre
+                | Type::SlicedVbiCapture
+                | Type::SdrCapture
+                | Type::MetaCapture
+        ) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?} is no single-planar capture buffer type", buf_type),
+            ));
+        }
+        let (inner, count) = Queue::new(dev, buf_type, Memory::UserPtr, buf_count)?;
+        let mut slots = Vec::new();
+        slots.resize_with(count, || None);
+
+        Ok(CaptureQueue { inner, slots })
+    }
+
+    /// Returns the raw device handle
+    pub fn handle(&self) -> Arc<Handle> {
+        self.inner.handle().clone()
+    }
+
+    /// Returns the current state of a buffer as reported by the driver
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the buffer
+    pub fn query_buffer(&self, index: usize) -> io::Result<State> {
+        self.inner.query_buffer(index)
+    }
+
+    /// Sets a timeout of the v4l file handle.
+    pub fn set_timeout(&mut self, duration: Duration) {
+        self.inner.set_timeout(Some(duration));
+    }
+
+    /// Clears the timeout of the v4l file handle.
+    pub fn clear_timeout(&mut self) {
+        self.inner.set_timeout(None);
+    }
+
+    /// Returns the number of buffers the queue can hold
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns whether the driver granted no buffers at all
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Returns the number of buffers currently owned by the driver
+    pub fn queued(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    /// Hands an empty buffer to the driver
+    ///
+    /// Streaming is started with the first buffer. Fails with [`io::ErrorKind::InvalidInput`] if
+    /// the driver owns all buffers already, in which case the buffer is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Buffer to fill, its whole length is available to the driver
+    pub fn queue(&mut self, buf: B) -> io::Result<()> {
+        let index = self
+            .slots
+            .iter()
+            .position(|slot| slot.is_none())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "all buffers are queued"))?;
+
+        // Take the pointer from the buffer in its final place, the driver accesses the memory
+        // long after this function returned.
+        let desc = self.inner.buffer_desc();
+        let data = self.slots[index].insert(buf).as_mut();
+        let mut v4l2_buf = v4l2_buffer {
+            index: index as u32,
+            m: v4l2_buffer__bindgen_ty_1 {
+                userptr: data.as_mut_ptr() as std::os::raw::c_ulong,
+            },
+            length: data.len() as u32,
+            ..desc
+        };
+        if let Err(e) = self.inner.qbuf(&mut v4l2_buf) {
+            self.slots[index] = None;
+            return Err(e);
+        }
+
+        if !self.inner.is_active() {
+            self.inner.start()?;
+        }
+
+        Ok(())
+    }
+
+    /// Waits for the driver to fill a buffer and returns it
+    pub fn dequeue(&mut self) -> io::Result<Frame<B>> {
+        if self.queued() == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no buffers are queued",
+            ));
+        }
+
+        let mut v4l2_buf = self.inner.buffer_desc();
+        self.inner.dqbuf(&mut v4l2_buf)?;
+
+        let buf = self
+            .slots
+            .get_mut(v4l2_buf.index as usize)
+            .and_then(Option::take)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid buffer index"))?;
+
+        Ok(Frame {
+            buf,
+            meta: Metadata::from_raw(&v4l2_buf),
+        })
+    }
+}
+
+impl<B: UserBufferMut> Drop for CaptureQueue<B> {
+    fn drop(&mut self) {
+        // The driver must be done with all buffers before they are dropped along with the slots.
+        queue::teardown(self.inner.release());
+    }
+}
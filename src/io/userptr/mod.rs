@@ -1,4 +1,12 @@
 pub(crate) mod arena;
 
+pub mod buffer;
+pub mod capture;
+pub mod mplane;
+pub mod output;
 pub mod stream;
+pub use buffer::{UserBuffer, UserBufferMut};
+pub use capture::CaptureQueue;
+pub use mplane::PlaneQueue;
+pub use output::OutputQueue;
 pub use stream::Stream;
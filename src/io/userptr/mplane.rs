@@ -0,0 +1,279 @@
+use std::time::Duration;
+use std::{io, mem, sync::Arc};
+
+use crate::buffer::{Flags, Metadata, Type};
+use crate::device::{Device, Handle};
+use crate::format::mplane::MAX_PLANES;
+use crate::format::FieldOrder;
+use crate::io::queue::{self, Queue};
+use crate::io::userptr::UserBufferMut;
+use crate::memory::Memory;
+use crate::timecode::Timecode;
+use crate::timestamp::Timestamp;
+use crate::v4l_sys::*;
+
+/// Frame handed back by a [`PlaneQueue`]
+pub struct Frame<B> {
+    /// Plane buffers, in the order they were queued
+    pub planes: Vec<B>,
+    /// Number of bytes of each plane holding data
+    pub bytesused: Vec<u32>,
+    /// Buffer metadata
+    pub meta: Metadata,
+}
+
+/// Multi-planar queue of caller-owned buffers
+///
+/// Each plane of a frame is a separate allocation, so e.g. the luma plane can come from one
+/// memory pool and the chroma plane from another. Like [`super::OutputQueue`], the queue owns the
+/// buffers while the driver uses them and hands them back once it is done. The plane buffers must
+/// be heap allocations which do not move along with their owner, see [`UserBufferMut`], e.g.
+/// `Vec<u8>` or `Box<[u8]>`.
+///
+/// Both [`Type::VideoCaptureMplane`] and [`Type::VideoOutputMplane`] queues are supported. Each
+/// plane must be at least as large as the size of the corresponding plane of the active format.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::buffer::Type;
+/// use v4l::device::Device;
+/// use v4l::io::userptr::PlaneQueue;
+/// use v4l::timestamp::Timestamp;
+///
+/// let dev = Device::new(0).expect("Failed to open device");
+/// let mut queue = PlaneQueue::new(&dev, Type::VideoCaptureMplane, 4)
+///     .expect("Failed to create queue");
+///
+/// // e.g. NV12M: luma and chroma planes from different pools
+/// for _ in 0..4 {
+///     let planes = queue
+///         .plane_sizes()
+///         .iter()
+///         .map(|&size| vec![0u8; size as usize])
+///         .collect();
+///     queue
+///         .queue(planes, Timestamp::default())
+///         .expect("Failed to queue buffer");
+/// }
+///
+/// let frame = queue.dequeue().expect("Failed to dequeue buffer");
+/// println!("luma: {} bytes", frame.bytesused[0]);
+/// ```
+pub struct PlaneQueue<B: UserBufferMut> {
+    inner: Queue,
+    plane_sizes: Vec<u32>,
+    slots: Vec<Option<Vec<B>>>,
+}
+
+impl<B: UserBufferMut> PlaneQueue<B> {
+    /// Returns a new multi-planar queue
+    ///
+    /// The plane sizes are taken from the active format, so set the format first.
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Device ref to get its file descriptor
+    /// * `buf_type` - Type of the buffers, [`Type::VideoCaptureMplane`] or
+    ///   [`Type::VideoOutputMplane`]
+    /// * `buf_count` - Maximum number of frames handed to the driver at once
+    pub fn new(dev: &Device, buf_type: Type, buf_count: u32) -> io::Result<Self> {
+        if !buf_type.is_multiplanar() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?} is no multi-planar buffer type", buf_type),
+            ));
+        }
+        let v4l2_fmt = dev.format_raw(buf_type)?;
+        let pix_mp = unsafe { v4l2_fmt.fmt.pix_mp };
+        let plane_sizes = pix_mp.plane_fmt[..(pix_mp.num_planes as usize).min(MAX_PLANES)]
+            .iter()
+            .map(|plane| plane.sizeimage)
+            .collect();
+
+        let (inner, count) = Queue::new(dev, buf_type, Memory::UserPtr, buf_count)?;
+        let mut slots = Vec::new();
+        slots.resize_with(count, || None);
+
+        Ok(PlaneQueue {
+            inner,
+            plane_sizes,
+            slots,
+        })
+    }
+
+    /// Returns the raw device handle
+    pub fn handle(&self) -> Arc<Handle> {
+        self.inner.handle().clone()
+    }
+
+    /// Returns the minimum size of each plane, as required by the active format
+    pub fn plane_sizes(&self) -> &[u32] {
+        &self.plane_sizes
+    }
+
+    /// Sets a timeout of the v4l file handle.
+    pub fn set_timeout(&mut self, duration: Duration) {
+        self.inner.set_timeout(Some(duration));
+    }
+
+    /// Clears the timeout of the v4l file handle.
+    pub fn clear_timeout(&mut self) {
+        self.inner.set_timeout(None);
+    }
+
+    /// Returns the number of frames currently owned by the driver
+    pub fn queued(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    /// Hands the planes of a frame to the driver
+    ///
+    /// Output planes are submitted with their whole length. Streaming is started with the first
+    /// frame. Fails if the driver owns all buffers already, dequeue one first in that case.
+    ///
+    /// # Arguments
+    ///
+    /// * `planes` - One buffer per plane of the active format
+    /// * `timestamp` - Timestamp of output frames, ignored for capture queues
+    pub fn queue(&mut self, planes: Vec<B>, timestamp: Timestamp) -> io::Result<()> {
+        self.queue_timecode(planes, timestamp, None)
+    }
+
+    /// Hands the planes of an output frame to the driver along with its SMPTE timecode
+    ///
+    /// Behaves like [`PlaneQueue::queue()`] otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `planes` - One buffer per plane of the active format
+    /// * `timestamp` - Timestamp of the frame
+    /// * `timecode` - Timecode of the frame
+    pub fn queue_with_timecode(
+        &mut self,
+        planes: Vec<B>,
+        timestamp: Timestamp,
+        timecode: Timecode,
+    ) -> io::Result<()> {
+        self.queue_timecode(planes, timestamp, Some(timecode))
+    }
+
+    fn queue_timecode(
+        &mut self,
+        mut planes: Vec<B>,
+        timestamp: Timestamp,
+        timecode: Option<Timecode>,
+    ) -> io::Result<()> {
+        if planes.len() != self.plane_sizes.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "format has {} planes, got {}",
+                    self.plane_sizes.len(),
+                    planes.len()
+                ),
+            ));
+        }
+        for (i, (plane, &size)) in planes.iter_mut().zip(&self.plane_sizes).enumerate() {
+            let len = plane.as_mut().len();
+            if len < size as usize {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("plane {} holds {} bytes, expected {}", i, len, size),
+                ));
+            }
+        }
+        let index = self
+            .slots
+            .iter()
+            .position(|slot| slot.is_none())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::WouldBlock, "all buffers are queued"))?;
+
+        // Take the pointers from the planes in their final place, the driver accesses the memory
+        // long after this function returned.
+        let output = self.inner.buf_type().is_output();
+        let desc = self.inner.buffer_desc();
+        let mut v4l2_planes: Vec<v4l2_plane> = self.slots[index]
+            .insert(planes)
+            .iter_mut()
+            .map(|plane| {
+                let data = plane.as_mut();
+                v4l2_plane {
+                    m: v4l2_plane__bindgen_ty_1 {
+                        userptr: data.as_mut_ptr() as std::os::raw::c_ulong,
+                    },
+                    length: data.len() as u32,
+                    bytesused: if output { data.len() as u32 } else { 0 },
+                    ..unsafe { mem::zeroed() }
+                }
+            })
+            .collect();
+        let mut v4l2_buf = v4l2_buffer {
+            index: index as u32,
+            m: v4l2_buffer__bindgen_ty_1 {
+                planes: v4l2_planes.as_mut_ptr(),
+            },
+            length: v4l2_planes.len() as u32,
+            field: FieldOrder::Progressive as u32,
+            timestamp: timestamp.into(),
+            ..desc
+        };
+        if let Some(timecode) = timecode {
+            v4l2_buf.timecode = timecode.into();
+            v4l2_buf.flags |= u32::from(Flags::TIMECODE);
+        }
+        if let Err(e) = self.inner.qbuf(&mut v4l2_buf) {
+            self.slots[index] = None;
+            return Err(e);
+        }
+
+        if !self.inner.is_active() {
+            self.inner.start()?;
+        }
+
+        Ok(())
+    }
+
+    /// Waits for the driver to hand back a frame and returns it
+    pub fn dequeue(&mut self) -> io::Result<Frame<B>> {
+        if self.queued() == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no buffers are queued",
+            ));
+        }
+
+        let mut v4l2_planes: [v4l2_plane; MAX_PLANES] = unsafe { mem::zeroed() };
+        let mut v4l2_buf = v4l2_buffer {
+            m: v4l2_buffer__bindgen_ty_1 {
+                planes: v4l2_planes.as_mut_ptr(),
+            },
+            length: self.plane_sizes.len() as u32,
+            ..self.inner.buffer_desc()
+        };
+        self.inner.dqbuf(&mut v4l2_buf)?;
+
+        let planes = self
+            .slots
+            .get_mut(v4l2_buf.index as usize)
+            .and_then(Option::take)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid buffer index"))?;
+        let bytesused = v4l2_planes[..planes.len()]
+            .iter()
+            .map(|plane| plane.bytesused)
+            .collect();
+
+        Ok(Frame {
+            planes,
+            bytesused,
+            meta: Metadata::from_raw(&v4l2_buf),
+        })
+    }
+}
+
+impl<B: UserBufferMut> Drop for PlaneQueue<B> {
+    fn drop(&mut self) {
+        // The driver must be done with all buffers before they are dropped along with the slots.
+        queue::teardown(self.inner.release());
+    }
+}
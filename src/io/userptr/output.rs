@@ -0,0 +1,207 @@
+use std::time::Duration;
+use std::{io, sync::Arc};
+
+use crate::buffer::{Flags, State, Type};
+use crate::device::{Device, Handle};
+use crate::format::FieldOrder;
+use crate::io::queue::{self, Queue};
+use crate::io::userptr::UserBuffer;
+use crate::memory::Memory;
+use crate::timecode::Timecode;
+use crate::timestamp::Timestamp;
+use crate::v4l_sys::*;
+
+/// Output queue submitting caller-owned buffers without copying
+///
+/// Instead of copying each frame into a driver allocated buffer, the memory of the frame itself
+/// is handed to the driver. The queue takes ownership of each buffer until the driver is done
+/// with it, at which point it is handed back by [`OutputQueue::dequeue()`]. The buffers must be
+/// heap allocations which do not move along with their owner, see [`UserBuffer`], e.g. `Vec<u8>`
+/// or reference counted `Arc<[u8]>` buffers.
+///
+/// Drivers require each buffer to be at least as large as the image size of the active format.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::buffer::Type;
+/// use v4l::device::Device;
+/// use v4l::io::userptr::OutputQueue;
+/// use v4l::timestamp::Timestamp;
+/// use v4l::video::Output;
+///
+/// let dev = Device::new(0).expect("Failed to open device");
+/// let format = Output::format(&dev).expect("Failed to get format");
+///
+/// let frames = vec![vec![0u8; format.size as usize]; 8];
+/// let mut queue = OutputQueue::new(&dev, Type::VideoOutput, 4).expect("Failed to create queue");
+/// for frame in frames {
+///     // frames are owned by the queue until the driver hands them back
+///     queue
+///         .queue(frame, Timestamp::default())
+///         .expect("Failed to queue frame");
+/// }
+/// ```
+pub struct OutputQueue<B: UserBuffer> {
+    inner: Queue,
+    slots: Vec<Option<B>>,
+}
+
+impl<B: UserBuffer> OutputQueue<B> {
+    /// Returns a new output queue
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Output device ref to get its file descriptor
+    /// * `buf_type` - Type of the buffers
+    /// * `buf_count` - Maximum number of buffers handed to the driver at once
+    pub fn new(dev: &Device, buf_type: Type, buf_count: u32) -> io::Result<Self> {
+        let (inner, count) = Queue::new(dev, buf_type, Memory::UserPtr, buf_count)?;
+        let mut slots = Vec::new();
+        slots.resize_with(count, || None);
+
+        Ok(OutputQueue { inner, slots })
+    }
+
+    /// Returns the raw device handle
+    pub fn handle(&self) -> Arc<Handle> {
+        self.inner.handle().clone()
+    }
+
+    /// Returns the current state of a buffer as reported by the driver
+    ///
+    /// This is mostly useful for debugging, e.g. to find out which buffers are stuck in the
+    /// driver.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the buffer
+    pub fn query_buffer(&self, index: usize) -> io::Result<State> {
+        self.inner.query_buffer(index)
+    }
+
+    /// Sets a timeout of the v4l file handle.
+    pub fn set_timeout(&mut self, duration: Duration) {
+        self.inner.set_timeout(Some(duration));
+    }
+
+    /// Clears the timeout of the v4l file handle.
+    pub fn clear_timeout(&mut self) {
+        self.inner.set_timeout(None);
+    }
+
+    /// Returns the number of buffers currently owned by the driver
+    pub fn queued(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    /// Hands a buffer to the driver
+    ///
+    /// If the driver owns all buffers already, this waits for one of them to be handed back
+    /// first and returns it. Streaming is started with the first buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Frame data, its whole length is submitted
+    /// * `timestamp` - Timestamp of the frame
+    pub fn queue(&mut self, buf: B, timestamp: Timestamp) -> io::Result<Option<B>> {
+        self.queue_timecode(buf, timestamp, None)
+    }
+
+    /// Hands a buffer to the driver along with its SMPTE timecode
+    ///
+    /// Behaves like [`OutputQueue::queue()`] otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Frame data, its whole length is submitted
+    /// * `timestamp` - Timestamp of the frame
+    /// * `timecode` - Timecode of the frame
+    pub fn queue_with_timecode(
+        &mut self,
+        buf: B,
+        timestamp: Timestamp,
+        timecode: Timecode,
+    ) -> io::Result<Option<B>> {
+        self.queue_timecode(buf, timestamp, Some(timecode))
+    }
+
+    fn queue_timecode(
+        &mut self,
+        buf: B,
+        timestamp: Timestamp,
+        timecode: Option<Timecode>,
+    ) -> io::Result<Option<B>> {
+        let mut reclaimed = None;
+        let index = match self.slots.iter().position(|slot| slot.is_none()) {
+            Some(index) => index,
+            None => {
+                let (index, buf) = self.dequeue_index()?;
+                reclaimed = Some(buf);
+                index
+            }
+        };
+
+        // Take the pointer from the buffer in its final place, the driver accesses the memory
+        // long after this function returned.
+        let desc = self.inner.buffer_desc();
+        let data = B::as_ref(self.slots[index].insert(buf));
+        let mut v4l2_buf = v4l2_buffer {
+            index: index as u32,
+            m: v4l2_buffer__bindgen_ty_1 {
+                userptr: data.as_ptr() as std::os::raw::c_ulong,
+            },
+            length: data.len() as u32,
+            bytesused: data.len() as u32,
+            field: FieldOrder::Progressive as u32,
+            timestamp: timestamp.into(),
+            ..desc
+        };
+        if let Some(timecode) = timecode {
+            v4l2_buf.timecode = timecode.into();
+            v4l2_buf.flags |= u32::from(Flags::TIMECODE);
+        }
+        if let Err(e) = self.inner.qbuf(&mut v4l2_buf) {
+            self.slots[index] = None;
+            return Err(e);
+        }
+
+        if !self.inner.is_active() {
+            self.inner.start()?;
+        }
+
+        Ok(reclaimed)
+    }
+
+    /// Waits for the driver to hand back a buffer and returns it
+    pub fn dequeue(&mut self) -> io::Result<B> {
+        self.dequeue_index().map(|(_, buf)| buf)
+    }
+
+    fn dequeue_index(&mut self) -> io::Result<(usize, B)> {
+        if self.queued() == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no buffers are queued",
+            ));
+        }
+
+        let mut v4l2_buf = self.inner.buffer_desc();
+        self.inner.dqbuf(&mut v4l2_buf)?;
+
+        let index = v4l2_buf.index as usize;
+        let buf = self
+            .slots
+            .get_mut(index)
+            .and_then(Option::take)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid buffer index"))?;
+        Ok((index, buf))
+    }
+}
+
+impl<B: UserBuffer> Drop for OutputQueue<B> {
+    fn drop(&mut self) {
+        // The driver must be done with all buffers before they are dropped along with the slots.
+        queue::teardown(self.inner.release());
+    }
+}
@@ -1,10 +1,10 @@
-use std::convert::TryInto;
 use std::time::Duration;
 use std::{io, mem, sync::Arc};
 
-use crate::buffer::{Metadata, Type};
+use crate::buffer::{Metadata, State, Type};
 use crate::device::{Device, Handle};
-use crate::io::traits::{CaptureStream, Stream as StreamTrait};
+use crate::io::queue;
+use crate::io::traits::{CaptureStream, OutputStream, Stream as StreamTrait};
 use crate::io::userptr::arena::Arena;
 use crate::memory::Memory;
 use crate::v4l2;
@@ -12,20 +12,22 @@ use crate::v4l_sys::*;
 
 /// Stream of user buffers
 ///
-/// An arena instance is used internally for buffer handling.
+/// An arena instance is used internally for buffer handling. Like its memory-mapped counterpart,
+/// the stream implements [`CaptureStream`] for capture buffer types and [`OutputStream`] for
+/// output buffer types, e.g. for feeding v4l2loopback devices.
 pub struct Stream {
     handle: Arc<Handle>,
     arena: Arena,
     arena_index: usize,
     buf_type: Type,
     buf_meta: Vec<Metadata>,
-    timeout: Option<i32>,
+    timeout: Option<Duration>,
 
     active: bool,
 }
 
 impl Stream {
-    /// Returns a stream for frame capturing
+    /// Returns a stream for frame capturing or output
     ///
     /// # Arguments
     ///
@@ -49,6 +51,13 @@ impl Stream {
     }
 
     pub fn with_buffers(dev: &Device, buf_type: Type, buf_count: u32) -> io::Result<Self> {
+        if buf_type.is_multiplanar() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?} buffers are not supported, use a PlaneQueue", buf_type),
+            ));
+        }
+        dev.check_streaming(buf_type)?;
         let mut arena = Arena::new(dev.handle(), buf_type);
         let count = arena.allocate(buf_count)?;
         let mut buf_meta = Vec::new();
@@ -70,9 +79,21 @@ impl Stream {
         self.handle.clone()
     }
 
+    /// Returns the current state of a buffer as reported by the driver
+    ///
+    /// This is mostly useful for debugging, e.g. to find out which buffers are stuck in the
+    /// driver.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the buffer
+    pub fn query_buffer(&self, index: usize) -> io::Result<State> {
+        State::query(&self.handle, self.buf_type, Memory::UserPtr, index as u32)
+    }
+
     /// Sets a timeout of the v4l file handle.
     pub fn set_timeout(&mut self, duration: Duration) {
-        self.timeout = Some(duration.as_millis().try_into().unwrap());
+        self.timeout = Some(duration);
     }
 
     /// Clears the timeout of the v4l file handle.
@@ -80,6 +101,63 @@ impl Stream {
         self.timeout = None;
     }
 
+    /// Removes a buffer from the drivers' outgoing queue, waiting at most for the given duration
+    ///
+    /// See [`crate::io::mmap::Stream::dequeue_timeout()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Maximum duration to wait for
+    pub fn dequeue_timeout(&mut self, timeout: Duration) -> io::Result<usize> {
+        let events = if self.buf_type.is_output() {
+            libc::POLLOUT
+        } else {
+            libc::POLLIN
+        };
+        if self.handle.poll(events, Some(timeout))? == 0 {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_DQBUF"));
+        }
+
+        self.dequeue_ready()
+    }
+
+    /// Locks all buffers into RAM, so they are never paged out
+    ///
+    /// This usually requires the `CAP_IPC_LOCK` capability or a sufficiently large
+    /// `RLIMIT_MEMLOCK` limit.
+    pub fn lock(&mut self) -> io::Result<()> {
+        self.arena.lock()
+    }
+
+    /// Touches every page of all buffers, so capturing does not cause any page faults
+    ///
+    /// Call this right after creating the stream to avoid latency spikes during the first frames
+    /// of real-time applications.
+    pub fn prefault(&mut self) {
+        self.arena.prefault()
+    }
+
+    /// Removes a buffer from the drivers' outgoing queue without waiting for one to become
+    /// ready
+    fn dequeue_ready(&mut self) -> io::Result<usize> {
+        let mut v4l2_buf = self.buffer_desc();
+
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_DQBUF,
+                &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+        self.arena_index = v4l2_buf.index as usize;
+
+        self.buf_meta[self.arena_index] = Metadata::from_raw(&v4l2_buf);
+        #[cfg(feature = "tracing")]
+        self.buf_meta[self.arena_index].trace_dequeue(self.arena_index);
+
+        Ok(self.arena_index)
+    }
+
     fn buffer_desc(&self) -> v4l2_buffer {
         v4l2_buffer {
             type_: self.buf_type as u32,
@@ -91,19 +169,7 @@ impl Stream {
 
 impl Drop for Stream {
     fn drop(&mut self) {
-        if let Err(e) = self.stop() {
-            if let Some(code) = e.raw_os_error() {
-                // ENODEV means the file descriptor wrapped in the handle became invalid, most
-                // likely because the device was unplugged or the connection (USB, PCI, ..)
-                // broke down. Handle this case gracefully by ignoring it.
-                if code == 19 {
-                    /* ignore */
-                    return;
-                }
-            }
-
-            panic!("{:?}", e)
-        }
+        queue::teardown(self.stop());
     }
 }
 
@@ -162,53 +228,114 @@ impl<'a> CaptureStream<'a> for Stream {
     }
 
     fn dequeue(&mut self) -> io::Result<usize> {
-        let mut v4l2_buf = self.buffer_desc();
-
-        if self.handle.poll(libc::POLLIN, self.timeout.unwrap_or(-1))? == 0 {
+        if self.handle.poll(libc::POLLIN, self.timeout)? == 0 {
             // This condition can only happen if there was a timeout.
             // A timeout is only possible if the `timeout` value is non-zero, meaning we should
             // propagate it to the caller.
             return Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_DQBUF"));
         }
 
+        self.dequeue_ready()
+    }
+
+    fn next(&'a mut self) -> io::Result<(&Self::Item, &Metadata)> {
+        if !self.active {
+            // Enqueue all buffers once on stream start
+            for index in 0..self.arena.bufs.len() {
+                CaptureStream::queue(self, index)?;
+            }
+
+            self.start()?;
+        } else {
+            CaptureStream::queue(self, self.arena_index)?;
+        }
+
+        self.arena_index = CaptureStream::dequeue(self)?;
+
+        // The index used to access the buffer elements is given to us by v4l2, so we assume it
+        // will always be valid.
+        let bytes = &mut self.arena.bufs[self.arena_index];
+        let meta = &self.buf_meta[self.arena_index];
+        Ok((bytes, meta))
+    }
+
+    fn swap(&'a mut self) -> io::Result<(&Self::Item, &Metadata)> {
+        if !self.active {
+            return CaptureStream::next(self);
+        }
+
+        CaptureStream::queue(self, self.arena_index)?;
+        // Only wait for the device if no buffer is ready yet
+        self.arena_index = match self.dequeue_ready() {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => CaptureStream::dequeue(self)?,
+            res => res?,
+        };
+
+        // The index used to access the buffer elements is given to us by v4l2, so we assume it
+        // will always be valid.
+        let bytes = &self.arena.bufs[self.arena_index];
+        let meta = &self.buf_meta[self.arena_index];
+        Ok((bytes, meta))
+    }
+}
+
+impl<'a> OutputStream<'a> for Stream {
+    fn queue(&mut self, index: usize) -> io::Result<()> {
+        if self.handle.poll(libc::POLLOUT, self.timeout)? == 0 {
+            // This condition can only happen if there was a timeout.
+            // A timeout is only possible if the `timeout` value is non-zero, meaning we should
+            // propagate it to the caller.
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_QBUF"));
+        }
+
+        let buf = &self.arena.bufs[index];
+        let meta = &self.buf_meta[index];
+        let mut v4l2_buf = v4l2_buffer {
+            index: index as u32,
+            m: v4l2_buffer__bindgen_ty_1 {
+                userptr: buf.as_ptr() as std::os::raw::c_ulong,
+            },
+            length: buf.len() as u32,
+            // a bytesused of 0 makes the driver use the whole buffer
+            bytesused: meta.bytesused,
+            field: meta.field as u32,
+            timestamp: meta.timestamp.into(),
+            ..self.buffer_desc()
+        };
+        meta.apply_timecode(&mut v4l2_buf);
         unsafe {
             v4l2::ioctl(
                 self.handle.fd(),
-                v4l2::vidioc::VIDIOC_DQBUF,
+                v4l2::vidioc::VIDIOC_QBUF,
                 &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
             )?;
         }
-        self.arena_index = v4l2_buf.index as usize;
 
-        self.buf_meta[self.arena_index] = Metadata {
-            bytesused: v4l2_buf.bytesused,
-            flags: v4l2_buf.flags.into(),
-            field: v4l2_buf.field,
-            timestamp: v4l2_buf.timestamp.into(),
-            sequence: v4l2_buf.sequence,
-        };
+        Ok(())
+    }
 
-        Ok(self.arena_index)
+    fn dequeue(&mut self) -> io::Result<usize> {
+        self.dequeue_ready()
     }
 
-    fn next(&'a mut self) -> io::Result<(&Self::Item, &Metadata)> {
+    fn next(&'a mut self) -> io::Result<(&mut Self::Item, &mut Metadata)> {
+        let init = !self.active;
         if !self.active {
-            // Enqueue all buffers once on stream start
-            for index in 0..self.arena.bufs.len() {
-                self.queue(index)?;
-            }
-
             self.start()?;
-        } else {
-            self.queue(self.arena_index)?;
         }
 
-        self.arena_index = self.dequeue()?;
+        // Only queue and dequeue once the buffer has been filled at the call site. The initial
+        // call to this function from the call site will happen just after the buffers have been
+        // allocated, meaning we need to return the empty buffer initially so it can be filled.
+        if !init {
+            OutputStream::queue(self, self.arena_index)?;
+            self.arena_index = OutputStream::dequeue(self)?;
+        }
 
         // The index used to access the buffer elements is given to us by v4l2, so we assume it
         // will always be valid.
         let bytes = &mut self.arena.bufs[self.arena_index];
-        let meta = &self.buf_meta[self.arena_index];
+        let meta = &mut self.buf_meta[self.arena_index];
         Ok((bytes, meta))
     }
 }
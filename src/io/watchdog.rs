@@ -0,0 +1,262 @@
+//! Stall detection and recovery
+//!
+//! Some hardware, most notably cheap UVC cameras, occasionally stops delivering frames without
+//! reporting any error. Unattended applications (kiosks, video recorders) must recover from this
+//! on their own. The [`Watchdog`] wraps a capture stream, detects stalls and recovers according to
+//! a [`Recovery`] policy, reporting each incident to the application.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use v4l::io::watchdog::{Config, Recovery, Watchdog};
+//!
+//! let config = Config {
+//!     interval: Duration::from_secs(2),
+//!     recovery: Recovery::Reopen,
+//!     ..Config::default()
+//! };
+//! let mut watchdog = Watchdog::new("/dev/video0", config).expect("Failed to open device");
+//! watchdog.on_incident(|incident| eprintln!("{}", incident));
+//!
+//! loop {
+//!     let (buf, meta) = watchdog.next().expect("Failed to recover");
+//!     println!("frame {}: {} bytes", meta.sequence, buf.len());
+//! }
+//! ```
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::buffer::{Metadata, Type};
+use crate::device::Device;
+use crate::format::Format;
+use crate::io::mmap::Stream;
+use crate::io::traits::{CaptureStream, Stream as _};
+use crate::video::Capture;
+
+/// How a stalled stream is recovered
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Recovery {
+    /// Stop and restart streaming, keeping the device open
+    Restart,
+    /// Close and reopen the device, restoring the format
+    ///
+    /// This also recovers from devices which were disconnected and reconnected, as long as they
+    /// reappear under the same path.
+    Reopen,
+}
+
+impl fmt::Display for Recovery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Configuration of a watchdog
+#[derive(Debug, Copy, Clone)]
+pub struct Config {
+    /// Type of the buffers
+    pub buf_type: Type,
+    /// Number of buffers to request, the driver may allocate more
+    pub buf_count: u32,
+    /// A stall is detected if no frame arrives for this long
+    pub interval: Duration,
+    /// Recovery policy
+    pub recovery: Recovery,
+    /// Give up after this many consecutive recoveries without receiving a frame
+    pub max_attempts: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            buf_type: Type::VideoCapture,
+            buf_count: 4,
+            interval: Duration::from_secs(1),
+            recovery: Recovery::Restart,
+            max_attempts: 3,
+        }
+    }
+}
+
+/// Stall detected by a watchdog
+#[derive(Debug)]
+pub struct Incident {
+    /// Time since the last frame arrived
+    pub stalled_for: Duration,
+    /// Recovery which was performed
+    pub recovery: Recovery,
+    /// Number of consecutive recoveries, starting at 1
+    pub attempt: u32,
+    /// Error the stream failed with, `None` if no frame arrived in time
+    pub cause: Option<io::Error>,
+    /// Error the recovery failed with, if any
+    pub error: Option<io::Error>,
+}
+
+impl fmt::Display for Incident {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "stream stalled for {:?}, attempt {}: {}",
+            self.stalled_for, self.attempt, self.recovery
+        )?;
+        if let Some(cause) = &self.cause {
+            write!(f, " (cause: {})", cause)?;
+        }
+        match &self.error {
+            Some(e) => write!(f, " failed: {}", e),
+            None => write!(f, " succeeded"),
+        }
+    }
+}
+
+type IncidentCallback = Box<dyn FnMut(&Incident) + Send>;
+
+/// Capture stream recovering from stalls
+pub struct Watchdog {
+    path: PathBuf,
+    config: Config,
+    format: Format,
+    dev: Device,
+    /// `None` while the device could not be reopened
+    stream: Option<Stream<'static>>,
+    last_frame: Instant,
+    incidents: u64,
+    on_incident: Option<IncidentCallback>,
+}
+
+impl Watchdog {
+    /// Opens a device and allocates the buffers of the stream
+    ///
+    /// The device is opened by path, so it can be reopened if needed. Its current format is
+    /// restored after reopening.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path (e.g. "/dev/video0")
+    /// * `config` - Watchdog configuration
+    pub fn new<P: AsRef<Path>>(path: P, config: Config) -> io::Result<Self> {
+        let dev = Device::with_path(&path)?;
+        let format = Capture::format(&dev)?;
+        let stream = Self::stream(&dev, &config)?;
+
+        Ok(Watchdog {
+            path: path.as_ref().to_path_buf(),
+            config,
+            format,
+            dev,
+            stream: Some(stream),
+            last_frame: Instant::now(),
+            incidents: 0,
+            on_incident: None,
+        })
+    }
+
+    /// Registers a function called for each incident, e.g. for logging
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Function called after each recovery
+    pub fn on_incident<F>(&mut self, f: F)
+    where
+        F: FnMut(&Incident) + Send + 'static,
+    {
+        self.on_incident = Some(Box::new(f));
+    }
+
+    /// Returns the device, which changes when it is reopened
+    pub fn device(&self) -> &Device {
+        &self.dev
+    }
+
+    /// Returns the number of incidents so far
+    pub fn incidents(&self) -> u64 {
+        self.incidents
+    }
+
+    /// Fetches a new frame, recovering from stalls on the way
+    ///
+    /// Fails once [`Config::max_attempts`] consecutive recoveries did not bring back any frame.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> io::Result<(&[u8], &Metadata)> {
+        let mut attempt = 0;
+        loop {
+            let result = match &mut self.stream {
+                Some(stream) => CaptureStream::next(stream).map(drop),
+                None => Err(io::Error::new(
+                    io::ErrorKind::NotConnected,
+                    "device could not be reopened",
+                )),
+            };
+            let reopen = self.config.recovery == Recovery::Reopen;
+            let cause = match result {
+                Ok(()) => break,
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => None,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                // ENODEV means the device was disconnected
+                Err(e) if reopen && (e.raw_os_error() == Some(19) || self.stream.is_none()) => {
+                    Some(e)
+                }
+                Err(e) => return Err(e),
+            };
+
+            attempt += 1;
+            if attempt > self.config.max_attempts {
+                return Err(cause.unwrap_or_else(|| {
+                    io::Error::new(io::ErrorKind::TimedOut, "stream did not recover")
+                }));
+            }
+
+            let error = self.recover().err();
+            if error.is_some() {
+                // give the device some time, e.g. to reappear after a reconnect
+                thread::sleep(self.config.interval);
+            }
+            self.incidents += 1;
+            let incident = Incident {
+                stalled_for: self.last_frame.elapsed(),
+                recovery: self.config.recovery,
+                attempt,
+                cause,
+                error,
+            };
+            if let Some(on_incident) = &mut self.on_incident {
+                on_incident(&incident);
+            }
+        }
+
+        self.last_frame = Instant::now();
+        // the stream is present, otherwise no frame would have arrived
+        Ok(self.stream.as_ref().unwrap().current())
+    }
+
+    fn recover(&mut self) -> io::Result<()> {
+        match self.config.recovery {
+            // the next frame restarts streaming
+            Recovery::Restart => match &mut self.stream {
+                Some(stream) => stream.stop(),
+                None => Ok(()),
+            },
+            Recovery::Reopen => {
+                // the old stream must release its buffers before new ones can be allocated
+                self.stream = None;
+                let dev = Device::with_path(&self.path)?;
+                Capture::set_format(&dev, &self.format)?;
+                self.stream = Some(Self::stream(&dev, &self.config)?);
+                self.dev = dev;
+                Ok(())
+            }
+        }
+    }
+
+    fn stream(dev: &Device, config: &Config) -> io::Result<Stream<'static>> {
+        let mut stream = Stream::with_buffers(dev, config.buf_type, config.buf_count)?;
+        stream.set_timeout(config.interval);
+        Ok(stream)
+    }
+}
@@ -0,0 +1,160 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use std::{io, panic};
+
+use crate::buffer::{Flags, Type};
+use crate::device::Device;
+use crate::io::mmap::split::{Buffer, Producer};
+use crate::io::mmap::Stream;
+
+/// Interval at which the capture thread checks whether it should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Interval at which the capture thread retries while the application holds all buffers
+const STARVED_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Configuration of a capture thread
+#[derive(Debug, Copy, Clone)]
+pub struct CaptureConfig {
+    /// Type of the buffers
+    pub buf_type: Type,
+    /// Number of buffers to request, the driver may allocate more
+    pub buf_count: u32,
+    /// Stop with [`io::ErrorKind::TimedOut`] if no frame arrives for this long
+    pub stall_timeout: Option<Duration>,
+    /// Requeue buffers flagged as corrupted by the driver instead of delivering them
+    pub skip_corrupted: bool,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        CaptureConfig {
+            buf_type: Type::VideoCapture,
+            buf_count: 4,
+            stall_timeout: None,
+            skip_corrupted: true,
+        }
+    }
+}
+
+/// Handle of a capture thread
+///
+/// The thread is stopped when the handle is dropped.
+pub struct CaptureThread {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl CaptureThread {
+    /// Starts a capture thread delivering frames to a callback
+    ///
+    /// See [`Device::start_capture_thread()`].
+    pub fn spawn<F>(dev: &Device, config: CaptureConfig, callback: F) -> io::Result<Self>
+    where
+        F: FnMut(Buffer<'static>) + Send + 'static,
+    {
+        // Allocate the buffers up front, so configuration errors are reported to the caller.
+        let stream = Stream::with_buffers(dev, config.buf_type, config.buf_count)?;
+        let (mut producer, _) = stream.split()?;
+        producer.set_timeout(POLL_INTERVAL);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name("v4l-capture".to_string())
+                .spawn(move || run(producer, config, &stop, callback))?
+        };
+
+        Ok(CaptureThread {
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Returns whether the thread is still capturing
+    pub fn is_running(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+    }
+
+    /// Stops the thread and waits for it to finish
+    ///
+    /// Returns the error the thread stopped with, if any.
+    pub fn stop(mut self) -> io::Result<()> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.join_thread()
+    }
+
+    /// Waits for the thread to stop on its own, i.e. because of an error
+    pub fn join(mut self) -> io::Result<()> {
+        self.join_thread()
+    }
+
+    fn join_thread(&mut self) -> io::Result<()> {
+        match self.thread.take() {
+            // forward panics of the callback to the caller
+            Some(thread) => thread.join().unwrap_or_else(|e| panic::resume_unwind(e)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for CaptureThread {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn run<F>(
+    mut producer: Producer<'static>,
+    config: CaptureConfig,
+    stop: &AtomicBool,
+    mut callback: F,
+) -> io::Result<()>
+where
+    F: FnMut(Buffer<'static>),
+{
+    let mut last_frame = Instant::now();
+    while !stop.load(Ordering::Relaxed) {
+        let buf = match producer.dequeue() {
+            Ok(buf) => buf,
+            Err(e) => {
+                match e.kind() {
+                    // no frame within the poll interval
+                    io::ErrorKind::TimedOut => {}
+                    io::ErrorKind::Interrupted => continue,
+                    // the application holds on to all buffers
+                    io::ErrorKind::WouldBlock => thread::sleep(STARVED_INTERVAL),
+                    _ => return Err(e),
+                }
+
+                if let Some(timeout) = config.stall_timeout {
+                    if last_frame.elapsed() >= timeout {
+                        return Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            "no frame received from device",
+                        ));
+                    }
+                }
+                continue;
+            }
+        };
+
+        last_frame = Instant::now();
+        if config.skip_corrupted && buf.meta().flags.contains(Flags::ERROR) {
+            // dropping the buffer hands it back to the driver
+            continue;
+        }
+
+        callback(buf);
+    }
+
+    Ok(())
+}
@@ -76,22 +76,58 @@ pub use v4l2_sys as v4l_sys;
 pub mod v4l2;
 
 pub mod buffer;
+pub mod camera;
 pub mod capability;
+#[cfg(feature = "cec")]
+pub mod cec;
+pub mod compliance;
+pub mod configuration;
 pub mod context;
 pub mod control;
+#[cfg(feature = "conversions")]
+pub mod convert;
+#[cfg(feature = "dangerous-debug-registers")]
+pub mod debug;
 pub mod device;
+pub mod event;
+pub mod feature;
+pub mod focus;
 pub mod format;
 pub mod fraction;
 pub mod frameinterval;
 pub mod framesize;
+pub mod interop;
+#[cfg(feature = "loopback")]
+pub mod loopback;
+pub mod lut;
+#[cfg(feature = "media-controller")]
+pub mod media;
 pub mod memory;
+pub mod meta;
+pub mod mjpeg;
+pub mod output;
 pub mod parameters;
+pub mod port;
+pub mod proxy;
+pub mod selection;
+pub mod snapshot;
+pub mod sync;
+#[cfg(feature = "test-virtual")]
+pub mod testing;
+pub mod timecode;
 pub mod timestamp;
+pub mod typed;
+#[cfg(feature = "vbi")]
+pub mod vbi;
 pub mod video;
+pub mod white_balance;
+
+mod util;
 
 pub mod io;
 
 pub use {
+    camera::Camera,
     capability::Capabilities,
     control::Control,
     device::Device,
@@ -106,4 +142,45 @@ pub use {
 pub mod prelude {
     pub use crate::device::Device;
     pub use crate::io::{mmap::Stream as MmapStream, userptr::Stream as UserptrStream};
+
+    /// Everything needed for capturing frames, including the device and stream traits
+    ///
+    /// The capture and output traits share method names, so they live in separate modules to
+    /// keep method calls on [`Device`] unambiguous.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::buffer::Type;
+    /// use v4l::prelude::capture::*;
+    ///
+    /// fn grab<D: Capture>(dev: &D) -> std::io::Result<Format> {
+    ///     dev.format()
+    /// }
+    ///
+    /// let dev = Device::new(0).expect("Failed to open device");
+    /// let format = grab(&dev).expect("Failed to get format");
+    ///
+    /// let mut stream = MmapStream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+    /// let (buf, meta) = stream.next().expect("Failed to capture frame");
+    /// ```
+    pub mod capture {
+        pub use super::{Device, MmapStream, UserptrStream};
+        pub use crate::format::{Format, FourCC};
+        pub use crate::io::traits::{CaptureStream, Stream as _};
+        pub use crate::video::capture::Parameters;
+        pub use crate::video::Capture;
+    }
+
+    /// Everything needed for outputting frames, including the device and stream traits
+    ///
+    /// See [`capture`] for why this is a separate module.
+    pub mod output {
+        pub use super::{Device, MmapStream, UserptrStream};
+        pub use crate::format::{Format, FourCC};
+        pub use crate::io::traits::{OutputStream, Stream as _};
+        pub use crate::io::userptr::OutputQueue;
+        pub use crate::video::output::Parameters;
+        pub use crate::video::Output;
+    }
 }
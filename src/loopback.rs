@@ -0,0 +1,296 @@
+//! v4l2loopback configuration
+//!
+//! [v4l2loopback](https://github.com/umlaeute/v4l2loopback) creates virtual video devices which
+//! applications write frames to and other applications capture from, see
+//! [`crate::video::output::VirtualCamera`]. This module covers the driver specific parts which
+//! `v4l2loopback-ctl` otherwise takes care of: adding and removing devices through the
+//! `/dev/v4l2loopback` control node and the custom controls of each device.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use v4l::loopback::{Config, Loopback, Manager};
+//! use v4l::Device;
+//!
+//! let manager = Manager::new().expect("Failed to open v4l2loopback control node");
+//! let nr = manager
+//!     .add(&Config {
+//!         card_label: "Virtual Camera".to_string(),
+//!         exclusive_caps: Some(true),
+//!         ..Default::default()
+//!     })
+//!     .expect("Failed to add loopback device");
+//!
+//! let dev = Device::new(nr as usize).expect("Failed to open loopback device");
+//! let loopback = Loopback::new(&dev);
+//! loopback.set_keep_format(true).expect("Failed to keep format");
+//! loopback.set_sustain_framerate(true).expect("Failed to sustain frame rate");
+//! ```
+
+use std::convert::TryFrom;
+use std::fs::{self, File, OpenOptions};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::time::Duration;
+use std::{io, mem};
+
+use crate::control::{Control, Value};
+use crate::device::Device;
+
+/// Path of the control node
+pub const CONTROL_PATH: &str = "/dev/v4l2loopback";
+
+/// Keep the format once the producer stops (`CID_KEEP_FORMAT`)
+pub const CID_KEEP_FORMAT: u32 = 0x0098_f900;
+/// Repeat frames if the producer falls behind the frame rate (`CID_SUSTAIN_FRAMERATE`)
+pub const CID_SUSTAIN_FRAMERATE: u32 = 0x0098_f901;
+/// Timeout in milliseconds after which consumers get the timeout image (`CID_TIMEOUT`)
+pub const CID_TIMEOUT: u32 = 0x0098_f902;
+/// Accept the timeout image through the output side (`CID_TIMEOUT_IMAGE_IO`)
+pub const CID_TIMEOUT_IMAGE_IO: u32 = 0x0098_f903;
+
+const V4L2LOOPBACK_CTL_ADD: std::os::raw::c_long = 0x4c80;
+const V4L2LOOPBACK_CTL_REMOVE: std::os::raw::c_long = 0x4c81;
+const V4L2LOOPBACK_CTL_QUERY: std::os::raw::c_long = 0x4c82;
+
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct v4l2_loopback_config {
+    output_nr: i32,
+    capture_nr: i32,
+    card_label: [u8; 32],
+    min_width: u32,
+    max_width: u32,
+    min_height: u32,
+    max_height: u32,
+    max_buffers: i32,
+    max_openers: i32,
+    debug: i32,
+    announce_all_caps: i32,
+}
+
+/// Configuration of a loopback device
+///
+/// Fields left at `None` or zero take the defaults of the driver.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Device number, e.g. 10 for /dev/video10
+    pub nr: Option<u32>,
+    /// Name reported as card by the capabilities
+    pub card_label: String,
+    /// Minimum frame width
+    pub min_width: u32,
+    /// Maximum frame width
+    pub max_width: u32,
+    /// Minimum frame height
+    pub min_height: u32,
+    /// Maximum frame height
+    pub max_height: u32,
+    /// Maximum number of buffers
+    pub max_buffers: u32,
+    /// Maximum number of simultaneous openers
+    pub max_openers: u32,
+    /// Only announce the capture capability once a producer is attached, which some
+    /// applications (e.g. Chrome) require to list the device as camera
+    pub exclusive_caps: Option<bool>,
+}
+
+impl From<&Config> for v4l2_loopback_config {
+    fn from(config: &Config) -> Self {
+        let nr = config.nr.map_or(-1, |nr| nr as i32);
+        let mut card_label = [0u8; 32];
+        // keep the terminating NUL
+        let len = config.card_label.len().min(card_label.len() - 1);
+        card_label[..len].copy_from_slice(&config.card_label.as_bytes()[..len]);
+
+        v4l2_loopback_config {
+            output_nr: nr,
+            capture_nr: nr,
+            card_label,
+            min_width: config.min_width,
+            max_width: config.max_width,
+            min_height: config.min_height,
+            max_height: config.max_height,
+            max_buffers: config.max_buffers as i32,
+            max_openers: config.max_openers as i32,
+            debug: -1,
+            announce_all_caps: config
+                .exclusive_caps
+                .map_or(-1, |exclusive| !exclusive as i32),
+        }
+    }
+}
+
+impl From<v4l2_loopback_config> for Config {
+    fn from(config: v4l2_loopback_config) -> Self {
+        let label = crate::util::until_nul(&config.card_label);
+        Config {
+            nr: u32::try_from(config.output_nr).ok(),
+            card_label: String::from_utf8_lossy(label).into_owned(),
+            min_width: config.min_width,
+            max_width: config.max_width,
+            min_height: config.min_height,
+            max_height: config.max_height,
+            max_buffers: config.max_buffers.max(0) as u32,
+            max_openers: config.max_openers.max(0) as u32,
+            exclusive_caps: u32::try_from(config.announce_all_caps)
+                .ok()
+                .map(|announce| announce == 0),
+        }
+    }
+}
+
+/// Adds and removes loopback devices through the control node
+///
+/// This requires the v4l2loopback module to be loaded and write access to the control node,
+/// usually root privileges.
+pub struct Manager {
+    file: File,
+}
+
+impl Manager {
+    /// Opens the control node
+    pub fn new() -> io::Result<Self> {
+        Self::with_path(CONTROL_PATH)
+    }
+
+    /// Opens a control node by path
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path (e.g. "/dev/v4l2loopback")
+    pub fn with_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Manager { file })
+    }
+
+    /// Adds a device and returns its number
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration of the new device
+    pub fn add(&self, config: &Config) -> io::Result<u32> {
+        let mut raw = v4l2_loopback_config::from(config);
+        let nr = self.ioctl(
+            V4L2LOOPBACK_CTL_ADD,
+            &mut raw as *mut _ as *mut std::os::raw::c_void,
+        )?;
+        Ok(nr as u32)
+    }
+
+    /// Removes a device
+    ///
+    /// Fails with EBUSY while the device is open.
+    ///
+    /// # Arguments
+    ///
+    /// * `nr` - Device number
+    pub fn remove(&self, nr: u32) -> io::Result<()> {
+        // the number is passed by value instead of through a pointer
+        self.ioctl(
+            V4L2LOOPBACK_CTL_REMOVE,
+            nr as usize as *mut std::os::raw::c_void,
+        )
+        .map(drop)
+    }
+
+    /// Returns the configuration of a device
+    ///
+    /// # Arguments
+    ///
+    /// * `nr` - Device number
+    pub fn query(&self, nr: u32) -> io::Result<Config> {
+        let mut raw: v4l2_loopback_config = unsafe { mem::zeroed() };
+        raw.output_nr = nr as i32;
+        raw.capture_nr = -1;
+        self.ioctl(
+            V4L2LOOPBACK_CTL_QUERY,
+            &mut raw as *mut _ as *mut std::os::raw::c_void,
+        )?;
+        Ok(Config::from(raw))
+    }
+
+    fn ioctl(
+        &self,
+        request: std::os::raw::c_long,
+        argp: *mut std::os::raw::c_void,
+    ) -> io::Result<std::os::raw::c_long> {
+        // The control node is no V4L2 device and its ioctls return the device number, so they
+        // bypass the syscall backend (see crate::v4l2::backend) and use syscall() for the
+        // reasons given in v4l2::api.
+        let ret = unsafe { libc::syscall(libc::SYS_ioctl, self.file.as_raw_fd(), request, argp) };
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret)
+        }
+    }
+}
+
+/// Custom controls of a loopback device
+pub struct Loopback<'a> {
+    dev: &'a Device,
+}
+
+impl<'a> Loopback<'a> {
+    /// Wraps a loopback device
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Loopback device, either side
+    pub fn new(dev: &'a Device) -> Self {
+        Loopback { dev }
+    }
+
+    /// Keeps the format when the producer closes the device, instead of accepting a new one
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to keep the format
+    pub fn set_keep_format(&self, enable: bool) -> io::Result<()> {
+        self.set(CID_KEEP_FORMAT, Value::Boolean(enable))
+    }
+
+    /// Repeats frames if the producer cannot keep up with the frame rate
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to sustain the frame rate
+    pub fn set_sustain_framerate(&self, enable: bool) -> io::Result<()> {
+        self.set(CID_SUSTAIN_FRAMERATE, Value::Boolean(enable))
+    }
+
+    /// Shows the timeout image to consumers if the producer stalls
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Time without new frames, or `None` to wait indefinitely
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        let ms = timeout.map_or(0, |timeout| timeout.as_millis().max(1) as i64);
+        self.set(CID_TIMEOUT, Value::Integer(ms))
+    }
+
+    /// Lets the next frame written to the output side become the timeout image
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether the output side sets the timeout image
+    pub fn set_timeout_image_io(&self, enable: bool) -> io::Result<()> {
+        self.set(CID_TIMEOUT_IMAGE_IO, Value::Boolean(enable))
+    }
+
+    /// Returns the current format as reported through sysfs, e.g. "YUYV:640x480@30"
+    ///
+    /// # Arguments
+    ///
+    /// * `nr` - Device number
+    pub fn sysfs_format(nr: u32) -> io::Result<String> {
+        let path = format!("/sys/devices/virtual/video4linux/video{}/format", nr);
+        Ok(fs::read_to_string(path)?.trim_end().to_string())
+    }
+
+    fn set(&self, id: u32, value: Value) -> io::Result<()> {
+        self.dev.set_control(Control { id, value })
+    }
+}
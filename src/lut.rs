@@ -0,0 +1,336 @@
+//! Lookup table controls
+//!
+//! Image signal processors take gamma tables, tone curves and color lookup tables as array
+//! controls of 16 or 32 bit elements. [`Lut1d`] and [`Lut3d`] wrap such arrays so curves can be
+//! manipulated without packing the raw elements by hand. They convert from and to
+//! [`Value::CompoundU16`] and [`Value::CompoundU32`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::convert::TryFrom;
+//!
+//! use v4l::control::Control;
+//! use v4l::lut::Lut1d;
+//! use v4l::Device;
+//!
+//! // driver-specific gamma table control
+//! const GAMMA_CURVE: u32 = 0x0098_1901;
+//!
+//! let dev = Device::new(0).expect("Failed to open device");
+//! let desc = dev.query_control(GAMMA_CURVE).expect("Failed to query control");
+//! let current = dev.control(&desc).expect("Failed to read control");
+//! let current = Lut1d::<u16>::try_from(current.value).expect("Control is no 16 bit table");
+//!
+//! let curve = Lut1d::<u16>::gamma(current.len(), 2.2, desc.maximum as u16);
+//! dev.set_control(Control {
+//!     id: GAMMA_CURVE,
+//!     value: curve.into(),
+//! })
+//! .expect("Failed to write control");
+//! ```
+
+use std::convert::TryFrom;
+use std::io;
+
+use crate::control::Value;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Element type of a lookup table
+pub trait Sample: Copy + Default + sealed::Sealed {
+    /// Converts the element to a float
+    fn to_f64(self) -> f64;
+    /// Converts a float to the nearest element, saturating at the ends of the range
+    fn from_f64(val: f64) -> Self;
+    /// Wraps elements in a control value
+    fn into_value(entries: Vec<Self>) -> Value;
+    /// Extracts elements from a control value
+    fn from_value(value: Value) -> Option<Vec<Self>>;
+}
+
+impl sealed::Sealed for u16 {}
+impl sealed::Sealed for u32 {}
+
+impl Sample for u16 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(val: f64) -> Self {
+        val.round() as Self
+    }
+
+    fn into_value(entries: Vec<Self>) -> Value {
+        Value::CompoundU16(entries)
+    }
+
+    fn from_value(value: Value) -> Option<Vec<Self>> {
+        match value {
+            Value::CompoundU16(entries) => Some(entries),
+            _ => None,
+        }
+    }
+}
+
+impl Sample for u32 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(val: f64) -> Self {
+        val.round() as Self
+    }
+
+    fn into_value(entries: Vec<Self>) -> Value {
+        Value::CompoundU32(entries)
+    }
+
+    fn from_value(value: Value) -> Option<Vec<Self>> {
+        match value {
+            Value::CompoundU32(entries) => Some(entries),
+            _ => None,
+        }
+    }
+}
+
+/// One-dimensional lookup table, e.g. a gamma curve
+///
+/// # Example
+///
+/// ```
+/// use v4l::lut::Lut1d;
+///
+/// let lut = Lut1d::<u16>::identity(5, 1024);
+/// assert_eq!(lut.entries(), &[0, 256, 512, 768, 1024]);
+/// assert_eq!(lut.sample(0.375), 384.0);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lut1d<T: Sample> {
+    entries: Vec<T>,
+}
+
+impl<T: Sample> Lut1d<T> {
+    /// Returns a table of given entries
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - Output values for equally spaced inputs
+    pub fn new(entries: Vec<T>) -> Self {
+        Lut1d { entries }
+    }
+
+    /// Returns a table evaluating a function
+    ///
+    /// # Arguments
+    ///
+    /// * `len` - Number of entries
+    /// * `max` - Output value for 1.0
+    /// * `f` - Function mapping inputs from 0.0 to 1.0 to outputs from 0.0 to 1.0
+    pub fn from_fn<F: Fn(f64) -> f64>(len: usize, max: T, f: F) -> Self {
+        let last = len.saturating_sub(1).max(1) as f64;
+        let entries = (0..len)
+            .map(|i| T::from_f64(f(i as f64 / last).clamp(0.0, 1.0) * max.to_f64()))
+            .collect();
+        Lut1d { entries }
+    }
+
+    /// Returns a table which leaves values unchanged
+    ///
+    /// # Arguments
+    ///
+    /// * `len` - Number of entries
+    /// * `max` - Output value of the last entry
+    pub fn identity(len: usize, max: T) -> Self {
+        Self::from_fn(len, max, |x| x)
+    }
+
+    /// Returns a gamma encoding curve
+    ///
+    /// # Arguments
+    ///
+    /// * `len` - Number of entries
+    /// * `gamma` - Display gamma, e.g. 2.2
+    /// * `max` - Output value of the last entry
+    pub fn gamma(len: usize, gamma: f64, max: T) -> Self {
+        Self::from_fn(len, max, |x| x.powf(1.0 / gamma))
+    }
+
+    /// Returns the entries
+    pub fn entries(&self) -> &[T] {
+        &self.entries
+    }
+
+    /// Returns the entries for modification
+    pub fn entries_mut(&mut self) -> &mut [T] {
+        &mut self.entries
+    }
+
+    /// Returns the number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the table has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the output for an input, interpolating linearly between entries
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - Input from 0.0 to 1.0, clamped
+    pub fn sample(&self, x: f64) -> f64 {
+        match self.entries.len() {
+            0 => 0.0,
+            1 => self.entries[0].to_f64(),
+            len => {
+                let pos = x.clamp(0.0, 1.0) * (len - 1) as f64;
+                let i = (pos as usize).min(len - 2);
+                let (a, b) = (self.entries[i].to_f64(), self.entries[i + 1].to_f64());
+                a + (pos - i as f64) * (b - a)
+            }
+        }
+    }
+}
+
+impl<T: Sample> From<Lut1d<T>> for Value {
+    fn from(lut: Lut1d<T>) -> Self {
+        T::into_value(lut.entries)
+    }
+}
+
+impl<T: Sample> TryFrom<Value> for Lut1d<T> {
+    type Error = ();
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        T::from_value(value).map(Lut1d::new).ok_or(())
+    }
+}
+
+/// Three-dimensional color lookup table
+///
+/// The table holds red, green and blue outputs for each point of a cube spanning the input
+/// colors. Entries are stored with the red input varying fastest, then green, then blue.
+///
+/// # Example
+///
+/// ```
+/// use v4l::lut::Lut3d;
+///
+/// let mut lut = Lut3d::<u16>::identity(17, 4095);
+/// assert_eq!(lut.get(16, 0, 8), [4095, 0, 2048]);
+///
+/// // swap red and green at one point
+/// lut.set(16, 0, 8, [0, 4095, 2048]);
+/// assert_eq!(lut.get(16, 0, 8), [0, 4095, 2048]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lut3d<T: Sample> {
+    size: usize,
+    entries: Vec<T>,
+}
+
+impl<T: Sample> Lut3d<T> {
+    /// Returns a table of given entries
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - Number of points along each axis
+    /// * `entries` - Red, green and blue outputs, `size³ * 3` of them
+    pub fn new(size: usize, entries: Vec<T>) -> io::Result<Self> {
+        if entries.len() != size * size * size * 3 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} entries do not form a lookup table of size {}",
+                    entries.len(),
+                    size
+                ),
+            ));
+        }
+
+        Ok(Lut3d { size, entries })
+    }
+
+    /// Returns a table which leaves colors unchanged
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - Number of points along each axis
+    /// * `max` - Output value for the last point of an axis
+    pub fn identity(size: usize, max: T) -> Self {
+        let last = size.saturating_sub(1).max(1) as f64;
+        let level = |i: usize| T::from_f64(i as f64 / last * max.to_f64());
+        let mut entries = Vec::with_capacity(size * size * size * 3);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    entries.extend_from_slice(&[level(r), level(g), level(b)]);
+                }
+            }
+        }
+        Lut3d { size, entries }
+    }
+
+    /// Returns the number of points along each axis
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the entries
+    pub fn entries(&self) -> &[T] {
+        &self.entries
+    }
+
+    /// Returns the output color at a point of the cube
+    ///
+    /// # Arguments
+    ///
+    /// * `r` - Index along the red axis
+    /// * `g` - Index along the green axis
+    /// * `b` - Index along the blue axis
+    pub fn get(&self, r: usize, g: usize, b: usize) -> [T; 3] {
+        let i = self.index(r, g, b);
+        [self.entries[i], self.entries[i + 1], self.entries[i + 2]]
+    }
+
+    /// Sets the output color at a point of the cube
+    ///
+    /// # Arguments
+    ///
+    /// * `r` - Index along the red axis
+    /// * `g` - Index along the green axis
+    /// * `b` - Index along the blue axis
+    /// * `rgb` - Output color
+    pub fn set(&mut self, r: usize, g: usize, b: usize, rgb: [T; 3]) {
+        let i = self.index(r, g, b);
+        self.entries[i..i + 3].copy_from_slice(&rgb);
+    }
+
+    fn index(&self, r: usize, g: usize, b: usize) -> usize {
+        assert!(r < self.size && g < self.size && b < self.size);
+        ((b * self.size + g) * self.size + r) * 3
+    }
+}
+
+impl<T: Sample> From<Lut3d<T>> for Value {
+    fn from(lut: Lut3d<T>) -> Self {
+        T::into_value(lut.entries)
+    }
+}
+
+impl<T: Sample> TryFrom<Value> for Lut3d<T> {
+    type Error = ();
+
+    /// Derives the size of the cube from the number of elements
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let entries = T::from_value(value).ok_or(())?;
+        let points = entries.len() / 3;
+        let size = (points as f64).cbrt().round() as usize;
+        Lut3d::new(size, entries).map_err(|_| ())
+    }
+}
@@ -0,0 +1,138 @@
+//! Exposure bracketing
+//!
+//! HDR pipelines merge several frames of the same scene captured with different exposure
+//! settings. Sensors driven through requests apply controls to exactly the frame a request
+//! belongs to, so a burst of requests with different settings yields a burst of frames with
+//! exactly those settings, without waiting for the settings of each frame to settle.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use v4l::buffer::Type;
+//! use v4l::control::{Control, Value};
+//! use v4l::io::mmap::Stream;
+//! use v4l::{media, Device};
+//!
+//! const V4L2_CID_EXPOSURE: u32 = 0x00980911;
+//!
+//! let media = media::Device::new(0).expect("Failed to open media device");
+//! let dev = Device::new(0).expect("Failed to open device");
+//! let mut stream = Stream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+//!
+//! let settings: Vec<_> = [100, 400, 1600]
+//!     .iter()
+//!     .map(|&exposure| {
+//!         vec![Control {
+//!             id: V4L2_CID_EXPOSURE,
+//!             value: Value::Integer(exposure),
+//!         }]
+//!     })
+//!     .collect();
+//! let frames = media::bracket::capture(&media, &dev, &mut stream, &settings, None)
+//!     .expect("Failed to capture bracket");
+//! for frame in &frames {
+//!     println!("{} bytes with {:?}", frame.data.len(), frame.controls);
+//! }
+//! ```
+
+use std::io;
+use std::time::Duration;
+
+use crate::buffer::Metadata;
+use crate::control::Control;
+use crate::io::mmap::Stream;
+use crate::io::traits::{CaptureStream, Stream as _};
+use crate::media::{Device, Request};
+
+/// Frame of a bracket along with the settings it was captured with
+#[derive(Clone)]
+pub struct Frame {
+    /// Frame data
+    pub data: Vec<u8>,
+    /// Frame metadata
+    pub meta: Metadata,
+    /// Control values applied by the driver, which may differ from the requested ones
+    pub controls: Vec<Control>,
+}
+
+/// Captures a burst of frames, one for each set of control values
+///
+/// A request is queued for each set of controls, so the stream must have at least as many
+/// buffers as there are sets. The stream must not be streaming yet, it is stopped again before
+/// returning. Frames are returned in the order of the settings.
+///
+/// # Arguments
+///
+/// * `media` - Media device the video device belongs to
+/// * `dev` - Video device the stream belongs to
+/// * `stream` - Capture stream
+/// * `settings` - Control values of each frame
+/// * `timeout` - Maximum time to wait for each frame, `None` means infinite timeout (blocking)
+pub fn capture(
+    media: &Device,
+    dev: &crate::Device,
+    stream: &mut Stream,
+    settings: &[Vec<Control>],
+    timeout: Option<Duration>,
+) -> io::Result<Vec<Frame>> {
+    if settings.len() > stream.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} frames requested, but the stream only has {} buffers",
+                settings.len(),
+                stream.len()
+            ),
+        ));
+    }
+
+    let requests = settings
+        .iter()
+        .enumerate()
+        .map(|(index, ctrls)| {
+            let request = media.alloc_request()?;
+            if !ctrls.is_empty() {
+                dev.set_request_controls(ctrls, &request)?;
+            }
+            stream.queue_request(index, &request)?;
+            Ok(request)
+        })
+        .collect::<io::Result<Vec<Request>>>()?;
+
+    stream.start()?;
+    let result = (|| {
+        for request in &requests {
+            request.queue()?;
+        }
+
+        let mut frames: Vec<Option<Frame>> = vec![None; requests.len()];
+        for _ in 0..requests.len() {
+            let index = CaptureStream::dequeue(stream)?;
+            let request = requests.get(index).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "invalid buffer index")
+            })?;
+            if !request.wait(timeout)? {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "request"));
+            }
+
+            let controls = settings[index]
+                .iter()
+                .map(|ctrl| {
+                    let desc = dev.query_control(ctrl.id)?;
+                    dev.request_control(&desc, request)
+                })
+                .collect::<io::Result<_>>()?;
+            let (data, meta) = stream.get_mut(index).unwrap();
+            frames[index] = Some(Frame {
+                data: data.to_vec(),
+                meta: *meta,
+                controls,
+            });
+        }
+
+        Ok(frames.into_iter().flatten().collect())
+    })();
+
+    stream.stop()?;
+    result
+}
@@ -0,0 +1,262 @@
+//! Media controller devices
+//!
+//! Complex hardware, e.g. camera pipelines of embedded SoCs or stateless codecs, is exposed as a
+//! graph of entities through a media device (`/dev/mediaX`) in addition to the video devices.
+//! The media device is also the factory for [`Request`]s, which bundle buffers and controls so
+//! the driver applies them atomically to a single frame.
+
+pub mod bracket;
+pub mod request;
+pub mod ring;
+pub use request::Request;
+pub mod topology;
+pub use topology::{Entity, Interface, Topology};
+
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{fs, io, mem, thread};
+
+use crate::device::Handle;
+use crate::v4l2;
+use crate::v4l2::media::*;
+
+/// Media controller device abstraction
+pub struct Device {
+    /// Raw handle
+    handle: Arc<Handle>,
+}
+
+impl Device {
+    /// Returns a media device by index
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index (0: first, 1: second, ..)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::media::Device;
+    /// let dev = Device::new(0);
+    /// ```
+    pub fn new(index: usize) -> io::Result<Self> {
+        Self::with_path(format!("{}{}", "/dev/media", index))
+    }
+
+    /// Returns a media device by path
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path (e.g. "/dev/media0")
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::media::Device;
+    /// let dev = Device::with_path("/dev/media0");
+    /// ```
+    pub fn with_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let fd = v4l2::open(&path, libc::O_RDWR | libc::O_NONBLOCK)?;
+
+        Ok(Device {
+            handle: Arc::new(Handle::new(fd)),
+        })
+    }
+
+    /// Returns the raw device handle
+    pub fn handle(&self) -> Arc<Handle> {
+        self.handle.clone()
+    }
+
+    /// Allocates a new request
+    ///
+    /// Requests are only supported by drivers reporting
+    /// [`crate::feature::Features::REQUESTS`], other drivers fail with `ENOTTY`.
+    pub fn alloc_request(&self) -> io::Result<Request> {
+        let mut fd: std::os::raw::c_int = -1;
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::MEDIA_IOC_REQUEST_ALLOC,
+                &mut fd as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        Ok(Request::new(Handle::new(fd)))
+    }
+
+    /// Returns the current version of the topology
+    ///
+    /// The kernel increments the version whenever entities, pads or links appear or disappear,
+    /// so comparing versions is a cheap way to detect changes of the graph.
+    pub fn topology_version(&self) -> io::Result<u64> {
+        unsafe {
+            let mut topo: media_v2_topology = mem::zeroed();
+            self.g_topology(&mut topo)?;
+            Ok(topo.topology_version)
+        }
+    }
+
+    /// Returns a snapshot of the whole graph
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::media::Device;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open media device");
+    /// let topology = dev.topology().expect("Failed to query topology");
+    /// for entity in &topology.entities {
+    ///     println!("{}: {}", entity.id, entity.name);
+    /// }
+    /// ```
+    pub fn topology(&self) -> io::Result<Topology> {
+        loop {
+            unsafe {
+                // the first call only returns the number of elements
+                let mut topo: media_v2_topology = mem::zeroed();
+                self.g_topology(&mut topo)?;
+                let version = topo.topology_version;
+
+                let mut entities =
+                    vec![mem::zeroed::<media_v2_entity>(); topo.num_entities as usize];
+                let mut interfaces =
+                    vec![mem::zeroed::<media_v2_interface>(); topo.num_interfaces as usize];
+                let mut pads = vec![mem::zeroed::<media_v2_pad>(); topo.num_pads as usize];
+                let mut links = vec![mem::zeroed::<media_v2_link>(); topo.num_links as usize];
+                topo.ptr_entities = entities.as_mut_ptr() as u64;
+                topo.ptr_interfaces = interfaces.as_mut_ptr() as u64;
+                topo.ptr_pads = pads.as_mut_ptr() as u64;
+                topo.ptr_links = links.as_mut_ptr() as u64;
+                self.g_topology(&mut topo)?;
+
+                // the graph changed in between, the arrays may be incomplete
+                if topo.topology_version != version {
+                    continue;
+                }
+
+                entities.truncate(topo.num_entities as usize);
+                interfaces.truncate(topo.num_interfaces as usize);
+                pads.truncate(topo.num_pads as usize);
+                links.truncate(topo.num_links as usize);
+                return Ok(Topology {
+                    version,
+                    entities: entities.into_iter().map(Into::into).collect(),
+                    interfaces: interfaces.into_iter().map(Into::into).collect(),
+                    pads: pads.into_iter().map(Into::into).collect(),
+                    links: links.into_iter().map(Into::into).collect(),
+                });
+            }
+        }
+    }
+
+    /// Waits for the topology to change and returns the new one
+    ///
+    /// Media devices do not signal topology changes, so the version is polled. Returns `None` if
+    /// the topology did not change within the timeout.
+    ///
+    /// # Arguments
+    ///
+    /// * `since` - Version of the last known topology
+    /// * `interval` - Time between two checks
+    /// * `timeout` - Timeout, `None` means infinite timeout (blocking)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use v4l::media::Device;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open media device");
+    /// let mut topology = dev.topology().expect("Failed to query topology");
+    /// loop {
+    ///     if let Some(new) = dev
+    ///         .wait_topology_change(topology.version, Duration::from_millis(500), None)
+    ///         .expect("Failed to query topology")
+    ///     {
+    ///         println!("{} entities", new.entities.len());
+    ///         topology = new;
+    ///     }
+    /// }
+    /// ```
+    pub fn wait_topology_change(
+        &self,
+        since: u64,
+        interval: Duration,
+        timeout: Option<Duration>,
+    ) -> io::Result<Option<Topology>> {
+        let start = Instant::now();
+        loop {
+            if self.topology_version()? != since {
+                let topology = self.topology()?;
+                if topology.version != since {
+                    return Ok(Some(topology));
+                }
+            }
+
+            let remaining = match timeout {
+                Some(timeout) => match timeout.checked_sub(start.elapsed()) {
+                    Some(remaining) if !remaining.is_zero() => remaining,
+                    _ => return Ok(None),
+                },
+                None => interval,
+            };
+            thread::sleep(interval.min(remaining));
+        }
+    }
+
+    /// Returns the entity of a device node, along with its interface
+    ///
+    /// Fails with [`io::ErrorKind::NotFound`] if the node does not belong to this media device.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of a video or subdevice node (e.g. "/dev/video0", "/dev/v4l-subdev0")
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::media::Device;
+    ///
+    /// let dev = Device::new(0).expect("Failed to open media device");
+    /// let (entity, _) = dev
+    ///     .entity_for_devnode("/dev/video0")
+    ///     .expect("Failed to find entity");
+    /// println!("/dev/video0 is {}", entity.name);
+    /// ```
+    pub fn entity_for_devnode<P: AsRef<Path>>(&self, path: P) -> io::Result<(Entity, Interface)> {
+        let rdev = fs::metadata(&path)?.rdev();
+        let (major, minor) = (libc::major(rdev), libc::minor(rdev));
+
+        let topology = self.topology()?;
+        let not_found = || {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "{} is not part of this media device",
+                    path.as_ref().display()
+                ),
+            )
+        };
+        let intf = topology
+            .interface_for_devnode(major, minor)
+            .ok_or_else(not_found)?;
+        let entity = topology
+            .entities_for_interface(intf.id)
+            .next()
+            .ok_or_else(not_found)?;
+        Ok((entity.clone(), *intf))
+    }
+
+    fn g_topology(&self, topo: &mut media_v2_topology) -> io::Result<()> {
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::MEDIA_IOC_G_TOPOLOGY,
+                topo as *mut _ as *mut std::os::raw::c_void,
+            )
+        }
+    }
+}
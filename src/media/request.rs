@@ -0,0 +1,110 @@
+use std::io;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::time::Duration;
+
+use crate::device::Handle;
+use crate::v4l2;
+
+/// Bundle of buffers and controls applied atomically to a single frame
+///
+/// A request is filled by setting controls through [`crate::Device::set_request_controls()`] and
+/// queueing buffers through e.g. [`crate::io::mmap::Stream::queue_request()`]. Neither takes
+/// effect before the request itself is queued. Once the driver is done with the request, the
+/// buffers can be dequeued as usual and the applied control values read back through
+/// [`crate::Device::request_control()`].
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::buffer::Type;
+/// use v4l::control::{Control, Value};
+/// use v4l::io::mmap::Stream;
+/// use v4l::io::traits::CaptureStream;
+/// use v4l::{media, Device};
+///
+/// const V4L2_CID_EXPOSURE: u32 = 0x00980911;
+///
+/// let media = media::Device::new(0).expect("Failed to open media device");
+/// let dev = Device::new(0).expect("Failed to open device");
+/// let mut stream = Stream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+///
+/// let request = media.alloc_request().expect("Failed to allocate request");
+/// let exposure = Control {
+///     id: V4L2_CID_EXPOSURE,
+///     value: Value::Integer(100),
+/// };
+/// dev.set_request_controls([exposure], &request)
+///     .expect("Failed to set controls");
+/// stream.queue_request(0, &request).expect("Failed to queue buffer");
+/// request.queue().expect("Failed to queue request");
+/// request.wait(None).expect("Failed to wait for request");
+/// ```
+///
+/// Instead of blocking in [`Request::wait()`], completion can also be awaited in an external
+/// event loop (epoll, mio, tokio, ..) by registering the file descriptor for exceptional
+/// conditions (`EPOLLPRI`), e.g. alongside the fds of the video devices.
+pub struct Request {
+    handle: Handle,
+}
+
+impl Request {
+    pub(crate) fn new(handle: Handle) -> Self {
+        Request { handle }
+    }
+
+    /// Returns the raw file descriptor
+    pub fn fd(&self) -> std::os::raw::c_int {
+        self.handle.fd()
+    }
+
+    /// Hands the request to the driver
+    ///
+    /// The request must contain at least one buffer.
+    pub fn queue(&self) -> io::Result<()> {
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::MEDIA_REQUEST_IOC_QUEUE,
+                std::ptr::null_mut(),
+            )
+        }
+    }
+
+    /// Waits for the driver to complete the request
+    ///
+    /// Returns `false` if the request did not complete within the timeout.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Timeout, `None` means infinite timeout (blocking)
+    pub fn wait(&self, timeout: Option<Duration>) -> io::Result<bool> {
+        // completion is signalled as an exceptional condition
+        Ok(self.handle.poll(libc::POLLPRI, timeout)? > 0)
+    }
+
+    /// Clears a completed request, so it can be filled and queued again
+    ///
+    /// Reusing requests avoids allocating a new one for each frame.
+    pub fn reinit(&self) -> io::Result<()> {
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::MEDIA_REQUEST_IOC_REINIT,
+                std::ptr::null_mut(),
+            )
+        }
+    }
+}
+
+impl AsRawFd for Request {
+    fn as_raw_fd(&self) -> RawFd {
+        self.handle.fd()
+    }
+}
+
+impl AsFd for Request {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // the fd stays open for as long as the request lives
+        unsafe { BorrowedFd::borrow_raw(self.handle.fd()) }
+    }
+}
@@ -0,0 +1,204 @@
+//! Pipelined requests
+//!
+//! Stateless decoders only stay busy if the next request is already queued when the current
+//! one completes. A [`RequestRing`] pre-allocates a fixed number of requests, keeps several of
+//! them in flight and recycles completed ones through [`Request::reinit()`], so no requests are
+//! allocated while streaming.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::ops::ControlFlow;
+//! use v4l::media::{self, ring::RequestRing};
+//!
+//! let media = media::Device::new(0).expect("Failed to open media device");
+//! let mut ring = RequestRing::new(&media, 4).expect("Failed to allocate requests");
+//!
+//! let mut frames = 0;
+//! ring.run(
+//!     3,
+//!     None,
+//!     |_request| {
+//!         // set the controls of the next frame and queue its buffers here
+//!         frames += 1;
+//!         if frames <= 100 {
+//!             Ok(ControlFlow::Continue(()))
+//!         } else {
+//!             Ok(ControlFlow::Break(()))
+//!         }
+//!     },
+//!     |_request| {
+//!         // dequeue the buffers of the completed request here
+//!         Ok(())
+//!     },
+//! )
+//! .expect("Failed to run requests");
+//! ```
+
+use std::collections::VecDeque;
+use std::io;
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+use crate::media::{Device, Request};
+
+/// Fixed set of requests which are recycled once completed
+pub struct RequestRing {
+    requests: Vec<Request>,
+    /// Indices of requests which can be filled
+    free: VecDeque<usize>,
+    /// Indices of queued requests, oldest first
+    in_flight: VecDeque<usize>,
+}
+
+impl RequestRing {
+    /// Allocates a ring of requests
+    ///
+    /// # Arguments
+    ///
+    /// * `media` - Media device to allocate the requests from
+    /// * `count` - Number of requests
+    pub fn new(media: &Device, count: usize) -> io::Result<Self> {
+        let requests = (0..count)
+            .map(|_| media.alloc_request())
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(RequestRing {
+            requests,
+            free: (0..count).collect(),
+            in_flight: VecDeque::with_capacity(count),
+        })
+    }
+
+    /// Returns the number of requests
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Returns true if the ring holds no requests
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Returns the number of queued requests which did not complete yet
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Fills a free request and queues it
+    ///
+    /// Returns `false` without calling `fill` if all requests are in flight. If `fill` fails,
+    /// the request is reinitialized and stays free.
+    ///
+    /// # Arguments
+    ///
+    /// * `fill` - Function setting the controls and queueing the buffers of the request
+    pub fn submit<F>(&mut self, fill: F) -> io::Result<bool>
+    where
+        F: FnOnce(&Request) -> io::Result<()>,
+    {
+        let submitted = self.try_submit(|request| fill(request).map(ControlFlow::Continue))?;
+        Ok(submitted.is_some())
+    }
+
+    /// Waits for the oldest request in flight and recycles it
+    ///
+    /// Requests are completed in the order they were submitted, which is the order drivers
+    /// process them in. Returns `None` if no request is in flight.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Fail with [`io::ErrorKind::TimedOut`] if the request does not complete in
+    ///   time, `None` means infinite timeout (blocking)
+    /// * `done` - Function called with the completed request, e.g. to dequeue its buffers
+    pub fn complete<F, R>(&mut self, timeout: Option<Duration>, done: F) -> io::Result<Option<R>>
+    where
+        F: FnOnce(&Request) -> io::Result<R>,
+    {
+        let index = match self.in_flight.front() {
+            Some(&index) => index,
+            None => return Ok(None),
+        };
+
+        let request = &self.requests[index];
+        if !request.wait(timeout)? {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "request"));
+        }
+        self.in_flight.pop_front();
+
+        let result = done(request);
+        request.reinit()?;
+        self.free.push_back(index);
+        result.map(Some)
+    }
+
+    /// Keeps up to `depth` requests in flight until `fill` breaks and all requests completed
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - Maximum number of requests in flight, limited to the size of the ring
+    /// * `timeout` - Maximum time to wait for each request, `None` means infinite timeout
+    ///   (blocking)
+    /// * `fill` - Function filling the next request, breaking once there is no more work; the
+    ///   request it was called with is not queued then
+    /// * `done` - Function called with each completed request
+    pub fn run<F, D>(
+        &mut self,
+        depth: usize,
+        timeout: Option<Duration>,
+        mut fill: F,
+        mut done: D,
+    ) -> io::Result<()>
+    where
+        F: FnMut(&Request) -> io::Result<ControlFlow<()>>,
+        D: FnMut(&Request) -> io::Result<()>,
+    {
+        let depth = depth.clamp(1, self.len().max(1));
+        let mut exhausted = false;
+        loop {
+            while !exhausted && self.in_flight() < depth {
+                match self.try_submit(&mut fill)? {
+                    Some(flow) => exhausted = flow.is_break(),
+                    None => break,
+                }
+            }
+
+            if self.complete(timeout, &mut done)?.is_none() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Fills a free request and queues it unless `fill` breaks
+    ///
+    /// Returns `None` if all requests are in flight.
+    fn try_submit<F>(&mut self, fill: F) -> io::Result<Option<ControlFlow<()>>>
+    where
+        F: FnOnce(&Request) -> io::Result<ControlFlow<()>>,
+    {
+        let index = match self.free.pop_front() {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+
+        let request = &self.requests[index];
+        let result = fill(request).and_then(|flow| {
+            if flow.is_continue() {
+                request.queue()?;
+            }
+            Ok(flow)
+        });
+        match result {
+            Ok(ControlFlow::Continue(())) => {
+                self.in_flight.push_back(index);
+                Ok(Some(ControlFlow::Continue(())))
+            }
+            Ok(ControlFlow::Break(())) | Err(_) => {
+                // discard whatever was added to the request
+                let _ = request.reinit();
+                self.free.push_front(index);
+                result.map(Some)
+            }
+        }
+    }
+}
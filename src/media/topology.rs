@@ -0,0 +1,150 @@
+use crate::util;
+use crate::v4l2::media::*;
+
+/// Entity of a media graph, e.g. a sensor, an ISP block or a DMA engine
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entity {
+    /// Unique ID of the entity
+    pub id: u32,
+    /// Name of the entity
+    pub name: String,
+    /// Main function of the entity (`MEDIA_ENT_F_*`)
+    pub function: u32,
+    /// Entity flags (`MEDIA_ENT_FL_*`)
+    pub flags: u32,
+}
+
+impl From<media_v2_entity> for Entity {
+    fn from(entity: media_v2_entity) -> Self {
+        Entity {
+            id: entity.id,
+            name: util::string_lossy(util::c_chars(&entity.name)),
+            function: entity.function,
+            flags: entity.flags,
+        }
+    }
+}
+
+/// Interface through which userspace controls entities, e.g. a video device node
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Interface {
+    /// Unique ID of the interface
+    pub id: u32,
+    /// Type of the interface (`MEDIA_INTF_T_*`)
+    pub intf_type: u32,
+    /// Interface flags
+    pub flags: u32,
+    /// Major number of the device node
+    pub major: u32,
+    /// Minor number of the device node
+    pub minor: u32,
+}
+
+impl From<media_v2_interface> for Interface {
+    fn from(intf: media_v2_interface) -> Self {
+        Interface {
+            id: intf.id,
+            intf_type: intf.intf_type,
+            flags: intf.flags,
+            major: intf.devnode.major,
+            minor: intf.devnode.minor,
+        }
+    }
+}
+
+/// Connection point of an entity
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pad {
+    /// Unique ID of the pad
+    pub id: u32,
+    /// ID of the entity the pad belongs to
+    pub entity_id: u32,
+    /// Pad flags (`MEDIA_PAD_FL_*`)
+    pub flags: u32,
+    /// Index of the pad within its entity
+    pub index: u32,
+}
+
+impl From<media_v2_pad> for Pad {
+    fn from(pad: media_v2_pad) -> Self {
+        Pad {
+            id: pad.id,
+            entity_id: pad.entity_id,
+            flags: pad.flags,
+            index: pad.index,
+        }
+    }
+}
+
+/// Link between two pads, or between an interface and an entity
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Link {
+    /// Unique ID of the link
+    pub id: u32,
+    /// ID of the source pad or interface
+    pub source_id: u32,
+    /// ID of the sink pad or entity
+    pub sink_id: u32,
+    /// Link flags (`MEDIA_LNK_FL_*`)
+    pub flags: u32,
+}
+
+impl From<media_v2_link> for Link {
+    fn from(link: media_v2_link) -> Self {
+        Link {
+            id: link.id,
+            source_id: link.source_id,
+            sink_id: link.sink_id,
+            flags: link.flags,
+        }
+    }
+}
+
+/// Snapshot of the graph of a media device
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Topology {
+    /// Version of the topology, incremented by the kernel whenever the graph changes
+    pub version: u64,
+    /// Entities of the graph
+    pub entities: Vec<Entity>,
+    /// Interfaces of the graph
+    pub interfaces: Vec<Interface>,
+    /// Pads of the graph
+    pub pads: Vec<Pad>,
+    /// Links of the graph
+    pub links: Vec<Link>,
+}
+
+impl Topology {
+    /// Returns the interface of a device node
+    ///
+    /// # Arguments
+    ///
+    /// * `major` - Major number of the device node
+    /// * `minor` - Minor number of the device node
+    pub fn interface_for_devnode(&self, major: u32, minor: u32) -> Option<&Interface> {
+        self.interfaces
+            .iter()
+            .find(|intf| intf.major == major && intf.minor == minor)
+    }
+
+    /// Returns the entities controlled through an interface
+    ///
+    /// # Arguments
+    ///
+    /// * `intf` - Interface ID
+    pub fn entities_for_interface(&self, intf: u32) -> impl Iterator<Item = &Entity> {
+        self.links
+            .iter()
+            .filter(move |link| {
+                link.flags & MEDIA_LNK_FL_LINK_TYPE == MEDIA_LNK_FL_INTERFACE_LINK
+                    && link.source_id == intf
+            })
+            .filter_map(move |link| self.entities.iter().find(|e| e.id == link.sink_id))
+    }
+}
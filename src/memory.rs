@@ -1,9 +1,10 @@
-use std::fmt;
+use std::{fmt, io, ptr};
 
 /// Memory used for buffer exchange
 #[allow(clippy::unreadable_literal)]
 #[rustfmt::skip]
 #[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Memory {
     Mmap        = 1,
     UserPtr     = 2,
@@ -21,3 +22,36 @@ impl fmt::Display for Memory {
         }
     }
 }
+
+/// Locks a buffer into RAM, preventing it from being paged out
+pub(crate) fn lock(buf: &[u8]) -> io::Result<()> {
+    match unsafe { libc::mlock(buf.as_ptr() as *const std::os::raw::c_void, buf.len()) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+/// Unlocks a buffer previously locked by [`lock`]
+pub(crate) fn unlock(buf: &[u8]) -> io::Result<()> {
+    match unsafe { libc::munlock(buf.as_ptr() as *const std::os::raw::c_void, buf.len()) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+/// Touches every page of a buffer for writing, so later accesses do not cause page faults
+///
+/// The contents of the buffer are left untouched.
+pub(crate) fn prefault(buf: &mut [u8]) {
+    let page_size = match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as usize,
+        _ => 4096,
+    };
+
+    for offset in (0..buf.len()).step_by(page_size) {
+        unsafe {
+            let byte = buf.as_mut_ptr().add(offset);
+            ptr::write_volatile(byte, ptr::read_volatile(byte));
+        }
+    }
+}
@@ -0,0 +1,8 @@
+//! Parsers for the payload of metadata buffers
+//!
+//! Metadata is captured from dedicated device nodes using the [`crate::buffer::Type::MetaCapture`]
+//! buffer type. The layout of each buffer depends on the metadata format of the node.
+
+pub mod rkisp1;
+pub mod rpi;
+pub mod uvc;
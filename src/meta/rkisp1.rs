@@ -0,0 +1,219 @@
+//! Rockchip ISP1 3A statistics (`V4L2_META_FMT_RK_ISP1_STAT_3A`, fourcc "RK1S")
+//!
+//! The rkisp1 driver exposes a statistics node (`rkisp1_stats`) next to its capture nodes. Every
+//! buffer holds the auto exposure, auto white balance, autofocus and histogram measurements of a
+//! single frame as configured through the parameters node. Measurements which were not enabled
+//! or not ready for the frame are absent.
+//!
+//! The layout is shared by all hardware revisions. Revision 1.0 (e.g. RK3399) fills only the
+//! first [`AE_MEAN_MAX_V10`] exposure means and [`HIST_BIN_N_MAX_V10`] histogram bins, the
+//! remaining ones are zero.
+
+use bitflags::bitflags;
+use std::convert::TryInto;
+use std::{fmt, io};
+
+/// Number of auto exposure means, a grid of 9x9 blocks
+pub const AE_MEAN_MAX: usize = 81;
+/// Number of auto exposure means of revision 1.0, a grid of 5x5 blocks
+pub const AE_MEAN_MAX_V10: usize = 25;
+/// Number of histogram bins
+pub const HIST_BIN_N_MAX: usize = 32;
+/// Number of histogram bins of revision 1.0
+pub const HIST_BIN_N_MAX_V10: usize = 16;
+/// Number of autofocus measurement windows
+pub const AFM_MAX_WINDOWS: usize = 3;
+
+/// Size of `struct rkisp1_stat_buffer`
+pub const BUFFER_SIZE: usize = 260;
+
+// offsets into struct rkisp1_stat_buffer
+const AWB_OFFSET: usize = 8;
+const AE_OFFSET: usize = 16;
+const BLS_OFFSET: usize = AE_OFFSET + AE_MEAN_MAX + 1;
+const AF_OFFSET: usize = 108;
+const HIST_OFFSET: usize = 132;
+
+bitflags! {
+    /// Measurements present in a statistics buffer
+    pub struct MeasType: u32 {
+        /// Auto white balance
+        const AWB_DONE          = 0x01;
+        /// Autofocus
+        const AFM_FIN           = 0x02;
+        /// Auto exposure
+        const EXP_END           = 0x04;
+        /// Histogram
+        const HIST_MEASURE_RDY  = 0x08;
+    }
+}
+
+impl From<u32> for MeasType {
+    fn from(flags: u32) -> Self {
+        Self::from_bits_truncate(flags)
+    }
+}
+
+impl From<MeasType> for u32 {
+    fn from(flags: MeasType) -> Self {
+        flags.bits()
+    }
+}
+
+impl fmt::Display for MeasType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// Auto white balance measurement
+///
+/// Depending on the configured mode, the means are either Y, Cb and Cr or G, B and R.
+pub struct Awb {
+    /// Number of white pixels which contributed to the means
+    pub count: u32,
+    /// Mean of Y or G
+    pub mean_y_or_g: u8,
+    /// Mean of Cb or B
+    pub mean_cb_or_b: u8,
+    /// Mean of Cr or R
+    pub mean_cr_or_r: u8,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// Black level measurement of the Bayer channels
+pub struct BlackLevel {
+    pub r: u16,
+    pub gr: u16,
+    pub gb: u16,
+    pub b: u16,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Auto exposure measurement
+pub struct Ae {
+    /// Mean luminance of each block, row by row
+    pub means: [u8; AE_MEAN_MAX],
+    /// Measured black level
+    pub black_level: BlackLevel,
+}
+
+impl Ae {
+    /// Returns the mean luminance over a number of blocks
+    ///
+    /// # Arguments
+    ///
+    /// * `blocks` - Number of valid blocks, [`AE_MEAN_MAX`] or [`AE_MEAN_MAX_V10`]
+    pub fn mean(&self, blocks: usize) -> f64 {
+        let means = &self.means[..blocks.clamp(1, AE_MEAN_MAX)];
+        means.iter().map(|&mean| mean as f64).sum::<f64>() / means.len() as f64
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// Autofocus measurement of a window
+pub struct AfWindow {
+    /// Sharpness, the sum of the filtered pixel values
+    pub sum: u32,
+    /// Sum of the luminance
+    pub lum: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Statistics of a frame
+pub struct Stats {
+    /// Measurements present
+    pub meas_type: MeasType,
+    /// Sequence number of the frame the statistics belong to
+    pub frame_id: u32,
+    /// Auto white balance measurement
+    pub awb: Option<Awb>,
+    /// Auto exposure measurement
+    pub ae: Option<Ae>,
+    /// Autofocus measurements
+    pub af: Option<[AfWindow; AFM_MAX_WINDOWS]>,
+    /// Luminance histogram bins
+    pub histogram: Option<[u32; HIST_BIN_N_MAX]>,
+}
+
+/// Parses a statistics buffer
+///
+/// Fails if the buffer is truncated.
+///
+/// # Arguments
+///
+/// * `buf` - Buffer data
+///
+/// # Example
+///
+/// ```
+/// use v4l::meta::rkisp1::{self, MeasType};
+///
+/// let mut buf = [0u8; rkisp1::BUFFER_SIZE];
+/// // exposure measurements of frame 7, all blocks at 128
+/// buf[0] = MeasType::EXP_END.bits() as u8;
+/// buf[4] = 7;
+/// buf[16..16 + rkisp1::AE_MEAN_MAX].fill(128);
+///
+/// let stats = rkisp1::parse(&buf).unwrap();
+/// assert_eq!(stats.frame_id, 7);
+/// assert_eq!(stats.ae.unwrap().mean(rkisp1::AE_MEAN_MAX), 128.0);
+/// assert!(stats.awb.is_none());
+/// ```
+pub fn parse(buf: &[u8]) -> io::Result<Stats> {
+    if buf.len() < BUFFER_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "statistics buffer holds {} bytes, expected {}",
+                buf.len(),
+                BUFFER_SIZE
+            ),
+        ));
+    }
+
+    let u16_at = |offset: usize| u16::from_ne_bytes(buf[offset..offset + 2].try_into().unwrap());
+    let u32_at = |offset: usize| u32::from_ne_bytes(buf[offset..offset + 4].try_into().unwrap());
+
+    let meas_type = MeasType::from(u32_at(0));
+    let awb = meas_type.contains(MeasType::AWB_DONE).then(|| Awb {
+        count: u32_at(AWB_OFFSET),
+        mean_y_or_g: buf[AWB_OFFSET + 4],
+        mean_cb_or_b: buf[AWB_OFFSET + 5],
+        mean_cr_or_r: buf[AWB_OFFSET + 6],
+    });
+    let ae = meas_type.contains(MeasType::EXP_END).then(|| Ae {
+        means: buf[AE_OFFSET..AE_OFFSET + AE_MEAN_MAX].try_into().unwrap(),
+        black_level: BlackLevel {
+            r: u16_at(BLS_OFFSET),
+            gr: u16_at(BLS_OFFSET + 2),
+            gb: u16_at(BLS_OFFSET + 4),
+            b: u16_at(BLS_OFFSET + 6),
+        },
+    });
+    let af = meas_type.contains(MeasType::AFM_FIN).then(|| {
+        let mut windows = [AfWindow::default(); AFM_MAX_WINDOWS];
+        for (i, window) in windows.iter_mut().enumerate() {
+            window.sum = u32_at(AF_OFFSET + i * 8);
+            window.lum = u32_at(AF_OFFSET + i * 8 + 4);
+        }
+        windows
+    });
+    let histogram = meas_type.contains(MeasType::HIST_MEASURE_RDY).then(|| {
+        let mut bins = [0; HIST_BIN_N_MAX];
+        for (i, bin) in bins.iter_mut().enumerate() {
+            *bin = u32_at(HIST_OFFSET + i * 4);
+        }
+        bins
+    });
+
+    Ok(Stats {
+        meas_type,
+        frame_id: u32_at(4),
+        awb,
+        ae,
+        af,
+        histogram,
+    })
+}
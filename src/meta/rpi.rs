@@ -0,0 +1,402 @@
+//! Raspberry Pi camera metadata
+//!
+//! Raspberry Pi pipelines which bypass libcamera have to run their own control algorithms, fed
+//! by the metadata nodes of the platform:
+//!
+//! * [`Registers`]: sensor embedded data (`V4L2_META_FMT_SENSOR_DATA`, fourcc "SENS"), the
+//!   register values the sensor used for a frame, as sent by Sony sensors such as the IMX219 or
+//!   IMX477 through the Unicam receiver.
+//! * [`Bcm2835Stats`]: statistics of the VideoCore ISP of Pi 0 to 4 (`V4L2_META_FMT_BCM2835_ISP_STATS`,
+//!   fourcc "BSTA").
+//! * [`PispStats`]: statistics of the PiSP front end of Pi 5 (`V4L2_META_FMT_RPI_FE_STATS`,
+//!   fourcc "RPFS").
+
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::io;
+
+/// Reads little endian values at fixed offsets of a buffer
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8], size: usize, what: &str) -> io::Result<Self> {
+        if buf.len() < size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} holds {} bytes, expected {}", what, buf.len(), size),
+            ));
+        }
+        Ok(Reader { buf })
+    }
+
+    fn u32(&self, offset: usize) -> u32 {
+        u32::from_le_bytes(self.buf[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn u64(&self, offset: usize) -> u64 {
+        u64::from_le_bytes(self.buf[offset..offset + 8].try_into().unwrap())
+    }
+
+    fn u32s(&self, offset: usize, count: usize) -> Vec<u32> {
+        (0..count).map(|i| self.u32(offset + i * 4)).collect()
+    }
+
+    fn u64s(&self, offset: usize, count: usize) -> Vec<u64> {
+        (0..count).map(|i| self.u64(offset + i * 8)).collect()
+    }
+}
+
+/// Packing of the embedded data lines, matching the bit depth of the image data
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Packing {
+    /// One byte per pixel
+    Raw8,
+    /// Every fifth byte holds the low bits of the previous four and is skipped
+    Raw10,
+    /// Every third byte holds the low bits of the previous two and is skipped
+    Raw12,
+}
+
+impl Packing {
+    /// Returns whether a byte of a line holds low bits instead of data
+    fn is_padding(&self, index: usize) -> bool {
+        match self {
+            Packing::Raw8 => false,
+            Packing::Raw10 => index % 5 == 4,
+            Packing::Raw12 => index % 3 == 2,
+        }
+    }
+}
+
+/// Sensor register values sent as embedded data
+///
+/// Embedded data lines use the SMIA/CCS encoding: a line start code followed by pairs of a tag
+/// and a data byte, which either set the register address or hold the value of the current
+/// register.
+///
+/// # Example
+///
+/// ```
+/// use v4l::meta::rpi::{Packing, Registers};
+///
+/// // IMX219 coarse integration time (0x015a) = 0x0400
+/// let line = [0x0a, 0xaa, 0x01, 0xa5, 0x5a, 0x5a, 0x04, 0x5a, 0x00, 0x07, 0x07];
+/// let regs = Registers::parse(&line, Packing::Raw8, None).unwrap();
+/// assert_eq!(regs.get(0x015b), Some(0x00));
+/// assert_eq!(regs.get_u16(0x015a), Some(0x0400));
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Registers {
+    values: BTreeMap<u16, u8>,
+}
+
+const LINE_START: u8 = 0x0a;
+const LINE_END: u8 = 0x07;
+const TAG_ADDR_HIGH: u8 = 0xaa;
+const TAG_ADDR_LOW: u8 = 0xa5;
+const TAG_VALUE: u8 = 0x5a;
+const TAG_SKIP: u8 = 0x55;
+
+impl Registers {
+    /// Parses embedded data
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Buffer data
+    /// * `packing` - Packing of the lines
+    /// * `line_length` - Bytes per line, or `None` to search for the start of the next line
+    pub fn parse(buf: &[u8], packing: Packing, line_length: Option<usize>) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let line_length = line_length.filter(|&len| len > 0);
+        let mut values = BTreeMap::new();
+        let mut addr: u16 = 0;
+        let mut start = 0;
+
+        while start < buf.len() && buf[start] == LINE_START {
+            let end = line_length.map_or(buf.len(), |len| (start + len).min(buf.len()));
+            let mut bytes = buf[start..end]
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !packing.is_padding(*i))
+                .skip(1);
+
+            let mut line_end = None;
+            while let (Some((_, &tag)), Some((pos, &data))) = (bytes.next(), bytes.next()) {
+                match tag {
+                    LINE_END => {
+                        line_end = Some(start + pos + 1);
+                        break;
+                    }
+                    TAG_ADDR_HIGH => addr = (addr & 0x00ff) | (data as u16) << 8,
+                    TAG_ADDR_LOW => addr = (addr & 0xff00) | data as u16,
+                    TAG_SKIP => addr = addr.wrapping_add(1),
+                    TAG_VALUE => {
+                        values.insert(addr, data);
+                        addr = addr.wrapping_add(1);
+                    }
+                    _ => {
+                        return Err(invalid(format!(
+                            "illegal tag {:#04x} in embedded data",
+                            tag
+                        )))
+                    }
+                }
+            }
+            let line_end = match line_end {
+                Some(line_end) => line_end,
+                None => break,
+            };
+
+            start = match line_length {
+                Some(len) => start + len,
+                None => buf[line_end..]
+                    .iter()
+                    .position(|&b| b == LINE_START)
+                    .map_or(buf.len(), |pos| line_end + pos),
+            };
+        }
+
+        if values.is_empty() {
+            return Err(invalid("no registers in embedded data".to_string()));
+        }
+        Ok(Registers { values })
+    }
+
+    /// Returns the value of a register
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Register address
+    pub fn get(&self, addr: u16) -> Option<u8> {
+        self.values.get(&addr).copied()
+    }
+
+    /// Returns the value of a 16 bit register, stored big endian like Sony sensors do
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the high byte
+    pub fn get_u16(&self, addr: u16) -> Option<u16> {
+        Some((self.get(addr)? as u16) << 8 | self.get(addr.wrapping_add(1))? as u16)
+    }
+
+    /// Returns all registers by address
+    pub fn iter(&self) -> impl Iterator<Item = (u16, u8)> + '_ {
+        self.values.iter().map(|(&addr, &value)| (addr, value))
+    }
+}
+
+/// Number of bins of each BCM2835 ISP histogram
+pub const BCM2835_HISTOGRAM_BINS: usize = 128;
+/// Number of BCM2835 ISP histograms
+pub const BCM2835_HISTOGRAMS: usize = 2;
+/// Number of BCM2835 ISP white balance regions, a grid of 16x12
+pub const BCM2835_AWB_REGIONS: usize = 16 * 12;
+/// Number of BCM2835 ISP floating regions
+pub const BCM2835_FLOATING_REGIONS: usize = 16;
+/// Number of BCM2835 ISP exposure regions
+pub const BCM2835_AGC_REGIONS: usize = 16;
+/// Number of BCM2835 ISP focus regions
+pub const BCM2835_FOCUS_REGIONS: usize = 12;
+/// Size of `struct bcm2835_isp_stats`
+pub const BCM2835_STATS_SIZE: usize = 10824;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// Color histogram of the BCM2835 ISP
+pub struct Histogram {
+    pub r: Vec<u32>,
+    pub g: Vec<u32>,
+    pub b: Vec<u32>,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// Color sums over a region of the BCM2835 ISP
+pub struct Region {
+    /// Number of pixels which contributed to the sums
+    pub counted: u32,
+    /// Number of pixels which were excluded, e.g. because they were saturated
+    pub not_counted: u32,
+    pub r_sum: u64,
+    pub g_sum: u64,
+    pub b_sum: u64,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// Contrast measurement of a focus region of the BCM2835 ISP, for two filters and two phases
+pub struct FocusRegion {
+    /// Sum of contrast values
+    pub contrast: [[u64; 2]; 2],
+    /// Number of contrast values summed up
+    pub count: [[u32; 2]; 2],
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// Statistics of the BCM2835 ISP
+pub struct Bcm2835Stats {
+    /// Version of the statistics layout
+    pub version: u32,
+    pub histograms: Vec<Histogram>,
+    pub awb: Vec<Region>,
+    pub floating: Vec<Region>,
+    pub agc: Vec<Region>,
+    pub focus: Vec<FocusRegion>,
+}
+
+/// Parses BCM2835 ISP statistics
+///
+/// Fails if the buffer is truncated.
+///
+/// # Arguments
+///
+/// * `buf` - Buffer data
+pub fn parse_bcm2835(buf: &[u8]) -> io::Result<Bcm2835Stats> {
+    let r = Reader::new(buf, BCM2835_STATS_SIZE, "statistics buffer")?;
+    let histogram_size = BCM2835_HISTOGRAM_BINS * 4;
+    let histograms = (0..BCM2835_HISTOGRAMS)
+        .map(|i| {
+            let offset = 8 + i * 3 * histogram_size;
+            Histogram {
+                r: r.u32s(offset, BCM2835_HISTOGRAM_BINS),
+                g: r.u32s(offset + histogram_size, BCM2835_HISTOGRAM_BINS),
+                b: r.u32s(offset + 2 * histogram_size, BCM2835_HISTOGRAM_BINS),
+            }
+        })
+        .collect();
+
+    let regions = |offset: usize, count: usize| -> Vec<Region> {
+        (0..count)
+            .map(|i| {
+                let offset = offset + i * 32;
+                Region {
+                    counted: r.u32(offset),
+                    not_counted: r.u32(offset + 4),
+                    r_sum: r.u64(offset + 8),
+                    g_sum: r.u64(offset + 16),
+                    b_sum: r.u64(offset + 24),
+                }
+            })
+            .collect()
+    };
+    let awb_offset = 8 + BCM2835_HISTOGRAMS * 3 * histogram_size;
+    let floating_offset = awb_offset + BCM2835_AWB_REGIONS * 32;
+    let agc_offset = floating_offset + BCM2835_FLOATING_REGIONS * 32;
+    let focus_offset = agc_offset + BCM2835_AGC_REGIONS * 32;
+
+    let focus = (0..BCM2835_FOCUS_REGIONS)
+        .map(|i| {
+            let offset = focus_offset + i * 48;
+            FocusRegion {
+                contrast: [
+                    [r.u64(offset), r.u64(offset + 8)],
+                    [r.u64(offset + 16), r.u64(offset + 24)],
+                ],
+                count: [
+                    [r.u32(offset + 32), r.u32(offset + 36)],
+                    [r.u32(offset + 40), r.u32(offset + 44)],
+                ],
+            }
+        })
+        .collect();
+
+    Ok(Bcm2835Stats {
+        version: r.u32(0),
+        histograms,
+        awb: regions(awb_offset, BCM2835_AWB_REGIONS),
+        floating: regions(floating_offset, BCM2835_FLOATING_REGIONS),
+        agc: regions(agc_offset, BCM2835_AGC_REGIONS),
+        focus,
+    })
+}
+
+/// Number of PiSP white balance zones, a grid of 32x32
+pub const PISP_AWB_ZONES: usize = 32 * 32;
+/// Number of PiSP floating zones
+pub const PISP_FLOATING_ZONES: usize = 4;
+/// Number of PiSP exposure row sums
+pub const PISP_AGC_ROW_SUMS: usize = 512;
+/// Number of PiSP exposure histogram bins
+pub const PISP_AGC_BINS: usize = 1024;
+/// Number of PiSP focus figures of merit, a grid of 8x8
+pub const PISP_CDAF_FOMS: usize = 8 * 8;
+/// Size of `struct pisp_statistics`
+pub const PISP_STATS_SIZE: usize = 23200;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// Color sums over a white balance zone of the PiSP front end
+pub struct AwbZone {
+    pub r_sum: u32,
+    pub g_sum: u32,
+    pub b_sum: u32,
+    /// Number of pixels which contributed to the sums
+    pub counted: u32,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// Luminance sum over an exposure zone of the PiSP front end
+pub struct AgcZone {
+    pub y_sum: u64,
+    /// Number of pixels which contributed to the sum
+    pub counted: u32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// Statistics of the PiSP front end
+pub struct PispStats {
+    /// White balance zones, row by row
+    pub awb: Vec<AwbZone>,
+    pub awb_floating: Vec<AwbZone>,
+    /// Luminance sums of pixel rows
+    pub agc_row_sums: Vec<u32>,
+    /// Luminance histogram
+    pub agc_histogram: Vec<u32>,
+    pub agc_floating: Vec<AgcZone>,
+    /// Contrast figures of merit, row by row
+    pub cdaf_foms: Vec<u64>,
+    pub cdaf_floating: Vec<u64>,
+}
+
+/// Parses PiSP front end statistics
+///
+/// Fails if the buffer is truncated.
+///
+/// # Arguments
+///
+/// * `buf` - Buffer data
+pub fn parse_pisp(buf: &[u8]) -> io::Result<PispStats> {
+    let r = Reader::new(buf, PISP_STATS_SIZE, "statistics buffer")?;
+    let awb_zones = |offset: usize, count: usize| -> Vec<AwbZone> {
+        (0..count)
+            .map(|i| {
+                let offset = offset + i * 16;
+                AwbZone {
+                    r_sum: r.u32(offset),
+                    g_sum: r.u32(offset + 4),
+                    b_sum: r.u32(offset + 8),
+                    counted: r.u32(offset + 12),
+                }
+            })
+            .collect()
+    };
+
+    let agc_offset = (PISP_AWB_ZONES + PISP_FLOATING_ZONES) * 16;
+    let histogram_offset = agc_offset + PISP_AGC_ROW_SUMS * 4;
+    let agc_floating_offset = histogram_offset + PISP_AGC_BINS * 4;
+    let cdaf_offset = agc_floating_offset + PISP_FLOATING_ZONES * 16;
+
+    Ok(PispStats {
+        awb: awb_zones(0, PISP_AWB_ZONES),
+        awb_floating: awb_zones(PISP_AWB_ZONES * 16, PISP_FLOATING_ZONES),
+        agc_row_sums: r.u32s(agc_offset, PISP_AGC_ROW_SUMS),
+        agc_histogram: r.u32s(histogram_offset, PISP_AGC_BINS),
+        agc_floating: (0..PISP_FLOATING_ZONES)
+            .map(|i| AgcZone {
+                y_sum: r.u64(agc_floating_offset + i * 16),
+                counted: r.u32(agc_floating_offset + i * 16 + 8),
+            })
+            .collect(),
+        cdaf_foms: r.u64s(cdaf_offset, PISP_CDAF_FOMS),
+        cdaf_floating: r.u64s(cdaf_offset + PISP_CDAF_FOMS * 8, PISP_FLOATING_ZONES),
+    })
+}
@@ -0,0 +1,360 @@
+//! UVC payload header metadata (`V4L2_META_FMT_UVC`, fourcc "UVCH")
+//!
+//! The uvcvideo driver exposes a metadata node next to each video node. Every metadata buffer
+//! holds the payload headers of all USB packets which made up the corresponding video frame,
+//! each prefixed with the system time and USB frame number at which the packet was received.
+
+use bitflags::bitflags;
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::time::Duration;
+use std::{fmt, io};
+
+use crate::buffer::{Metadata, Type};
+use crate::device::Device;
+use crate::io::mmap;
+use crate::io::traits::CaptureStream;
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    pub struct HeaderFlags: u8 {
+        /// Frame identifier, toggles at each frame start boundary
+        const FRAME_ID          = 0x01;
+        /// End of frame
+        const END_OF_FRAME      = 0x02;
+        /// Presentation time is present
+        const PTS               = 0x04;
+        /// Source clock reference is present
+        const SCR               = 0x08;
+        /// Payload specific bit
+        const PAYLOAD_SPECIFIC  = 0x10;
+        /// Still image
+        const STILL_IMAGE       = 0x20;
+        /// Error in the device streaming
+        const ERROR             = 0x40;
+        /// End of header
+        const END_OF_HEADER     = 0x80;
+    }
+}
+
+impl From<u8> for HeaderFlags {
+    fn from(flags: u8) -> Self {
+        Self::from_bits_truncate(flags)
+    }
+}
+
+impl From<HeaderFlags> for u8 {
+    fn from(flags: HeaderFlags) -> Self {
+        flags.bits()
+    }
+}
+
+impl fmt::Display for HeaderFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// Source clock reference of a payload header
+pub struct Scr {
+    /// Source time clock of the device when the packet was transmitted
+    pub stc: u32,
+    /// USB frame number (11 bit) when the source time clock was sampled
+    pub sof: u16,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Metadata block describing a single UVC payload header
+pub struct Block {
+    /// System time (CLOCK_MONOTONIC) in nanoseconds when the packet was received
+    pub ns: u64,
+    /// USB frame number when the packet was received
+    pub sof: u16,
+    /// Header flags (bmHeaderInfo)
+    pub flags: HeaderFlags,
+    /// Presentation time stamp in device clock units
+    pub pts: Option<u32>,
+    /// Source clock reference
+    pub scr: Option<Scr>,
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ns: {}, sof: {}, flags: {}",
+            self.ns, self.sof, self.flags
+        )?;
+        if let Some(pts) = self.pts {
+            write!(f, ", pts: {}", pts)?;
+        }
+        if let Some(scr) = self.scr {
+            write!(f, ", scr: {} @ {}", scr.stc, scr.sof)?;
+        }
+        Ok(())
+    }
+}
+
+/// Size of the fixed part of a block: ns (8), sof (2), length (1) and flags (1)
+const BLOCK_HEADER_SIZE: usize = 12;
+
+/// Iterator over the blocks of a metadata buffer
+///
+/// Iteration stops at the first truncated or malformed block.
+pub struct Blocks<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = Block;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.len() < BLOCK_HEADER_SIZE {
+            return None;
+        }
+
+        let ns = u64::from_le_bytes(self.buf[0..8].try_into().unwrap());
+        let sof = u16::from_le_bytes(self.buf[8..10].try_into().unwrap());
+        // The length is the one of the UVC payload header, which includes its length and flags
+        // fields.
+        let length = self.buf[10] as usize;
+        let flags = HeaderFlags::from(self.buf[11]);
+        if length < 2 || self.buf.len() < BLOCK_HEADER_SIZE + length - 2 {
+            self.buf = &[];
+            return None;
+        }
+
+        let mut data = &self.buf[BLOCK_HEADER_SIZE..BLOCK_HEADER_SIZE + length - 2];
+        self.buf = &self.buf[BLOCK_HEADER_SIZE + length - 2..];
+
+        let mut pts = None;
+        if flags.contains(HeaderFlags::PTS) && data.len() >= 4 {
+            pts = Some(u32::from_le_bytes(data[0..4].try_into().unwrap()));
+            data = &data[4..];
+        }
+        let mut scr = None;
+        if flags.contains(HeaderFlags::SCR) && data.len() >= 6 {
+            scr = Some(Scr {
+                stc: u32::from_le_bytes(data[0..4].try_into().unwrap()),
+                sof: u16::from_le_bytes(data[4..6].try_into().unwrap()) & 0x7ff,
+            });
+        }
+
+        Some(Block {
+            ns,
+            sof,
+            flags,
+            pts,
+            scr,
+        })
+    }
+}
+
+/// Parses the blocks of a metadata buffer
+///
+/// # Arguments
+///
+/// * `buf` - Metadata buffer, only the bytes used as reported by the driver
+///
+/// # Example
+///
+/// ```
+/// use v4l::meta::uvc;
+///
+/// let mut buf = Vec::new();
+/// buf.extend_from_slice(&1_000_000u64.to_le_bytes()); // ns
+/// buf.extend_from_slice(&42u16.to_le_bytes()); // sof
+/// buf.extend_from_slice(&[6, 0x84]); // length, flags (PTS | EOH)
+/// buf.extend_from_slice(&1234u32.to_le_bytes()); // pts
+///
+/// let blocks: Vec<_> = uvc::parse(&buf).collect();
+/// assert_eq!(blocks.len(), 1);
+/// assert_eq!(blocks[0].pts, Some(1234));
+/// assert_eq!(blocks[0].scr, None);
+/// ```
+pub fn parse(buf: &[u8]) -> Blocks<'_> {
+    Blocks { buf }
+}
+
+/// Matches metadata buffers with video frames by their sequence number
+///
+/// The uvcvideo driver completes the metadata buffer of a frame together with the video buffer,
+/// so both carry the same sequence number. Since the two nodes are dequeued independently, the
+/// metadata is kept around until the video frame with the same sequence number shows up.
+pub struct Correlator {
+    pending: VecDeque<(u32, Vec<Block>)>,
+    capacity: usize,
+}
+
+impl Correlator {
+    /// Returns a new correlator
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - Maximum number of metadata buffers to keep around
+    ///
+    /// Once the capacity is exceeded, the oldest metadata buffers are dropped. At least one
+    /// metadata buffer is kept around.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Correlator {
+            pending: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Stores the blocks of a dequeued metadata buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `sequence` - Sequence number of the metadata buffer
+    /// * `buf` - Metadata buffer, only the bytes used as reported by the driver
+    pub fn push(&mut self, sequence: u32, buf: &[u8]) {
+        if self.pending.len() >= self.capacity {
+            self.pending.pop_front();
+        }
+        self.pending.push_back((sequence, parse(buf).collect()));
+    }
+
+    /// Returns the blocks belonging to a video frame
+    ///
+    /// Metadata of older frames which were never requested is discarded.
+    ///
+    /// # Arguments
+    ///
+    /// * `sequence` - Sequence number of the video buffer
+    pub fn take(&mut self, sequence: u32) -> Option<Vec<Block>> {
+        while let Some((other, _)) = self.pending.front() {
+            // wrapping comparison, the sequence counter is only 32 bit wide
+            if (sequence.wrapping_sub(*other) as i32) <= 0 {
+                break;
+            }
+            self.pending.pop_front();
+        }
+
+        match self.pending.front() {
+            Some((other, _)) if *other == sequence => self.pending.pop_front().map(|(_, b)| b),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the presentation time stamp of a frame
+///
+/// All payload headers of a frame carry the same presentation time stamp, so the first one is
+/// used.
+///
+/// # Arguments
+///
+/// * `blocks` - Metadata blocks of the frame
+pub fn pts(blocks: &[Block]) -> Option<u32> {
+    blocks.iter().find_map(|block| block.pts)
+}
+
+/// Returns the last source clock reference of a frame along with the system time it was received
+///
+/// Collecting these samples over multiple frames allows for estimating the relation between the
+/// device clock and the system clock, which in turn can be used to convert the presentation time
+/// stamp into system time.
+///
+/// # Arguments
+///
+/// * `blocks` - Metadata blocks of the frame
+pub fn clock_sample(blocks: &[Block]) -> Option<(Scr, u64)> {
+    blocks
+        .iter()
+        .rev()
+        .find_map(|block| block.scr.map(|scr| (scr, block.ns)))
+}
+
+/// Capture stream of a video node along with its metadata node
+///
+/// Frames are yielded together with the metadata blocks of the same frame, matched by sequence
+/// number through a [`Correlator`]. Frames whose metadata is lost, e.g. because the metadata
+/// node ran out of buffers, come with an empty list of blocks.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::meta::uvc;
+/// use v4l::Device;
+///
+/// let video = Device::with_path("/dev/video0").expect("Failed to open video node");
+/// let meta = Device::with_path("/dev/video1").expect("Failed to open metadata node");
+/// let mut stream = uvc::Stream::new(&video, &meta).expect("Failed to create stream");
+///
+/// loop {
+///     let (buf, meta, blocks) = stream.next().expect("Failed to capture frame");
+///     println!(
+///         "frame {}: {} bytes, pts {:?}",
+///         meta.sequence,
+///         buf.len(),
+///         uvc::pts(&blocks)
+///     );
+/// }
+/// ```
+pub struct Stream<'a> {
+    video: mmap::Stream<'a>,
+    meta: mmap::Stream<'a>,
+    correlator: Correlator,
+    /// Sequence number of the last metadata buffer
+    last: Option<u32>,
+}
+
+impl<'a> Stream<'a> {
+    /// Returns a stream capturing from a video node and its metadata node
+    ///
+    /// # Arguments
+    ///
+    /// * `video` - Video node
+    /// * `meta` - Metadata node belonging to the video node
+    pub fn new(video: &Device, meta: &Device) -> io::Result<Self> {
+        let video = mmap::Stream::new(video, Type::VideoCapture)?;
+        let mut meta = mmap::Stream::new(meta, Type::MetaCapture)?;
+        // the metadata of a frame is completed along with the frame itself
+        meta.set_timeout(METADATA_TIMEOUT);
+
+        Ok(Stream {
+            video,
+            meta,
+            correlator: Correlator::new(8),
+            last: None,
+        })
+    }
+
+    /// Sets a timeout for waiting on the next video frame
+    pub fn set_timeout(&mut self, duration: Duration) {
+        self.video.set_timeout(duration);
+    }
+
+    /// Fetches a new frame along with its metadata blocks
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> io::Result<(&[u8], &Metadata, Vec<Block>)> {
+        let (buf, meta) = CaptureStream::next(&mut self.video)?;
+        let sequence = meta.sequence;
+
+        // wrapping comparison, the sequence counter is only 32 bit wide
+        while self
+            .last
+            .is_none_or(|last| (sequence.wrapping_sub(last) as i32) > 0)
+        {
+            match CaptureStream::next(&mut self.meta) {
+                Ok((data, meta)) => {
+                    let len = (meta.bytesused as usize).min(data.len());
+                    self.correlator.push(meta.sequence, &data[..len]);
+                    self.last = Some(meta.sequence);
+                }
+                // the metadata of this frame was lost
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let blocks = self.correlator.take(sequence).unwrap_or_default();
+        Ok((buf, meta, blocks))
+    }
+}
+
+/// Maximum time to wait for the metadata of a frame once the frame itself arrived
+const METADATA_TIMEOUT: Duration = Duration::from_millis(50);
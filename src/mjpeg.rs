@@ -0,0 +1,199 @@
+//! Helpers for Motion-JPEG frames
+//!
+//! Many UVC cameras omit the Huffman tables (DHT segment) from their MJPEG frames and rely on
+//! the default tables from the JPEG standard (ITU T.81, Annex K.3) instead. Standard JPEG
+//! decoders reject such frames, so [`fixup`] inserts the default tables where necessary.
+
+use std::borrow::Cow;
+use std::io;
+
+const SOI: u8 = 0xD8;
+const EOI: u8 = 0xD9;
+const SOS: u8 = 0xDA;
+const DHT: u8 = 0xC4;
+
+/// Default Huffman tables: (table class and id, code counts per length, symbol values)
+#[rustfmt::skip]
+const HUFFMAN_TABLES: [(u8, [u8; 16], &[u8]); 4] = [
+    // DC luminance
+    (0x00, [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0], &[
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b,
+    ]),
+    // AC luminance
+    (0x10, [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7d], &[
+        0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51,
+        0x61, 0x07, 0x22, 0x71, 0x14, 0x32, 0x81, 0x91, 0xa1, 0x08, 0x23, 0x42, 0xb1, 0xc1,
+        0x15, 0x52, 0xd1, 0xf0, 0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0a, 0x16, 0x17, 0x18,
+        0x19, 0x1a, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39,
+        0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57,
+        0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x73, 0x74, 0x75,
+        0x76, 0x77, 0x78, 0x79, 0x7a, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a, 0x92,
+        0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7,
+        0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3,
+        0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8,
+        0xd9, 0xda, 0xe1, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea, 0xf1, 0xf2,
+        0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa,
+    ]),
+    // DC chrominance
+    (0x01, [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0], &[
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b,
+    ]),
+    // AC chrominance
+    (0x11, [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77], &[
+        0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07,
+        0x61, 0x71, 0x13, 0x22, 0x32, 0x81, 0x08, 0x14, 0x42, 0x91, 0xa1, 0xb1, 0xc1, 0x09,
+        0x23, 0x33, 0x52, 0xf0, 0x15, 0x62, 0x72, 0xd1, 0x0a, 0x16, 0x24, 0x34, 0xe1, 0x25,
+        0xf1, 0x17, 0x18, 0x19, 0x1a, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x35, 0x36, 0x37, 0x38,
+        0x39, 0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55, 0x56,
+        0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x73, 0x74,
+        0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89,
+        0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5,
+        0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba,
+        0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6,
+        0xd7, 0xd8, 0xd9, 0xda, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea, 0xf2,
+        0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa,
+    ]),
+];
+
+/// Markers found in the header of a frame, i.e. before the scan data
+struct Header {
+    /// Offset of the start of scan marker
+    sos: usize,
+    /// Whether the frame carries a DHT segment
+    has_dht: bool,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Walks the marker segments of a frame up to the start of scan
+fn parse_header(buf: &[u8]) -> io::Result<Header> {
+    if buf.len() < 2 || buf[0] != 0xFF || buf[1] != SOI {
+        return Err(invalid("missing start of image marker"));
+    }
+
+    let mut pos = 2;
+    let mut has_dht = false;
+    loop {
+        if pos >= buf.len() || buf[pos] != 0xFF {
+            return Err(invalid("expected marker"));
+        }
+        // any marker may be preceded by fill bytes
+        let start = pos;
+        while pos < buf.len() && buf[pos] == 0xFF {
+            pos += 1;
+        }
+        let marker = *buf.get(pos).ok_or_else(|| invalid("truncated marker"))?;
+        pos += 1;
+
+        match marker {
+            SOS => {
+                return Ok(Header {
+                    sos: start,
+                    has_dht,
+                })
+            }
+            EOI => return Err(invalid("end of image before start of scan")),
+            // standalone markers without a length field
+            0x01 | 0xD0..=0xD7 => continue,
+            DHT => has_dht = true,
+            _ => {}
+        }
+
+        if pos + 2 > buf.len() {
+            return Err(invalid("truncated segment length"));
+        }
+        let length = u16::from_be_bytes([buf[pos], buf[pos + 1]]) as usize;
+        if length < 2 || pos + length > buf.len() {
+            return Err(invalid("invalid segment length"));
+        }
+        pos += length;
+    }
+}
+
+/// Validates the markers of an MJPEG frame
+///
+/// The frame must start with a start of image marker, contain well formed segments up to the
+/// start of scan and end with an end of image marker. Drivers may pad frames with zeros, so
+/// trailing zeros after the end of image marker are accepted.
+///
+/// # Arguments
+///
+/// * `buf` - Frame data, only the bytes used as reported by the driver
+pub fn validate(buf: &[u8]) -> io::Result<()> {
+    let header = parse_header(buf)?;
+
+    let end = buf.len() - buf.iter().rev().take_while(|b| **b == 0).count();
+    if end < header.sos + 4 || buf[end - 2] != 0xFF || buf[end - 1] != EOI {
+        return Err(invalid("missing end of image marker"));
+    }
+
+    Ok(())
+}
+
+/// Returns whether an MJPEG frame carries its own Huffman tables
+///
+/// # Arguments
+///
+/// * `buf` - Frame data, only the bytes used as reported by the driver
+pub fn has_huffman_tables(buf: &[u8]) -> io::Result<bool> {
+    Ok(parse_header(buf)?.has_dht)
+}
+
+/// Returns the DHT segment holding the default Huffman tables
+pub fn default_huffman_tables() -> Vec<u8> {
+    let length: usize = 2 + HUFFMAN_TABLES
+        .iter()
+        .map(|(_, _, values)| 1 + 16 + values.len())
+        .sum::<usize>();
+
+    let mut segment = Vec::with_capacity(2 + length);
+    segment.extend_from_slice(&[0xFF, DHT]);
+    segment.extend_from_slice(&(length as u16).to_be_bytes());
+    for (class_id, counts, values) in HUFFMAN_TABLES.iter() {
+        segment.push(*class_id);
+        segment.extend_from_slice(counts);
+        segment.extend_from_slice(values);
+    }
+    segment
+}
+
+/// Validates an MJPEG frame and inserts the default Huffman tables if they are missing
+///
+/// Frames which are valid on their own are returned as is, without copying.
+///
+/// # Arguments
+///
+/// * `buf` - Frame data, only the bytes used as reported by the driver
+///
+/// # Example
+///
+/// ```
+/// use v4l::mjpeg;
+///
+/// // a (truncated) frame without Huffman tables: SOI, SOS, EOI
+/// let frame = [
+///     0xFF, 0xD8, 0xFF, 0xDA, 0x00, 0x08, 0x01, 0x01, 0x00, 0x00, 0x3F, 0x00, 0xFF, 0xD9,
+/// ];
+/// assert!(!mjpeg::has_huffman_tables(&frame).unwrap());
+///
+/// let fixed = mjpeg::fixup(&frame).unwrap();
+/// assert!(mjpeg::has_huffman_tables(&fixed).unwrap());
+/// assert!(mjpeg::validate(&fixed).is_ok());
+/// ```
+pub fn fixup(buf: &[u8]) -> io::Result<Cow<'_, [u8]>> {
+    validate(buf)?;
+
+    let header = parse_header(buf)?;
+    if header.has_dht {
+        return Ok(Cow::Borrowed(buf));
+    }
+
+    let dht = default_huffman_tables();
+    let mut fixed = Vec::with_capacity(buf.len() + dht.len());
+    fixed.extend_from_slice(&buf[..header.sos]);
+    fixed.extend_from_slice(&dht);
+    fixed.extend_from_slice(&buf[header.sos..]);
+    Ok(Cow::Owned(fixed))
+}
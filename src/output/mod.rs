@@ -0,0 +1,7 @@
+//! Writers persisting captured frames
+//!
+//! These are meant for quickly dumping what a device produces, e.g. for inspecting frames with
+//! standard tools or for reproducing issues offline.
+
+pub mod raw;
+pub mod y4m;
@@ -0,0 +1,335 @@
+//! Raw frame recordings
+//!
+//! A recording consists of two files: the raw frame data as dequeued from the driver, written
+//! back to back, and a text sidecar next to it (same path with `.meta` appended) describing the
+//! format and the metadata of each frame, i.e. sequence number, timestamp, flags and field.
+//!
+//! Recordings can be replayed through [`Replay`], which implements [`CaptureStream`] just like
+//! the streams of real devices. This makes it possible to reproduce driver bugs and to test frame
+//! processing code offline.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use v4l::buffer::Type;
+//! use v4l::io::mmap::Stream;
+//! use v4l::io::traits::CaptureStream;
+//! use v4l::output::raw::{Recorder, Replay};
+//! use v4l::video::Capture;
+//! use v4l::Device;
+//!
+//! let dev = Device::new(0).expect("Failed to open device");
+//! let format = dev.format().expect("Failed to get format");
+//!
+//! let mut recorder = Recorder::create("capture.raw", &format).expect("Failed to create recording");
+//! let mut stream = Stream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+//! for _ in 0..100 {
+//!     let (buf, meta) = stream.next().expect("Failed to capture frame");
+//!     recorder.write_frame(buf, meta).expect("Failed to record frame");
+//! }
+//! recorder.flush().expect("Failed to flush recording");
+//!
+//! // later on, possibly on another machine
+//! let mut replay = Replay::open("capture.raw").expect("Failed to open recording");
+//! while let Ok((buf, meta)) = replay.next() {
+//!     println!("frame {}: {} bytes", meta.sequence, buf.len());
+//! }
+//! ```
+
+use std::convert::TryFrom;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::buffer::{Flags as BufferFlags, Metadata};
+use crate::format::{
+    Colorspace, FieldOrder, Flags as FormatFlags, Format, FourCC, Quantization, TransferFunction,
+};
+use crate::io::traits::{CaptureStream, Stream};
+use crate::timestamp::Timestamp;
+
+/// First line of a sidecar, identifying the file type and version
+const MAGIC: &str = "v4l-raw 1";
+
+/// Returns the path of the sidecar belonging to a recording
+fn sidecar(path: &Path) -> PathBuf {
+    let mut sidecar = OsString::from(path.as_os_str());
+    sidecar.push(".meta");
+    PathBuf::from(sidecar)
+}
+
+/// Recorder writing raw frames along with their metadata
+pub struct Recorder {
+    data: BufWriter<File>,
+    meta: BufWriter<File>,
+    offset: u64,
+}
+
+impl Recorder {
+    /// Creates a recording, truncating existing files
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the raw frame data, the sidecar is placed next to it
+    /// * `format` - Format of the frames
+    pub fn create<P: AsRef<Path>>(path: P, format: &Format) -> io::Result<Self> {
+        let data = BufWriter::new(File::create(path.as_ref())?);
+        let mut meta = BufWriter::new(File::create(sidecar(path.as_ref()))?);
+
+        writeln!(meta, "{}", MAGIC)?;
+        writeln!(
+            meta,
+            "format {} {} {} {} {} {} {} {} {} {}",
+            format.width,
+            format.height,
+            u32::from(format.fourcc),
+            format.field_order as u32,
+            format.stride,
+            format.size,
+            u32::from(format.flags),
+            format.colorspace as u32,
+            format.quantization as u32,
+            format.transfer as u32,
+        )?;
+
+        Ok(Recorder {
+            data,
+            meta,
+            offset: 0,
+        })
+    }
+
+    /// Appends a frame to the recording
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Frame data, only the first [`Metadata::bytesused`] bytes are recorded
+    /// * `meta` - Frame metadata
+    pub fn write_frame(&mut self, data: &[u8], meta: &Metadata) -> io::Result<()> {
+        let len = (meta.bytesused as usize).min(data.len());
+        self.data.write_all(&data[..len])?;
+        writeln!(
+            self.meta,
+            "frame {} {} {} {} {} {} {}",
+            self.offset,
+            len,
+            meta.sequence,
+            meta.timestamp.sec,
+            meta.timestamp.usec,
+            u32::from(meta.flags),
+            meta.field as u32,
+        )?;
+        self.offset += len as u64;
+
+        Ok(())
+    }
+
+    /// Flushes both files
+    ///
+    /// Dropping the recorder flushes as well, but ignores errors.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.data.flush()?;
+        self.meta.flush()
+    }
+}
+
+/// Entry of the sidecar describing a single frame
+#[derive(Copy, Clone)]
+struct Entry {
+    offset: u64,
+    len: usize,
+    meta: Metadata,
+}
+
+/// Stream replaying a recording
+///
+/// Dequeueing yields the recorded frames in order and fails with
+/// [`io::ErrorKind::UnexpectedEof`] after the last one. Queueing buffers is a no-op.
+///
+/// # Example
+///
+/// ```
+/// use v4l::buffer::Metadata;
+/// use v4l::io::traits::CaptureStream;
+/// use v4l::output::raw::{Recorder, Replay};
+/// use v4l::{Format, FourCC};
+///
+/// let path = std::env::temp_dir().join("v4l-replay-example.raw");
+/// let format = Format::new(2, 1, FourCC::new(b"YUYV"));
+///
+/// let mut recorder = Recorder::create(&path, &format).unwrap();
+/// let meta = Metadata {
+///     bytesused: 4,
+///     sequence: 7,
+///     ..Default::default()
+/// };
+/// recorder.write_frame(&[1, 2, 3, 4], &meta).unwrap();
+/// recorder.flush().unwrap();
+///
+/// let mut replay = Replay::open(&path).unwrap();
+/// assert_eq!(replay.format().fourcc, FourCC::new(b"YUYV"));
+/// let (buf, meta) = replay.next().unwrap();
+/// assert_eq!((buf, meta.sequence), (&[1u8, 2, 3, 4][..], 7));
+/// assert!(replay.next().is_err());
+/// ```
+pub struct Replay {
+    data: File,
+    format: Format,
+    entries: Vec<Entry>,
+    position: usize,
+
+    buf: Vec<u8>,
+    meta: Metadata,
+}
+
+impl Replay {
+    /// Opens a recording
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the raw frame data, as passed to [`Recorder::create()`]
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let data = File::open(path.as_ref())?;
+        let mut lines = BufReader::new(File::open(sidecar(path.as_ref()))?).lines();
+
+        if lines.next().transpose()?.as_deref() != Some(MAGIC) {
+            return Err(invalid("not a raw recording"));
+        }
+
+        let mut format = None;
+        let mut entries = Vec::new();
+        for line in lines {
+            let line = line?;
+            let mut fields = line.split_ascii_whitespace();
+            match fields.next() {
+                Some("format") => format = Some(parse_format(&mut fields)?),
+                Some("frame") => entries.push(parse_entry(&mut fields)?),
+                // unknown records are reserved for future use
+                _ => continue,
+            }
+        }
+
+        Ok(Replay {
+            data,
+            format: format.ok_or_else(|| invalid("missing format"))?,
+            entries,
+            position: 0,
+            buf: Vec::new(),
+            meta: Metadata::default(),
+        })
+    }
+
+    /// Returns the format of the recorded frames
+    pub fn format(&self) -> &Format {
+        &self.format
+    }
+
+    /// Returns the number of recorded frames
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the recording contains no frames
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Starts replaying from the first frame again
+    pub fn rewind(&mut self) {
+        self.position = 0;
+    }
+}
+
+impl Stream for Replay {
+    type Item = [u8];
+
+    fn start(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn stop(&mut self) -> io::Result<()> {
+        self.rewind();
+        Ok(())
+    }
+}
+
+impl<'a> CaptureStream<'a> for Replay {
+    fn queue(&mut self, _index: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn dequeue(&mut self) -> io::Result<usize> {
+        let entry = *self.entries.get(self.position).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::UnexpectedEof, "end of recording reached")
+        })?;
+
+        self.buf.resize(entry.len, 0);
+        self.data.seek(SeekFrom::Start(entry.offset))?;
+        self.data.read_exact(&mut self.buf)?;
+        self.meta = entry.meta;
+        self.position += 1;
+
+        Ok(0)
+    }
+
+    fn next(&'a mut self) -> io::Result<(&'a Self::Item, &'a Metadata)> {
+        self.dequeue()?;
+        Ok((&self.buf, &self.meta))
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn parse<'a, T: FromStr>(fields: &mut impl Iterator<Item = &'a str>) -> io::Result<T> {
+    fields
+        .next()
+        .and_then(|field| field.parse().ok())
+        .ok_or_else(|| invalid("malformed sidecar"))
+}
+
+fn parse_format<'a>(fields: &mut impl Iterator<Item = &'a str>) -> io::Result<Format> {
+    let mut format = Format::new(
+        parse(fields)?,
+        parse(fields)?,
+        FourCC::from(parse::<u32>(fields)?),
+    );
+    format.field_order =
+        FieldOrder::try_from(parse::<u32>(fields)?).map_err(|_| invalid("invalid field order"))?;
+    format.stride = parse(fields)?;
+    format.size = parse(fields)?;
+    format.flags = FormatFlags::from(parse::<u32>(fields)?);
+    format.colorspace =
+        Colorspace::try_from(parse::<u32>(fields)?).map_err(|_| invalid("invalid colorspace"))?;
+    format.quantization = Quantization::try_from(parse::<u32>(fields)?)
+        .map_err(|_| invalid("invalid quantization"))?;
+    format.transfer = TransferFunction::try_from(parse::<u32>(fields)?)
+        .map_err(|_| invalid("invalid transfer function"))?;
+    Ok(format)
+}
+
+fn parse_entry<'a>(fields: &mut impl Iterator<Item = &'a str>) -> io::Result<Entry> {
+    let offset = parse(fields)?;
+    let len: usize = parse(fields)?;
+    let sequence = parse(fields)?;
+    let timestamp = Timestamp::new(parse(fields)?, parse(fields)?);
+    let flags = BufferFlags::from(parse::<u32>(fields)?);
+    let field =
+        FieldOrder::try_from(parse::<u32>(fields)?).map_err(|_| invalid("invalid field order"))?;
+
+    Ok(Entry {
+        offset,
+        len,
+        meta: Metadata {
+            bytesused: len as u32,
+            flags,
+            field,
+            timestamp,
+            sequence,
+            timecode: None,
+        },
+    })
+}
@@ -0,0 +1,272 @@
+//! YUV4MPEG2 (y4m) writer
+//!
+//! Y4M is a trivial container for uncompressed planar YUV frames, which is understood by most
+//! video tools such as ffmpeg and mpv. Frames of common YUV pixel formats are converted to the
+//! planar layout on the fly, so dequeued buffers can be written as they are.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use v4l::buffer::Type;
+//! use v4l::io::mmap::Stream;
+//! use v4l::io::traits::CaptureStream;
+//! use v4l::output::y4m;
+//! use v4l::video::Capture;
+//! use v4l::Device;
+//!
+//! let dev = Device::new(0).expect("Failed to open device");
+//! let format = dev.format().expect("Failed to get format");
+//! let params = dev.params().expect("Failed to get parameters");
+//!
+//! let file = File::create("capture.y4m").expect("Failed to create file");
+//! let mut writer = y4m::Writer::new(file, &format, params.interval).expect("Unsupported format");
+//!
+//! let mut stream = Stream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+//! for _ in 0..100 {
+//!     let (buf, meta) = stream.next().expect("Failed to capture frame");
+//!     writer
+//!         .write_frame(&buf[..meta.bytesused as usize])
+//!         .expect("Failed to write frame");
+//! }
+//! ```
+
+use std::io::{self, Write};
+
+use crate::format::{FieldOrder, Format, Quantization};
+use crate::fraction::Fraction;
+
+/// Memory layout of a supported pixel format
+#[derive(Debug, Copy, Clone)]
+enum Layout {
+    /// Luma only
+    Grey,
+    /// Planar 4:2:0, optionally with the chroma planes swapped (YVU)
+    Planar420 { swap: bool },
+    /// Planar 4:2:2
+    Planar422,
+    /// Luma plane followed by an interleaved chroma plane, optionally swapped (VU)
+    SemiPlanar420 { swap: bool },
+    /// Packed 4:2:2, offsets of Y, U and V within each macropixel
+    Packed422 { y: usize, u: usize, v: usize },
+}
+
+impl Layout {
+    fn from_format(format: &Format) -> Option<Self> {
+        match &format.fourcc.repr {
+            b"GREY" => Some(Layout::Grey),
+            b"YU12" => Some(Layout::Planar420 { swap: false }),
+            b"YV12" => Some(Layout::Planar420 { swap: true }),
+            b"422P" => Some(Layout::Planar422),
+            b"NV12" => Some(Layout::SemiPlanar420 { swap: false }),
+            b"NV21" => Some(Layout::SemiPlanar420 { swap: true }),
+            b"YUYV" => Some(Layout::Packed422 { y: 0, u: 1, v: 3 }),
+            b"YVYU" => Some(Layout::Packed422 { y: 0, u: 3, v: 1 }),
+            b"UYVY" => Some(Layout::Packed422 { y: 1, u: 0, v: 2 }),
+            b"VYUY" => Some(Layout::Packed422 { y: 1, u: 2, v: 0 }),
+            _ => None,
+        }
+    }
+
+    /// Y4M colorspace tag
+    fn tag(&self) -> &'static str {
+        match self {
+            Layout::Grey => "mono",
+            Layout::Planar420 { .. } | Layout::SemiPlanar420 { .. } => "420jpeg",
+            Layout::Planar422 | Layout::Packed422 { .. } => "422",
+        }
+    }
+}
+
+/// Writer producing a Y4M stream
+pub struct Writer<W: Write> {
+    inner: W,
+    layout: Layout,
+    width: usize,
+    height: usize,
+    stride: usize,
+    /// Scratch buffer holding the planar frame
+    frame: Vec<u8>,
+}
+
+impl<W: Write> Writer<W> {
+    /// Returns a writer, writing the stream header right away
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the pixel format cannot be represented.
+    /// Supported are GREY, YU12, YV12, 422P, NV12, NV21, YUYV, YVYU, UYVY and VYUY.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - Destination, e.g. a file
+    /// * `format` - Format of the frames
+    /// * `interval` - Frame interval
+    pub fn new(mut inner: W, format: &Format, interval: Fraction) -> io::Result<Self> {
+        let layout = Layout::from_format(format).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("pixel format {} is not supported by y4m", format.fourcc),
+            )
+        })?;
+
+        let interlacing = match format.field_order {
+            FieldOrder::Interlaced | FieldOrder::InterlacedTB => "t",
+            FieldOrder::InterlacedBT => "b",
+            _ => "p",
+        };
+        // frame rate is the inverse of the interval
+        let rate = interval.reduced();
+        write!(
+            inner,
+            "YUV4MPEG2 W{} H{} F{}:{} I{} A1:1 C{}",
+            format.width,
+            format.height,
+            rate.denominator,
+            rate.numerator,
+            interlacing,
+            layout.tag()
+        )?;
+        match format.quantization {
+            Quantization::FullRange => write!(inner, " XCOLORRANGE=FULL")?,
+            Quantization::LimitedRange => write!(inner, " XCOLORRANGE=LIMITED")?,
+            Quantization::Default => {}
+        }
+        writeln!(inner)?;
+
+        let width = format.width as usize;
+        let stride = match layout {
+            // drivers may leave the stride empty
+            _ if format.stride != 0 => format.stride as usize,
+            Layout::Packed422 { .. } => width * 2,
+            _ => width,
+        };
+        let min_stride = match layout {
+            Layout::Grey => width,
+            // macropixels span two pixels
+            Layout::Packed422 { .. } if width & 1 == 1 => usize::MAX,
+            Layout::Packed422 { .. } => width * 2,
+            // chroma rows are half as wide, but cover odd widths as well
+            _ => width.div_ceil(2) * 2,
+        };
+        if stride < min_stride {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid stride {} for width {}", stride, width),
+            ));
+        }
+
+        Ok(Writer {
+            inner,
+            layout,
+            width,
+            height: format.height as usize,
+            stride,
+            frame: Vec::new(),
+        })
+    }
+
+    /// Writes a single frame
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Frame data in the format the writer was created with
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::output::y4m::Writer;
+    /// use v4l::{Format, FourCC, Fraction};
+    ///
+    /// let format = Format::new(2, 1, FourCC::new(b"YUYV"));
+    /// let mut writer = Writer::new(Vec::new(), &format, Fraction::new(1, 30)).unwrap();
+    /// writer.write_frame(&[1, 2, 3, 4]).unwrap();
+    ///
+    /// let out = writer.into_inner();
+    /// assert_eq!(&out[..], &b"YUV4MPEG2 W2 H1 F30:1 Ip A1:1 C422\nFRAME\n\x01\x03\x02\x04"[..]);
+    /// ```
+    pub fn write_frame(&mut self, data: &[u8]) -> io::Result<()> {
+        let (w, h, stride) = (self.width, self.height, self.stride);
+        let (cw, ch) = (w.div_ceil(2), h.div_ceil(2));
+
+        let required = match self.layout {
+            Layout::Grey => stride * h,
+            Layout::Planar420 { .. } => stride * h + (stride / 2) * ch * 2,
+            Layout::Planar422 => stride * h + (stride / 2) * h * 2,
+            Layout::SemiPlanar420 { .. } => stride * h + stride * ch,
+            Layout::Packed422 { .. } => stride * h,
+        };
+        if data.len() < required {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("frame too small: {} < {} bytes", data.len(), required),
+            ));
+        }
+
+        let frame = &mut self.frame;
+        frame.clear();
+
+        match self.layout {
+            Layout::Grey => copy_plane(frame, data, stride, w, h),
+            Layout::Planar420 { swap } => {
+                let cstride = stride / 2;
+                let (y, chroma) = data.split_at(stride * h);
+                let (first, second) = chroma.split_at(cstride * ch);
+                let (u, v) = if swap {
+                    (second, first)
+                } else {
+                    (first, second)
+                };
+                copy_plane(frame, y, stride, w, h);
+                copy_plane(frame, u, cstride, cw, ch);
+                copy_plane(frame, v, cstride, cw, ch);
+            }
+            Layout::Planar422 => {
+                let cstride = stride / 2;
+                let (y, chroma) = data.split_at(stride * h);
+                let (u, v) = chroma.split_at(cstride * h);
+                copy_plane(frame, y, stride, w, h);
+                copy_plane(frame, u, cstride, cw, h);
+                copy_plane(frame, v, cstride, cw, h);
+            }
+            Layout::SemiPlanar420 { swap } => {
+                let (y, chroma) = data.split_at(stride * h);
+                copy_plane(frame, y, stride, w, h);
+                let (u, v) = if swap { (1, 0) } else { (0, 1) };
+                for offset in [u, v] {
+                    for row in chroma.chunks(stride).take(ch) {
+                        frame.extend(row.iter().skip(offset).step_by(2).take(cw));
+                    }
+                }
+            }
+            Layout::Packed422 { y, u, v } => {
+                for row in data.chunks(stride).take(h) {
+                    frame.extend(row[..w * 2].chunks(2).map(|px| px[y % 2]));
+                }
+                for offset in [u, v] {
+                    for row in data.chunks(stride).take(h) {
+                        frame.extend(row[..cw * 4].chunks(4).map(|mp| mp[offset]));
+                    }
+                }
+            }
+        }
+
+        self.inner.write_all(b"FRAME\n")?;
+        self.inner.write_all(frame)
+    }
+
+    /// Flushes the underlying writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Returns the underlying writer
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Appends the visible part of each row of a plane
+fn copy_plane(dst: &mut Vec<u8>, src: &[u8], stride: usize, width: usize, height: usize) {
+    for row in src.chunks(stride).take(height) {
+        dst.extend_from_slice(&row[..width]);
+    }
+}
@@ -0,0 +1,358 @@
+//! Video inputs and outputs
+//!
+//! Devices may have several physical connectors, e.g. a capture card with composite, S-Video and
+//! HDMI inputs. Only one of them is routed to the capture or output queue at a time.
+
+use bitflags::bitflags;
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::control;
+use crate::format;
+use crate::util;
+use crate::v4l_sys::*;
+
+/// Input type
+#[allow(clippy::unreadable_literal)]
+#[rustfmt::skip]
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InputType {
+    /// RF demodulator, i.e. a TV tuner
+    Tuner   = 1,
+    /// Analog baseband or digital input, e.g. composite or HDMI
+    Camera  = 2,
+    /// Touch device
+    Touch   = 3,
+}
+
+impl TryFrom<u32> for InputType {
+    type Error = ();
+
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        match code {
+            1 => Ok(Self::Tuner),
+            2 => Ok(Self::Camera),
+            3 => Ok(Self::Touch),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for InputType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Output type
+#[allow(clippy::unreadable_literal)]
+#[rustfmt::skip]
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputType {
+    /// RF modulator
+    Modulator           = 1,
+    /// Analog baseband or digital output
+    Analog              = 2,
+    /// Analog output with a VGA overlay
+    AnalogVgaOverlay    = 3,
+}
+
+impl TryFrom<u32> for OutputType {
+    type Error = ();
+
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        match code {
+            1 => Ok(Self::Modulator),
+            2 => Ok(Self::Analog),
+            3 => Ok(Self::AnalogVgaOverlay),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for OutputType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Status: u32 {
+        /// Attached device is off
+        const NO_POWER      = 0x00000001;
+        /// No signal on the input
+        const NO_SIGNAL     = 0x00000002;
+        /// No color modulation detected
+        const NO_COLOR      = 0x00000004;
+        /// Image is flipped horizontally by the hardware
+        const HFLIP         = 0x00000010;
+        /// Image is flipped vertically by the hardware
+        const VFLIP         = 0x00000020;
+        /// No horizontal sync lock
+        const NO_H_LOCK     = 0x00000100;
+        /// Color killer is active
+        const COLOR_KILL    = 0x00000200;
+        /// No vertical sync lock
+        const NO_V_LOCK     = 0x00000400;
+        /// No standard format lock
+        const NO_STD_LOCK   = 0x00000800;
+        /// No synchronization lock (digital)
+        const NO_SYNC       = 0x00010000;
+        /// No equalizer lock (digital)
+        const NO_EQU        = 0x00020000;
+        /// Carrier recovery failed (digital)
+        const NO_CARRIER    = 0x00040000;
+        /// Macrovision copy protection detected
+        const MACROVISION   = 0x01000000;
+        /// Conditional access denied
+        const NO_ACCESS     = 0x02000000;
+        /// Video tape recorder time constants are in use
+        const VTR           = 0x04000000;
+    }
+}
+
+impl From<u32> for Status {
+    fn from(status: u32) -> Self {
+        Self::from_bits_truncate(status)
+    }
+}
+
+impl From<Status> for u32 {
+    fn from(status: Status) -> Self {
+        status.bits()
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl Status {
+    /// Returns whether a signal is present, i.e. whether the hardware reports no loss of power,
+    /// signal or lock
+    pub fn has_signal(&self) -> bool {
+        !self.intersects(
+            Status::NO_POWER
+                | Status::NO_SIGNAL
+                | Status::NO_H_LOCK
+                | Status::NO_V_LOCK
+                | Status::NO_SYNC
+                | Status::NO_CARRIER,
+        )
+    }
+}
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Capabilities: u32 {
+        /// DV timings can be set and queried
+        const DV_TIMINGS    = 0x00000002;
+        /// Analog video standards can be set and queried
+        const STD           = 0x00000004;
+        /// The native size can be set, see [`crate::selection::Target::NativeSize`]
+        const NATIVE_SIZE   = 0x00000008;
+    }
+}
+
+impl From<u32> for Capabilities {
+    fn from(caps: u32) -> Self {
+        Self::from_bits_truncate(caps)
+    }
+}
+
+impl From<Capabilities> for u32 {
+    fn from(caps: Capabilities) -> Self {
+        caps.bits()
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Standard: u64 {
+        const PAL_B         = 0x00000001;
+        const PAL_B1        = 0x00000002;
+        const PAL_G         = 0x00000004;
+        const PAL_H         = 0x00000008;
+        const PAL_I         = 0x00000010;
+        const PAL_D         = 0x00000020;
+        const PAL_D1        = 0x00000040;
+        const PAL_K         = 0x00000080;
+        const PAL_M         = 0x00000100;
+        const PAL_N         = 0x00000200;
+        const PAL_NC        = 0x00000400;
+        const PAL_60        = 0x00000800;
+        const NTSC_M        = 0x00001000;
+        const NTSC_M_JP     = 0x00002000;
+        const NTSC_443      = 0x00004000;
+        const NTSC_M_KR     = 0x00008000;
+        const SECAM_B       = 0x00010000;
+        const SECAM_D       = 0x00020000;
+        const SECAM_G       = 0x00040000;
+        const SECAM_H       = 0x00080000;
+        const SECAM_K       = 0x00100000;
+        const SECAM_K1      = 0x00200000;
+        const SECAM_L       = 0x00400000;
+        const SECAM_LC      = 0x00800000;
+        const ATSC_8_VSB    = 0x01000000;
+        const ATSC_16_VSB   = 0x02000000;
+
+        /// All PAL variants
+        const PAL = Self::PAL_B.bits | Self::PAL_B1.bits | Self::PAL_G.bits | Self::PAL_H.bits
+            | Self::PAL_I.bits | Self::PAL_D.bits | Self::PAL_D1.bits | Self::PAL_K.bits;
+        /// All NTSC variants
+        const NTSC = Self::NTSC_M.bits | Self::NTSC_M_JP.bits | Self::NTSC_M_KR.bits;
+        /// All SECAM variants
+        const SECAM = Self::SECAM_B.bits | Self::SECAM_D.bits | Self::SECAM_G.bits
+            | Self::SECAM_H.bits | Self::SECAM_K.bits | Self::SECAM_K1.bits | Self::SECAM_L.bits
+            | Self::SECAM_LC.bits;
+        /// All ATSC variants
+        const ATSC = Self::ATSC_8_VSB.bits | Self::ATSC_16_VSB.bits;
+    }
+}
+
+impl From<u64> for Standard {
+    fn from(std: u64) -> Self {
+        Self::from_bits_truncate(std)
+    }
+}
+
+impl From<Standard> for u64 {
+    fn from(std: Standard) -> Self {
+        std.bits()
+    }
+}
+
+impl fmt::Display for Standard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Video input, as returned by [`crate::v4l2::vidioc::VIDIOC_ENUMINPUT`]
+pub struct Input {
+    /// Index of the input, used for selecting it
+    pub index: u32,
+    /// Name of the input, e.g. the label of the connector
+    pub name: String,
+    /// Input type
+    pub typ: InputType,
+    /// Bitmask of the audio inputs associated with this input
+    pub audioset: u32,
+    /// Index of the tuner, only valid for [`InputType::Tuner`]
+    pub tuner: u32,
+    /// Supported analog video standards
+    pub std: Standard,
+    /// Signal status, only valid for the current input
+    pub status: Status,
+    /// Input capabilities
+    pub capabilities: Capabilities,
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "index          : {}", self.index)?;
+        writeln!(f, "name           : {}", self.name)?;
+        writeln!(f, "type           : {}", self.typ)?;
+        writeln!(f, "audioset       : {:#x}", self.audioset)?;
+        writeln!(f, "tuner          : {}", self.tuner)?;
+        writeln!(f, "std            : {}", self.std)?;
+        writeln!(f, "status         : {}", self.status)?;
+        writeln!(f, "capabilities   : {}", self.capabilities)?;
+        Ok(())
+    }
+}
+
+impl TryFrom<v4l2_input> for Input {
+    type Error = ();
+
+    fn try_from(input: v4l2_input) -> Result<Self, Self::Error> {
+        Ok(Self {
+            index: input.index,
+            name: util::string_lossy(&input.name),
+            typ: InputType::try_from(input.type_)?,
+            audioset: input.audioset,
+            tuner: input.tuner,
+            std: Standard::from(input.std),
+            status: Status::from(input.status),
+            capabilities: Capabilities::from(input.capabilities),
+        })
+    }
+}
+
+/// Video input along with what it offers, see [`crate::Device::input_state()`]
+#[derive(Debug)]
+pub struct InputState {
+    /// Input description, including the supported standards and capabilities
+    pub input: Input,
+    /// Formats of the capture queue
+    pub formats: Vec<format::Description>,
+    /// Controls
+    pub controls: Vec<control::Description>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Video output, as returned by [`crate::v4l2::vidioc::VIDIOC_ENUMOUTPUT`]
+pub struct Output {
+    /// Index of the output, used for selecting it
+    pub index: u32,
+    /// Name of the output, e.g. the label of the connector
+    pub name: String,
+    /// Output type
+    pub typ: OutputType,
+    /// Bitmask of the audio outputs associated with this output
+    pub audioset: u32,
+    /// Index of the modulator, only valid for [`OutputType::Modulator`]
+    pub modulator: u32,
+    /// Supported analog video standards
+    pub std: Standard,
+    /// Output capabilities
+    pub capabilities: Capabilities,
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "index          : {}", self.index)?;
+        writeln!(f, "name           : {}", self.name)?;
+        writeln!(f, "type           : {}", self.typ)?;
+        writeln!(f, "audioset       : {:#x}", self.audioset)?;
+        writeln!(f, "modulator      : {}", self.modulator)?;
+        writeln!(f, "std            : {}", self.std)?;
+        writeln!(f, "capabilities   : {}", self.capabilities)?;
+        Ok(())
+    }
+}
+
+impl TryFrom<v4l2_output> for Output {
+    type Error = ();
+
+    fn try_from(output: v4l2_output) -> Result<Self, Self::Error> {
+        Ok(Self {
+            index: output.index,
+            name: util::string_lossy(&output.name),
+            typ: OutputType::try_from(output.type_)?,
+            audioset: output.audioset,
+            modulator: output.modulator,
+            std: Standard::from(output.std),
+            capabilities: Capabilities::from(output.capabilities),
+        })
+    }
+}
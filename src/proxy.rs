@@ -0,0 +1,433 @@
+//! Serving devices over Unix domain sockets
+//!
+//! Privilege-separated camera daemons open devices on behalf of unprivileged clients. A
+//! [`Server`] passes the file descriptor of a device (and the DMABUF file descriptors of its
+//! buffers) over a Unix domain socket, so the client talks to the driver directly, without any
+//! copying or additional latency.
+//!
+//! When streaming through the server, the server owns the queue: it sends a notification for
+//! each captured frame and requeues the buffer once the client released it.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::os::unix::net::UnixListener;
+//! use std::thread;
+//! use v4l::buffer::Type;
+//! use v4l::io::mmap::Stream;
+//! use v4l::proxy::{Client, Server};
+//! use v4l::Device;
+//!
+//! let listener = UnixListener::bind("/tmp/camera.sock").expect("Failed to bind socket");
+//! thread::spawn(move || {
+//!     let (sock, _) = listener.accept().expect("Failed to accept client");
+//!     let server = Server::new(sock);
+//!     let dev = Device::new(0).expect("Failed to open device");
+//!     let mut stream = Stream::new(&dev, Type::VideoCapture).expect("Failed to create stream");
+//!     server.send_buffers(&stream).expect("Failed to send buffers");
+//!     server.serve(&mut stream).expect("Failed to serve frames");
+//! });
+//!
+//! let client = Client::connect("/tmp/camera.sock").expect("Failed to connect");
+//! let buffers = client.recv_buffers().expect("Failed to receive buffers");
+//! loop {
+//!     let (index, meta) = client.recv_frame().expect("Failed to receive frame");
+//!     let data = &buffers[index][..meta.bytesused as usize];
+//!     println!("frame {}: {} bytes", meta.sequence, data.len());
+//!     client.release(index).expect("Failed to release buffer");
+//! }
+//! ```
+
+use std::convert::TryFrom;
+use std::ops::Deref;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::{io, mem, ptr, slice};
+
+use crate::buffer::{Flags, Metadata};
+use crate::device::{Device, Handle};
+use crate::format::FieldOrder;
+use crate::io::mmap::Stream;
+use crate::io::traits::{CaptureStream, Stream as _};
+use crate::timestamp::Timestamp;
+use crate::v4l2;
+use crate::v4l_sys::time_t;
+
+const DEVICE: u32 = 1;
+const BUFFERS: u32 = 2;
+const BUFFER: u32 = 3;
+const FRAME: u32 = 4;
+const RELEASE: u32 = 5;
+
+/// Message exchanged over the socket, both ends run on the same machine
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct Message {
+    kind: u32,
+    index: u32,
+    length: u32,
+    bytesused: u32,
+    flags: u32,
+    field: u32,
+    sequence: u32,
+    reserved: u32,
+    sec: time_t,
+    usec: time_t,
+}
+
+impl Message {
+    fn new(kind: u32, index: usize) -> Self {
+        Message {
+            kind,
+            index: index as u32,
+            ..Default::default()
+        }
+    }
+}
+
+fn send(sock: &UnixStream, msg: &Message, fd: Option<BorrowedFd<'_>>) -> io::Result<()> {
+    unsafe {
+        let mut iov = libc::iovec {
+            iov_base: msg as *const _ as *mut std::os::raw::c_void,
+            iov_len: mem::size_of::<Message>(),
+        };
+        // u64 for cmsghdr alignment, large enough for a single descriptor
+        let mut control = [0u64; 4];
+        let mut hdr: libc::msghdr = mem::zeroed();
+        hdr.msg_iov = &mut iov;
+        hdr.msg_iovlen = 1;
+
+        if let Some(fd) = fd {
+            hdr.msg_control = control.as_mut_ptr() as *mut std::os::raw::c_void;
+            hdr.msg_controllen =
+                libc::CMSG_SPACE(mem::size_of::<std::os::raw::c_int>() as u32) as _;
+            let cmsg = libc::CMSG_FIRSTHDR(&hdr);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<std::os::raw::c_int>() as u32) as _;
+            ptr::write_unaligned(
+                libc::CMSG_DATA(cmsg) as *mut std::os::raw::c_int,
+                fd.as_raw_fd(),
+            );
+        }
+
+        match libc::sendmsg(sock.as_raw_fd(), &hdr, libc::MSG_NOSIGNAL) {
+            -1 => Err(io::Error::last_os_error()),
+            n if n as usize != mem::size_of::<Message>() => Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "message was truncated",
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn recv(sock: &UnixStream, kind: u32) -> io::Result<(Message, Option<OwnedFd>)> {
+    unsafe {
+        let mut msg = Message::default();
+        let mut iov = libc::iovec {
+            iov_base: &mut msg as *mut _ as *mut std::os::raw::c_void,
+            iov_len: mem::size_of::<Message>(),
+        };
+        let mut control = [0u64; 4];
+        let mut hdr: libc::msghdr = mem::zeroed();
+        hdr.msg_iov = &mut iov;
+        hdr.msg_iovlen = 1;
+        hdr.msg_control = control.as_mut_ptr() as *mut std::os::raw::c_void;
+        hdr.msg_controllen = mem::size_of_val(&control) as _;
+
+        match libc::recvmsg(sock.as_raw_fd(), &mut hdr, libc::MSG_CMSG_CLOEXEC) {
+            -1 => return Err(io::Error::last_os_error()),
+            0 => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed",
+                ))
+            }
+            n if n as usize != mem::size_of::<Message>() => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "message was truncated",
+                ))
+            }
+            _ => {}
+        }
+
+        // take ownership first, so the descriptor is closed in case of errors
+        let cmsg = libc::CMSG_FIRSTHDR(&hdr);
+        let fd = if !cmsg.is_null()
+            && (*cmsg).cmsg_level == libc::SOL_SOCKET
+            && (*cmsg).cmsg_type == libc::SCM_RIGHTS
+        {
+            Some(OwnedFd::from_raw_fd(ptr::read_unaligned(
+                libc::CMSG_DATA(cmsg) as *const std::os::raw::c_int,
+            )))
+        } else {
+            None
+        };
+
+        if msg.kind != kind {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected message {}, got {}", kind, msg.kind),
+            ));
+        }
+        Ok((msg, fd))
+    }
+}
+
+fn missing_fd() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "message lacks file descriptor")
+}
+
+/// Returns true if the peer hung up
+fn disconnected(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::UnexpectedEof | io::ErrorKind::BrokenPipe | io::ErrorKind::ConnectionReset
+    )
+}
+
+/// Serving end of the socket, usually the privileged daemon
+pub struct Server {
+    sock: UnixStream,
+}
+
+impl Server {
+    /// Returns a server talking to a connected client
+    ///
+    /// # Arguments
+    ///
+    /// * `sock` - Socket, e.g. accepted from a [`std::os::unix::net::UnixListener`]
+    pub fn new(sock: UnixStream) -> Self {
+        Server { sock }
+    }
+
+    /// Passes the device to the client
+    ///
+    /// Both ends share the open file afterwards, i.e. the format, controls and buffer queue
+    /// ownership.
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Device to pass
+    pub fn send_device(&self, dev: &Device) -> io::Result<()> {
+        let fd = unsafe { BorrowedFd::borrow_raw(dev.handle().fd()) };
+        send(&self.sock, &Message::new(DEVICE, 0), Some(fd))
+    }
+
+    /// Exports all buffers of a stream and passes them to the client
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - Stream whose buffers are exported
+    pub fn send_buffers(&self, stream: &Stream) -> io::Result<()> {
+        let mut msg = Message::new(BUFFERS, 0);
+        msg.length = stream.len() as u32;
+        send(&self.sock, &msg, None)?;
+
+        for index in 0..stream.len() {
+            let fd = stream.export(index)?;
+            let mut msg = Message::new(BUFFER, index);
+            msg.length = stream.buffer_len(index) as u32;
+            send(&self.sock, &msg, Some(fd.as_fd()))?;
+        }
+        Ok(())
+    }
+
+    /// Notifies the client about a captured frame
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the buffer holding the frame
+    /// * `meta` - Metadata of the frame
+    pub fn send_frame(&self, index: usize, meta: &Metadata) -> io::Result<()> {
+        let msg = Message {
+            bytesused: meta.bytesused,
+            flags: meta.flags.into(),
+            field: meta.field as u32,
+            sequence: meta.sequence,
+            sec: meta.timestamp.sec,
+            usec: meta.timestamp.usec,
+            ..Message::new(FRAME, index)
+        };
+        send(&self.sock, &msg, None)
+    }
+
+    /// Waits for the client to release a buffer and returns its index
+    pub fn recv_release(&self) -> io::Result<usize> {
+        let (msg, _) = recv(&self.sock, RELEASE)?;
+        Ok(msg.index as usize)
+    }
+
+    /// Streams frames to the client until it disconnects
+    ///
+    /// The buffers must have been sent through [`Server::send_buffers()`] before. Unless the
+    /// stream is active already, all buffers are queued and streaming is started. Each frame is
+    /// requeued as soon as the client released it, so slow clients cause frames to be dropped
+    /// rather than queued up. Releasing any other buffer than the one handed out last fails with
+    /// [`io::ErrorKind::InvalidData`]. Streaming is stopped before returning.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - Capture stream
+    pub fn serve(&self, stream: &mut Stream) -> io::Result<()> {
+        let result = (|| {
+            if !stream.is_active() {
+                stream.queue_all()?;
+                stream.start()?;
+            }
+
+            loop {
+                let index = CaptureStream::dequeue(stream)?;
+                let (_, meta) = stream.get_mut(index).unwrap();
+                self.send_frame(index, meta)?;
+
+                let released = self.recv_release()?;
+                if released != index {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "client released buffer {} while holding buffer {}",
+                            released, index
+                        ),
+                    ));
+                }
+                CaptureStream::queue(stream, index)?;
+            }
+        })();
+
+        stream.stop()?;
+        match result {
+            Err(e) if disconnected(&e) => Ok(()),
+            result => result,
+        }
+    }
+}
+
+/// Mapping of a buffer received from the server
+pub struct RemoteBuffer {
+    fd: OwnedFd,
+    ptr: *mut u8,
+    len: usize,
+}
+
+unsafe impl Send for RemoteBuffer {}
+unsafe impl Sync for RemoteBuffer {}
+
+impl RemoteBuffer {
+    fn map(fd: OwnedFd, len: usize) -> io::Result<Self> {
+        let ptr = unsafe {
+            v4l2::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                fd.as_raw_fd(),
+                0,
+            )?
+        };
+
+        Ok(RemoteBuffer {
+            fd,
+            ptr: ptr as *mut u8,
+            len,
+        })
+    }
+
+    /// Returns the DMABUF file descriptor, e.g. for importing it into a graphics API
+    pub fn fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl Deref for RemoteBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for RemoteBuffer {
+    fn drop(&mut self) {
+        let _ = unsafe { v4l2::munmap(self.ptr as *mut std::os::raw::c_void, self.len) };
+    }
+}
+
+/// Client end of the socket, usually the unprivileged application
+pub struct Client {
+    sock: UnixStream,
+}
+
+impl Client {
+    /// Connects to a server
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the socket
+    pub fn connect<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(UnixStream::connect(path)?))
+    }
+
+    /// Returns a client talking to a connected server
+    ///
+    /// # Arguments
+    ///
+    /// * `sock` - Socket
+    pub fn new(sock: UnixStream) -> Self {
+        Client { sock }
+    }
+
+    /// Receives a device passed through [`Server::send_device()`]
+    pub fn recv_device(&self) -> io::Result<Device> {
+        let (_, fd) = recv(&self.sock, DEVICE)?;
+        let fd = fd.ok_or_else(missing_fd)?;
+        Ok(Device::from_handle(Handle::new(fd.into_raw_fd())))
+    }
+
+    /// Receives and maps the buffers passed through [`Server::send_buffers()`]
+    ///
+    /// The buffers are indexed by their index in the stream of the server.
+    pub fn recv_buffers(&self) -> io::Result<Vec<RemoteBuffer>> {
+        let (msg, _) = recv(&self.sock, BUFFERS)?;
+        (0..msg.length)
+            .map(|index| {
+                let (msg, fd) = recv(&self.sock, BUFFER)?;
+                if msg.index != index {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "buffers out of order",
+                    ));
+                }
+                RemoteBuffer::map(fd.ok_or_else(missing_fd)?, msg.length as usize)
+            })
+            .collect()
+    }
+
+    /// Waits for the next frame and returns the index of its buffer along with its metadata
+    ///
+    /// The buffer must be released through [`Client::release()`] once the frame was processed.
+    pub fn recv_frame(&self) -> io::Result<(usize, Metadata)> {
+        let (msg, _) = recv(&self.sock, FRAME)?;
+        let meta = Metadata {
+            bytesused: msg.bytesused,
+            flags: Flags::from(msg.flags),
+            field: FieldOrder::try_from(msg.field).unwrap_or(FieldOrder::Any),
+            timestamp: Timestamp::new(msg.sec, msg.usec),
+            sequence: msg.sequence,
+            timecode: None,
+        };
+        Ok((msg.index as usize, meta))
+    }
+
+    /// Hands a buffer back to the server
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the buffer, as returned by [`Client::recv_frame()`]
+    pub fn release(&self, index: usize) -> io::Result<()> {
+        send(&self.sock, &Message::new(RELEASE, index), None)
+    }
+}
@@ -0,0 +1,217 @@
+use bitflags::bitflags;
+use std::fmt;
+
+use crate::v4l_sys::*;
+
+/// Selection target
+#[allow(clippy::unreadable_literal)]
+#[rustfmt::skip]
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// Current cropping area
+    Crop            = 0x0000,
+    /// Default cropping area
+    CropDefault     = 0x0001,
+    /// Cropping bounds
+    CropBounds      = 0x0002,
+    /// Native size of the device
+    NativeSize      = 0x0003,
+    /// Current composing area
+    Compose         = 0x0100,
+    /// Default composing area
+    ComposeDefault  = 0x0101,
+    /// Composing bounds
+    ComposeBounds   = 0x0102,
+    /// Current composing area plus all padding pixels modified by the hardware
+    ComposePadded   = 0x0103,
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    pub struct Flags: u32 {
+        /// The rectangle may grow, but not shrink
+        const GE            = 0x00000001;
+        /// The rectangle may shrink, but not grow
+        const LE            = 0x00000002;
+        /// Do not propagate the change to other configuration
+        const KEEP_CONFIG   = 0x00000004;
+    }
+}
+
+impl Default for Flags {
+    fn default() -> Self {
+        Flags::from(0)
+    }
+}
+
+impl From<u32> for Flags {
+    fn from(flags: u32) -> Self {
+        Self::from_bits_truncate(flags)
+    }
+}
+
+impl From<Flags> for u32 {
+    fn from(flags: Flags) -> Self {
+        flags.bits()
+    }
+}
+
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Rectangle, e.g. the cropping area of an image
+pub struct Rect {
+    /// Horizontal offset of the top left corner in pixels
+    pub left: i32,
+    /// Vertical offset of the top left corner in pixels
+    pub top: i32,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+}
+
+impl Rect {
+    /// Returns a rectangle
+    ///
+    /// # Arguments
+    ///
+    /// * `left` - Horizontal offset of the top left corner in pixels
+    /// * `top` - Vertical offset of the top left corner in pixels
+    /// * `width` - Width in pixels
+    /// * `height` - Height in pixels
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::selection::Rect;
+    /// let rect = Rect::new(0, 0, 640, 480);
+    /// ```
+    pub fn new(left: i32, top: i32, width: u32, height: u32) -> Self {
+        Rect {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the rectangle of a raw struct
+    ///
+    /// # Arguments
+    ///
+    /// * `raw` - Raw rectangle
+    pub fn from_raw(raw: v4l2_rect) -> Self {
+        Self::from(raw)
+    }
+
+    /// Returns the raw struct of the rectangle
+    pub fn as_raw(&self) -> v4l2_rect {
+        (*self).into()
+    }
+
+    /// Returns the largest rectangle of the given aspect ratio centered within this one
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Horizontal part of the aspect ratio, e.g. 16
+    /// * `height` - Vertical part of the aspect ratio, e.g. 9
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::selection::Rect;
+    /// let rect = Rect::new(0, 0, 640, 480).fit_aspect(16, 9);
+    /// assert_eq!(rect, Rect::new(0, 60, 640, 360));
+    /// ```
+    pub fn fit_aspect(&self, width: u32, height: u32) -> Rect {
+        if width == 0 || height == 0 {
+            return *self;
+        }
+
+        let (bw, bh) = (self.width as u64, self.height as u64);
+        let (w, h) = if bw * height as u64 > bh * width as u64 {
+            (bh * width as u64 / height as u64, bh)
+        } else {
+            (bw, bw * height as u64 / width as u64)
+        };
+        self.centered(w as u32, h as u32)
+    }
+
+    /// Returns the centered part of this rectangle magnified by a zoom factor
+    ///
+    /// Factors below 1 are treated as 1, i.e. the rectangle never grows.
+    ///
+    /// # Arguments
+    ///
+    /// * `factor` - Zoom factor, e.g. 2.0 to show half the width and height
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::selection::Rect;
+    /// let rect = Rect::new(0, 0, 640, 480).zoom(2.0);
+    /// assert_eq!(rect, Rect::new(160, 120, 320, 240));
+    /// ```
+    pub fn zoom(&self, factor: f64) -> Rect {
+        let factor = factor.max(1.0);
+        self.centered(
+            (self.width as f64 / factor).round() as u32,
+            (self.height as f64 / factor).round() as u32,
+        )
+    }
+
+    /// Returns a rectangle of the given size centered within this one
+    fn centered(&self, width: u32, height: u32) -> Rect {
+        Rect {
+            left: self.left + ((self.width - width) / 2) as i32,
+            top: self.top + ((self.height - height) / 2) as i32,
+            width,
+            height,
+        }
+    }
+}
+
+impl fmt::Display for Rect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}x{} @ ({}, {})",
+            self.width, self.height, self.left, self.top
+        )
+    }
+}
+
+impl From<v4l2_rect> for Rect {
+    fn from(rect: v4l2_rect) -> Self {
+        Self {
+            left: rect.left,
+            top: rect.top,
+            width: rect.width,
+            height: rect.height,
+        }
+    }
+}
+
+impl From<Rect> for v4l2_rect {
+    fn from(rect: Rect) -> Self {
+        Self {
+            left: rect.left,
+            top: rect.top,
+            width: rect.width,
+            height: rect.height,
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Full device state snapshots
+//!
+//! A snapshot captures everything there is to know about the current state of a device, similar to
+//! `v4l2-ctl --all`. It is meant for logging and bug reports; its [`fmt::Display`] output is human
+//! readable and it can be serialized when the `serde` feature is enabled.
+
+use std::fmt;
+use std::io;
+
+use crate::buffer;
+use crate::capability::Capabilities;
+use crate::control::{self, Description, Value};
+use crate::device::Device;
+use crate::format::Format;
+use crate::fraction::Fraction;
+use crate::port::{Input, Output as VideoOutput};
+use crate::selection::{Rect, Target};
+use crate::video::{Capture, Output};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// State of a single queue
+pub struct QueueState {
+    /// Current format
+    pub format: Format,
+    /// Current frame interval, if the driver supports streaming parameters
+    pub interval: Option<Fraction>,
+    /// Current cropping area, if the driver supports selections
+    pub crop: Option<Rect>,
+    /// Current composing area, if the driver supports selections
+    pub compose: Option<Rect>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// State of a single control
+pub struct ControlState {
+    /// Control description
+    pub description: Description,
+    /// Current value, unless the control cannot be read, e.g. buttons
+    pub value: Option<Value>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Snapshot of the state of a device, see [`Device::snapshot()`]
+///
+/// Parts of the state the device does not support are left empty.
+pub struct Snapshot {
+    /// Device capabilities
+    pub capabilities: Capabilities,
+    /// State of the video capture queue
+    pub video_capture: Option<QueueState>,
+    /// State of the video output queue
+    pub video_output: Option<QueueState>,
+    /// Video inputs
+    pub inputs: Vec<Input>,
+    /// Index of the current video input
+    pub input: Option<u32>,
+    /// Video outputs
+    pub outputs: Vec<VideoOutput>,
+    /// Index of the current video output
+    pub output: Option<u32>,
+    /// Controls along with their current values
+    pub controls: Vec<ControlState>,
+}
+
+impl Snapshot {
+    pub(crate) fn take(dev: &Device) -> io::Result<Self> {
+        let capture = Capture::format(dev).ok().map(|format| QueueState {
+            format,
+            interval: Capture::params(dev).ok().map(|params| params.interval),
+            crop: dev.selection(buffer::Type::VideoCapture, Target::Crop).ok(),
+            compose: dev
+                .selection(buffer::Type::VideoCapture, Target::Compose)
+                .ok(),
+        });
+        let output = Output::format(dev).ok().map(|format| QueueState {
+            format,
+            interval: Output::params(dev).ok().map(|params| params.interval),
+            crop: dev.selection(buffer::Type::VideoOutput, Target::Crop).ok(),
+            compose: dev
+                .selection(buffer::Type::VideoOutput, Target::Compose)
+                .ok(),
+        });
+
+        let controls = dev
+            .query_controls()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|description| {
+                let value = match description.typ {
+                    control::Type::Button | control::Type::CtrlClass => None,
+                    _ if description.flags.contains(control::Flags::WRITE_ONLY) => None,
+                    _ => dev.control(&description).ok().map(|ctrl| ctrl.value),
+                };
+                ControlState { description, value }
+            })
+            .collect();
+
+        Ok(Snapshot {
+            capabilities: dev.query_caps()?,
+            video_capture: capture,
+            video_output: output,
+            inputs: dev.enum_inputs()?,
+            input: dev.input().ok(),
+            outputs: dev.enum_outputs()?,
+            output: dev.output().ok(),
+            controls,
+        })
+    }
+}
+
+impl fmt::Display for QueueState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format)?;
+        if let Some(interval) = self.interval {
+            writeln!(f, "interval       : {}", interval)?;
+        }
+        for (name, rect) in [("crop", self.crop), ("compose", self.compose)] {
+            if let Some(rect) = rect {
+                writeln!(
+                    f,
+                    "{:<15}: {}x{}@({}, {})",
+                    name, rect.width, rect.height, rect.left, rect.top
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Capabilities:")?;
+        write!(f, "{}", self.capabilities)?;
+        if let Some(state) = &self.video_capture {
+            writeln!(f, "\nVideo capture:")?;
+            write!(f, "{}", state)?;
+        }
+        if let Some(state) = &self.video_output {
+            writeln!(f, "\nVideo output:")?;
+            write!(f, "{}", state)?;
+        }
+        for input in &self.inputs {
+            let current = if self.input == Some(input.index) {
+                " (current)"
+            } else {
+                ""
+            };
+            writeln!(f, "\nInput {}{}:", input.index, current)?;
+            write!(f, "{}", input)?;
+        }
+        for output in &self.outputs {
+            let current = if self.output == Some(output.index) {
+                " (current)"
+            } else {
+                ""
+            };
+            writeln!(f, "\nOutput {}{}:", output.index, current)?;
+            write!(f, "{}", output)?;
+        }
+        if !self.controls.is_empty() {
+            writeln!(f, "\nControls:")?;
+        }
+        for ctrl in &self.controls {
+            let desc = &ctrl.description;
+            write!(f, "{:>32} {:#010x} ({}): ", desc.name, desc.id, desc.typ)?;
+            match &ctrl.value {
+                Some(Value::Integer(value)) => write!(f, "value={}", value)?,
+                Some(Value::Boolean(value)) => write!(f, "value={}", value)?,
+                Some(Value::String(value)) => write!(f, "value={:?}", value)?,
+                Some(value) => write!(f, "value={:?}", value)?,
+                None => write!(f, "value=n/a")?,
+            }
+            writeln!(
+                f,
+                " min={} max={} step={} default={} flags={}",
+                desc.minimum, desc.maximum, desc.step, desc.default, desc.flags
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+//! Synchronization of frames captured by multiple devices
+//!
+//! Stereo and multi-view rigs capture the same scene with several devices. Even if the sensors are
+//! triggered simultaneously, their frames are dequeued independently and occasionally dropped by
+//! one of the drivers. The [`Synchronizer`] matches frames by their timestamps and yields sets of
+//! frames which were captured at (roughly) the same time.
+//!
+//! Matching requires the timestamps of all devices to be taken from the same clock, i.e. the
+//! buffers should be flagged with [`crate::buffer::Flags::TIMESTAMP_MONOTONIC`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use v4l::buffer::Type;
+//! use v4l::io::mmap::Stream;
+//! use v4l::io::traits::CaptureStream;
+//! use v4l::sync::Synchronizer;
+//! use v4l::Device;
+//!
+//! let left = Device::new(0).expect("Failed to open device");
+//! let right = Device::new(1).expect("Failed to open device");
+//! let mut streams = [
+//!     Stream::new(&left, Type::VideoCapture).expect("Failed to create stream"),
+//!     Stream::new(&right, Type::VideoCapture).expect("Failed to create stream"),
+//! ];
+//!
+//! // frames of a 30 fps rig are considered simultaneous if they are less than 5ms apart
+//! let mut sync = Synchronizer::new(streams.len(), Duration::from_millis(5));
+//! loop {
+//!     for (i, stream) in streams.iter_mut().enumerate() {
+//!         let (buf, meta) = stream.next().expect("Failed to capture frame");
+//!         if let Some(set) = sync.push(i, meta.timestamp, buf.to_vec()) {
+//!             println!("matched {} frames at {:?}", set.frames.len(), set.timestamp);
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::timestamp::Timestamp;
+
+/// How frames which cannot be matched are handled
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Unmatched frames are discarded, only complete sets are yielded
+    Drop,
+    /// Unmatched frames are yielded as incomplete sets, lacking the frames of other devices
+    Partial,
+}
+
+/// Frames of multiple devices captured at the same time
+#[derive(Debug, Clone)]
+pub struct FrameSet<T> {
+    /// Timestamp of the oldest frame within the set
+    pub timestamp: Duration,
+    /// Frames indexed by device, `None` for devices lacking a matching frame
+    pub frames: Vec<Option<T>>,
+}
+
+impl<T> FrameSet<T> {
+    /// Returns whether the set contains a frame of every device
+    pub fn is_complete(&self) -> bool {
+        self.frames.iter().all(Option::is_some)
+    }
+}
+
+/// Matcher aligning frames of multiple devices by timestamp
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use v4l::sync::Synchronizer;
+/// use v4l::Timestamp;
+///
+/// let mut sync = Synchronizer::new(2, Duration::from_millis(5));
+/// assert!(sync.push(0, Timestamp::new(1, 0), "a0").is_none());
+/// // the first frame of device 1 was lost, so "a0" is dropped
+/// assert!(sync.push(1, Timestamp::new(1, 33_000), "b1").is_none());
+/// let set = sync.push(0, Timestamp::new(1, 34_000), "a1").unwrap();
+/// assert_eq!(set.frames, [Some("a1"), Some("b1")]);
+/// assert_eq!(sync.dropped(), &[1, 0]);
+/// ```
+#[derive(Debug)]
+pub struct Synchronizer<T> {
+    tolerance: Duration,
+    policy: DropPolicy,
+    max_pending: usize,
+    queues: Vec<VecDeque<(Duration, T)>>,
+    dropped: Vec<u64>,
+    /// Sets waiting to be yielded
+    ready: VecDeque<FrameSet<T>>,
+}
+
+impl<T> Synchronizer<T> {
+    /// Returns a synchronizer dropping unmatched frames
+    ///
+    /// # Arguments
+    ///
+    /// * `devices` - Number of devices
+    /// * `tolerance` - Maximum difference between the timestamps of matching frames
+    pub fn new(devices: usize, tolerance: Duration) -> Self {
+        Synchronizer {
+            tolerance,
+            policy: DropPolicy::Drop,
+            max_pending: 8,
+            queues: (0..devices).map(|_| VecDeque::new()).collect(),
+            dropped: vec![0; devices],
+            ready: VecDeque::new(),
+        }
+    }
+
+    /// Sets how unmatched frames are handled
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Drop policy
+    pub fn set_policy(&mut self, policy: DropPolicy) {
+        self.policy = policy;
+    }
+
+    /// Sets the maximum number of frames kept per device while waiting for the other devices
+    ///
+    /// This bounds the memory use if a device stops delivering frames. The oldest frame is
+    /// considered unmatched once the limit is exceeded. Defaults to 8.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_pending` - Maximum number of pending frames per device
+    pub fn set_max_pending(&mut self, max_pending: usize) {
+        self.max_pending = max_pending.max(1);
+    }
+
+    /// Returns the number of frames which could not be matched, indexed by device
+    pub fn dropped(&self) -> &[u64] {
+        &self.dropped
+    }
+
+    /// Returns the next set of frames which is ready, if any
+    ///
+    /// A single frame may complete several sets, e.g. when incomplete sets are yielded. Only the
+    /// first one is returned by [`Synchronizer::push()`], the remaining ones are returned here.
+    pub fn pop(&mut self) -> Option<FrameSet<T>> {
+        self.ready.pop_front()
+    }
+
+    /// Adds a frame and returns the next set of frames, if any
+    ///
+    /// Timestamps of a single device must not decrease.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - Index of the device the frame was captured by
+    /// * `timestamp` - Capture timestamp, e.g. [`crate::buffer::Metadata::timestamp`]
+    /// * `frame` - Frame data
+    pub fn push(&mut self, device: usize, timestamp: Timestamp, frame: T) -> Option<FrameSet<T>> {
+        self.queues[device].push_back((Duration::from(timestamp), frame));
+        if self.queues[device].len() > self.max_pending {
+            self.discard(device);
+        }
+
+        while self.queues.iter().all(|queue| !queue.is_empty()) {
+            let heads = self.queues.iter().map(|queue| queue[0].0);
+            let (oldest, newest) = heads.fold((Duration::MAX, Duration::ZERO), |(min, max), ts| {
+                (min.min(ts), max.max(ts))
+            });
+
+            if newest - oldest <= self.tolerance {
+                let frames = self
+                    .queues
+                    .iter_mut()
+                    .map(|queue| queue.pop_front().map(|(_, frame)| frame))
+                    .collect();
+                self.ready.push_back(FrameSet {
+                    timestamp: oldest,
+                    frames,
+                });
+                continue;
+            }
+
+            // heads lagging behind cannot be matched anymore, since the frames of the other
+            // devices only get newer
+            for device in 0..self.queues.len() {
+                if newest - self.queues[device][0].0 > self.tolerance {
+                    self.discard(device);
+                }
+            }
+        }
+
+        self.ready.pop_front()
+    }
+
+    /// Drops the oldest pending frame of a device, or yields it as an incomplete set
+    fn discard(&mut self, device: usize) {
+        let (timestamp, frame) = match self.queues[device].pop_front() {
+            Some(head) => head,
+            None => return,
+        };
+
+        self.dropped[device] += 1;
+        if self.policy == DropPolicy::Partial {
+            let mut frames: Vec<Option<T>> = (0..self.queues.len()).map(|_| None).collect();
+            frames[device] = Some(frame);
+            self.ready.push_back(FrameSet { timestamp, frames });
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! Fixtures for testing against virtual drivers
+//!
+//! The kernel ships several drivers which emulate real hardware: vivid emulates capture and output
+//! devices, vim2m a memory-to-memory scaler and vicodec a hardware codec. They allow for testing
+//! all code paths end-to-end without any hardware, e.g. in CI. The fixtures in this module locate
+//! the device nodes of these drivers and try to load the kernel modules if none are present, which
+//! requires root privileges.
+//!
+//! Tests should be skipped rather than fail if a fixture is unavailable, see [`skip_unless()`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use v4l::testing;
+//! use v4l::video::Capture;
+//!
+//! let dev = match testing::skip_unless(testing::vivid_capture()) {
+//!     Some(dev) => dev,
+//!     None => return,
+//! };
+//! let format = dev.format().expect("Failed to get format");
+//! ```
+
+use std::io;
+use std::process::Command;
+
+use crate::capability::{Capabilities, Flags};
+use crate::context;
+use crate::device::Device;
+use crate::format::FourCC;
+use crate::video::{Capture, Output};
+
+/// Virtual driver
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Driver {
+    /// Virtual video test driver, emulating capture and output devices
+    Vivid,
+    /// Virtual memory-to-memory driver, emulating a scaler
+    Vim2m,
+    /// Virtual codec driver, emulating a stateful FWHT encoder and decoder
+    Vicodec,
+}
+
+impl Driver {
+    /// Returns the name of the driver, as reported in [`Capabilities::driver`]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Driver::Vivid => "vivid",
+            Driver::Vim2m => "vim2m",
+            Driver::Vicodec => "vicodec",
+        }
+    }
+}
+
+/// Loads the kernel module of a driver
+///
+/// This requires root privileges and the module being available for the running kernel.
+///
+/// # Arguments
+///
+/// * `driver` - Driver to load
+pub fn load(driver: Driver) -> io::Result<()> {
+    let status = Command::new("modprobe").arg(driver.name()).status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "modprobe {} failed: {}",
+            driver.name(),
+            status
+        )));
+    }
+
+    Ok(())
+}
+
+/// Returns the first device of a driver which matches a predicate
+///
+/// # Arguments
+///
+/// * `driver` - Driver of the device
+/// * `pred` - Predicate checking the capabilities of a candidate device
+pub fn find<F>(driver: Driver, pred: F) -> Option<Device>
+where
+    F: Fn(&Device, &Capabilities) -> bool,
+{
+    let mut nodes = context::enum_devices();
+    nodes.sort_by_key(|node| node.index());
+
+    nodes.into_iter().find_map(|node| {
+        let dev = Device::with_path(node.path()).ok()?;
+        let caps = dev.query_caps().ok()?;
+        if caps.driver == driver.name() && pred(&dev, &caps) {
+            Some(dev)
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns the first device of a driver which matches a predicate, loading the driver if needed
+///
+/// # Arguments
+///
+/// * `driver` - Driver of the device
+/// * `pred` - Predicate checking the capabilities of a candidate device
+pub fn find_or_load<F>(driver: Driver, pred: F) -> io::Result<Device>
+where
+    F: Fn(&Device, &Capabilities) -> bool,
+{
+    if let Some(dev) = find(driver, &pred) {
+        return Ok(dev);
+    }
+
+    load(driver)?;
+    find(driver, &pred).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no matching {} device found", driver.name()),
+        )
+    })
+}
+
+/// Returns a vivid video capture device
+pub fn vivid_capture() -> io::Result<Device> {
+    find_or_load(Driver::Vivid, |_, caps| {
+        caps.capabilities
+            .contains(Flags::VIDEO_CAPTURE | Flags::STREAMING)
+    })
+}
+
+/// Returns a vivid video output device
+pub fn vivid_output() -> io::Result<Device> {
+    find_or_load(Driver::Vivid, |_, caps| {
+        caps.capabilities
+            .contains(Flags::VIDEO_OUTPUT | Flags::STREAMING)
+    })
+}
+
+/// Returns a vim2m memory-to-memory device
+pub fn vim2m() -> io::Result<Device> {
+    find_or_load(Driver::Vim2m, |_, caps| {
+        caps.capabilities
+            .intersects(Flags::VIDEO_M2M | Flags::VIDEO_M2M_MPLANE)
+    })
+}
+
+/// Returns a vicodec encoder, which turns raw frames into FWHT frames
+pub fn vicodec_encoder() -> io::Result<Device> {
+    find_or_load(Driver::Vicodec, |dev, _| {
+        Capture::enum_formats(dev)
+            .map(|formats| {
+                formats
+                    .iter()
+                    .any(|desc| desc.fourcc == FourCC::new(b"FWHT"))
+            })
+            .unwrap_or(false)
+    })
+}
+
+/// Returns a vicodec decoder, which turns FWHT frames into raw frames
+pub fn vicodec_decoder() -> io::Result<Device> {
+    find_or_load(Driver::Vicodec, |dev, _| {
+        Output::enum_formats(dev)
+            .map(|formats| {
+                formats
+                    .iter()
+                    .any(|desc| desc.fourcc == FourCC::new(b"FWHT"))
+            })
+            .unwrap_or(false)
+    })
+}
+
+/// Returns the fixture or prints why the calling test is skipped
+///
+/// # Arguments
+///
+/// * `fixture` - Result of a fixture
+pub fn skip_unless<T>(fixture: io::Result<T>) -> Option<T> {
+    match fixture {
+        Ok(fixture) => Some(fixture),
+        Err(e) => {
+            eprintln!("skipping test, virtual device unavailable: {}", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use bitflags::bitflags;
+use std::convert::TryFrom;
+use std::{fmt, io};
+
+use crate::v4l_sys::*;
+
+/// Frame rate a timecode counts frames at
+#[allow(clippy::unreadable_literal)]
+#[rustfmt::skip]
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Type {
+    Fps24   = 1,
+    Fps25   = 2,
+    Fps30   = 3,
+    Fps50   = 4,
+    Fps60   = 5,
+}
+
+impl Type {
+    /// Returns the number of frames per second
+    pub fn fps(&self) -> u8 {
+        match self {
+            Type::Fps24 => 24,
+            Type::Fps25 => 25,
+            Type::Fps30 => 30,
+            Type::Fps50 => 50,
+            Type::Fps60 => 60,
+        }
+    }
+}
+
+impl TryFrom<u32> for Type {
+    type Error = ();
+
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        match code {
+            1 => Ok(Type::Fps24),
+            2 => Ok(Type::Fps25),
+            3 => Ok(Type::Fps30),
+            4 => Ok(Type::Fps50),
+            5 => Ok(Type::Fps60),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} fps", self.fps())
+    }
+}
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    pub struct Flags: u32 {
+        /// Frames are dropped to stay in sync with 29.97 and 59.94 fps video
+        const DROPFRAME             = 0x0001;
+        /// Color frame flag of the SMPTE 12M timecode
+        const COLORFRAME            = 0x0002;
+        /// Format of the user bits
+        const USERBITS_MASK         = 0x000c;
+        const USERBITS_USERDEFINED  = 0x0000;
+        const USERBITS_8BITCHARS    = 0x0008;
+    }
+}
+
+impl Default for Flags {
+    fn default() -> Self {
+        Flags::from(0)
+    }
+}
+
+impl From<u32> for Flags {
+    fn from(flags: u32) -> Self {
+        Self::from_bits_truncate(flags)
+    }
+}
+
+impl From<Flags> for u32 {
+    fn from(flags: Flags) -> Self {
+        flags.bits()
+    }
+}
+
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// SMPTE timecode carried by a buffer
+///
+/// Drivers only fill in the timecode of a captured buffer if
+/// [`crate::buffer::Flags::TIMECODE`] is set, which is reflected by
+/// [`crate::buffer::Metadata::timecode`].
+///
+/// # Example
+///
+/// ```
+/// use v4l::timecode::{Timecode, Type};
+///
+/// let tc = Timecode::new(Type::Fps25, 10, 0, 59, 24).expect("Invalid timecode");
+/// assert_eq!(tc.to_string(), "10:00:59:24");
+/// assert_eq!(tc.frame_count(), (10 * 3600 + 59) * 25 + 24);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Timecode {
+    /// Frame rate
+    pub typ: Type,
+    /// Timecode flags
+    pub flags: Flags,
+    /// Hours, 0 to 23
+    pub hours: u8,
+    /// Minutes, 0 to 59
+    pub minutes: u8,
+    /// Seconds, 0 to 59
+    pub seconds: u8,
+    /// Frames, 0 to the frame rate
+    pub frames: u8,
+    /// User bits, their format is given by [`Flags::USERBITS_MASK`]
+    pub userbits: [u8; 4],
+}
+
+impl Timecode {
+    /// Returns a timecode without flags or user bits
+    ///
+    /// # Arguments
+    ///
+    /// * `typ` - Frame rate
+    /// * `hours` - Hours, 0 to 23
+    /// * `minutes` - Minutes, 0 to 59
+    /// * `seconds` - Seconds, 0 to 59
+    /// * `frames` - Frames, less than the frame rate
+    pub fn new(typ: Type, hours: u8, minutes: u8, seconds: u8, frames: u8) -> io::Result<Self> {
+        if hours > 23 || minutes > 59 || seconds > 59 || frames >= typ.fps() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "invalid timecode {:02}:{:02}:{:02}:{:02} at {}",
+                    hours, minutes, seconds, frames, typ
+                ),
+            ));
+        }
+
+        Ok(Timecode {
+            typ,
+            flags: Flags::default(),
+            hours,
+            minutes,
+            seconds,
+            frames,
+            userbits: [0; 4],
+        })
+    }
+
+    /// Returns the number of frames since midnight, ignoring dropped frames
+    pub fn frame_count(&self) -> u32 {
+        let seconds = self.hours as u32 * 3600 + self.minutes as u32 * 60 + self.seconds as u32;
+        seconds * self.typ.fps() as u32 + self.frames as u32
+    }
+}
+
+impl fmt::Display for Timecode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // drop frame timecodes conventionally separate the frames with a semicolon
+        let sep = if self.flags.contains(Flags::DROPFRAME) {
+            ';'
+        } else {
+            ':'
+        };
+        write!(
+            f,
+            "{:02}:{:02}:{:02}{}{:02}",
+            self.hours, self.minutes, self.seconds, sep, self.frames
+        )
+    }
+}
+
+impl TryFrom<v4l2_timecode> for Timecode {
+    type Error = ();
+
+    fn try_from(tc: v4l2_timecode) -> Result<Self, Self::Error> {
+        Ok(Timecode {
+            typ: Type::try_from(tc.type_)?,
+            flags: Flags::from(tc.flags),
+            hours: tc.hours,
+            minutes: tc.minutes,
+            seconds: tc.seconds,
+            frames: tc.frames,
+            userbits: tc.userbits,
+        })
+    }
+}
+
+impl From<Timecode> for v4l2_timecode {
+    fn from(tc: Timecode) -> Self {
+        v4l2_timecode {
+            type_: tc.typ as u32,
+            flags: tc.flags.into(),
+            frames: tc.frames,
+            seconds: tc.seconds,
+            minutes: tc.minutes,
+            hours: tc.hours,
+            userbits: tc.userbits,
+        }
+    }
+}
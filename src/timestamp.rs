@@ -1,9 +1,14 @@
-use std::{fmt, time};
+use std::convert::TryFrom;
+use std::{fmt, io, mem, time};
 
 use crate::v4l_sys::*;
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 /// Timestamp consisting of a seconds and a microseconds component
+///
+/// Timestamps compare exactly, which allows for matching the capture buffers of
+/// memory-to-memory devices with the output buffers they were produced from, see
+/// [`crate::buffer::Metadata::is_timestamp_copied()`].
 pub struct Timestamp {
     pub sec: time_t,
     pub usec: time_t,
@@ -26,6 +31,97 @@ impl Timestamp {
     pub fn new(sec: time_t, usec: time_t) -> Self {
         Timestamp { sec, usec }
     }
+
+    /// Converts a timestamp of [`Clock::Monotonic`] to wall clock time
+    ///
+    /// The conversion uses the current offset between both clocks, so it is off by however much
+    /// the wall clock was adjusted since the timestamp was taken.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::{Duration, SystemTime};
+    /// use v4l::timestamp::Timestamp;
+    ///
+    /// // the monotonic clock starts at boot
+    /// let ts = Timestamp::new(1, 0);
+    /// let booted = ts.to_system_time().unwrap();
+    /// assert!(booted < SystemTime::now());
+    /// ```
+    pub fn to_system_time(&self) -> io::Result<time::SystemTime> {
+        let mut now: libc::timespec = unsafe { mem::zeroed() };
+        if unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let system_now = time::SystemTime::now();
+        let monotonic_now = time::Duration::new(now.tv_sec as u64, now.tv_nsec as u32);
+
+        let ts = time::Duration::from(*self);
+        Ok(if ts <= monotonic_now {
+            system_now - (monotonic_now - ts)
+        } else {
+            system_now + (ts - monotonic_now)
+        })
+    }
+}
+
+/// Clock a buffer timestamp was taken from, see [`crate::buffer::Metadata::clock()`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Clock {
+    /// Unknown clock, the timestamps cannot be correlated with other sources
+    Unknown,
+    /// `CLOCK_MONOTONIC`, as used by `clock_gettime()`, see [`Timestamp::to_system_time()`]
+    Monotonic,
+    /// Copied from the output buffer a capture buffer was produced from, e.g. by codecs
+    Copy,
+}
+
+impl TryFrom<u32> for Clock {
+    type Error = ();
+
+    /// Decodes the timestamp type of raw buffer flags
+    fn try_from(flags: u32) -> Result<Self, Self::Error> {
+        match flags & 0x0000e000 {
+            0x00000000 => Ok(Clock::Unknown),
+            0x00002000 => Ok(Clock::Monotonic),
+            0x00004000 => Ok(Clock::Copy),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Moment of a frame a buffer timestamp refers to
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// The last pixel of the frame was received
+    EndOfFrame,
+    /// The exposure of the frame started
+    StartOfExposure,
+}
+
+impl TryFrom<u32> for Source {
+    type Error = ();
+
+    /// Decodes the timestamp source of raw buffer flags
+    fn try_from(flags: u32) -> Result<Self, Self::Error> {
+        match flags & 0x00070000 {
+            0x00000000 => Ok(Source::EndOfFrame),
+            0x00010000 => Ok(Source::StartOfExposure),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
 }
 
 impl fmt::Display for Timestamp {
@@ -0,0 +1,316 @@
+//! Devices tagged with their kind
+//!
+//! A plain [`crate::Device`] implements both the [`Capture`] and [`Output`] traits, no matter
+//! what the hardware supports. The wrappers in this module check the capabilities once when the
+//! device is opened and only implement the traits which apply, so e.g. setting the output format
+//! of a webcam fails to compile instead of failing with EINVAL at runtime.
+
+use bitflags::bitflags;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use std::{fmt, io};
+
+use crate::capability::{Capabilities, Flags};
+use crate::control::{Control, Description};
+use crate::device::Handle;
+use crate::format::{Description as FormatDescription, Format, FourCC};
+use crate::frameinterval::FrameInterval;
+use crate::framesize::FrameSize;
+use crate::v4l2;
+use crate::video::capture::Parameters as CaptureParameters;
+use crate::video::output::Parameters as OutputParameters;
+use crate::video::{Capture, Output};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Kind of a device
+pub trait Kind: sealed::Sealed {
+    /// Capabilities of which a device must have any to be of this kind
+    ///
+    /// Both the single- and the multi-planar variants qualify, like for
+    /// [`crate::buffer::Type::capabilities()`]. The formats of multi-planar devices are accessible
+    /// through [`Device::inner()`].
+    const CAPABILITIES: Flags;
+}
+
+/// Video capture device, e.g. a webcam
+pub struct VideoCapture;
+
+/// Video output device, e.g. a v4l2loopback node
+pub struct VideoOutput;
+
+/// Memory-to-memory device, e.g. a hardware codec or scaler
+///
+/// Such devices have both a capture and an output queue, so they implement both traits. Use
+/// fully qualified syntax (e.g. `Capture::format(&dev)`) to call their methods.
+pub struct M2m;
+
+impl sealed::Sealed for VideoCapture {}
+impl sealed::Sealed for VideoOutput {}
+impl sealed::Sealed for M2m {}
+
+impl Kind for VideoCapture {
+    const CAPABILITIES: Flags =
+        Flags::from_bits_truncate(Flags::VIDEO_CAPTURE.bits() | Flags::VIDEO_CAPTURE_MPLANE.bits());
+}
+
+impl Kind for VideoOutput {
+    const CAPABILITIES: Flags =
+        Flags::from_bits_truncate(Flags::VIDEO_OUTPUT.bits() | Flags::VIDEO_OUTPUT_MPLANE.bits());
+}
+
+impl Kind for M2m {
+    const CAPABILITIES: Flags =
+        Flags::from_bits_truncate(Flags::VIDEO_M2M.bits() | Flags::VIDEO_M2M_MPLANE.bits());
+}
+
+/// Device of a specific kind
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::typed::{Device, VideoCapture};
+/// use v4l::video::Capture;
+///
+/// let dev = Device::<VideoCapture>::new(0).expect("Failed to open capture device");
+/// let format = dev.format().expect("Failed to get format");
+/// ```
+pub struct Device<K: Kind> {
+    dev: crate::Device,
+    _kind: PhantomData<K>,
+}
+
+impl<K: Kind> Device<K> {
+    /// Returns a device by index
+    ///
+    /// Fails if the device is not of the requested kind.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index (0: first, 1: second, ..)
+    pub fn new(index: usize) -> io::Result<Self> {
+        Self::with_device(crate::Device::new(index)?)
+    }
+
+    /// Returns a device by path
+    ///
+    /// Fails if the device is not of the requested kind.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path (e.g. "/dev/video0")
+    pub fn with_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::with_device(crate::Device::with_path(path)?)
+    }
+
+    /// Tags an untyped device
+    ///
+    /// Fails if the device is not of the requested kind.
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Untyped device
+    pub fn with_device(dev: crate::Device) -> io::Result<Self> {
+        let caps = dev.query_caps()?;
+        if !caps.capabilities.intersects(K::CAPABILITIES) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "device lacks the required capabilities: {}",
+                    K::CAPABILITIES
+                ),
+            ));
+        }
+
+        Ok(Device {
+            dev,
+            _kind: PhantomData,
+        })
+    }
+
+    /// Returns the untyped device
+    ///
+    /// This gives access to all APIs again, regardless of whether they apply.
+    pub fn inner(&self) -> &crate::Device {
+        &self.dev
+    }
+
+    /// Returns the untyped device, consuming the wrapper
+    pub fn into_inner(self) -> crate::Device {
+        self.dev
+    }
+
+    /// Returns the raw device handle
+    pub fn handle(&self) -> Arc<Handle> {
+        self.dev.handle()
+    }
+
+    /// Returns video4linux framework defined information such as card, driver, etc.
+    pub fn query_caps(&self) -> io::Result<Capabilities> {
+        self.dev.query_caps()
+    }
+
+    /// Returns the supported controls for a device such as gain, focus, white balance, etc.
+    pub fn query_controls(&self) -> io::Result<Vec<Description>> {
+        self.dev.query_controls()
+    }
+
+    /// Returns the current control value from its [`Description`]
+    pub fn control(&self, desc: &Description) -> io::Result<Control> {
+        self.dev.control(desc)
+    }
+
+    /// Modifies the control value
+    pub fn set_control(&self, ctrl: Control) -> io::Result<()> {
+        self.dev.set_control(ctrl)
+    }
+
+    /// Modifies the control values atomically
+    pub fn set_controls<C: AsRef<[Control]>>(&self, ctrls: C) -> io::Result<()> {
+        self.dev.set_controls(ctrls)
+    }
+}
+
+bitflags! {
+    /// Queues of a [`M2m`] device which can be serviced, see [`Device::wait()`]
+    pub struct Ready: u32 {
+        /// A processed buffer can be dequeued from the capture queue
+        const CAPTURE   = 0x01;
+        /// A consumed buffer can be dequeued from the output queue, i.e. it is free for new data
+        const OUTPUT    = 0x02;
+        /// An event, e.g. a source change, can be dequeued
+        const EVENT     = 0x04;
+        /// A queue is not streaming or has no buffers queued
+        const ERROR     = 0x08;
+    }
+}
+
+impl From<u32> for Ready {
+    fn from(flags: u32) -> Self {
+        Self::from_bits_truncate(flags)
+    }
+}
+
+impl From<Ready> for u32 {
+    fn from(flags: Ready) -> Self {
+        flags.bits()
+    }
+}
+
+impl fmt::Display for Ready {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl Device<M2m> {
+    /// Waits until the capture or the output queue can be serviced
+    ///
+    /// Both directions are polled at once, so a single thread can feed and drain the device
+    /// without busy looping. An empty set is returned if the timeout expired.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Maximum duration to wait for
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use v4l::typed::{Device, M2m, Ready};
+    ///
+    /// let dev = Device::<M2m>::new(0).expect("Failed to open m2m device");
+    /// let ready = dev.wait(Duration::from_millis(100)).expect("Failed to wait");
+    /// if ready.contains(Ready::OUTPUT) {
+    ///     // dequeue the consumed output buffer and queue the next input frame
+    /// }
+    /// if ready.contains(Ready::CAPTURE) {
+    ///     // dequeue the processed frame
+    /// }
+    /// ```
+    pub fn wait(&self, timeout: Duration) -> io::Result<Ready> {
+        let mut fds = [libc::pollfd {
+            fd: self.handle().fd(),
+            events: libc::POLLIN | libc::POLLOUT | libc::POLLPRI,
+            revents: 0,
+        }];
+        v4l2::ppoll(&mut fds, Some(timeout), None)?;
+
+        let revents = fds[0].revents;
+        let mut ready = Ready::empty();
+        for (event, flag) in [
+            (libc::POLLIN, Ready::CAPTURE),
+            (libc::POLLOUT, Ready::OUTPUT),
+            (libc::POLLPRI, Ready::EVENT),
+            (libc::POLLERR, Ready::ERROR),
+        ] {
+            if revents & event != 0 {
+                ready |= flag;
+            }
+        }
+        Ok(ready)
+    }
+}
+
+impl<K: Kind> AsRef<crate::Device> for Device<K> {
+    fn as_ref(&self) -> &crate::Device {
+        &self.dev
+    }
+}
+
+macro_rules! forward {
+    ($trait:ident, $params:ty) => {
+        fn enum_frameintervals(
+            &self,
+            fourcc: FourCC,
+            width: u32,
+            height: u32,
+        ) -> io::Result<Vec<FrameInterval>> {
+            $trait::enum_frameintervals(&self.dev, fourcc, width, height)
+        }
+
+        fn enum_framesizes(&self, fourcc: FourCC) -> io::Result<Vec<FrameSize>> {
+            $trait::enum_framesizes(&self.dev, fourcc)
+        }
+
+        fn enum_formats(&self) -> io::Result<Vec<FormatDescription>> {
+            $trait::enum_formats(&self.dev)
+        }
+
+        fn format(&self) -> io::Result<Format> {
+            $trait::format(&self.dev)
+        }
+
+        fn set_format(&self, fmt: &Format) -> io::Result<Format> {
+            $trait::set_format(&self.dev, fmt)
+        }
+
+        fn params(&self) -> io::Result<$params> {
+            $trait::params(&self.dev)
+        }
+
+        fn set_params(&self, params: &$params) -> io::Result<$params> {
+            $trait::set_params(&self.dev, params)
+        }
+    };
+}
+
+impl Capture for Device<VideoCapture> {
+    forward!(Capture, CaptureParameters);
+}
+
+impl Output for Device<VideoOutput> {
+    forward!(Output, OutputParameters);
+}
+
+impl Capture for Device<M2m> {
+    forward!(Capture, CaptureParameters);
+}
+
+impl Output for Device<M2m> {
+    forward!(Output, OutputParameters);
+}
@@ -0,0 +1,22 @@
+/// Returns the bytes of a fixed size C string buffer up to the first nul terminator
+///
+/// Drivers are expected to nul terminate strings, but the whole buffer is returned if they don't.
+pub(crate) fn until_nul(bytes: &[u8]) -> &[u8] {
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    &bytes[..len]
+}
+
+/// Converts a fixed size C string buffer to a string, replacing invalid UTF-8 sequences
+pub(crate) fn string_lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(until_nul(bytes)).into_owned()
+}
+
+/// Returns the bytes as OS string, without any conversion
+pub(crate) fn os_str(bytes: &[u8]) -> &std::ffi::OsStr {
+    std::os::unix::ffi::OsStrExt::from_bytes(bytes)
+}
+
+/// Reinterprets a C char buffer as bytes
+pub(crate) fn c_chars(chars: &[std::os::raw::c_char]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(chars.as_ptr() as *const u8, chars.len()) }
+}
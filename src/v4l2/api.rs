@@ -1,34 +1,52 @@
-use std::ffi::CString;
-use std::os::unix::ffi::OsStrExt;
-use std::{io, path::Path};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+use std::{io, path::Path, thread};
 
+use crate::v4l2::backend;
 use crate::v4l2::vidioc;
 
 #[cfg(feature = "v4l-sys")]
-mod detail {
+pub(crate) mod detail {
     use crate::v4l2::vidioc;
     use crate::v4l_sys::*;
     use std::convert::TryInto;
+    use std::io;
 
-    pub unsafe fn open(path: *const std::os::raw::c_char, flags: i32) -> std::os::raw::c_int {
-        v4l2_open(path, flags)
+    fn check(ret: std::os::raw::c_int) -> io::Result<std::os::raw::c_int> {
+        if ret == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret)
+        }
     }
-    pub unsafe fn close(fd: std::os::raw::c_int) -> std::os::raw::c_int {
-        v4l2_close(fd)
+
+    pub unsafe fn open(
+        path: *const std::os::raw::c_char,
+        flags: i32,
+    ) -> io::Result<std::os::raw::c_int> {
+        check(v4l2_open(path, flags))
+    }
+    pub unsafe fn close(fd: std::os::raw::c_int) -> io::Result<()> {
+        check(v4l2_close(fd)).map(drop)
+    }
+    pub unsafe fn dup(fd: std::os::raw::c_int) -> io::Result<std::os::raw::c_int> {
+        // libv4l keeps per-descriptor state, so it must be told about the duplicate
+        check(v4l2_dup(fd))
     }
     pub unsafe fn ioctl(
         fd: std::os::raw::c_int,
         request: vidioc::_IOC_TYPE,
         argp: *mut std::os::raw::c_void,
-    ) -> std::os::raw::c_int {
+    ) -> io::Result<()> {
         // libv4l expects `request` to be a u64, but this is not guaranteed on all platforms.
         // For the default CI platform (x86_64) clippy will complain about a useless conversion.
         #![allow(clippy::useless_conversion)]
-        v4l2_ioctl(
+        check(v4l2_ioctl(
             fd,
             request.try_into().expect("vidioc::_IOC_TYPE -> u64 failed"),
             argp,
-        )
+        ))
+        .map(drop)
     }
     pub unsafe fn mmap(
         start: *mut std::os::raw::c_void,
@@ -37,39 +55,63 @@ mod detail {
         flags: std::os::raw::c_int,
         fd: std::os::raw::c_int,
         offset: libc::off_t,
-    ) -> *mut std::os::raw::c_void {
+    ) -> io::Result<*mut std::os::raw::c_void> {
         // libv4l expects `request` to be a u64, but this is not guaranteed on all platforms.
         // For the default CI platform (x86_64) clippy will complain about a useless conversion.
         #![allow(clippy::useless_conversion)]
-        v4l2_mmap(
+        let ret = v4l2_mmap(
             start,
             length.try_into().expect("usize -> c size_t failed"),
             prot,
             flags,
             fd,
             offset as i64,
-        )
+        );
+        if ret as usize == std::usize::MAX {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret)
+        }
     }
-    pub unsafe fn munmap(start: *mut std::os::raw::c_void, length: usize) -> std::os::raw::c_int {
-        v4l2_munmap(start, length.try_into().expect("usize -> c size_t failed"))
+    pub unsafe fn munmap(start: *mut std::os::raw::c_void, length: usize) -> io::Result<()> {
+        check(v4l2_munmap(
+            start,
+            length.try_into().expect("usize -> c size_t failed"),
+        ))
+        .map(drop)
     }
 }
 
-#[cfg(feature = "v4l2-sys")]
-mod detail {
+#[cfg(all(feature = "v4l2-sys", not(feature = "rustix")))]
+pub(crate) mod detail {
     use crate::v4l2::vidioc;
+    use std::io;
+
+    fn check(ret: std::os::raw::c_int) -> io::Result<std::os::raw::c_int> {
+        if ret == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret)
+        }
+    }
 
-    pub unsafe fn open(path: *const std::os::raw::c_char, flags: i32) -> std::os::raw::c_int {
-        libc::open(path, flags)
+    pub unsafe fn open(
+        path: *const std::os::raw::c_char,
+        flags: i32,
+    ) -> io::Result<std::os::raw::c_int> {
+        check(libc::open(path, flags))
+    }
+    pub unsafe fn close(fd: std::os::raw::c_int) -> io::Result<()> {
+        check(libc::close(fd)).map(drop)
     }
-    pub unsafe fn close(fd: std::os::raw::c_int) -> std::os::raw::c_int {
-        libc::close(fd)
+    pub unsafe fn dup(fd: std::os::raw::c_int) -> io::Result<std::os::raw::c_int> {
+        check(libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0))
     }
     pub unsafe fn ioctl(
         fd: std::os::raw::c_int,
         request: vidioc::_IOC_TYPE,
         argp: *mut std::os::raw::c_void,
-    ) -> std::os::raw::c_int {
+    ) -> io::Result<()> {
         /*
          * It turns out the libc crate (and libc itself!) defines ioctl() with
          * different, incompatible argument types on different platforms. To
@@ -77,7 +119,68 @@ mod detail {
          * instead as a drop-in replacement. Details:
          * https://github.com/rust-lang/libc/issues/1036
          */
-        libc::syscall(libc::SYS_ioctl, fd, request, argp) as std::os::raw::c_int
+        check(libc::syscall(libc::SYS_ioctl, fd, request, argp) as std::os::raw::c_int).map(drop)
+    }
+    pub unsafe fn mmap(
+        start: *mut std::os::raw::c_void,
+        length: usize,
+        prot: std::os::raw::c_int,
+        flags: std::os::raw::c_int,
+        fd: std::os::raw::c_int,
+        offset: libc::off_t,
+    ) -> io::Result<*mut std::os::raw::c_void> {
+        let ret = libc::mmap(start, length, prot, flags, fd, offset);
+        if ret == libc::MAP_FAILED {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret)
+        }
+    }
+    pub unsafe fn munmap(start: *mut std::os::raw::c_void, length: usize) -> io::Result<()> {
+        check(libc::munmap(start, length)).map(drop)
+    }
+}
+
+#[cfg(all(feature = "v4l2-sys", feature = "rustix"))]
+pub(crate) mod detail {
+    use crate::v4l2::vidioc;
+    use rustix::fd::{BorrowedFd, IntoRawFd};
+    use rustix::fs::{Mode, OFlags};
+    use rustix::mm::{MapFlags, ProtFlags};
+    use std::ffi::CStr;
+    use std::io;
+
+    pub unsafe fn open(
+        path: *const std::os::raw::c_char,
+        flags: i32,
+    ) -> io::Result<std::os::raw::c_int> {
+        let fd = rustix::fs::open(
+            CStr::from_ptr(path),
+            OFlags::from_bits_retain(flags as u32),
+            Mode::empty(),
+        )?;
+        Ok(fd.into_raw_fd())
+    }
+    pub unsafe fn close(fd: std::os::raw::c_int) -> io::Result<()> {
+        // close() errors are not reported by rustix since the descriptor is released regardless
+        rustix::io::close(fd);
+        Ok(())
+    }
+    pub unsafe fn dup(fd: std::os::raw::c_int) -> io::Result<std::os::raw::c_int> {
+        let fd = rustix::io::fcntl_dupfd_cloexec(BorrowedFd::borrow_raw(fd), 0)?;
+        Ok(fd.into_raw_fd())
+    }
+    pub unsafe fn ioctl(
+        fd: std::os::raw::c_int,
+        request: vidioc::_IOC_TYPE,
+        argp: *mut std::os::raw::c_void,
+    ) -> io::Result<()> {
+        // rustix only takes opcodes known at compile time, while requests are passed at runtime
+        if libc::syscall(libc::SYS_ioctl, fd, request, argp) == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
     }
     pub unsafe fn mmap(
         start: *mut std::os::raw::c_void,
@@ -86,11 +189,18 @@ mod detail {
         flags: std::os::raw::c_int,
         fd: std::os::raw::c_int,
         offset: libc::off_t,
-    ) -> *mut std::os::raw::c_void {
-        libc::mmap(start, length, prot, flags, fd, offset)
+    ) -> io::Result<*mut std::os::raw::c_void> {
+        Ok(rustix::mm::mmap(
+            start,
+            length,
+            ProtFlags::from_bits_retain(prot as u32),
+            MapFlags::from_bits_retain(flags as u32),
+            BorrowedFd::borrow_raw(fd),
+            offset as u64,
+        )?)
     }
-    pub unsafe fn munmap(start: *mut std::os::raw::c_void, length: usize) -> std::os::raw::c_int {
-        libc::munmap(start, length)
+    pub unsafe fn munmap(start: *mut std::os::raw::c_void, length: usize) -> io::Result<()> {
+        Ok(rustix::mm::munmap(start, length)?)
     }
 }
 
@@ -114,18 +224,7 @@ mod detail {
 /// let fd = v4l2::open("/dev/video0", libc::O_RDWR);
 /// ```
 pub fn open<P: AsRef<Path>>(path: P, flags: i32) -> io::Result<std::os::raw::c_int> {
-    let fd: std::os::raw::c_int;
-    let c_path = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
-
-    unsafe {
-        fd = detail::open(c_path.as_ptr(), flags);
-    }
-
-    if fd == -1 {
-        Err(io::Error::last_os_error())
-    } else {
-        Ok(fd)
-    }
+    backend::current().open(path.as_ref(), flags)
 }
 
 /// A convenience wrapper around v4l2_close.
@@ -149,21 +248,151 @@ pub fn open<P: AsRef<Path>>(path: P, flags: i32) -> io::Result<std::os::raw::c_i
 /// }
 /// ```
 pub fn close(fd: std::os::raw::c_int) -> io::Result<()> {
-    let ret: std::os::raw::c_int;
-    unsafe {
-        ret = detail::close(fd);
+    backend::current().close(fd)
+}
+
+/// A convenience wrapper around v4l2_dup.
+///
+/// The duplicate refers to the same open file description, i.e. it shares the device state (such
+/// as the format and buffer queue ownership) with the original descriptor. It is created with the
+/// close-on-exec flag set.
+///
+/// In case of errors, the last OS error will be reported, aka errno on Linux.
+///
+/// # Arguments
+///
+/// * `fd` - File descriptor of a previously opened device
+///
+/// # Example
+///
+/// ```
+/// extern crate v4l;
+///
+/// use v4l::v4l2;
+///
+/// let fd = v4l2::open("/dev/video0", libc::O_RDWR);
+/// if let Ok(fd) = fd {
+///     let dup = v4l2::dup(fd).unwrap();
+///     v4l2::close(fd).unwrap();
+///     v4l2::close(dup).unwrap();
+/// }
+/// ```
+pub fn dup(fd: std::os::raw::c_int) -> io::Result<std::os::raw::c_int> {
+    backend::current().dup(fd)
+}
+
+/// A convenience wrapper around read.
+///
+/// Returns the number of bytes read.
+/// In case of errors, the last OS error will be reported, aka errno on Linux.
+///
+/// # Arguments
+///
+/// * `fd` - File descriptor of a previously opened device
+/// * `buf` - Buffer to read into
+pub fn read(fd: std::os::raw::c_int, buf: &mut [u8]) -> io::Result<usize> {
+    backend::current().read(fd, buf)
+}
+
+/// A convenience wrapper around write.
+///
+/// Returns the number of bytes written.
+/// In case of errors, the last OS error will be reported, aka errno on Linux.
+///
+/// # Arguments
+///
+/// * `fd` - File descriptor of a previously opened device
+/// * `buf` - Data to write
+pub fn write(fd: std::os::raw::c_int, buf: &[u8]) -> io::Result<usize> {
+    backend::current().write(fd, buf)
+}
+
+/// A convenience wrapper around flock.
+///
+/// In case of errors, the last OS error will be reported, aka errno on Linux.
+///
+/// # Arguments
+///
+/// * `fd` - File descriptor of a previously opened device
+/// * `op` - Lock operation, e.g. `libc::LOCK_EX | libc::LOCK_NB`
+pub fn flock(fd: std::os::raw::c_int, op: i32) -> io::Result<()> {
+    backend::current().flock(fd, op)
+}
+
+/// Policy for retrying ioctls which failed temporarily, see [`set_retry_policy()`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retry ioctls interrupted by a signal (`EINTR`)
+    pub interrupted: bool,
+    /// Number of times to retry dequeuing buffers and events from non-blocking descriptors which
+    /// have none ready (`EAGAIN`)
+    pub would_block: u32,
+    /// Pause before each retry on `EAGAIN`
+    pub would_block_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            interrupted: true,
+            would_block: 0,
+            would_block_delay: Duration::from_millis(1),
+        }
     }
+}
 
-    if ret == -1 {
-        Err(io::Error::last_os_error())
-    } else {
-        Ok(())
+static RETRY_INTERRUPTED: AtomicBool = AtomicBool::new(true);
+static RETRY_WOULD_BLOCK: AtomicU32 = AtomicU32::new(0);
+static RETRY_WOULD_BLOCK_DELAY_US: AtomicU64 = AtomicU64::new(1000);
+
+/// Returns the active retry policy
+pub fn retry_policy() -> RetryPolicy {
+    RetryPolicy {
+        interrupted: RETRY_INTERRUPTED.load(Ordering::Relaxed),
+        would_block: RETRY_WOULD_BLOCK.load(Ordering::Relaxed),
+        would_block_delay: Duration::from_micros(
+            RETRY_WOULD_BLOCK_DELAY_US.load(Ordering::Relaxed),
+        ),
     }
 }
 
+/// Sets the retry policy for all subsequent ioctls of the process
+///
+/// By default, ioctls interrupted by a signal are restarted transparently, so applications using
+/// signals (e.g. timers or child reaping) do not see spurious `EINTR` errors. Retrying on `EAGAIN`
+/// is disabled by default and only applies to `VIDIOC_DQBUF` and `VIDIOC_DQEVENT`.
+///
+/// # Arguments
+///
+/// * `policy` - Retry policy
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use v4l::v4l2::{self, RetryPolicy};
+///
+/// assert!(v4l2::retry_policy().interrupted);
+/// v4l2::set_retry_policy(RetryPolicy {
+///     would_block: 3,
+///     would_block_delay: Duration::from_millis(2),
+///     ..RetryPolicy::default()
+/// });
+/// assert_eq!(v4l2::retry_policy().would_block, 3);
+/// ```
+pub fn set_retry_policy(policy: RetryPolicy) {
+    RETRY_INTERRUPTED.store(policy.interrupted, Ordering::Relaxed);
+    RETRY_WOULD_BLOCK.store(policy.would_block, Ordering::Relaxed);
+    RETRY_WOULD_BLOCK_DELAY_US.store(
+        policy.would_block_delay.as_micros() as u64,
+        Ordering::Relaxed,
+    );
+}
+
 /// A convenience wrapper around v4l2_ioctl.
 ///
-/// In case of errors, the last OS error will be reported, aka errno on Linux.
+/// In case of errors, the last OS error will be reported, aka errno on Linux. Temporary errors
+/// are retried according to the [`retry_policy()`].
 ///
 /// # Arguments
 ///
@@ -203,13 +432,62 @@ pub unsafe fn ioctl(
     request: vidioc::_IOC_TYPE,
     argp: *mut std::os::raw::c_void,
 ) -> io::Result<()> {
-    let ret = detail::ioctl(fd, request, argp);
+    #[cfg(feature = "tracing")]
+    let _span = tracing::trace_span!(
+        "ioctl",
+        request = vidioc::name(request).unwrap_or("unknown"),
+        fd
+    )
+    .entered();
+    #[cfg(feature = "tracing")]
+    let start = std::time::Instant::now();
 
-    if ret == -1 {
-        Err(io::Error::last_os_error())
-    } else {
-        Ok(())
+    let backend = backend::current();
+    let policy = retry_policy();
+    // the 32-bit time variants of the dequeue requests are retried just the same
+    let dequeue = request == vidioc::VIDIOC_DQBUF || request == vidioc::VIDIOC_DQEVENT;
+    let ioctl = |request, argp| {
+        let mut would_block = 0;
+        loop {
+            match backend.ioctl(fd, request, argp) {
+                Err(e) if policy.interrupted && e.raw_os_error() == Some(libc::EINTR) => continue,
+                Err(e)
+                    if dequeue
+                        && would_block < policy.would_block
+                        && e.raw_os_error() == Some(libc::EAGAIN) =>
+                {
+                    would_block += 1;
+                    thread::sleep(policy.would_block_delay);
+                }
+                ret => break ret,
+            }
+        }
+    };
+    let ret = ioctl(request, argp);
+
+    #[cfg(all(feature = "v4l2-sys", target_pointer_width = "32"))]
+    let ret = match ret {
+        Err(e)
+            if e.raw_os_error() == Some(libc::ENOTTY)
+                && super::time32::request(request).is_some() =>
+        {
+            super::time32::ioctl(request, argp, ioctl)
+        }
+        ret => ret,
+    };
+
+    #[cfg(feature = "tracing")]
+    match &ret {
+        Ok(()) => tracing::trace!(duration_us = start.elapsed().as_micros() as u64, "ok"),
+        Err(e) => tracing::debug!(
+            duration_us = start.elapsed().as_micros() as u64,
+            errno = e.raw_os_error().unwrap_or(0),
+            "failed: {}",
+            e
+        ),
     }
+
+    ret
 }
 
 /// A convenience wrapper around v4l2_mmap.
@@ -259,12 +537,7 @@ pub unsafe fn mmap(
     fd: std::os::raw::c_int,
     offset: libc::off_t,
 ) -> io::Result<*mut std::os::raw::c_void> {
-    let ret = detail::mmap(start, length, prot, flags, fd, offset);
-    if ret as usize == std::usize::MAX {
-        Err(io::Error::last_os_error())
-    } else {
-        Ok(ret)
-    }
+    backend::current().mmap(start, length, prot, flags, fd, offset)
 }
 
 /// A convenience wrapper around v4l2_munmap.
@@ -306,10 +579,43 @@ pub unsafe fn mmap(
 /// }
 /// ```
 pub unsafe fn munmap(start: *mut std::os::raw::c_void, length: usize) -> io::Result<()> {
-    let ret = detail::munmap(start, length);
-    if ret == -1 {
-        Err(io::Error::last_os_error())
-    } else {
-        Ok(())
-    }
+    backend::current().munmap(start, length)
+}
+
+/// A convenience wrapper around ppoll.
+///
+/// Returns the number of descriptors with events, zero means the call timed out.
+/// In case of errors, the last OS error will be reported, aka errno on Linux.
+///
+/// # Arguments
+///
+/// * `fds` - Descriptors to wait for along with the requested events
+/// * `timeout` - Timeout with nanosecond resolution, `None` means infinite timeout (blocking)
+/// * `sigmask` - Signal mask to install during the call, `None` keeps the current one
+///
+/// # Example
+///
+/// ```
+/// extern crate v4l;
+///
+/// use std::time::Duration;
+/// use v4l::v4l2;
+///
+/// let fd = v4l2::open("/dev/video0", libc::O_RDWR);
+/// if let Ok(fd) = fd {
+///     let mut fds = [libc::pollfd {
+///         fd,
+///         events: libc::POLLIN,
+///         revents: 0,
+///     }];
+///     let ready = v4l2::ppoll(&mut fds, Some(Duration::from_millis(100)), None);
+///     v4l2::close(fd).unwrap();
+/// }
+/// ```
+pub fn ppoll(
+    fds: &mut [libc::pollfd],
+    timeout: Option<Duration>,
+    sigmask: Option<&libc::sigset_t>,
+) -> io::Result<usize> {
+    backend::current().ppoll(fds, timeout, sigmask)
 }
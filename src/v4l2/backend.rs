@@ -0,0 +1,212 @@
+//! Exchangeable implementation of the kernel interface
+//!
+//! All system calls on V4L2 device nodes go through a [`Backend`]. By default, this is the
+//! [`System`] backend talking to the kernel (or libv4l). With the `mock` feature enabled, another
+//! backend can be installed for the current thread through [`set()`], e.g. a
+//! [`crate::v4l2::mock::Mock`] which lets capture logic be tested without any device nodes.
+//!
+//! System calls which do not operate on V4L2 device nodes talk to the kernel directly: locking
+//! buffers into RAM, passing descriptors over Unix domain sockets in [`crate::proxy`] and the
+//! IO controls of the v4l2loopback control node, which return the device number.
+
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::v4l2::api::detail;
+use crate::v4l2::vidioc;
+
+/// Operations of the kernel interface, see the wrappers in [`crate::v4l2`] for their semantics
+pub trait Backend: Send + Sync {
+    /// Opens a device node and returns its file descriptor
+    fn open(&self, path: &Path, flags: i32) -> io::Result<std::os::raw::c_int>;
+
+    /// Closes a file descriptor
+    fn close(&self, fd: std::os::raw::c_int) -> io::Result<()>;
+
+    /// Duplicates a file descriptor
+    fn dup(&self, fd: std::os::raw::c_int) -> io::Result<std::os::raw::c_int>;
+
+    /// Reads data, e.g. frames of devices supporting read I/O
+    fn read(&self, fd: std::os::raw::c_int, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Writes data, e.g. frames of devices supporting write I/O
+    fn write(&self, fd: std::os::raw::c_int, buf: &[u8]) -> io::Result<usize>;
+
+    /// Applies or removes an advisory lock, see `flock(2)`
+    fn flock(&self, fd: std::os::raw::c_int, op: i32) -> io::Result<()>;
+
+    /// Performs an IO control
+    ///
+    /// # Safety
+    ///
+    /// `argp` must point to memory holding the argument type of `request`.
+    unsafe fn ioctl(
+        &self,
+        fd: std::os::raw::c_int,
+        request: vidioc::_IOC_TYPE,
+        argp: *mut std::os::raw::c_void,
+    ) -> io::Result<()>;
+
+    /// Maps device memory
+    ///
+    /// # Safety
+    ///
+    /// `start` must be NULL or a valid address hint.
+    unsafe fn mmap(
+        &self,
+        start: *mut std::os::raw::c_void,
+        length: usize,
+        prot: std::os::raw::c_int,
+        flags: std::os::raw::c_int,
+        fd: std::os::raw::c_int,
+        offset: libc::off_t,
+    ) -> io::Result<*mut std::os::raw::c_void>;
+
+    /// Unmaps device memory
+    ///
+    /// # Safety
+    ///
+    /// `start` and `length` must describe a mapping returned by [`Backend::mmap()`].
+    unsafe fn munmap(&self, start: *mut std::os::raw::c_void, length: usize) -> io::Result<()>;
+
+    /// Waits for events on file descriptors and returns the number of ready ones
+    fn ppoll(
+        &self,
+        fds: &mut [libc::pollfd],
+        timeout: Option<Duration>,
+        sigmask: Option<&libc::sigset_t>,
+    ) -> io::Result<usize>;
+}
+
+/// Backend talking to the kernel, or libv4l if the `libv4l` feature is enabled
+#[derive(Debug, Default, Copy, Clone)]
+pub struct System;
+
+impl Backend for System {
+    fn open(&self, path: &Path, flags: i32) -> io::Result<std::os::raw::c_int> {
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        unsafe { detail::open(c_path.as_ptr(), flags) }
+    }
+
+    fn close(&self, fd: std::os::raw::c_int) -> io::Result<()> {
+        unsafe { detail::close(fd) }
+    }
+
+    fn dup(&self, fd: std::os::raw::c_int) -> io::Result<std::os::raw::c_int> {
+        unsafe { detail::dup(fd) }
+    }
+
+    fn read(&self, fd: std::os::raw::c_int, buf: &mut [u8]) -> io::Result<usize> {
+        match unsafe { libc::read(fd, buf.as_mut_ptr() as *mut std::os::raw::c_void, buf.len()) } {
+            -1 => Err(io::Error::last_os_error()),
+            ret => Ok(ret as usize),
+        }
+    }
+
+    fn write(&self, fd: std::os::raw::c_int, buf: &[u8]) -> io::Result<usize> {
+        match unsafe { libc::write(fd, buf.as_ptr() as *const std::os::raw::c_void, buf.len()) } {
+            -1 => Err(io::Error::last_os_error()),
+            ret => Ok(ret as usize),
+        }
+    }
+
+    fn flock(&self, fd: std::os::raw::c_int, op: i32) -> io::Result<()> {
+        match unsafe { libc::flock(fd, op) } {
+            -1 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    unsafe fn ioctl(
+        &self,
+        fd: std::os::raw::c_int,
+        request: vidioc::_IOC_TYPE,
+        argp: *mut std::os::raw::c_void,
+    ) -> io::Result<()> {
+        detail::ioctl(fd, request, argp)
+    }
+
+    unsafe fn mmap(
+        &self,
+        start: *mut std::os::raw::c_void,
+        length: usize,
+        prot: std::os::raw::c_int,
+        flags: std::os::raw::c_int,
+        fd: std::os::raw::c_int,
+        offset: libc::off_t,
+    ) -> io::Result<*mut std::os::raw::c_void> {
+        detail::mmap(start, length, prot, flags, fd, offset)
+    }
+
+    unsafe fn munmap(&self, start: *mut std::os::raw::c_void, length: usize) -> io::Result<()> {
+        detail::munmap(start, length)
+    }
+
+    fn ppoll(
+        &self,
+        fds: &mut [libc::pollfd],
+        timeout: Option<Duration>,
+        sigmask: Option<&libc::sigset_t>,
+    ) -> io::Result<usize> {
+        let timeout = timeout.map(|timeout| libc::timespec {
+            tv_sec: timeout.as_secs() as libc::time_t,
+            tv_nsec: timeout.subsec_nanos() as libc::c_long,
+        });
+
+        match unsafe {
+            libc::ppoll(
+                fds.as_mut_ptr(),
+                fds.len() as libc::nfds_t,
+                timeout
+                    .as_ref()
+                    .map_or(std::ptr::null(), |timeout| timeout as *const _),
+                sigmask.map_or(std::ptr::null(), |sigmask| sigmask as *const _),
+            )
+        } {
+            -1 => Err(io::Error::last_os_error()),
+            ret => Ok(ret as usize),
+        }
+    }
+}
+
+#[cfg(not(feature = "mock"))]
+pub(crate) fn current() -> &'static dyn Backend {
+    &System
+}
+
+#[cfg(feature = "mock")]
+thread_local! {
+    static CURRENT: std::cell::RefCell<Option<std::sync::Arc<dyn Backend>>> =
+        std::cell::RefCell::new(None);
+}
+
+#[cfg(feature = "mock")]
+pub(crate) fn current() -> std::sync::Arc<dyn Backend> {
+    static SYSTEM: std::sync::OnceLock<std::sync::Arc<dyn Backend>> = std::sync::OnceLock::new();
+
+    CURRENT.with(|current| match &*current.borrow() {
+        Some(backend) => backend.clone(),
+        None => SYSTEM.get_or_init(|| std::sync::Arc::new(System)).clone(),
+    })
+}
+
+/// Installs a backend for all subsequent system calls of the current thread and returns the
+/// previous one
+///
+/// The backend is scoped to the calling thread, so tests running concurrently can install
+/// different backends. Threads spawned afterwards, including those of the crate (e.g. the
+/// workers in [`crate::io`]), start out with the [`System`] backend. Descriptors must not outlive
+/// the backend they were opened with.
+///
+/// # Arguments
+///
+/// * `backend` - Backend to install, `None` restores the [`System`] backend
+#[cfg(feature = "mock")]
+pub fn set(backend: Option<std::sync::Arc<dyn Backend>>) -> Option<std::sync::Arc<dyn Backend>> {
+    CURRENT.with(|current| std::mem::replace(&mut *current.borrow_mut(), backend))
+}
@@ -0,0 +1,60 @@
+// The CEC API lives in linux/cec.h, which is not covered by the bindings, so we carry our own
+// copies of the structs we need. The ioctl numbers depend on their layout, so they stay around
+// when the cec feature is disabled.
+#![cfg_attr(not(feature = "cec"), allow(dead_code))]
+
+pub(crate) const CEC_MAX_MSG_SIZE: usize = 16;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub(crate) struct cec_caps {
+    pub driver: [std::os::raw::c_char; 32],
+    pub name: [std::os::raw::c_char; 32],
+    pub available_log_addrs: u32,
+    pub capabilities: u32,
+    pub version: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub(crate) struct cec_msg {
+    pub tx_ts: u64,
+    pub rx_ts: u64,
+    pub len: u32,
+    pub timeout: u32,
+    pub sequence: u32,
+    pub flags: u32,
+    pub msg: [u8; CEC_MAX_MSG_SIZE],
+    pub reply: u8,
+    pub rx_status: u8,
+    pub tx_status: u8,
+    pub tx_arb_lost_cnt: u8,
+    pub tx_nack_cnt: u8,
+    pub tx_low_drive_cnt: u8,
+    pub tx_error_cnt: u8,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub(crate) struct cec_event_state_change {
+    pub phys_addr: u16,
+    pub log_addr_mask: u16,
+    pub have_conn_info: u16,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub(crate) union cec_event_payload {
+    pub state_change: cec_event_state_change,
+    pub lost_msgs: u32,
+    pub raw: [u32; 16],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub(crate) struct cec_event {
+    pub ts: u64,
+    pub event: u32,
+    pub flags: u32,
+    pub payload: cec_event_payload,
+}
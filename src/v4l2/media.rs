@@ -0,0 +1,73 @@
+// The media controller API lives in linux/media.h, which is not covered by the bindings, so we
+// carry our own copies of the structs we need. The ioctl numbers depend on their layout, so they
+// stay around when the media-controller feature is disabled.
+#![cfg_attr(not(feature = "media-controller"), allow(dead_code))]
+
+#[repr(C)]
+pub(crate) struct media_v2_topology {
+    pub topology_version: u64,
+    pub num_entities: u32,
+    pub reserved1: u32,
+    pub ptr_entities: u64,
+    pub num_interfaces: u32,
+    pub reserved2: u32,
+    pub ptr_interfaces: u64,
+    pub num_pads: u32,
+    pub reserved3: u32,
+    pub ptr_pads: u64,
+    pub num_links: u32,
+    pub reserved4: u32,
+    pub ptr_links: u64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub(crate) struct media_v2_entity {
+    pub id: u32,
+    pub name: [std::os::raw::c_char; 64],
+    pub function: u32,
+    pub flags: u32,
+    pub reserved: [u32; 5],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub(crate) struct media_v2_intf_devnode {
+    pub major: u32,
+    pub minor: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub(crate) struct media_v2_interface {
+    pub id: u32,
+    pub intf_type: u32,
+    pub flags: u32,
+    pub reserved: [u32; 9],
+    pub devnode: media_v2_intf_devnode,
+    // the devnode is part of a union padded to 16 words
+    pub raw: [u32; 14],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub(crate) struct media_v2_pad {
+    pub id: u32,
+    pub entity_id: u32,
+    pub flags: u32,
+    pub index: u32,
+    pub reserved: [u32; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub(crate) struct media_v2_link {
+    pub id: u32,
+    pub source_id: u32,
+    pub sink_id: u32,
+    pub flags: u32,
+    pub reserved: [u32; 6],
+}
+
+pub(crate) const MEDIA_LNK_FL_LINK_TYPE: u32 = 0xf << 28;
+pub(crate) const MEDIA_LNK_FL_INTERFACE_LINK: u32 = 1 << 28;
@@ -0,0 +1,310 @@
+//! Scriptable backend for testing without hardware
+//!
+//! A [`Mock`] accepts any path as device node and answers IO controls through handlers
+//! registered by the test. Memory mappings are backed by heap memory and all descriptors report
+//! being ready when polled, unless a poll handler is registered. Unhandled IO controls fail with
+//! `ENOTTY`, just like drivers do for unsupported ones, and reading or writing fails with
+//! `EINVAL` like it does for drivers without read/write I/O. Handlers may call back into the
+//! mock, e.g. to register other handlers or inspect the calls so far.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::Arc;
+//! use v4l::v4l2::{backend, mock::Mock, vidioc};
+//! use v4l::v4l_sys::*;
+//! use v4l::Device;
+//!
+//! let mock = Arc::new(Mock::new());
+//! mock.on_ioctl(vidioc::VIDIOC_QUERYCAP, |caps: &mut v4l2_capability| {
+//!     caps.driver[..4].copy_from_slice(b"mock");
+//!     caps.capabilities = V4L2_CAP_VIDEO_CAPTURE | V4L2_CAP_STREAMING;
+//!     Ok(())
+//! });
+//! let previous = backend::set(Some(mock.clone()));
+//!
+//! let dev = Device::new(0).expect("Failed to open device");
+//! let caps = dev.query_caps().expect("Failed to query capabilities");
+//! assert_eq!(caps.driver, "mock");
+//! assert_eq!(mock.ioctls(), [vidioc::VIDIOC_QUERYCAP]);
+//!
+//! drop(dev);
+//! backend::set(previous);
+//! ```
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::v4l2::backend::Backend;
+use crate::v4l2::vidioc;
+
+type IoctlHandler = Box<dyn FnMut(*mut std::os::raw::c_void) -> io::Result<()> + Send>;
+type PollHandler = Box<dyn FnMut(&mut [libc::pollfd]) -> io::Result<usize> + Send>;
+
+/// System call recorded by a [`Mock`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Call {
+    Open(PathBuf),
+    Close(std::os::raw::c_int),
+    Dup(std::os::raw::c_int),
+    Ioctl(std::os::raw::c_int, vidioc::_IOC_TYPE),
+    Read(std::os::raw::c_int, usize),
+    Write(std::os::raw::c_int, usize),
+    Flock(std::os::raw::c_int, i32),
+    Mmap(std::os::raw::c_int, usize, libc::off_t),
+    Munmap(usize),
+    Poll,
+}
+
+#[derive(Default)]
+struct State {
+    next_fd: std::os::raw::c_int,
+    fds: HashMap<std::os::raw::c_int, PathBuf>,
+    ioctls: HashMap<vidioc::_IOC_TYPE, IoctlHandler>,
+    poll: Option<PollHandler>,
+    // keyed by a counter rather than the address, which zero-length mappings share
+    next_mapping: u64,
+    mappings: HashMap<u64, Box<[u8]>>,
+    calls: Vec<Call>,
+}
+
+/// Backend answering system calls through handlers registered by a test
+pub struct Mock {
+    state: Mutex<State>,
+}
+
+impl Default for Mock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mock {
+    /// Returns a mock without any handlers
+    pub fn new() -> Self {
+        Mock {
+            state: Mutex::new(State {
+                // stay clear of real descriptors
+                next_fd: 10000,
+                ..State::default()
+            }),
+        }
+    }
+
+    /// Registers the handler of an IO control, replacing any previous one
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - IO control code (see [`vidioc`])
+    /// * `f` - Handler called with the argument of the IO control
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size of `T` does not match the argument size encoded in `request`.
+    pub fn on_ioctl<T, F>(&self, request: vidioc::_IOC_TYPE, mut f: F)
+    where
+        F: FnMut(&mut T) -> io::Result<()> + Send + 'static,
+    {
+        assert_eq!(
+            vidioc::size(request),
+            std::mem::size_of::<T>(),
+            "argument type does not match {}",
+            vidioc::name(request).unwrap_or("request")
+        );
+
+        let handler: IoctlHandler = Box::new(move |argp| f(unsafe { &mut *(argp as *mut T) }));
+        self.state.lock().unwrap().ioctls.insert(request, handler);
+    }
+
+    /// Registers the handler of poll calls, replacing any previous one
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Handler setting `revents` and returning the number of ready descriptors, zero
+    ///   means the call timed out
+    pub fn on_poll<F>(&self, f: F)
+    where
+        F: FnMut(&mut [libc::pollfd]) -> io::Result<usize> + Send + 'static,
+    {
+        self.state.lock().unwrap().poll = Some(Box::new(f));
+    }
+
+    /// Returns all system calls so far
+    pub fn calls(&self) -> Vec<Call> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    /// Returns the IO control codes of all IO controls so far
+    pub fn ioctls(&self) -> Vec<vidioc::_IOC_TYPE> {
+        self.calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                Call::Ioctl(_, request) => Some(request),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the path a descriptor was opened with
+    pub fn path(&self, fd: std::os::raw::c_int) -> Option<PathBuf> {
+        self.state.lock().unwrap().fds.get(&fd).cloned()
+    }
+
+    fn new_fd(state: &mut State, path: PathBuf) -> std::os::raw::c_int {
+        let fd = state.next_fd;
+        state.next_fd += 1;
+        state.fds.insert(fd, path);
+        fd
+    }
+
+    fn check_fd(state: &State, fd: std::os::raw::c_int) -> io::Result<()> {
+        if state.fds.contains_key(&fd) {
+            Ok(())
+        } else {
+            Err(io::Error::from_raw_os_error(libc::EBADF))
+        }
+    }
+}
+
+impl Backend for Mock {
+    fn open(&self, path: &Path, _flags: i32) -> io::Result<std::os::raw::c_int> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(Call::Open(path.to_path_buf()));
+        Ok(Self::new_fd(&mut state, path.to_path_buf()))
+    }
+
+    fn close(&self, fd: std::os::raw::c_int) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(Call::Close(fd));
+        state
+            .fds
+            .remove(&fd)
+            .map(drop)
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::EBADF))
+    }
+
+    fn dup(&self, fd: std::os::raw::c_int) -> io::Result<std::os::raw::c_int> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(Call::Dup(fd));
+        let path = state
+            .fds
+            .get(&fd)
+            .cloned()
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::EBADF))?;
+        Ok(Self::new_fd(&mut state, path))
+    }
+
+    fn read(&self, fd: std::os::raw::c_int, buf: &mut [u8]) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(Call::Read(fd, buf.len()));
+        Self::check_fd(&state, fd)?;
+        Err(io::Error::from_raw_os_error(libc::EINVAL))
+    }
+
+    fn write(&self, fd: std::os::raw::c_int, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(Call::Write(fd, buf.len()));
+        Self::check_fd(&state, fd)?;
+        Err(io::Error::from_raw_os_error(libc::EINVAL))
+    }
+
+    fn flock(&self, fd: std::os::raw::c_int, op: i32) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(Call::Flock(fd, op));
+        Self::check_fd(&state, fd)
+    }
+
+    unsafe fn ioctl(
+        &self,
+        fd: std::os::raw::c_int,
+        request: vidioc::_IOC_TYPE,
+        argp: *mut std::os::raw::c_void,
+    ) -> io::Result<()> {
+        let mut handler = {
+            let mut state = self.state.lock().unwrap();
+            state.calls.push(Call::Ioctl(fd, request));
+            Self::check_fd(&state, fd)?;
+            match state.ioctls.remove(&request) {
+                Some(handler) => handler,
+                None => return Err(io::Error::from_raw_os_error(libc::ENOTTY)),
+            }
+        };
+
+        // the state is unlocked while the handler runs, so it can call back into the mock
+        let ret = handler(argp);
+        // keep a handler the handler registered itself
+        self.state
+            .lock()
+            .unwrap()
+            .ioctls
+            .entry(request)
+            .or_insert(handler);
+        ret
+    }
+
+    unsafe fn mmap(
+        &self,
+        _start: *mut std::os::raw::c_void,
+        length: usize,
+        _prot: std::os::raw::c_int,
+        _flags: std::os::raw::c_int,
+        fd: std::os::raw::c_int,
+        offset: libc::off_t,
+    ) -> io::Result<*mut std::os::raw::c_void> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(Call::Mmap(fd, length, offset));
+        Self::check_fd(&state, fd)?;
+        let mut mapping = vec![0u8; length].into_boxed_slice();
+        let ptr = mapping.as_mut_ptr();
+        let id = state.next_mapping;
+        state.next_mapping += 1;
+        state.mappings.insert(id, mapping);
+        Ok(ptr as *mut std::os::raw::c_void)
+    }
+
+    unsafe fn munmap(&self, start: *mut std::os::raw::c_void, length: usize) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(Call::Munmap(length));
+        let id = state
+            .mappings
+            .iter()
+            .find(|(_, mapping)| {
+                mapping.as_ptr() as *mut std::os::raw::c_void == start && mapping.len() == length
+            })
+            .map(|(&id, _)| id)
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::EINVAL))?;
+        state.mappings.remove(&id);
+        Ok(())
+    }
+
+    fn ppoll(
+        &self,
+        fds: &mut [libc::pollfd],
+        _timeout: Option<Duration>,
+        _sigmask: Option<&libc::sigset_t>,
+    ) -> io::Result<usize> {
+        let poll = {
+            let mut state = self.state.lock().unwrap();
+            state.calls.push(Call::Poll);
+            state.poll.take()
+        };
+
+        match poll {
+            Some(mut poll) => {
+                // the state is unlocked while the handler runs, so it can call back into the mock
+                let ret = poll(fds);
+                self.state.lock().unwrap().poll.get_or_insert(poll);
+                ret
+            }
+            None => {
+                for fd in fds.iter_mut() {
+                    fd.revents = fd.events;
+                }
+                Ok(fds.len())
+            }
+        }
+    }
+}
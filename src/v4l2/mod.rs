@@ -1,5 +1,13 @@
 pub mod api;
 pub use api::*;
+pub mod backend;
+#[cfg(feature = "mock")]
+pub mod mock;
 
+#[cfg(all(feature = "v4l2-sys", target_pointer_width = "32"))]
+mod time32;
+
+pub mod cec;
+pub mod media;
 pub mod videodev;
 pub mod vidioc;
@@ -0,0 +1,177 @@
+//! Buffer and event ioctls of kernels which predate 64-bit time on 32-bit architectures
+//!
+//! Since Linux 5.6, the buffer and event ioctls of 32-bit architectures take a `v4l2_buffer` or
+//! `v4l2_event` with a 64-bit timestamp, while the variants with a 32-bit timestamp are kept for
+//! compatibility. The bindings are generated with 64-bit time to be year 2038 safe, so older
+//! kernels reject these ioctls with ENOTTY. In that case, the argument is converted to the old
+//! layout and the request is retried, just like C libraries such as musl do.
+
+use std::{io, mem, os::raw::c_void};
+
+use crate::v4l2::vidioc::{self, _IOC_TYPE};
+use crate::v4l_sys::*;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct old_timeval32 {
+    tv_sec: i32,
+    tv_usec: i32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct old_timespec32 {
+    tv_sec: i32,
+    tv_nsec: i32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub(crate) struct v4l2_buffer_time32 {
+    index: u32,
+    type_: u32,
+    bytesused: u32,
+    flags: u32,
+    field: u32,
+    timestamp: old_timeval32,
+    timecode: v4l2_timecode,
+    sequence: u32,
+    memory: u32,
+    m: v4l2_buffer__bindgen_ty_1,
+    length: u32,
+    reserved2: u32,
+    __bindgen_anon_1: v4l2_buffer__bindgen_ty_2,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub(crate) struct v4l2_event_time32 {
+    type_: u32,
+    u: v4l2_event__bindgen_ty_1,
+    pending: u32,
+    sequence: u32,
+    timestamp: old_timespec32,
+    id: u32,
+    reserved: [u32; 8],
+}
+
+const VIDIOC_QUERYBUF_TIME32: _IOC_TYPE = vidioc::iowr::<v4l2_buffer_time32>(b'V', 9);
+const VIDIOC_QBUF_TIME32: _IOC_TYPE = vidioc::iowr::<v4l2_buffer_time32>(b'V', 15);
+const VIDIOC_DQBUF_TIME32: _IOC_TYPE = vidioc::iowr::<v4l2_buffer_time32>(b'V', 17);
+const VIDIOC_PREPARE_BUF_TIME32: _IOC_TYPE = vidioc::iowr::<v4l2_buffer_time32>(b'V', 93);
+const VIDIOC_DQEVENT_TIME32: _IOC_TYPE = vidioc::ior::<v4l2_event_time32>(b'V', 89);
+
+/// Returns the 32-bit time variant of a request, if there is a different one
+pub(crate) fn request(request: _IOC_TYPE) -> Option<_IOC_TYPE> {
+    let buffer = mem::size_of::<v4l2_buffer>() != mem::size_of::<v4l2_buffer_time32>();
+    let event = mem::size_of::<v4l2_event>() != mem::size_of::<v4l2_event_time32>();
+
+    // if the sizes match, the bindings use 32-bit time already
+    match request {
+        vidioc::VIDIOC_QUERYBUF if buffer => Some(VIDIOC_QUERYBUF_TIME32),
+        vidioc::VIDIOC_QBUF if buffer => Some(VIDIOC_QBUF_TIME32),
+        vidioc::VIDIOC_DQBUF if buffer => Some(VIDIOC_DQBUF_TIME32),
+        vidioc::VIDIOC_PREPARE_BUF if buffer => Some(VIDIOC_PREPARE_BUF_TIME32),
+        vidioc::VIDIOC_DQEVENT if event => Some(VIDIOC_DQEVENT_TIME32),
+        _ => None,
+    }
+}
+
+/// Performs the 32-bit time variant of a request by converting its argument
+///
+/// # Arguments
+///
+/// * `request` - The original request, which takes a 64-bit time argument
+/// * `argp` - The original argument
+/// * `ioctl` - Performs the given request on the given argument
+///
+/// # Safety
+///
+/// `argp` must point to the argument type of `request`, which must have a 32-bit time variant
+/// as reported by [`request`].
+pub(crate) unsafe fn ioctl(
+    request: _IOC_TYPE,
+    argp: *mut c_void,
+    mut ioctl: impl FnMut(_IOC_TYPE, *mut c_void) -> io::Result<()>,
+) -> io::Result<()> {
+    let request32 = self::request(request).expect("request without a 32-bit time variant");
+
+    if request == vidioc::VIDIOC_DQEVENT {
+        // the event is only written by the kernel
+        let ev = &mut *(argp as *mut v4l2_event);
+        let mut ev32: v4l2_event_time32 = mem::zeroed();
+        ioctl(request32, &mut ev32 as *mut _ as *mut c_void)?;
+        *ev = ev32.into();
+    } else {
+        let buf = &mut *(argp as *mut v4l2_buffer);
+        let mut buf32 = v4l2_buffer_time32::from(*buf);
+        ioctl(request32, &mut buf32 as *mut _ as *mut c_void)?;
+        *buf = buf32.into();
+    }
+
+    Ok(())
+}
+
+impl From<v4l2_buffer> for v4l2_buffer_time32 {
+    fn from(buf: v4l2_buffer) -> Self {
+        Self {
+            index: buf.index,
+            type_: buf.type_,
+            bytesused: buf.bytesused,
+            flags: buf.flags,
+            field: buf.field,
+            // timestamps beyond 2038 cannot be represented by such kernels anyways
+            timestamp: old_timeval32 {
+                tv_sec: buf.timestamp.tv_sec as i32,
+                tv_usec: buf.timestamp.tv_usec as i32,
+            },
+            timecode: buf.timecode,
+            sequence: buf.sequence,
+            memory: buf.memory,
+            m: buf.m,
+            length: buf.length,
+            reserved2: buf.reserved2,
+            __bindgen_anon_1: buf.__bindgen_anon_1,
+        }
+    }
+}
+
+impl From<v4l2_buffer_time32> for v4l2_buffer {
+    fn from(buf: v4l2_buffer_time32) -> Self {
+        Self {
+            index: buf.index,
+            type_: buf.type_,
+            bytesused: buf.bytesused,
+            flags: buf.flags,
+            field: buf.field,
+            timestamp: timeval {
+                tv_sec: buf.timestamp.tv_sec as _,
+                tv_usec: buf.timestamp.tv_usec as _,
+            },
+            timecode: buf.timecode,
+            sequence: buf.sequence,
+            memory: buf.memory,
+            m: buf.m,
+            length: buf.length,
+            reserved2: buf.reserved2,
+            __bindgen_anon_1: buf.__bindgen_anon_1,
+        }
+    }
+}
+
+impl From<v4l2_event_time32> for v4l2_event {
+    fn from(ev: v4l2_event_time32) -> Self {
+        Self {
+            type_: ev.type_,
+            u: ev.u,
+            pending: ev.pending,
+            sequence: ev.sequence,
+            timestamp: timespec {
+                tv_sec: ev.timestamp.tv_sec as _,
+                tv_nsec: ev.timestamp.tv_nsec as _,
+            },
+            id: ev.id,
+            reserved: ev.reserved,
+        }
+    }
+}
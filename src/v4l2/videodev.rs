@@ -15,3 +15,12 @@ pub(crate) struct v4l2_ext_controls {
     pub reserved: u32,
     pub controls: *mut v4l2_ext_control,
 }
+
+// Added in Linux 6.10, so older headers (and thus bindings) lack this struct.
+#[repr(C)]
+pub(crate) struct v4l2_remove_buffers {
+    pub index: u32,
+    pub count: u32,
+    pub type_: u32,
+    pub reserved: [u32; 13],
+}
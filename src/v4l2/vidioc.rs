@@ -1,3 +1,6 @@
+use crate::v4l2::cec::{cec_caps, cec_event, cec_msg};
+use crate::v4l2::media::media_v2_topology;
+use crate::v4l2::videodev::v4l2_remove_buffers;
 use crate::v4l_sys::*;
 
 #[cfg(not(target_env = "musl"))]
@@ -76,6 +79,64 @@ macro_rules! _IOWR {
     };
 }
 
+/// Encodes a request without argument, like `_IO` of linux/ioctl.h
+///
+/// # Arguments
+///
+/// * `typ` - Type (aka magic number) of the request, e.g. `b'V'` for V4L2
+/// * `nr` - Sequence number of the request
+pub const fn io(typ: u8, nr: u8) -> _IOC_TYPE {
+    _IO!(typ, nr)
+}
+
+/// Encodes a request which reads a `T` from the driver, like `_IOR` of linux/ioctl.h
+///
+/// # Arguments
+///
+/// * `typ` - Type (aka magic number) of the request, e.g. `b'V'` for V4L2
+/// * `nr` - Sequence number of the request
+pub const fn ior<T>(typ: u8, nr: u8) -> _IOC_TYPE {
+    _IOR!(typ, nr, T)
+}
+
+/// Encodes a request which writes a `T` to the driver, like `_IOW` of linux/ioctl.h
+///
+/// # Arguments
+///
+/// * `typ` - Type (aka magic number) of the request, e.g. `b'V'` for V4L2
+/// * `nr` - Sequence number of the request
+pub const fn iow<T>(typ: u8, nr: u8) -> _IOC_TYPE {
+    _IOW!(typ, nr, T)
+}
+
+/// Encodes a request which writes and reads back a `T`, like `_IOWR` of linux/ioctl.h
+///
+/// # Arguments
+///
+/// * `typ` - Type (aka magic number) of the request, e.g. `b'V'` for V4L2
+/// * `nr` - Sequence number of the request
+///
+/// # Example
+///
+/// ```
+/// use v4l::v4l2::vidioc;
+/// use v4l::v4l_sys::v4l2_format;
+///
+/// assert_eq!(vidioc::iowr::<v4l2_format>(b'V', 5), vidioc::VIDIOC_S_FMT);
+/// ```
+pub const fn iowr<T>(typ: u8, nr: u8) -> _IOC_TYPE {
+    _IOWR!(typ, nr, T)
+}
+
+/// Returns the size of the argument encoded in a request
+///
+/// # Arguments
+///
+/// * `request` - IO control code
+pub const fn size(request: _IOC_TYPE) -> usize {
+    ((request >> _IOC_SIZESHIFT) & ((1 << _IOC_SIZEBITS) - 1)) as usize
+}
+
 pub const VIDIOC_QUERYCAP: _IOC_TYPE = _IOR!(b'V', 0, v4l2_capability);
 pub const VIDIOC_RESERVED: _IOC_TYPE = _IO!(b'V', 1);
 pub const VIDIOC_ENUM_FMT: _IOC_TYPE = _IOWR!(b'V', 2, v4l2_fmtdesc);
@@ -139,4 +200,142 @@ pub const VIDIOC_ENUM_FRAMEINTERVALS: _IOC_TYPE = _IOWR!(b'V', 75, v4l2_frmivale
 pub const VIDIOC_G_ENC_INDEX: _IOC_TYPE = _IOR!(b'V', 76, v4l2_enc_idx);
 pub const VIDIOC_ENCODER_CMD: _IOC_TYPE = _IOWR!(b'V', 77, v4l2_encoder_cmd);
 pub const VIDIOC_TRY_ENCODER_CMD: _IOC_TYPE = _IOWR!(b'V', 78, v4l2_encoder_cmd);
+pub const VIDIOC_DBG_S_REGISTER: _IOC_TYPE = _IOW!(b'V', 79, v4l2_dbg_register);
+pub const VIDIOC_DBG_G_REGISTER: _IOC_TYPE = _IOWR!(b'V', 80, v4l2_dbg_register);
+pub const VIDIOC_DQEVENT: _IOC_TYPE = _IOR!(b'V', 89, v4l2_event);
+pub const VIDIOC_SUBSCRIBE_EVENT: _IOC_TYPE = _IOW!(b'V', 90, v4l2_event_subscription);
+pub const VIDIOC_UNSUBSCRIBE_EVENT: _IOC_TYPE = _IOW!(b'V', 91, v4l2_event_subscription);
+pub const VIDIOC_CREATE_BUFS: _IOC_TYPE = _IOWR!(b'V', 92, v4l2_create_buffers);
+pub const VIDIOC_PREPARE_BUF: _IOC_TYPE = _IOWR!(b'V', 93, v4l2_buffer);
+pub const VIDIOC_G_SELECTION: _IOC_TYPE = _IOWR!(b'V', 94, v4l2_selection);
+pub const VIDIOC_S_SELECTION: _IOC_TYPE = _IOWR!(b'V', 95, v4l2_selection);
+pub const VIDIOC_QUERY_DV_TIMINGS: _IOC_TYPE = _IOR!(b'V', 99, v4l2_dv_timings);
+pub const VIDIOC_DBG_G_CHIP_INFO: _IOC_TYPE = _IOWR!(b'V', 102, v4l2_dbg_chip_info);
 pub const VIDIOC_QUERY_EXT_CTRL: _IOC_TYPE = _IOWR!(b'V', 103, v4l2_query_ext_ctrl);
+pub const VIDIOC_REMOVE_BUFS: _IOC_TYPE = _IOWR!(b'V', 104, v4l2_remove_buffers);
+
+// linux media.h
+pub const MEDIA_IOC_G_TOPOLOGY: _IOC_TYPE = _IOWR!(b'|', 0x04, media_v2_topology);
+pub const MEDIA_IOC_REQUEST_ALLOC: _IOC_TYPE = _IOR!(b'|', 0x05, std::os::raw::c_int);
+pub const MEDIA_REQUEST_IOC_QUEUE: _IOC_TYPE = _IO!(b'|', 0x80);
+pub const MEDIA_REQUEST_IOC_REINIT: _IOC_TYPE = _IO!(b'|', 0x81);
+
+// linux cec.h
+pub const CEC_ADAP_G_CAPS: _IOC_TYPE = _IOWR!(b'a', 0, cec_caps);
+pub const CEC_ADAP_G_PHYS_ADDR: _IOC_TYPE = _IOR!(b'a', 1, u16);
+pub const CEC_TRANSMIT: _IOC_TYPE = _IOWR!(b'a', 5, cec_msg);
+pub const CEC_RECEIVE: _IOC_TYPE = _IOWR!(b'a', 6, cec_msg);
+pub const CEC_DQEVENT: _IOC_TYPE = _IOWR!(b'a', 7, cec_event);
+pub const CEC_G_MODE: _IOC_TYPE = _IOR!(b'a', 8, u32);
+pub const CEC_S_MODE: _IOC_TYPE = _IOW!(b'a', 9, u32);
+
+/// Names of all known requests, used for diagnostics
+const NAMES: &[(_IOC_TYPE, &str)] = &[
+    (VIDIOC_QUERYCAP, "VIDIOC_QUERYCAP"),
+    (VIDIOC_RESERVED, "VIDIOC_RESERVED"),
+    (VIDIOC_ENUM_FMT, "VIDIOC_ENUM_FMT"),
+    (VIDIOC_G_FMT, "VIDIOC_G_FMT"),
+    (VIDIOC_S_FMT, "VIDIOC_S_FMT"),
+    (VIDIOC_REQBUFS, "VIDIOC_REQBUFS"),
+    (VIDIOC_QUERYBUF, "VIDIOC_QUERYBUF"),
+    (VIDIOC_G_FBUF, "VIDIOC_G_FBUF"),
+    (VIDIOC_S_FBUF, "VIDIOC_S_FBUF"),
+    (VIDIOC_OVERLAY, "VIDIOC_OVERLAY"),
+    (VIDIOC_QBUF, "VIDIOC_QBUF"),
+    (VIDIOC_EXPBUF, "VIDIOC_EXPBUF"),
+    (VIDIOC_DQBUF, "VIDIOC_DQBUF"),
+    (VIDIOC_STREAMON, "VIDIOC_STREAMON"),
+    (VIDIOC_STREAMOFF, "VIDIOC_STREAMOFF"),
+    (VIDIOC_G_PARM, "VIDIOC_G_PARM"),
+    (VIDIOC_S_PARM, "VIDIOC_S_PARM"),
+    (VIDIOC_G_STD, "VIDIOC_G_STD"),
+    (VIDIOC_S_STD, "VIDIOC_S_STD"),
+    (VIDIOC_ENUMSTD, "VIDIOC_ENUMSTD"),
+    (VIDIOC_ENUMINPUT, "VIDIOC_ENUMINPUT"),
+    (VIDIOC_G_CTRL, "VIDIOC_G_CTRL"),
+    (VIDIOC_S_CTRL, "VIDIOC_S_CTRL"),
+    (VIDIOC_G_TUNER, "VIDIOC_G_TUNER"),
+    (VIDIOC_S_TUNER, "VIDIOC_S_TUNER"),
+    (VIDIOC_G_AUDIO, "VIDIOC_G_AUDIO"),
+    (VIDIOC_S_AUDIO, "VIDIOC_S_AUDIO"),
+    (VIDIOC_QUERYCTRL, "VIDIOC_QUERYCTRL"),
+    (VIDIOC_QUERYMENU, "VIDIOC_QUERYMENU"),
+    (VIDIOC_G_INPUT, "VIDIOC_G_INPUT"),
+    (VIDIOC_S_INPUT, "VIDIOC_S_INPUT"),
+    (VIDIOC_G_EDID, "VIDIOC_G_EDID"),
+    (VIDIOC_S_EDID, "VIDIOC_S_EDID"),
+    (VIDIOC_G_OUTPUT, "VIDIOC_G_OUTPUT"),
+    (VIDIOC_S_OUTPUT, "VIDIOC_S_OUTPUT"),
+    (VIDIOC_ENUMOUTPUT, "VIDIOC_ENUMOUTPUT"),
+    (VIDIOC_G_AUDOUT, "VIDIOC_G_AUDOUT"),
+    (VIDIOC_S_AUDOUT, "VIDIOC_S_AUDOUT"),
+    (VIDIOC_G_MODULATOR, "VIDIOC_G_MODULATOR"),
+    (VIDIOC_S_MODULATOR, "VIDIOC_S_MODULATOR"),
+    (VIDIOC_G_FREQUENCY, "VIDIOC_G_FREQUENCY"),
+    (VIDIOC_S_FREQUENCY, "VIDIOC_S_FREQUENCY"),
+    (VIDIOC_CROPCAP, "VIDIOC_CROPCAP"),
+    (VIDIOC_G_CROP, "VIDIOC_G_CROP"),
+    (VIDIOC_S_CROP, "VIDIOC_S_CROP"),
+    (VIDIOC_G_JPEGCOMP, "VIDIOC_G_JPEGCOMP"),
+    (VIDIOC_S_JPEGCOMP, "VIDIOC_S_JPEGCOMP"),
+    (VIDIOC_QUERYSTD, "VIDIOC_QUERYSTD"),
+    (VIDIOC_TRY_FMT, "VIDIOC_TRY_FMT"),
+    (VIDIOC_ENUMAUDIO, "VIDIOC_ENUMAUDIO"),
+    (VIDIOC_ENUMAUDOUT, "VIDIOC_ENUMAUDOUT"),
+    (VIDIOC_G_PRIORITY, "VIDIOC_G_PRIORITY"),
+    (VIDIOC_S_PRIORITY, "VIDIOC_S_PRIORITY"),
+    (VIDIOC_G_SLICED_VBI_CAP, "VIDIOC_G_SLICED_VBI_CAP"),
+    (VIDIOC_LOG_STATUS, "VIDIOC_LOG_STATUS"),
+    (VIDIOC_G_EXT_CTRLS, "VIDIOC_G_EXT_CTRLS"),
+    (VIDIOC_S_EXT_CTRLS, "VIDIOC_S_EXT_CTRLS"),
+    (VIDIOC_TRY_EXT_CTRLS, "VIDIOC_TRY_EXT_CTRLS"),
+    (VIDIOC_ENUM_FRAMESIZES, "VIDIOC_ENUM_FRAMESIZES"),
+    (VIDIOC_ENUM_FRAMEINTERVALS, "VIDIOC_ENUM_FRAMEINTERVALS"),
+    (VIDIOC_G_ENC_INDEX, "VIDIOC_G_ENC_INDEX"),
+    (VIDIOC_ENCODER_CMD, "VIDIOC_ENCODER_CMD"),
+    (VIDIOC_TRY_ENCODER_CMD, "VIDIOC_TRY_ENCODER_CMD"),
+    (VIDIOC_DBG_S_REGISTER, "VIDIOC_DBG_S_REGISTER"),
+    (VIDIOC_DBG_G_REGISTER, "VIDIOC_DBG_G_REGISTER"),
+    (VIDIOC_DQEVENT, "VIDIOC_DQEVENT"),
+    (VIDIOC_SUBSCRIBE_EVENT, "VIDIOC_SUBSCRIBE_EVENT"),
+    (VIDIOC_UNSUBSCRIBE_EVENT, "VIDIOC_UNSUBSCRIBE_EVENT"),
+    (VIDIOC_CREATE_BUFS, "VIDIOC_CREATE_BUFS"),
+    (VIDIOC_PREPARE_BUF, "VIDIOC_PREPARE_BUF"),
+    (VIDIOC_G_SELECTION, "VIDIOC_G_SELECTION"),
+    (VIDIOC_S_SELECTION, "VIDIOC_S_SELECTION"),
+    (VIDIOC_QUERY_DV_TIMINGS, "VIDIOC_QUERY_DV_TIMINGS"),
+    (VIDIOC_DBG_G_CHIP_INFO, "VIDIOC_DBG_G_CHIP_INFO"),
+    (VIDIOC_QUERY_EXT_CTRL, "VIDIOC_QUERY_EXT_CTRL"),
+    (VIDIOC_REMOVE_BUFS, "VIDIOC_REMOVE_BUFS"),
+    (MEDIA_IOC_G_TOPOLOGY, "MEDIA_IOC_G_TOPOLOGY"),
+    (MEDIA_IOC_REQUEST_ALLOC, "MEDIA_IOC_REQUEST_ALLOC"),
+    (MEDIA_REQUEST_IOC_QUEUE, "MEDIA_REQUEST_IOC_QUEUE"),
+    (MEDIA_REQUEST_IOC_REINIT, "MEDIA_REQUEST_IOC_REINIT"),
+    (CEC_ADAP_G_CAPS, "CEC_ADAP_G_CAPS"),
+    (CEC_ADAP_G_PHYS_ADDR, "CEC_ADAP_G_PHYS_ADDR"),
+    (CEC_TRANSMIT, "CEC_TRANSMIT"),
+    (CEC_RECEIVE, "CEC_RECEIVE"),
+    (CEC_DQEVENT, "CEC_DQEVENT"),
+    (CEC_G_MODE, "CEC_G_MODE"),
+    (CEC_S_MODE, "CEC_S_MODE"),
+];
+
+/// Returns the name of a request, e.g. "VIDIOC_QUERYCAP"
+///
+/// # Arguments
+///
+/// * `request` - IO control code
+///
+/// # Example
+///
+/// ```
+/// use v4l::v4l2::vidioc;
+///
+/// assert_eq!(vidioc::name(vidioc::VIDIOC_QUERYCAP), Some("VIDIOC_QUERYCAP"));
+/// ```
+pub fn name(request: _IOC_TYPE) -> Option<&'static str> {
+    NAMES
+        .iter()
+        .find(|(other, _)| *other == request)
+        .map(|(_, name)| *name)
+}
@@ -0,0 +1,245 @@
+//! CEA-608 closed caption decoding
+//!
+//! Closed captions are transmitted as two bytes per field on line 21 of 525 line systems. Both
+//! bytes carry an odd parity bit. Each field multiplexes two data channels, i.e. CC1 and CC2 on the
+//! first field and CC3 and CC4 on the second one.
+//!
+//! The decoder implements the subset needed to extract the caption text: the standard and special
+//! character sets along with the pop-on, roll-up and paint-on styles. Positioning and attributes
+//! are reduced to line breaks and spaces, extended characters are dropped in favor of the standard
+//! characters transmitted before them, as the standard recommends for simple decoders.
+
+use std::mem;
+
+/// Returns the seven data bits of a byte, or `None` if its parity is even
+///
+/// # Arguments
+///
+/// * `byte` - Byte including the parity bit
+///
+/// # Example
+///
+/// ```
+/// use v4l::vbi::cea608::strip_parity;
+/// assert_eq!(strip_parity(0xc1), Some(0x41));
+/// assert_eq!(strip_parity(0x41), None);
+/// ```
+pub fn strip_parity(byte: u8) -> Option<u8> {
+    if byte.count_ones() & 1 == 1 {
+        Some(byte & 0x7f)
+    } else {
+        None
+    }
+}
+
+/// Returns the character of the standard character set
+fn standard(code: u8) -> Option<char> {
+    Some(match code {
+        0x2a => 'á',
+        0x5c => 'é',
+        0x5e => 'í',
+        0x5f => 'ó',
+        0x60 => 'ú',
+        0x7b => 'ç',
+        0x7c => '÷',
+        0x7d => 'Ñ',
+        0x7e => 'ñ',
+        0x7f => '█',
+        0x20..=0x7e => code as char,
+        _ => return None,
+    })
+}
+
+/// Returns the character of the special character set
+fn special(code: u8) -> char {
+    const SPECIAL: [char; 16] = [
+        '®', '°', '½', '¿', '™', '¢', '£', '♪', 'à', ' ', 'è', 'â', 'ê', 'î', 'ô', 'û',
+    ];
+    SPECIAL[(code & 0x0f) as usize]
+}
+
+/// Caption style, selected by the broadcaster
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Style {
+    /// Captions are composed off-screen and displayed at once
+    PopOn,
+    /// Captions scroll up line by line
+    RollUp,
+    /// Characters are displayed as they arrive
+    PaintOn,
+}
+
+/// Decoder turning byte pairs of a single data channel into caption text
+///
+/// # Example
+///
+/// ```
+/// use v4l::vbi::cea608::Decoder;
+///
+/// // adds odd parity to a 7 bit code
+/// fn p(b: u8) -> u8 {
+///     if b.count_ones() % 2 == 0 { b | 0x80 } else { b }
+/// }
+///
+/// let mut decoder = Decoder::new(1);
+/// let pairs = [[0x14, 0x20], [b'H', b'I'], [0x14, 0x2f]];
+/// let captions: Vec<_> = pairs
+///     .iter()
+///     .filter_map(|pair| decoder.push([p(pair[0]), p(pair[1])]))
+///     .collect();
+/// assert_eq!(captions, ["HI"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Decoder {
+    channel: u8,
+    current: u8,
+    style: Style,
+    last_control: Option<[u8; 2]>,
+    /// Text being composed off-screen (pop-on) or on-screen (roll-up, paint-on)
+    text: String,
+}
+
+impl Decoder {
+    /// Returns a decoder
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - Data channel to decode, 1 or 2 (CC3 and CC4 are channels 1 and 2 of the
+    ///   second field)
+    pub fn new(channel: u8) -> Self {
+        Decoder {
+            channel,
+            current: 1,
+            style: Style::PopOn,
+            last_control: None,
+            text: String::new(),
+        }
+    }
+
+    /// Returns the current caption style
+    pub fn style(&self) -> Style {
+        self.style
+    }
+
+    /// Feeds a byte pair and returns the caption text once it is complete
+    ///
+    /// Pop-on captions are complete once they are displayed, roll-up captions at the end of each
+    /// line and paint-on captions once the screen is erased or a new line is started.
+    ///
+    /// # Arguments
+    ///
+    /// * `pair` - Byte pair including the parity bits, as found on line 21
+    pub fn push(&mut self, pair: [u8; 2]) -> Option<String> {
+        let (b1, b2) = match (strip_parity(pair[0]), strip_parity(pair[1])) {
+            (Some(b1), Some(b2)) => (b1, b2),
+            // transmission error, drop the pair
+            _ => return None,
+        };
+
+        if b1 == 0 && b2 == 0 {
+            // padding
+            return None;
+        }
+
+        if (0x10..=0x1f).contains(&b1) {
+            // control codes are transmitted twice for redundancy
+            if self.last_control.take() == Some([b1, b2]) {
+                return None;
+            }
+            self.last_control = Some([b1, b2]);
+
+            self.current = if b1 & 0x08 == 0 { 1 } else { 2 };
+            if self.current != self.channel {
+                return None;
+            }
+            return self.control(b1 & !0x08, b2);
+        }
+
+        self.last_control = None;
+        if self.current == self.channel {
+            self.text.extend(standard(b1));
+            self.text.extend(standard(b2));
+        }
+
+        None
+    }
+
+    fn control(&mut self, b1: u8, b2: u8) -> Option<String> {
+        match (b1, b2) {
+            // miscellaneous control codes, 0x15 is used on the second field
+            (0x14 | 0x15, 0x20) => self.set_style(Style::PopOn),
+            (0x14 | 0x15, 0x21) => {
+                self.text.pop();
+                None
+            }
+            (0x14 | 0x15, 0x25..=0x27) => self.set_style(Style::RollUp),
+            (0x14 | 0x15, 0x29) => self.set_style(Style::PaintOn),
+            // erase displayed memory
+            (0x14 | 0x15, 0x2c) => match self.style {
+                Style::PaintOn => self.take(),
+                _ => None,
+            },
+            // carriage return
+            (0x14 | 0x15, 0x2d) => match self.style {
+                Style::PopOn => {
+                    self.newline();
+                    None
+                }
+                _ => self.take(),
+            },
+            // erase non-displayed memory
+            (0x14 | 0x15, 0x2e) => {
+                if self.style == Style::PopOn {
+                    self.text.clear();
+                }
+                None
+            }
+            // end of caption, i.e. flip memories
+            (0x14 | 0x15, 0x2f) => {
+                self.style = Style::PopOn;
+                self.take()
+            }
+            // tab offsets
+            (0x17, 0x21..=0x23) => {
+                self.text.extend((0..b2 - 0x20).map(|_| ' '));
+                None
+            }
+            // mid-row attribute changes are displayed as a space
+            (0x11, 0x20..=0x2f) => {
+                self.text.push(' ');
+                None
+            }
+            (0x11, 0x30..=0x3f) => {
+                self.text.push(special(b2));
+                None
+            }
+            // preamble address codes position the cursor on a new row
+            (0x10..=0x17, 0x40..=0x7f) => {
+                self.newline();
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn set_style(&mut self, style: Style) -> Option<String> {
+        self.style = style;
+        None
+    }
+
+    fn newline(&mut self) {
+        if !self.text.is_empty() && !self.text.ends_with('\n') {
+            self.text.push('\n');
+        }
+    }
+
+    fn take(&mut self) -> Option<String> {
+        let text = mem::take(&mut self.text);
+        let text = text.trim_end();
+        if text.is_empty() {
+            None
+        } else {
+            Some(text.to_string())
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Vertical blanking interval (VBI) data
+//!
+//! Analog video signals carry data such as teletext, closed captions, WSS or VPS in the lines of
+//! the vertical blanking interval. Raw VBI devices sample these lines like regular video, leaving
+//! the decoding to the application. Sliced VBI devices decode the services in hardware and return
+//! their payload instead, see [`sliced::Data`].
+//!
+//! Buffers are captured through the regular streams using [`Type::VbiCaputre`] or
+//! [`Type::SlicedVbiCapture`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use v4l::buffer::Type;
+//! use v4l::io::mmap::Stream;
+//! use v4l::io::traits::CaptureStream;
+//! use v4l::vbi::Capture;
+//! use v4l::Device;
+//!
+//! let dev = Device::with_path("/dev/vbi0").expect("Failed to open device");
+//! let format = dev.raw_format().expect("Failed to get format");
+//!
+//! let mut stream = Stream::new(&dev, Type::VbiCaputre).expect("Failed to create stream");
+//! let (buf, _) = stream.next().expect("Failed to capture frame");
+//! for line in format.split(buf) {
+//!     println!("field {} line {}: {:?}", line.field, line.number, &line.samples[..8]);
+//! }
+//! ```
+//!
+//! Closed captions can be extracted from sliced VBI devices along these lines:
+//!
+//! ```no_run
+//! use v4l::buffer::Type;
+//! use v4l::io::mmap::Stream;
+//! use v4l::io::traits::CaptureStream;
+//! use v4l::vbi::cea608::Decoder;
+//! use v4l::vbi::sliced::{Data, Format, Services};
+//! use v4l::vbi::Capture;
+//! use v4l::Device;
+//!
+//! let dev = Device::with_path("/dev/vbi0").expect("Failed to open device");
+//! dev.set_sliced_format(&Format::new(Services::CAPTION_525))
+//!     .expect("Failed to set format");
+//!
+//! let mut decoder = Decoder::new(1);
+//! let mut stream = Stream::new(&dev, Type::SlicedVbiCapture).expect("Failed to create stream");
+//! loop {
+//!     let (buf, meta) = stream.next().expect("Failed to capture frame");
+//!     for data in Data::parse(&buf[..meta.bytesused as usize]) {
+//!         // CC1 is carried on the first field
+//!         if data.field != 0 {
+//!             continue;
+//!         }
+//!         if let Some(text) = data.caption().and_then(|pair| decoder.push(pair)) {
+//!             println!("{}", text);
+//!         }
+//!     }
+//! }
+//! ```
+
+pub mod cea608;
+pub mod raw;
+pub mod sliced;
+
+pub mod traits;
+pub use traits::Capture;
+
+use std::{io, mem};
+
+use crate::buffer::Type;
+use crate::device::Device;
+use crate::v4l2;
+use crate::v4l_sys::*;
+
+impl Capture for Device {
+    fn raw_format(&self) -> io::Result<raw::Format> {
+        unsafe {
+            let mut v4l2_fmt = v4l2_format {
+                type_: Type::VbiCaputre as u32,
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_G_FMT,
+                &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            Ok(raw::Format::from(v4l2_fmt.fmt.vbi))
+        }
+    }
+
+    fn set_raw_format(&self, fmt: &raw::Format) -> io::Result<raw::Format> {
+        unsafe {
+            let mut v4l2_fmt = v4l2_format {
+                type_: Type::VbiCaputre as u32,
+                fmt: v4l2_format__bindgen_ty_1 { vbi: (*fmt).into() },
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_S_FMT,
+                &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        self.raw_format()
+    }
+
+    fn sliced_capabilities(&self) -> io::Result<sliced::Capabilities> {
+        unsafe {
+            let mut v4l2_cap = v4l2_sliced_vbi_cap {
+                type_: Type::SlicedVbiCapture as u32,
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_G_SLICED_VBI_CAP,
+                &mut v4l2_cap as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            Ok(sliced::Capabilities::from(v4l2_cap))
+        }
+    }
+
+    fn sliced_format(&self) -> io::Result<sliced::Format> {
+        unsafe {
+            let mut v4l2_fmt = v4l2_format {
+                type_: Type::SlicedVbiCapture as u32,
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_G_FMT,
+                &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            Ok(sliced::Format::from(v4l2_fmt.fmt.sliced))
+        }
+    }
+
+    fn set_sliced_format(&self, fmt: &sliced::Format) -> io::Result<sliced::Format> {
+        unsafe {
+            let mut v4l2_fmt = v4l2_format {
+                type_: Type::SlicedVbiCapture as u32,
+                fmt: v4l2_format__bindgen_ty_1 {
+                    sliced: (*fmt).into(),
+                },
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_S_FMT,
+                &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        self.sliced_format()
+    }
+}
@@ -0,0 +1,173 @@
+use bitflags::bitflags;
+use std::{fmt, mem};
+
+use crate::format::FourCC;
+use crate::v4l_sys::*;
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    pub struct Flags: u32 {
+        /// The driver cannot tell the fields apart, so the field order of the lines is unknown
+        const UNSYNC        = 0x0001;
+        /// Lines of both fields are interleaved instead of stored one field after the other
+        const INTERLACED    = 0x0002;
+    }
+}
+
+impl Default for Flags {
+    fn default() -> Self {
+        Flags::from(0)
+    }
+}
+
+impl From<u32> for Flags {
+    fn from(flags: u32) -> Self {
+        Self::from_bits_truncate(flags)
+    }
+}
+
+impl From<Flags> for u32 {
+    fn from(flags: Flags) -> Self {
+        flags.bits()
+    }
+}
+
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// Raw VBI format
+///
+/// Raw VBI buffers contain the sampled video signal of the lines in the vertical blanking
+/// interval, which carry data such as teletext, WSS or VPS. Decoding the data is up to the
+/// application.
+pub struct Format {
+    /// Samples per second, e.g. 27 MHz for ITU-R BT.601
+    pub sampling_rate: u32,
+    /// Number of samples between the horizontal sync pulse and the first sample of each line
+    pub offset: u32,
+    /// Samples per line
+    pub samples_per_line: u32,
+    /// Format of the samples, always GREY (unsigned 8 bit) in practice
+    pub sample_format: FourCC,
+    /// First line captured in each field, using ITU-R line numbering
+    pub start: [i32; 2],
+    /// Number of lines captured in each field
+    pub count: [u32; 2],
+    /// Format flags
+    pub flags: Flags,
+}
+
+impl Format {
+    /// Returns the number of lines of both fields
+    pub fn lines(&self) -> u32 {
+        self.count[0] + self.count[1]
+    }
+
+    /// Returns the size of a buffer holding all lines of both fields in bytes
+    pub fn size(&self) -> usize {
+        self.samples_per_line as usize * self.lines() as usize
+    }
+
+    /// Splits a buffer into the lines it consists of
+    ///
+    /// Lines not fully contained in the buffer are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Buffer filled by the driver
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::vbi::raw::Format;
+    ///
+    /// let format = Format {
+    ///     samples_per_line: 2,
+    ///     start: [10, 273],
+    ///     count: [1, 2],
+    ///     ..Default::default()
+    /// };
+    /// let data = [1, 2, 3, 4, 5, 6];
+    /// let lines: Vec<_> = format.split(&data).map(|l| (l.field, l.number)).collect();
+    /// assert_eq!(lines, [(0, 10), (1, 273), (1, 274)]);
+    /// ```
+    pub fn split<'a>(&self, data: &'a [u8]) -> impl Iterator<Item = Line<'a>> {
+        let format = *self;
+        data.chunks_exact(self.samples_per_line.max(1) as usize)
+            .take(self.lines() as usize)
+            .enumerate()
+            .map(move |(i, samples)| {
+                let i = i as u32;
+                let (field, index) = if format.flags.contains(Flags::INTERLACED) {
+                    ((i & 1) as usize, i / 2)
+                } else if i < format.count[0] {
+                    (0, i)
+                } else {
+                    (1, i - format.count[0])
+                };
+
+                Line {
+                    field: field as u8,
+                    number: format.start[field] + index as i32,
+                    samples,
+                }
+            })
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "sampling rate  : {}", self.sampling_rate)?;
+        writeln!(f, "offset         : {}", self.offset)?;
+        writeln!(f, "samples/line   : {}", self.samples_per_line)?;
+        writeln!(f, "sample format  : {}", self.sample_format)?;
+        writeln!(f, "start          : {:?}", self.start)?;
+        writeln!(f, "count          : {:?}", self.count)?;
+        writeln!(f, "flags          : {}", self.flags)?;
+        Ok(())
+    }
+}
+
+impl From<v4l2_vbi_format> for Format {
+    fn from(fmt: v4l2_vbi_format) -> Self {
+        Self {
+            sampling_rate: fmt.sampling_rate,
+            offset: fmt.offset,
+            samples_per_line: fmt.samples_per_line,
+            sample_format: FourCC::from(fmt.sample_format),
+            start: fmt.start,
+            count: fmt.count,
+            flags: Flags::from(fmt.flags),
+        }
+    }
+}
+
+impl From<Format> for v4l2_vbi_format {
+    fn from(format: Format) -> Self {
+        Self {
+            sampling_rate: format.sampling_rate,
+            offset: format.offset,
+            samples_per_line: format.samples_per_line,
+            sample_format: format.sample_format.into(),
+            start: format.start,
+            count: format.count,
+            flags: format.flags.into(),
+            ..unsafe { mem::zeroed() }
+        }
+    }
+}
+
+/// Single line of a raw VBI buffer
+#[derive(Debug, Copy, Clone)]
+pub struct Line<'a> {
+    /// Field the line belongs to, 0 for the first and 1 for the second field
+    pub field: u8,
+    /// Line number, using ITU-R line numbering
+    pub number: i32,
+    /// Samples of the line
+    pub samples: &'a [u8],
+}
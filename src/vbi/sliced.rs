@@ -0,0 +1,195 @@
+use bitflags::bitflags;
+use std::convert::TryInto;
+use std::{fmt, mem};
+
+use crate::v4l_sys::*;
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    pub struct Services: u16 {
+        /// Teletext system B, used on 625 line systems
+        const TELETEXT_B    = 0x0001;
+        /// Video programming system, used on line 16 of 625 line systems
+        const VPS           = 0x0400;
+        /// Closed captions (CEA-608), used on line 21 of 525 line systems
+        const CAPTION_525   = 0x1000;
+        /// Wide screen signaling, used on line 23 of 625 line systems
+        const WSS_625       = 0x4000;
+
+        /// All services of 525 line systems
+        const VBI_525       = Self::CAPTION_525.bits;
+        /// All services of 625 line systems
+        const VBI_625       = Self::TELETEXT_B.bits | Self::VPS.bits | Self::WSS_625.bits;
+    }
+}
+
+impl Default for Services {
+    fn default() -> Self {
+        Services::from(0)
+    }
+}
+
+impl From<u16> for Services {
+    fn from(services: u16) -> Self {
+        Self::from_bits_truncate(services)
+    }
+}
+
+impl From<Services> for u16 {
+    fn from(services: Services) -> Self {
+        services.bits()
+    }
+}
+
+impl fmt::Display for Services {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Services per line and field, indexed by field (0 or 1) and line number (0 to 23)
+pub type ServiceLines = [[Services; 24]; 2];
+
+fn lines_from(lines: [[u16; 24]; 2]) -> ServiceLines {
+    let mut services = ServiceLines::default();
+    for (field, lines) in lines.iter().enumerate() {
+        for (line, bits) in lines.iter().enumerate() {
+            services[field][line] = Services::from(*bits);
+        }
+    }
+    services
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// Sliced VBI format
+///
+/// Sliced VBI devices decode the data services themselves and return the payload of each line.
+pub struct Format {
+    /// Union of all services in [`Format::service_lines`]
+    ///
+    /// When setting the format and all lines are empty, the driver picks the lines for these
+    /// services instead.
+    pub services: Services,
+    /// Services expected on each line
+    pub service_lines: ServiceLines,
+    /// Size of a buffer in bytes, i.e. the maximum number of [`Data`] entries per frame times
+    /// [`Data::SIZE`]
+    pub io_size: u32,
+}
+
+impl Format {
+    /// Returns a format, letting the driver pick the lines for the services
+    ///
+    /// # Arguments
+    ///
+    /// * `services` - Services to capture
+    pub fn new(services: Services) -> Self {
+        Format {
+            services,
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "services       : {}", self.services)?;
+        writeln!(f, "io size        : {}", self.io_size)?;
+        Ok(())
+    }
+}
+
+impl From<v4l2_sliced_vbi_format> for Format {
+    fn from(fmt: v4l2_sliced_vbi_format) -> Self {
+        Self {
+            services: Services::from(fmt.service_set),
+            service_lines: lines_from(fmt.service_lines),
+            io_size: fmt.io_size,
+        }
+    }
+}
+
+impl From<Format> for v4l2_sliced_vbi_format {
+    fn from(format: Format) -> Self {
+        let mut fmt: v4l2_sliced_vbi_format = unsafe { mem::zeroed() };
+        fmt.service_set = format.services.into();
+        for (field, lines) in format.service_lines.iter().enumerate() {
+            for (line, services) in lines.iter().enumerate() {
+                fmt.service_lines[field][line] = (*services).into();
+            }
+        }
+        fmt.io_size = format.io_size;
+        fmt
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+/// Sliced VBI capabilities
+pub struct Capabilities {
+    /// Union of all services in [`Capabilities::service_lines`]
+    pub services: Services,
+    /// Services the hardware can decode on each line
+    pub service_lines: ServiceLines,
+}
+
+impl From<v4l2_sliced_vbi_cap> for Capabilities {
+    fn from(cap: v4l2_sliced_vbi_cap) -> Self {
+        Self {
+            services: Services::from(cap.service_set),
+            service_lines: lines_from(cap.service_lines),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+/// Payload of a single line, as found in sliced VBI buffers
+pub struct Data {
+    /// Service the payload belongs to
+    pub service: Services,
+    /// Field the line belongs to, 0 for the first and 1 for the second field
+    pub field: u32,
+    /// Line number, using ITU-R line numbering
+    pub line: u32,
+    /// Payload, the meaningful length depends on the service
+    pub data: [u8; 48],
+}
+
+impl Data {
+    /// Size of each entry within a buffer in bytes
+    pub const SIZE: usize = mem::size_of::<v4l2_sliced_vbi_data>();
+
+    /// Parses the entries of a buffer
+    ///
+    /// Empty entries, i.e. lines on which no data was found, are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Buffer filled by the driver, up to its `bytesused`
+    pub fn parse(buf: &[u8]) -> impl Iterator<Item = Data> + '_ {
+        buf.chunks_exact(Self::SIZE).filter_map(|entry| {
+            let word = |i: usize| u32::from_ne_bytes(entry[i * 4..i * 4 + 4].try_into().unwrap());
+            let service = Services::from(word(0) as u16);
+            if service.is_empty() {
+                return None;
+            }
+
+            Some(Data {
+                service,
+                field: word(1),
+                line: word(2),
+                data: entry[16..64].try_into().unwrap(),
+            })
+        })
+    }
+
+    /// Returns the closed caption byte pair of CEA-608 lines
+    ///
+    /// The pair still contains the parity bits, see [`super::cea608::Decoder`].
+    pub fn caption(&self) -> Option<[u8; 2]> {
+        if self.service == Services::CAPTION_525 {
+            Some([self.data[0], self.data[1]])
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use std::io;
+
+use crate::vbi::raw::Format as RawFormat;
+use crate::vbi::sliced::{Capabilities as SlicedCapabilities, Format as SlicedFormat};
+
+/// VBI capture device protocol
+pub trait Capture {
+    /// Returns the raw VBI format currently in use
+    fn raw_format(&self) -> io::Result<RawFormat>;
+
+    /// Modifies the raw VBI format and returns the actual format
+    ///
+    /// Most drivers only allow for changing the captured lines, the sampling parameters are fixed
+    /// by the hardware and reported back.
+    ///
+    /// # Arguments
+    ///
+    /// * `fmt` - Desired format
+    fn set_raw_format(&self, fmt: &RawFormat) -> io::Result<RawFormat>;
+
+    /// Returns the services a sliced VBI device can decode
+    fn sliced_capabilities(&self) -> io::Result<SlicedCapabilities>;
+
+    /// Returns the sliced VBI format currently in use
+    fn sliced_format(&self) -> io::Result<SlicedFormat>;
+
+    /// Modifies the sliced VBI format and returns the actual format
+    ///
+    /// Services or lines which the hardware cannot decode are removed by the driver.
+    ///
+    /// # Arguments
+    ///
+    /// * `fmt` - Desired format
+    fn set_sliced_format(&self, fmt: &SlicedFormat) -> io::Result<SlicedFormat>;
+}
@@ -5,5 +5,6 @@ pub mod traits;
 
 pub mod capture;
 pub mod output;
+pub mod overlay;
 
-pub use traits::{Capture, Output};
+pub use traits::{Capture, Output, OutputOverlay, Overlay};
@@ -1,6 +1,9 @@
 pub mod parameters;
 pub use parameters::Parameters;
 
+pub mod virtual_camera;
+pub use virtual_camera::VirtualCamera;
+
 use std::convert::TryFrom;
 use std::{io, mem};
 
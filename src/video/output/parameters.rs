@@ -25,6 +25,11 @@ impl Parameters {
             interval: Fraction::new(1, fps),
         }
     }
+
+    /// Returns the frame rate
+    pub fn fps(&self) -> f64 {
+        self.interval.to_fps()
+    }
 }
 
 impl fmt::Display for Parameters {
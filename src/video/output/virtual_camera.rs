@@ -0,0 +1,181 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+use std::{io, thread};
+
+use crate::buffer::Type;
+use crate::device::Device;
+use crate::format::{FieldOrder, Format};
+use crate::fraction::Fraction;
+use crate::io::mmap::Stream;
+use crate::io::traits::{OutputStream, Stream as StreamTrait};
+use crate::video::output::Parameters;
+use crate::video::traits::Output;
+
+/// Virtual camera, e.g. a v4l2loopback output node
+///
+/// Frames pushed into the virtual camera show up on the capture side of the loopback device,
+/// where any regular video application can pick them up.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use v4l::video::output::VirtualCamera;
+/// use v4l::{Format, FourCC, Fraction};
+///
+/// let format = Format::new(640, 480, FourCC::new(b"YUYV"));
+/// let mut cam = VirtualCamera::with_path("/dev/video10", &format, Fraction::new(1, 30))
+///     .expect("Failed to open virtual camera");
+///
+/// let frame = vec![0u8; 640 * 480 * 2];
+/// for i in 0..300 {
+///     cam.push_frame(&frame, Duration::from_millis(i * 1000 / 30))
+///         .expect("Failed to push frame");
+/// }
+/// ```
+pub struct VirtualCamera {
+    dev: Device,
+    format: Format,
+    stream: Stream<'static>,
+    /// Number of buffers handed to the driver so far, until all of them are in flight
+    queued: usize,
+    /// Wallclock time and timestamp of the first frame, used to pace the following ones
+    clock: Option<(Instant, Duration)>,
+}
+
+impl VirtualCamera {
+    /// Returns a virtual camera by index
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the output node (0: first, 1: second, ..)
+    /// * `format` - Format of the frames which will be pushed
+    /// * `interval` - Time per frame
+    pub fn new(index: usize, format: &Format, interval: Fraction) -> io::Result<Self> {
+        Self::with_device(Device::new(index)?, format, interval)
+    }
+
+    /// Returns a virtual camera by path
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the output node (e.g. "/dev/video10")
+    /// * `format` - Format of the frames which will be pushed
+    /// * `interval` - Time per frame
+    pub fn with_path<P: AsRef<Path>>(
+        path: P,
+        format: &Format,
+        interval: Fraction,
+    ) -> io::Result<Self> {
+        Self::with_device(Device::with_path(path)?, format, interval)
+    }
+
+    /// Configures an output device as virtual camera
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Output device
+    /// * `format` - Format of the frames which will be pushed
+    /// * `interval` - Time per frame
+    pub fn with_device(dev: Device, format: &Format, interval: Fraction) -> io::Result<Self> {
+        // v4l2loopback only sizes its buffers once a format has been set, so always set one
+        // instead of relying on the active format.
+        let actual = Output::set_format(&dev, format)?;
+        if actual.width != format.width
+            || actual.height != format.height
+            || actual.fourcc != format.fourcc
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "failed to enforce format on output device",
+            ));
+        }
+        Output::set_params(&dev, &Parameters::new(interval))?;
+
+        let stream = Stream::with_buffers(&dev, Type::VideoOutput, 4)?;
+
+        Ok(VirtualCamera {
+            dev,
+            format: actual,
+            stream,
+            queued: 0,
+            clock: None,
+        })
+    }
+
+    /// Returns the output device
+    pub fn device(&self) -> &Device {
+        &self.dev
+    }
+
+    /// Returns the format negotiated with the device
+    pub fn format(&self) -> &Format {
+        &self.format
+    }
+
+    /// Pushes a frame to the virtual camera
+    ///
+    /// Frames are paced according to their timestamps: this call blocks until the timestamp,
+    /// relative to the one of the first frame, has passed on the wallclock. Frames carrying an
+    /// older timestamp than the first frame are pushed immediately.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Frame data in the format of the virtual camera
+    /// * `timestamp` - Presentation timestamp of the frame
+    pub fn push_frame(&mut self, data: &[u8], timestamp: Duration) -> io::Result<()> {
+        // all buffers share the same size, so check before taking one from the driver
+        let size = self.stream.get_mut(0).map_or(0, |(buf, _)| buf.len());
+        if data.len() > size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame exceeds the buffer size",
+            ));
+        }
+
+        self.pace(timestamp);
+
+        let index = if self.queued < self.stream.len() {
+            self.queued
+        } else {
+            // wait for the driver to hand back a consumed buffer
+            if self.dev.handle().poll(libc::POLLOUT, None)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_DQBUF"));
+            }
+            OutputStream::dequeue(&mut self.stream)?
+        };
+
+        let (buf, meta) = self
+            .stream
+            .get_mut(index)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid buffer index"))?;
+        buf[..data.len()].copy_from_slice(data);
+        meta.bytesused = data.len() as u32;
+        meta.field = FieldOrder::Progressive;
+        meta.timestamp = timestamp.into();
+
+        OutputStream::queue(&mut self.stream, index)?;
+        if self.queued < self.stream.len() {
+            if self.queued == 0 {
+                self.stream.start()?;
+            }
+            self.queued += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Sleeps until it is time to present a frame with the given timestamp
+    fn pace(&mut self, timestamp: Duration) {
+        let (start, first) = *self
+            .clock
+            .get_or_insert_with(|| (Instant::now(), timestamp));
+        if let Some(offset) = timestamp.checked_sub(first) {
+            let deadline = start + offset;
+            let now = Instant::now();
+            if deadline > now {
+                thread::sleep(deadline - now);
+            }
+        }
+    }
+}
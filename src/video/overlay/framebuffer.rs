@@ -0,0 +1,156 @@
+use bitflags::bitflags;
+use std::convert::TryFrom;
+use std::{fmt, mem};
+
+use crate::format::{Colorspace, FieldOrder, Format, FourCC};
+use crate::v4l_sys::*;
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    pub struct Capabilities: u32 {
+        /// The overlay is mixed into the video signal externally, e.g. by the graphics card
+        const EXTERN_OVERLAY    = 0x0001;
+        const CHROMAKEY         = 0x0002;
+        const LIST_CLIPPING     = 0x0004;
+        const BITMAP_CLIPPING   = 0x0008;
+        const LOCAL_ALPHA       = 0x0010;
+        const GLOBAL_ALPHA      = 0x0020;
+        const LOCAL_INV_ALPHA   = 0x0040;
+        const SRC_CHROMAKEY     = 0x0080;
+    }
+}
+
+impl From<u32> for Capabilities {
+    fn from(caps: u32) -> Self {
+        Self::from_bits_truncate(caps)
+    }
+}
+
+impl From<Capabilities> for u32 {
+    fn from(caps: Capabilities) -> Self {
+        caps.bits()
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+bitflags! {
+    #[allow(clippy::unreadable_literal)]
+    pub struct Flags: u32 {
+        /// The framebuffer is the primary graphics surface
+        const PRIMARY           = 0x0001;
+        /// The framebuffer is overlaid onto the video, rather than the other way around
+        const OVERLAY           = 0x0002;
+        const CHROMAKEY         = 0x0004;
+        const LOCAL_ALPHA       = 0x0008;
+        const GLOBAL_ALPHA      = 0x0010;
+        const LOCAL_INV_ALPHA   = 0x0020;
+        const SRC_CHROMAKEY     = 0x0040;
+    }
+}
+
+impl Default for Flags {
+    fn default() -> Self {
+        Flags::from(0)
+    }
+}
+
+impl From<u32> for Flags {
+    fn from(flags: u32) -> Self {
+        Self::from_bits_truncate(flags)
+    }
+}
+
+impl From<Flags> for u32 {
+    fn from(flags: Flags) -> Self {
+        flags.bits()
+    }
+}
+
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+/// Framebuffer the video overlay is drawn into
+pub struct Framebuffer {
+    /// Overlay features supported by the driver, ignored when setting the framebuffer
+    pub capabilities: Capabilities,
+    /// Overlay features in use
+    pub flags: Flags,
+    /// Physical base address of the framebuffer, null for external overlays
+    pub base: *mut std::os::raw::c_void,
+    /// Layout of the framebuffer memory
+    pub format: Format,
+}
+
+impl Framebuffer {
+    /// Returns a framebuffer description
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - Physical base address of the framebuffer
+    /// * `format` - Layout of the framebuffer memory
+    pub fn new(base: *mut std::os::raw::c_void, format: Format) -> Self {
+        Framebuffer {
+            capabilities: Capabilities::empty(),
+            flags: Flags::default(),
+            base,
+            format,
+        }
+    }
+}
+
+impl fmt::Display for Framebuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "capabilities   : {}", self.capabilities)?;
+        writeln!(f, "flags          : {}", self.flags)?;
+        writeln!(f, "base           : {:p}", self.base)?;
+        write!(f, "{}", self.format)
+    }
+}
+
+impl From<v4l2_framebuffer> for Framebuffer {
+    fn from(fbuf: v4l2_framebuffer) -> Self {
+        let mut format = Format::new(
+            fbuf.fmt.width,
+            fbuf.fmt.height,
+            FourCC::from(fbuf.fmt.pixelformat),
+        );
+        format.field_order = FieldOrder::try_from(fbuf.fmt.field).unwrap_or(FieldOrder::Any);
+        format.stride = fbuf.fmt.bytesperline;
+        format.size = fbuf.fmt.sizeimage;
+        format.colorspace =
+            Colorspace::try_from(fbuf.fmt.colorspace).unwrap_or(Colorspace::Default);
+
+        Self {
+            capabilities: Capabilities::from(fbuf.capability),
+            flags: Flags::from(fbuf.flags),
+            base: fbuf.base,
+            format,
+        }
+    }
+}
+
+impl From<Framebuffer> for v4l2_framebuffer {
+    fn from(fbuf: Framebuffer) -> Self {
+        let mut v4l2_fbuf: v4l2_framebuffer = unsafe { mem::zeroed() };
+        v4l2_fbuf.capability = fbuf.capabilities.into();
+        v4l2_fbuf.flags = fbuf.flags.into();
+        v4l2_fbuf.base = fbuf.base;
+        v4l2_fbuf.fmt.width = fbuf.format.width;
+        v4l2_fbuf.fmt.height = fbuf.format.height;
+        v4l2_fbuf.fmt.pixelformat = fbuf.format.fourcc.into();
+        v4l2_fbuf.fmt.field = fbuf.format.field_order as u32;
+        v4l2_fbuf.fmt.bytesperline = fbuf.format.stride;
+        v4l2_fbuf.fmt.sizeimage = fbuf.format.size;
+        v4l2_fbuf.fmt.colorspace = fbuf.format.colorspace as u32;
+        v4l2_fbuf
+    }
+}
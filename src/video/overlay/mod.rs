@@ -0,0 +1,177 @@
+//! Video overlay
+//!
+//! Some capture cards can write video straight into a framebuffer (usually that of the graphics
+//! card), which allows for a live preview without any copying by the CPU. The framebuffer is
+//! configured through [`Overlay::set_framebuffer()`], the part of it which is covered by the video
+//! through [`Overlay::set_window()`].
+//!
+//! Output overlays work the other way around, blending the framebuffer over the video output of
+//! the device. Their window is configured through [`OutputOverlay::set_window()`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use v4l::selection::Rect;
+//! use v4l::video::overlay::Window;
+//! use v4l::video::Overlay;
+//! use v4l::Device;
+//!
+//! let dev = Device::new(0).expect("Failed to open device");
+//! let fbuf = dev.framebuffer().expect("Failed to get framebuffer");
+//! println!("{}", fbuf);
+//!
+//! dev.set_window(&Window::new(Rect::new(0, 0, 640, 480)))
+//!     .expect("Failed to set overlay window");
+//! dev.start_overlay().expect("Failed to start overlay");
+//! ```
+
+pub mod framebuffer;
+pub use framebuffer::Framebuffer;
+
+pub mod window;
+pub use window::Window;
+
+use std::convert::TryFrom;
+use std::{io, mem, ptr};
+
+use crate::buffer::Type;
+use crate::device::Device;
+use crate::format::FieldOrder;
+use crate::selection::Rect;
+use crate::v4l2;
+use crate::v4l_sys::*;
+use crate::video::traits::{OutputOverlay, Overlay};
+
+/// Maximum number of clips read back from the driver
+const MAX_CLIPS: usize = 256;
+
+impl Overlay for Device {
+    fn framebuffer(&self) -> io::Result<Framebuffer> {
+        unsafe {
+            let mut v4l2_fbuf: v4l2_framebuffer = mem::zeroed();
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_G_FBUF,
+                &mut v4l2_fbuf as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            Ok(Framebuffer::from(v4l2_fbuf))
+        }
+    }
+
+    fn set_framebuffer(&self, fbuf: &Framebuffer) -> io::Result<Framebuffer> {
+        unsafe {
+            let mut v4l2_fbuf: v4l2_framebuffer = (*fbuf).into();
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_S_FBUF,
+                &mut v4l2_fbuf as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        self.framebuffer()
+    }
+
+    fn window(&self) -> io::Result<Window> {
+        get_window(self, Type::VideoOverlay)
+    }
+
+    fn set_window(&self, win: &Window) -> io::Result<Window> {
+        set_window(self, Type::VideoOverlay, win)
+    }
+
+    fn start_overlay(&self) -> io::Result<()> {
+        set_overlay(self, true)
+    }
+
+    fn stop_overlay(&self) -> io::Result<()> {
+        set_overlay(self, false)
+    }
+}
+
+impl OutputOverlay for Device {
+    fn window(&self) -> io::Result<Window> {
+        get_window(self, Type::VideoOutputOverlay)
+    }
+
+    fn set_window(&self, win: &Window) -> io::Result<Window> {
+        set_window(self, Type::VideoOutputOverlay, win)
+    }
+}
+
+fn get_window(dev: &Device, buf_type: Type) -> io::Result<Window> {
+    let mut clips: Vec<v4l2_clip> = vec![unsafe { mem::zeroed() }; MAX_CLIPS];
+
+    let win = unsafe {
+        let mut v4l2_fmt = v4l2_format {
+            type_: buf_type as u32,
+            ..mem::zeroed()
+        };
+        v4l2_fmt.fmt.win.clips = clips.as_mut_ptr();
+        v4l2_fmt.fmt.win.clipcount = MAX_CLIPS as u32;
+        v4l2::ioctl(
+            dev.handle().fd(),
+            v4l2::vidioc::VIDIOC_G_FMT,
+            &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
+        )?;
+
+        v4l2_fmt.fmt.win
+    };
+
+    // drivers without clipping support leave the list untouched
+    let count = (win.clipcount as usize).min(MAX_CLIPS);
+    Ok(Window {
+        rect: Rect::from(win.w),
+        field_order: FieldOrder::try_from(win.field).unwrap_or(FieldOrder::Any),
+        chromakey: win.chromakey,
+        clips: clips[..count]
+            .iter()
+            .map(|clip| Rect::from(clip.c))
+            .collect(),
+        global_alpha: win.global_alpha,
+    })
+}
+
+fn set_window(dev: &Device, buf_type: Type, win: &Window) -> io::Result<Window> {
+    let mut clips: Vec<v4l2_clip> = win
+        .clips
+        .iter()
+        .map(|rect| v4l2_clip {
+            c: (*rect).into(),
+            next: ptr::null_mut(),
+        })
+        .collect();
+
+    unsafe {
+        let mut v4l2_fmt = v4l2_format {
+            type_: buf_type as u32,
+            ..mem::zeroed()
+        };
+        v4l2_fmt.fmt.win.w = win.rect.into();
+        v4l2_fmt.fmt.win.field = win.field_order as u32;
+        v4l2_fmt.fmt.win.chromakey = win.chromakey;
+        if !clips.is_empty() {
+            v4l2_fmt.fmt.win.clips = clips.as_mut_ptr();
+            v4l2_fmt.fmt.win.clipcount = clips.len() as u32;
+        }
+        v4l2_fmt.fmt.win.global_alpha = win.global_alpha;
+        v4l2::ioctl(
+            dev.handle().fd(),
+            v4l2::vidioc::VIDIOC_S_FMT,
+            &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
+        )?;
+    }
+
+    get_window(dev, buf_type)
+}
+
+fn set_overlay(dev: &Device, on: bool) -> io::Result<()> {
+    let mut on = on as std::os::raw::c_int;
+    unsafe {
+        v4l2::ioctl(
+            dev.handle().fd(),
+            v4l2::vidioc::VIDIOC_OVERLAY,
+            &mut on as *mut _ as *mut std::os::raw::c_void,
+        )
+    }
+}
@@ -0,0 +1,63 @@
+use std::fmt;
+
+use crate::format::FieldOrder;
+use crate::selection::Rect;
+
+#[derive(Debug, Clone)]
+/// Placement of the video overlay within the framebuffer
+pub struct Window {
+    /// Position and size of the video within the framebuffer
+    pub rect: Rect,
+    /// Field order of the overlaid video
+    pub field_order: FieldOrder,
+    /// Color key in the pixel format of the framebuffer, used if chroma keying is enabled
+    pub chromakey: u32,
+    /// Areas of the window the video must not be drawn into, e.g. windows on top of it
+    ///
+    /// Clip lists are only honored if the framebuffer reports
+    /// [`LIST_CLIPPING`](super::framebuffer::Capabilities::LIST_CLIPPING), recent kernels dropped
+    /// support for them altogether.
+    pub clips: Vec<Rect>,
+    /// Alpha value applied to the whole window, used if global alpha is enabled
+    pub global_alpha: u8,
+}
+
+impl Window {
+    /// Returns an overlay window without clips
+    ///
+    /// # Arguments
+    ///
+    /// * `rect` - Position and size of the video within the framebuffer
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::selection::Rect;
+    /// use v4l::video::overlay::Window;
+    /// let win = Window::new(Rect::new(0, 0, 640, 480));
+    /// ```
+    pub fn new(rect: Rect) -> Self {
+        Window {
+            rect,
+            field_order: FieldOrder::Any,
+            chromakey: 0,
+            clips: Vec::new(),
+            global_alpha: 0xff,
+        }
+    }
+}
+
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "rect           : {}x{}@({}, {})",
+            self.rect.width, self.rect.height, self.rect.left, self.rect.top
+        )?;
+        writeln!(f, "field          : {}", self.field_order)?;
+        writeln!(f, "chromakey      : {:#010x}", self.chromakey)?;
+        writeln!(f, "clips          : {}", self.clips.len())?;
+        writeln!(f, "global alpha   : {}", self.global_alpha)?;
+        Ok(())
+    }
+}
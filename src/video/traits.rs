@@ -2,8 +2,9 @@ use std::io;
 
 use crate::video::capture::Parameters as CaptureParameters;
 use crate::video::output::Parameters as OutputParameters;
+use crate::video::overlay::{Framebuffer, Window};
 use crate::{
-    format::Description as FormatDescription, format::Format, format::FourCC,
+    format::Adjustments, format::Description as FormatDescription, format::Format, format::FourCC,
     frameinterval::FrameInterval, framesize::FrameSize,
 };
 
@@ -42,6 +43,19 @@ pub trait Capture {
     /// * `fmt` - Desired format
     fn set_format(&self, fmt: &Format) -> io::Result<Format>;
 
+    /// Modifies the format and reports which properties the driver adjusted
+    ///
+    /// Drivers silently pick the closest format they support, e.g. 720p MJPEG when asked for
+    /// 1080p YUYV. Check [`Adjustments::is_empty()`] to catch that right away.
+    ///
+    /// # Arguments
+    ///
+    /// * `fmt` - Desired format
+    fn set_format_verified(&self, fmt: &Format) -> io::Result<Adjustments> {
+        let applied = self.set_format(fmt)?;
+        Ok(Adjustments::new(*fmt, applied))
+    }
+
     /// Returns the parameters currently in use
     fn params(&self) -> io::Result<CaptureParameters>;
 
@@ -87,6 +101,19 @@ pub trait Output {
     /// * `fmt` - Desired format
     fn set_format(&self, fmt: &Format) -> io::Result<Format>;
 
+    /// Modifies the format and reports which properties the driver adjusted
+    ///
+    /// Drivers silently pick the closest format they support, e.g. 720p MJPEG when asked for
+    /// 1080p YUYV. Check [`Adjustments::is_empty()`] to catch that right away.
+    ///
+    /// # Arguments
+    ///
+    /// * `fmt` - Desired format
+    fn set_format_verified(&self, fmt: &Format) -> io::Result<Adjustments> {
+        let applied = self.set_format(fmt)?;
+        Ok(Adjustments::new(*fmt, applied))
+    }
+
     /// Returns the parameters currently in use
     fn params(&self) -> io::Result<OutputParameters>;
 
@@ -97,3 +124,59 @@ pub trait Output {
     /// * `params` - Desired parameters
     fn set_params(&self, params: &OutputParameters) -> io::Result<OutputParameters>;
 }
+
+/// Video overlay protocol
+pub trait Overlay {
+    /// Returns the framebuffer the overlay is drawn into
+    fn framebuffer(&self) -> io::Result<Framebuffer>;
+
+    /// Modifies the framebuffer and returns the actual framebuffer
+    ///
+    /// Setting a destructive overlay usually requires the `CAP_SYS_ADMIN` capability, since the
+    /// device writes to physical memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `fbuf` - Desired framebuffer
+    fn set_framebuffer(&self, fbuf: &Framebuffer) -> io::Result<Framebuffer>;
+
+    /// Returns the overlay window currently in use
+    fn window(&self) -> io::Result<Window>;
+
+    /// Modifies the overlay window and returns the actual window
+    ///
+    /// The driver may adjust the window, e.g. to align it or to fit it into the framebuffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `win` - Desired window
+    fn set_window(&self, win: &Window) -> io::Result<Window>;
+
+    /// Starts drawing the video into the framebuffer
+    fn start_overlay(&self) -> io::Result<()>;
+
+    /// Stops drawing the video into the framebuffer
+    fn stop_overlay(&self) -> io::Result<()>;
+}
+
+/// Video output overlay protocol
+///
+/// Output overlays, also known as on-screen displays, blend graphics from a framebuffer over the
+/// video output of the device, e.g. on TV-out or encoder hardware. The framebuffer holding the
+/// graphics is configured through [`Overlay::set_framebuffer()`]. Unlike capture overlays, the
+/// output overlay is active while the video output is streaming.
+pub trait OutputOverlay {
+    /// Returns the output overlay window currently in use
+    fn window(&self) -> io::Result<Window>;
+
+    /// Modifies the output overlay window and returns the actual window
+    ///
+    /// The window describes the part of the video the graphics are blended into. Clips, the
+    /// chroma key and global alpha are applied to the graphics as well, if enabled in the flags
+    /// of the framebuffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `win` - Desired window
+    fn set_window(&self, win: &Window) -> io::Result<Window>;
+}
@@ -0,0 +1,238 @@
+//! White balance helpers
+//!
+//! Some devices let applications choose a color temperature, others only expose the gains of the
+//! red and blue channels. [`WhiteBalance`] accepts color temperatures either way and converts them
+//! to gains through a [`Curve`] if needed.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use v4l::white_balance::WhiteBalance;
+//! use v4l::Device;
+//!
+//! let dev = Device::new(0).expect("Failed to open device");
+//! let wb = WhiteBalance::new(&dev).expect("Device has no white balance controls");
+//! wb.set_auto(false).expect("Failed to disable automatic white balance");
+//! wb.set_temperature(3200).expect("Failed to set color temperature");
+//! ```
+
+use std::io;
+
+use crate::control::{Control, Description, Flags, Value};
+use crate::device::Device;
+
+/// Automatic white balance (`V4L2_CID_AUTO_WHITE_BALANCE`)
+pub const V4L2_CID_AUTO_WHITE_BALANCE: u32 = 0x0098_090c;
+/// Red channel gain (`V4L2_CID_RED_BALANCE`)
+pub const V4L2_CID_RED_BALANCE: u32 = 0x0098_090e;
+/// Blue channel gain (`V4L2_CID_BLUE_BALANCE`)
+pub const V4L2_CID_BLUE_BALANCE: u32 = 0x0098_090f;
+/// Color temperature in Kelvin (`V4L2_CID_WHITE_BALANCE_TEMPERATURE`)
+pub const V4L2_CID_WHITE_BALANCE_TEMPERATURE: u32 = 0x0098_091a;
+
+/// Red and blue gains needed to render light of a color temperature neutral
+///
+/// Gains are relative to the default value of the balance controls, which drivers usually
+/// calibrate for daylight. Between two points, gains are interpolated linearly in mired
+/// (1,000,000 / Kelvin), which matches the perceived color difference better than Kelvin does.
+///
+/// # Example
+///
+/// ```
+/// use v4l::white_balance::Curve;
+///
+/// let curve = Curve::new(vec![(3000, 0.7, 1.6), (6500, 1.0, 1.0)]).unwrap();
+/// assert_eq!(curve.gains(6500), (1.0, 1.0));
+/// // clamped to the ends of the curve
+/// assert_eq!(curve.gains(2000), (0.7, 1.6));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    points: Vec<(u32, f64, f64)>,
+}
+
+impl Curve {
+    /// Returns a curve through a set of points
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - Color temperature in Kelvin, red gain and blue gain, in any order
+    pub fn new(mut points: Vec<(u32, f64, f64)>) -> io::Result<Self> {
+        if points.is_empty() || points.iter().any(|&(kelvin, _, _)| kelvin == 0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "curve needs at least one point with a positive temperature",
+            ));
+        }
+
+        points.sort_by_key(|&(kelvin, _, _)| kelvin);
+        Ok(Curve { points })
+    }
+
+    /// Returns the points of the curve, ordered by color temperature
+    pub fn points(&self) -> &[(u32, f64, f64)] {
+        &self.points
+    }
+
+    /// Returns the range of color temperatures covered by the curve
+    pub fn range(&self) -> (u32, u32) {
+        (self.points[0].0, self.points[self.points.len() - 1].0)
+    }
+
+    /// Returns the red and blue gains for a color temperature
+    ///
+    /// # Arguments
+    ///
+    /// * `kelvin` - Color temperature, clamped to the range of the curve
+    pub fn gains(&self, kelvin: u32) -> (f64, f64) {
+        let (min, max) = self.range();
+        let kelvin = kelvin.clamp(min, max);
+        let upper = self
+            .points
+            .iter()
+            .position(|&(k, _, _)| k >= kelvin)
+            .unwrap_or(self.points.len() - 1);
+        let (k1, r1, b1) = self.points[upper];
+        if upper == 0 || k1 == kelvin {
+            return (r1, b1);
+        }
+
+        let (k0, r0, b0) = self.points[upper - 1];
+        let mired = |k: u32| 1e6 / k as f64;
+        let t = (mired(kelvin) - mired(k0)) / (mired(k1) - mired(k0));
+        (r0 + t * (r1 - r0), b0 + t * (b1 - b0))
+    }
+}
+
+impl Default for Curve {
+    /// Approximate gains of a typical CMOS sensor, neutral at 6500 K
+    fn default() -> Self {
+        Curve {
+            points: vec![
+                (2000, 0.55, 2.20),
+                (2800, 0.68, 1.75),
+                (4000, 0.82, 1.35),
+                (5000, 0.91, 1.15),
+                (6500, 1.00, 1.00),
+                (7500, 1.05, 0.92),
+                (10000, 1.14, 0.80),
+            ],
+        }
+    }
+}
+
+/// White balance of a device
+pub struct WhiteBalance<'a> {
+    dev: &'a Device,
+    temperature: Option<Description>,
+    gains: Option<(Description, Description)>,
+    curve: Curve,
+}
+
+impl<'a> WhiteBalance<'a> {
+    /// Returns the white balance of a device
+    ///
+    /// Fails if the device has neither a color temperature control nor red and blue balance
+    /// controls.
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Device
+    pub fn new(dev: &'a Device) -> io::Result<Self> {
+        let query = |id| {
+            dev.query_control(id)
+                .ok()
+                .filter(|desc| !desc.flags.contains(Flags::DISABLED))
+        };
+        let temperature = query(V4L2_CID_WHITE_BALANCE_TEMPERATURE);
+        let gains = query(V4L2_CID_RED_BALANCE).zip(query(V4L2_CID_BLUE_BALANCE));
+        if temperature.is_none() && gains.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "device has no white balance controls",
+            ));
+        }
+
+        Ok(WhiteBalance {
+            dev,
+            temperature,
+            gains,
+            curve: Curve::default(),
+        })
+    }
+
+    /// Sets the curve used for devices without a color temperature control
+    ///
+    /// # Arguments
+    ///
+    /// * `curve` - Gain curve of the sensor
+    pub fn with_curve(mut self, curve: Curve) -> Self {
+        self.curve = curve;
+        self
+    }
+
+    /// Returns whether the device accepts color temperatures directly
+    pub fn has_temperature(&self) -> bool {
+        self.temperature.is_some()
+    }
+
+    /// Enables or disables automatic white balance
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether the device balances by itself
+    pub fn set_auto(&self, enable: bool) -> io::Result<()> {
+        self.dev.set_control(Control {
+            id: V4L2_CID_AUTO_WHITE_BALANCE,
+            value: Value::Boolean(enable),
+        })
+    }
+
+    /// Sets the color temperature of the light and returns the one applied
+    ///
+    /// Devices without a color temperature control get red and blue gains from the curve
+    /// instead, in which case the temperature is clamped to the range of the curve.
+    ///
+    /// # Arguments
+    ///
+    /// * `kelvin` - Color temperature of the scene lighting
+    pub fn set_temperature(&self, kelvin: u32) -> io::Result<u32> {
+        if let Some(desc) = &self.temperature {
+            let step = desc.step.max(1) as i64;
+            let val = kelvin as i64 - desc.minimum;
+            let val =
+                (desc.minimum + (val + step / 2) / step * step).clamp(desc.minimum, desc.maximum);
+            self.dev.set_control(Control {
+                id: desc.id,
+                value: Value::Integer(val),
+            })?;
+            return Ok(val as u32);
+        }
+
+        let (red, blue) = self.gains.as_ref().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "device has no white balance gain controls",
+            )
+        })?;
+        let (min, max) = self.curve.range();
+        let kelvin = kelvin.clamp(min, max);
+        let (red_gain, blue_gain) = self.curve.gains(kelvin);
+        self.dev.set_controls([
+            Control {
+                id: red.id,
+                value: Value::Integer(scale(red, red_gain)),
+            },
+            Control {
+                id: blue.id,
+                value: Value::Integer(scale(blue, blue_gain)),
+            },
+        ])?;
+        Ok(kelvin)
+    }
+}
+
+/// Applies a gain to the default value of a balance control
+fn scale(desc: &Description, gain: f64) -> i64 {
+    ((desc.default as f64 * gain).round() as i64).clamp(desc.minimum, desc.maximum)
+}
@@ -0,0 +1,133 @@
+//! End-to-end tests against virtual drivers
+//!
+//! Run with `cargo test --features test-virtual`. Tests are skipped if the drivers are not
+//! available, see [`v4l::testing`].
+
+#![cfg(feature = "test-virtual")]
+
+use std::time::Duration;
+
+use v4l::buffer::Type;
+use v4l::control::{Control, Value};
+use v4l::event::{self, Payload, SubscriptionFlags};
+use v4l::io::mmap::Stream as MmapStream;
+use v4l::io::traits::CaptureStream;
+use v4l::io::userptr::OutputQueue;
+use v4l::testing;
+use v4l::video::{Capture, Output};
+use v4l::Timestamp;
+
+const BRIGHTNESS: u32 = 0x00980900;
+
+#[test]
+fn capture_mmap() {
+    let dev = match testing::skip_unless(testing::vivid_capture()) {
+        Some(dev) => dev,
+        None => return,
+    };
+
+    let format = Capture::format(&dev).expect("Failed to get format");
+    let mut stream =
+        MmapStream::with_buffers(&dev, Type::VideoCapture, 4).expect("Failed to create stream");
+    stream.set_timeout(Duration::from_secs(5));
+
+    let mut last = None;
+    for _ in 0..8 {
+        let (buf, meta) = stream.next().expect("Failed to capture frame");
+        assert_eq!(buf.len() as u32, format.size);
+        assert!(meta.bytesused > 0);
+        if let Some(last) = last {
+            assert!(meta.sequence > last);
+        }
+        last = Some(meta.sequence);
+    }
+}
+
+#[test]
+fn controls() {
+    let dev = match testing::skip_unless(testing::vivid_capture()) {
+        Some(dev) => dev,
+        None => return,
+    };
+
+    let desc = dev
+        .query_controls()
+        .expect("Failed to query controls")
+        .into_iter()
+        .find(|desc| desc.id == BRIGHTNESS)
+        .expect("vivid lacks a brightness control");
+
+    let value = (desc.minimum + desc.maximum) / 2;
+    dev.set_control(Control {
+        id: BRIGHTNESS,
+        value: Value::Integer(value),
+    })
+    .expect("Failed to set control");
+    let ctrl = dev.control(&desc).expect("Failed to get control");
+    assert!(matches!(ctrl.value, Value::Integer(v) if v == value));
+}
+
+#[test]
+fn control_events() {
+    let dev = match testing::skip_unless(testing::vivid_capture()) {
+        Some(dev) => dev,
+        None => return,
+    };
+
+    dev.subscribe_event(
+        event::Type::Ctrl,
+        BRIGHTNESS,
+        SubscriptionFlags::SEND_INITIAL,
+    )
+    .expect("Failed to subscribe event");
+    let ev = dev
+        .wait_event(Some(Duration::from_secs(1)))
+        .expect("Failed to receive initial event");
+    assert!(matches!(ev.payload, Payload::Ctrl(_)));
+
+    let value = match dev
+        .control_from_id(BRIGHTNESS)
+        .expect("Failed to get control")
+        .value
+    {
+        Value::Integer(value) => value,
+        _ => panic!("brightness is not an integer control"),
+    };
+    dev.set_control(Control {
+        id: BRIGHTNESS,
+        value: Value::Integer(if value > 0 { value - 1 } else { value + 1 }),
+    })
+    .expect("Failed to set control");
+    let ev = dev
+        .wait_event(Some(Duration::from_secs(1)))
+        .expect("Failed to receive change event");
+    assert_eq!(ev.id, BRIGHTNESS);
+
+    dev.unsubscribe_event(event::Type::Ctrl, BRIGHTNESS)
+        .expect("Failed to unsubscribe event");
+}
+
+#[test]
+fn m2m() {
+    let dev = match testing::skip_unless(testing::vim2m()) {
+        Some(dev) => dev,
+        None => return,
+    };
+
+    let format = Output::format(&dev).expect("Failed to get output format");
+    let cap_format = Capture::set_format(&dev, &format).expect("Failed to set capture format");
+
+    let stream =
+        MmapStream::with_buffers(&dev, Type::VideoCapture, 2).expect("Failed to create stream");
+    let (mut producer, _consumer) = stream.split().expect("Failed to split stream");
+    producer.set_timeout(Duration::from_secs(5));
+
+    let mut queue =
+        OutputQueue::new(&dev, Type::VideoOutput, 2).expect("Failed to create output queue");
+    queue
+        .queue(vec![0x80u8; format.size as usize], Timestamp::new(1, 0))
+        .expect("Failed to queue frame");
+
+    let buf = producer.dequeue().expect("Failed to dequeue frame");
+    assert_eq!(buf.meta().bytesused, cap_format.size);
+}
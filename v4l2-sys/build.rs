@@ -4,10 +4,15 @@ use std::env;
 use std::path::PathBuf;
 
 fn main() {
-    let bindings = bindgen::Builder::default()
-        .header("wrapper.h")
-        .generate()
-        .expect("Failed to generate bindings");
+    let mut builder = bindgen::Builder::default().header("wrapper.h");
+
+    // Use 64-bit time on 32-bit targets, otherwise buffer timestamps overflow in 2038. musl does
+    // so unconditionally, glibc needs to be asked for it.
+    if env::var("CARGO_CFG_TARGET_POINTER_WIDTH").as_deref() == Ok("32") {
+        builder = builder.clang_args(["-D_TIME_BITS=64", "-D_FILE_OFFSET_BITS=64"]);
+    }
+
+    let bindings = builder.generate().expect("Failed to generate bindings");
 
     let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
     bindings